@@ -0,0 +1,212 @@
+//! Dual numbers for forward-mode differentiation.
+//!
+//! A dual number `a + b ε` with `ε² = 0` carries a value and a directional derivative. Evaluating
+//! a function at `x + d ε` yields `f(x) + (∇f(x)·d) ε`.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::scalar::Scalar;
+//}}}
+//{{{ std imports
+use std::cmp::Ordering;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Dual
+/// A dual number `re + eps ε`.
+#[derive(Debug, Copy, Clone)]
+pub struct Dual {
+    pub re: f64,
+    pub eps: f64,
+}
+//}}}
+//{{{ impl: Dual
+impl Dual {
+    pub fn new(re: f64, eps: f64) -> Self {
+        Self { re, eps }
+    }
+
+    /// A variable with unit derivative part.
+    pub fn variable(re: f64) -> Self {
+        Self { re, eps: 1.0 }
+    }
+
+    /// Applies a scalar function with value `f` and derivative `df` at `self.re`.
+    fn chain(self, f: f64, df: f64) -> Self {
+        Self {
+            re: f,
+            eps: df * self.eps,
+        }
+    }
+}
+//}}}
+//{{{ impl: PartialEq, PartialOrd for Dual
+impl PartialEq for Dual {
+    fn eq(&self, other: &Self) -> bool {
+        self.re == other.re
+    }
+}
+
+impl PartialOrd for Dual {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.re.partial_cmp(&other.re)
+    }
+}
+//}}}
+//{{{ impl: arithmetic for Dual
+impl Add for Dual {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.eps + rhs.eps)
+    }
+}
+
+impl Sub for Dual {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.eps - rhs.eps)
+    }
+}
+
+impl Mul for Dual {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(self.re * rhs.re, self.re * rhs.eps + self.eps * rhs.re)
+    }
+}
+
+impl Div for Dual {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        let inv = 1.0 / rhs.re;
+        Self::new(
+            self.re * inv,
+            (self.eps * rhs.re - self.re * rhs.eps) * inv * inv,
+        )
+    }
+}
+
+impl Neg for Dual {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.re, -self.eps)
+    }
+}
+
+impl Add<f64> for Dual {
+    type Output = Self;
+    fn add(self, rhs: f64) -> Self {
+        Self::new(self.re + rhs, self.eps)
+    }
+}
+
+impl Sub<f64> for Dual {
+    type Output = Self;
+    fn sub(self, rhs: f64) -> Self {
+        Self::new(self.re - rhs, self.eps)
+    }
+}
+
+impl Mul<f64> for Dual {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self {
+        Self::new(self.re * rhs, self.eps * rhs)
+    }
+}
+
+impl Div<f64> for Dual {
+    type Output = Self;
+    fn div(self, rhs: f64) -> Self {
+        Self::new(self.re / rhs, self.eps / rhs)
+    }
+}
+
+impl Add<Dual> for f64 {
+    type Output = Dual;
+    fn add(self, rhs: Dual) -> Dual {
+        rhs + self
+    }
+}
+
+impl Sub<Dual> for f64 {
+    type Output = Dual;
+    fn sub(self, rhs: Dual) -> Dual {
+        -rhs + self
+    }
+}
+
+impl Mul<Dual> for f64 {
+    type Output = Dual;
+    fn mul(self, rhs: Dual) -> Dual {
+        rhs * self
+    }
+}
+
+impl Div<Dual> for f64 {
+    type Output = Dual;
+    fn div(self, rhs: Dual) -> Dual {
+        Dual::from_f64(self) / rhs
+    }
+}
+//}}}
+//{{{ impl: Scalar for Dual
+impl Scalar for Dual {
+    fn from_f64(value: f64) -> Self {
+        Self::new(value, 0.0)
+    }
+
+    fn value(&self) -> f64 {
+        self.re
+    }
+
+    fn sqrt(self) -> Self {
+        let s = self.re.sqrt();
+        self.chain(s, 0.5 / s)
+    }
+
+    fn exp(self) -> Self {
+        let e = self.re.exp();
+        self.chain(e, e)
+    }
+
+    fn ln(self) -> Self {
+        self.chain(self.re.ln(), 1.0 / self.re)
+    }
+
+    fn sin(self) -> Self {
+        self.chain(self.re.sin(), self.re.cos())
+    }
+
+    fn cos(self) -> Self {
+        self.chain(self.re.cos(), -self.re.sin())
+    }
+
+    fn tan(self) -> Self {
+        let t = self.re.tan();
+        self.chain(t, 1.0 + t * t)
+    }
+
+    fn tanh(self) -> Self {
+        let t = self.re.tanh();
+        self.chain(t, 1.0 - t * t)
+    }
+
+    fn abs(self) -> Self {
+        self.chain(self.re.abs(), self.re.signum())
+    }
+
+    fn powi(self, n: i32) -> Self {
+        if n == 0 {
+            return Self::from_f64(1.0);
+        }
+        self.chain(self.re.powi(n), n as f64 * self.re.powi(n - 1))
+    }
+
+    fn powf(self, p: f64) -> Self {
+        self.chain(self.re.powf(p), p * self.re.powf(p - 1.0))
+    }
+}
+//}}}
@@ -0,0 +1,109 @@
+//! Forward-mode adapter turning a [`ScalarFn`] into a [`RealFn`].
+//!
+//! The gradient is assembled one component at a time by seeding a dual number along each
+//! coordinate direction, so a gradient costs `n` evaluations of the objective. Hessian-vector
+//! products are obtained in the same way using hyper-dual numbers.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::dual::Dual;
+use super::hyperdual::HyperDual;
+use super::scalar::{Scalar, ScalarFn};
+use crate::common::DenseVector;
+use crate::RealFn;
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+use std::marker::PhantomData;
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: ForwardDiff
+/// Wraps a [`ScalarFn`] so that its gradient is computed exactly with dual numbers.
+#[derive(Debug, Clone)]
+pub struct ForwardDiff<F: ScalarFn, V> {
+    pub fcn: F,
+    _vector: PhantomData<V>,
+}
+//}}}
+//{{{ impl: ForwardDiff
+impl<F, V> ForwardDiff<F, V>
+where
+    F: ScalarFn,
+    V: DenseVector + Debug,
+{
+    pub fn new(fcn: F) -> Self {
+        Self {
+            fcn,
+            _vector: PhantomData,
+        }
+    }
+
+    /// Computes the Hessian-vector product `∇²f(x) v`.
+    pub fn hess_vec(&mut self, x: &V, v: &V) -> V {
+        self.grad_hess_vec(x, v).1
+    }
+
+    /// Computes the gradient `∇f(x)` and the Hessian-vector product `∇²f(x) v` together.
+    ///
+    /// Component `i` of both results comes from a single hyper-dual evaluation at
+    /// `x + e_i ε₁ + v ε₂`.
+    pub fn grad_hess_vec(&mut self, x: &V, v: &V) -> (V, V) {
+        let n = x.len();
+        let mut seeded: Vec<HyperDual> = (0..n)
+            .map(|j| HyperDual::new(x[j], 0.0, v[j], 0.0))
+            .collect();
+        let mut grad = x.clone();
+        let mut hv = x.clone();
+        for i in 0..n {
+            seeded[i].e1 = 1.0;
+            let out = self.fcn.eval(&seeded);
+            seeded[i].e1 = 0.0;
+            grad[i] = out.e1;
+            hv[i] = out.e12;
+        }
+        (grad, hv)
+    }
+
+    /// Computes the directional derivative `∇f(x)·d` with a single dual evaluation.
+    pub fn directional(&mut self, x: &V, d: &V) -> f64 {
+        let seeded: Vec<Dual> = (0..x.len()).map(|j| Dual::new(x[j], d[j])).collect();
+        self.fcn.eval(&seeded).eps
+    }
+}
+//}}}
+//{{{ impl: RealFn for ForwardDiff
+impl<F, V> RealFn for ForwardDiff<F, V>
+where
+    F: ScalarFn,
+    V: DenseVector + Debug,
+{
+    type Vector = V;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        self.fcn.eval(&x.to_vec())
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        self.eval_with_grad(x).1
+    }
+
+    fn eval_with_grad(&mut self, x: &Self::Vector) -> (f64, Self::Vector) {
+        let n = x.len();
+        let mut seeded: Vec<Dual> = (0..n).map(|j| Dual::from_f64(x[j])).collect();
+        let mut grad = x.clone();
+        let mut value = None;
+        for i in 0..n {
+            seeded[i].eps = 1.0;
+            let out = self.fcn.eval(&seeded);
+            seeded[i].eps = 0.0;
+            grad[i] = out.eps;
+            value.get_or_insert(out.re);
+        }
+        let value = value.unwrap_or_else(|| self.fcn.eval(&seeded).re);
+        (value, grad)
+    }
+}
+//}}}
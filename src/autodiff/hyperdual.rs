@@ -0,0 +1,249 @@
+//! Hyper-dual numbers for exact second derivatives.
+//!
+//! A hyper-dual number `a + b ε₁ + c ε₂ + d ε₁ε₂` with `ε₁² = ε₂² = 0` carries two first
+//! derivative parts and one mixed second derivative part. Evaluating a function at
+//! `x + u ε₁ + v ε₂` yields `f(x) + (∇f·u) ε₁ + (∇f·v) ε₂ + (uᵀ∇²f v) ε₁ε₂` without any
+//! truncation error.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::scalar::Scalar;
+//}}}
+//{{{ std imports
+use std::cmp::Ordering;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: HyperDual
+/// A hyper-dual number `re + e1 ε₁ + e2 ε₂ + e12 ε₁ε₂`.
+#[derive(Debug, Copy, Clone)]
+pub struct HyperDual {
+    pub re: f64,
+    pub e1: f64,
+    pub e2: f64,
+    pub e12: f64,
+}
+//}}}
+//{{{ impl: HyperDual
+impl HyperDual {
+    pub fn new(re: f64, e1: f64, e2: f64, e12: f64) -> Self {
+        Self { re, e1, e2, e12 }
+    }
+
+    /// Applies a scalar function with value `f`, first derivative `df` and second derivative
+    /// `ddf` at `self.re`.
+    fn chain(self, f: f64, df: f64, ddf: f64) -> Self {
+        Self {
+            re: f,
+            e1: df * self.e1,
+            e2: df * self.e2,
+            e12: df * self.e12 + ddf * self.e1 * self.e2,
+        }
+    }
+
+    fn recip(self) -> Self {
+        let inv = 1.0 / self.re;
+        self.chain(inv, -inv * inv, 2.0 * inv * inv * inv)
+    }
+}
+//}}}
+//{{{ impl: PartialEq, PartialOrd for HyperDual
+impl PartialEq for HyperDual {
+    fn eq(&self, other: &Self) -> bool {
+        self.re == other.re
+    }
+}
+
+impl PartialOrd for HyperDual {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.re.partial_cmp(&other.re)
+    }
+}
+//}}}
+//{{{ impl: arithmetic for HyperDual
+impl Add for HyperDual {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self::new(
+            self.re + rhs.re,
+            self.e1 + rhs.e1,
+            self.e2 + rhs.e2,
+            self.e12 + rhs.e12,
+        )
+    }
+}
+
+impl Sub for HyperDual {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(
+            self.re - rhs.re,
+            self.e1 - rhs.e1,
+            self.e2 - rhs.e2,
+            self.e12 - rhs.e12,
+        )
+    }
+}
+
+impl Mul for HyperDual {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re * rhs.re,
+            self.re * rhs.e1 + self.e1 * rhs.re,
+            self.re * rhs.e2 + self.e2 * rhs.re,
+            self.re * rhs.e12 + self.e1 * rhs.e2 + self.e2 * rhs.e1 + self.e12 * rhs.re,
+        )
+    }
+}
+
+impl Div for HyperDual {
+    type Output = Self;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self {
+        self * rhs.recip()
+    }
+}
+
+impl Neg for HyperDual {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.re, -self.e1, -self.e2, -self.e12)
+    }
+}
+
+impl Add<f64> for HyperDual {
+    type Output = Self;
+    fn add(self, rhs: f64) -> Self {
+        Self::new(self.re + rhs, self.e1, self.e2, self.e12)
+    }
+}
+
+impl Sub<f64> for HyperDual {
+    type Output = Self;
+    fn sub(self, rhs: f64) -> Self {
+        Self::new(self.re - rhs, self.e1, self.e2, self.e12)
+    }
+}
+
+impl Mul<f64> for HyperDual {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self {
+        Self::new(self.re * rhs, self.e1 * rhs, self.e2 * rhs, self.e12 * rhs)
+    }
+}
+
+impl Div<f64> for HyperDual {
+    type Output = Self;
+    fn div(self, rhs: f64) -> Self {
+        Self::new(self.re / rhs, self.e1 / rhs, self.e2 / rhs, self.e12 / rhs)
+    }
+}
+
+impl Add<HyperDual> for f64 {
+    type Output = HyperDual;
+    fn add(self, rhs: HyperDual) -> HyperDual {
+        rhs + self
+    }
+}
+
+impl Sub<HyperDual> for f64 {
+    type Output = HyperDual;
+    fn sub(self, rhs: HyperDual) -> HyperDual {
+        -rhs + self
+    }
+}
+
+impl Mul<HyperDual> for f64 {
+    type Output = HyperDual;
+    fn mul(self, rhs: HyperDual) -> HyperDual {
+        rhs * self
+    }
+}
+
+impl Div<HyperDual> for f64 {
+    type Output = HyperDual;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: HyperDual) -> HyperDual {
+        rhs.recip() * self
+    }
+}
+//}}}
+//{{{ impl: Scalar for HyperDual
+impl Scalar for HyperDual {
+    fn from_f64(value: f64) -> Self {
+        Self::new(value, 0.0, 0.0, 0.0)
+    }
+
+    fn value(&self) -> f64 {
+        self.re
+    }
+
+    fn sqrt(self) -> Self {
+        let s = self.re.sqrt();
+        self.chain(s, 0.5 / s, -0.25 / (s * self.re))
+    }
+
+    fn exp(self) -> Self {
+        let e = self.re.exp();
+        self.chain(e, e, e)
+    }
+
+    fn ln(self) -> Self {
+        let inv = 1.0 / self.re;
+        self.chain(self.re.ln(), inv, -inv * inv)
+    }
+
+    fn sin(self) -> Self {
+        let (s, c) = self.re.sin_cos();
+        self.chain(s, c, -s)
+    }
+
+    fn cos(self) -> Self {
+        let (s, c) = self.re.sin_cos();
+        self.chain(c, -s, -c)
+    }
+
+    fn tan(self) -> Self {
+        let t = self.re.tan();
+        let dt = 1.0 + t * t;
+        self.chain(t, dt, 2.0 * t * dt)
+    }
+
+    fn tanh(self) -> Self {
+        let t = self.re.tanh();
+        let dt = 1.0 - t * t;
+        self.chain(t, dt, -2.0 * t * dt)
+    }
+
+    fn abs(self) -> Self {
+        self.chain(self.re.abs(), self.re.signum(), 0.0)
+    }
+
+    fn powi(self, n: i32) -> Self {
+        match n {
+            0 => Self::from_f64(1.0),
+            1 => self,
+            _ => {
+                let nf = n as f64;
+                self.chain(
+                    self.re.powi(n),
+                    nf * self.re.powi(n - 1),
+                    nf * (nf - 1.0) * self.re.powi(n - 2),
+                )
+            }
+        }
+    }
+
+    fn powf(self, p: f64) -> Self {
+        self.chain(
+            self.re.powf(p),
+            p * self.re.powf(p - 1.0),
+            p * (p - 1.0) * self.re.powf(p - 2.0),
+        )
+    }
+}
+//}}}
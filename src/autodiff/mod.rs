@@ -0,0 +1,32 @@
+//! Automatic differentiation of objectives written once, generically over a scalar type.
+//!
+//! An objective implements [`ScalarFn`], whose `eval` method is generic over any type
+//! implementing [`Scalar`]. Evaluating it with `f64` gives the function value, evaluating it
+//! with [`Dual`] numbers gives exact directional derivatives, and evaluating it with
+//! [`HyperDual`] numbers gives exact second directional derivatives. [`ForwardDiff`] wraps such an
+//! objective as a [`RealFn`](crate::RealFn) so it can be handed straight to a minimizer, and
+//! [`ReverseDiff`] does the same by recording the evaluation on a [`Tape`] of [`Var`]s and
+//! sweeping it backwards, at a cost independent of the number of variables.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+mod dual;
+mod forward;
+mod hyperdual;
+mod reverse;
+mod scalar;
+mod tape;
+
+pub use dual::Dual;
+pub use forward::ForwardDiff;
+pub use hyperdual::HyperDual;
+pub use reverse::ReverseDiff;
+pub use scalar::{Scalar, ScalarFn};
+pub use tape::{Tape, Var};
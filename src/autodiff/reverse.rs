@@ -0,0 +1,116 @@
+//! Reverse-mode adapter turning a [`ScalarFn`] into a [`RealFn`].
+//!
+//! The objective is evaluated once with [`Var`] inputs to record a [`Tape`], and the gradient is
+//! obtained from a single reverse sweep over it. Its cost does not grow with the number of
+//! variables, which makes this adapter preferable to [`ForwardDiff`](super::ForwardDiff) for
+//! large problems.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::scalar::Scalar;
+use super::scalar::ScalarFn;
+use super::tape::Tape;
+use crate::common::DenseVector;
+use crate::RealFn;
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+use std::marker::PhantomData;
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: ReverseDiff
+/// Wraps a [`ScalarFn`] so that its gradient is computed by reverse-mode differentiation.
+///
+/// By default the tape is recorded afresh for every gradient. When created with
+/// [`ReverseDiff::replaying`] the tape is recorded on the first gradient only and replayed at the
+/// new point afterwards, which is valid as long as the objective does not branch on its inputs.
+#[derive(Debug, Clone)]
+pub struct ReverseDiff<F: ScalarFn, V> {
+    pub fcn: F,
+    replay: bool,
+    recorded: Option<(Tape, usize)>,
+    _vector: PhantomData<V>,
+}
+//}}}
+//{{{ impl: ReverseDiff
+impl<F, V> ReverseDiff<F, V>
+where
+    F: ScalarFn,
+    V: DenseVector + Debug,
+{
+    /// Creates an adapter which records a new tape for every gradient.
+    pub fn new(fcn: F) -> Self {
+        Self {
+            fcn,
+            replay: false,
+            recorded: None,
+            _vector: PhantomData,
+        }
+    }
+
+    /// Creates an adapter which records the tape once and replays it for later gradients.
+    pub fn replaying(fcn: F) -> Self {
+        Self {
+            replay: true,
+            ..Self::new(fcn)
+        }
+    }
+
+    /// Number of nodes on the most recently recorded tape, if one is kept.
+    pub fn tape_len(&self) -> Option<usize> {
+        self.recorded.as_ref().map(|(tape, _)| tape.len())
+    }
+
+    /// Evaluates the objective and its gradient from one forward and one reverse sweep.
+    fn value_and_gradient(&mut self, x: &[f64]) -> (f64, Vec<f64>) {
+        if self.replay {
+            if let Some((tape, output)) = self.recorded.as_ref() {
+                tape.replay(x);
+                return (tape.value(*output), tape.gradient_at(*output));
+            }
+        }
+        let tape = Tape::new();
+        let (value, output, grad) = {
+            let vars = tape.vars(x);
+            let y = self.fcn.eval(&vars);
+            (y.value(), y.index(), tape.gradient(y))
+        };
+        if self.replay {
+            if let Some(output) = output {
+                self.recorded = Some((tape, output));
+            }
+        }
+        (value, grad)
+    }
+}
+//}}}
+//{{{ impl: RealFn for ReverseDiff
+impl<F, V> RealFn for ReverseDiff<F, V>
+where
+    F: ScalarFn,
+    V: DenseVector + Debug,
+{
+    type Vector = V;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        self.fcn.eval(&x.to_vec())
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        let (_, grad) = self.value_and_gradient(&x.to_vec());
+        let mut out = x.clone();
+        out.copy_from_slice(&grad);
+        out
+    }
+
+    fn eval_with_grad(&mut self, x: &Self::Vector) -> (f64, Self::Vector) {
+        let (value, grad) = self.value_and_gradient(&x.to_vec());
+        let mut out = x.clone();
+        out.copy_from_slice(&grad);
+        (value, out)
+    }
+}
+//}}}
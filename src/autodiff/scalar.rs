@@ -0,0 +1,114 @@
+//! The scalar abstraction shared by `f64` and the automatic differentiation number types.
+//!
+//! Objectives written against [`Scalar`] can be evaluated with plain floats or with any of the
+//! derivative-carrying number types in this module without modification.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ trait: Scalar
+/// A float-like number type.
+///
+/// Arithmetic is available between two scalars and between a scalar and an `f64` on the right.
+/// Comparisons only look at the value part, so branches in an objective behave as they would for
+/// `f64`.
+pub trait Scalar:
+    Copy
+    + Debug
+    + PartialEq
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + Add<f64, Output = Self>
+    + Sub<f64, Output = Self>
+    + Mul<f64, Output = Self>
+    + Div<f64, Output = Self>
+{
+    /// Lifts a constant into the scalar type, with all derivative parts zero.
+    fn from_f64(value: f64) -> Self;
+    /// The value part of the scalar.
+    fn value(&self) -> f64;
+
+    fn sqrt(self) -> Self;
+    fn exp(self) -> Self;
+    fn ln(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn tanh(self) -> Self;
+    fn abs(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn powf(self, p: f64) -> Self;
+}
+//}}}
+//{{{ impl: Scalar for f64
+impl Scalar for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn value(&self) -> f64 {
+        *self
+    }
+
+    fn sqrt(self) -> Self {
+        f64::sqrt(self)
+    }
+
+    fn exp(self) -> Self {
+        f64::exp(self)
+    }
+
+    fn ln(self) -> Self {
+        f64::ln(self)
+    }
+
+    fn sin(self) -> Self {
+        f64::sin(self)
+    }
+
+    fn cos(self) -> Self {
+        f64::cos(self)
+    }
+
+    fn tan(self) -> Self {
+        f64::tan(self)
+    }
+
+    fn tanh(self) -> Self {
+        f64::tanh(self)
+    }
+
+    fn abs(self) -> Self {
+        f64::abs(self)
+    }
+
+    fn powi(self, n: i32) -> Self {
+        f64::powi(self, n)
+    }
+
+    fn powf(self, p: f64) -> Self {
+        f64::powf(self, p)
+    }
+}
+//}}}
+//{{{ trait: ScalarFn
+/// A real-valued function of several variables written generically over [`Scalar`].
+///
+/// Implementations must only use operations provided by [`Scalar`], and should not branch on
+/// anything but the value part of the inputs.
+pub trait ScalarFn: Clone + Debug {
+    fn eval<S: Scalar>(&self, x: &[S]) -> S;
+}
+//}}}
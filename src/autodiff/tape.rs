@@ -0,0 +1,438 @@
+//! A tape recording the operations of an evaluation for reverse-mode differentiation.
+//!
+//! Every arithmetic operation or elementary function applied to a [`Var`] appends a node to the
+//! [`Tape`] it was created from. A single reverse sweep over the nodes then yields the derivative
+//! of one output with respect to every input, so a full gradient costs a small constant multiple
+//! of one function evaluation regardless of the number of inputs. A recorded tape can also be
+//! replayed at new inputs, which avoids rebuilding it when the control flow of the objective does
+//! not depend on the input values.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::scalar::Scalar;
+//}}}
+//{{{ std imports
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::ptr;
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ enum: Op
+/// A recorded operation, referring to its operands by node index.
+#[derive(Debug, Copy, Clone)]
+enum Op {
+    Input(usize),
+    Const(f64),
+    Add(usize, usize),
+    Sub(usize, usize),
+    Mul(usize, usize),
+    Div(usize, usize),
+    Neg(usize),
+    Sqrt(usize),
+    Exp(usize),
+    Ln(usize),
+    Sin(usize),
+    Cos(usize),
+    Tan(usize),
+    Tanh(usize),
+    Abs(usize),
+    Powi(usize, i32),
+    Powf(usize, f64),
+}
+//}}}
+//{{{ struct: Node
+#[derive(Debug, Copy, Clone)]
+struct Node {
+    op: Op,
+    value: f64,
+}
+//}}}
+//{{{ struct: Tape
+/// Records the nodes of a computation.
+#[derive(Debug, Clone, Default)]
+pub struct Tape {
+    nodes: RefCell<Vec<Node>>,
+    num_inputs: Cell<usize>,
+}
+//}}}
+//{{{ impl: Tape
+impl Tape {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of recorded nodes, including inputs and constants.
+    pub fn len(&self) -> usize {
+        self.nodes.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of input variables registered on the tape.
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs.get()
+    }
+
+    /// Registers a new input variable with the given value.
+    pub fn var(&self, value: f64) -> Var<'_> {
+        let k = self.num_inputs();
+        self.num_inputs.set(k + 1);
+        self.push(Op::Input(k), value)
+    }
+
+    /// Registers one input variable per entry of `values`.
+    pub fn vars(&self, values: &[f64]) -> Vec<Var<'_>> {
+        values.iter().map(|v| self.var(*v)).collect()
+    }
+
+    /// The value currently stored at node `index`.
+    pub fn value(&self, index: usize) -> f64 {
+        self.nodes.borrow()[index].value
+    }
+
+    /// Computes the derivative of `output` with respect to every input, in input order.
+    ///
+    /// Outputs which do not depend on any input have a zero gradient.
+    pub fn gradient(&self, output: Var<'_>) -> Vec<f64> {
+        match output.index {
+            Some(index) => self.gradient_at(index),
+            None => vec![0.0; self.num_inputs()],
+        }
+    }
+
+    /// Computes the derivative of the node at `output` with respect to every input.
+    pub fn gradient_at(&self, output: usize) -> Vec<f64> {
+        let nodes = self.nodes.borrow();
+        let mut adjoint = vec![0.0; output + 1];
+        let mut grad = vec![0.0; self.num_inputs()];
+        adjoint[output] = 1.0;
+        for i in (0..=output).rev() {
+            let g = adjoint[i];
+            if g == 0.0 {
+                continue;
+            }
+            let value = nodes[i].value;
+            let val = |j: usize| nodes[j].value;
+            match nodes[i].op {
+                Op::Input(k) => grad[k] += g,
+                Op::Const(_) => {}
+                Op::Add(a, b) => {
+                    adjoint[a] += g;
+                    adjoint[b] += g;
+                }
+                Op::Sub(a, b) => {
+                    adjoint[a] += g;
+                    adjoint[b] -= g;
+                }
+                Op::Mul(a, b) => {
+                    adjoint[a] += g * val(b);
+                    adjoint[b] += g * val(a);
+                }
+                Op::Div(a, b) => {
+                    let inv = 1.0 / val(b);
+                    adjoint[a] += g * inv;
+                    adjoint[b] -= g * value * inv;
+                }
+                Op::Neg(a) => adjoint[a] -= g,
+                Op::Sqrt(a) => adjoint[a] += g * 0.5 / value,
+                Op::Exp(a) => adjoint[a] += g * value,
+                Op::Ln(a) => adjoint[a] += g / val(a),
+                Op::Sin(a) => adjoint[a] += g * val(a).cos(),
+                Op::Cos(a) => adjoint[a] -= g * val(a).sin(),
+                Op::Tan(a) => adjoint[a] += g * (1.0 + value * value),
+                Op::Tanh(a) => adjoint[a] += g * (1.0 - value * value),
+                Op::Abs(a) => adjoint[a] += g * val(a).signum(),
+                // x⁰ is constant, even at 0 where n xⁿ⁻¹ would be 0 · inf
+                Op::Powi(_, 0) => {}
+                Op::Powi(a, n) => adjoint[a] += g * n as f64 * val(a).powi(n - 1),
+                Op::Powf(a, p) => adjoint[a] += g * p * val(a).powf(p - 1.0),
+            }
+        }
+        grad
+    }
+
+    /// Re-evaluates every recorded node with new input values.
+    ///
+    /// The recorded operations are reused as they are, so the result is only valid when the
+    /// recorded computation does not branch on the input values.
+    pub fn replay(&self, inputs: &[f64]) {
+        debug_assert_eq!(inputs.len(), self.num_inputs());
+        let mut nodes = self.nodes.borrow_mut();
+        for i in 0..nodes.len() {
+            let val = |j: usize| nodes[j].value;
+            let value = match nodes[i].op {
+                Op::Input(k) => inputs[k],
+                Op::Const(c) => c,
+                Op::Add(a, b) => val(a) + val(b),
+                Op::Sub(a, b) => val(a) - val(b),
+                Op::Mul(a, b) => val(a) * val(b),
+                Op::Div(a, b) => val(a) / val(b),
+                Op::Neg(a) => -val(a),
+                Op::Sqrt(a) => val(a).sqrt(),
+                Op::Exp(a) => val(a).exp(),
+                Op::Ln(a) => val(a).ln(),
+                Op::Sin(a) => val(a).sin(),
+                Op::Cos(a) => val(a).cos(),
+                Op::Tan(a) => val(a).tan(),
+                Op::Tanh(a) => val(a).tanh(),
+                Op::Abs(a) => val(a).abs(),
+                Op::Powi(a, n) => val(a).powi(n),
+                Op::Powf(a, p) => val(a).powf(p),
+            };
+            nodes[i].value = value;
+        }
+    }
+
+    fn push(&self, op: Op, value: f64) -> Var<'_> {
+        let mut nodes = self.nodes.borrow_mut();
+        nodes.push(Node { op, value });
+        Var {
+            tape: Some(self),
+            index: Some(nodes.len() - 1),
+            value,
+        }
+    }
+
+    /// Returns the node index of `v`, recording it as a constant if it is not on the tape yet.
+    fn index_of(&self, v: Var<'_>) -> usize {
+        match v.index {
+            Some(index) => index,
+            None => self.push(Op::Const(v.value), v.value).index.unwrap(),
+        }
+    }
+}
+//}}}
+//{{{ struct: Var
+/// A scalar whose operations are recorded on a [`Tape`].
+///
+/// Constants created through [`Scalar::from_f64`] are not attached to a tape; they are recorded
+/// lazily the first time they are combined with a recorded variable.
+///
+/// # Panics
+///
+/// Combining variables recorded on two different tapes panics.
+#[derive(Copy, Clone)]
+pub struct Var<'t> {
+    tape: Option<&'t Tape>,
+    index: Option<usize>,
+    value: f64,
+}
+//}}}
+//{{{ impl: Var
+impl<'t> Var<'t> {
+    /// The index of the tape node holding this variable, or `None` for an unrecorded constant.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    fn constant(value: f64) -> Self {
+        Self {
+            tape: None,
+            index: None,
+            value,
+        }
+    }
+
+    fn unary(self, op: fn(usize) -> Op, value: f64) -> Self {
+        match self.tape {
+            Some(tape) => tape.push(op(self.index.unwrap()), value),
+            None => Self::constant(value),
+        }
+    }
+
+    fn binary(self, rhs: Self, op: fn(usize, usize) -> Op, value: f64) -> Self {
+        if let (Some(a), Some(b)) = (self.tape, rhs.tape) {
+            assert!(ptr::eq(a, b), "operands recorded on different tapes");
+        }
+        match self.tape.or(rhs.tape) {
+            Some(tape) => {
+                let a = tape.index_of(self);
+                let b = tape.index_of(rhs);
+                tape.push(op(a, b), value)
+            }
+            None => Self::constant(value),
+        }
+    }
+}
+//}}}
+//{{{ impl: Debug for Var
+impl fmt::Debug for Var<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Var")
+            .field("index", &self.index)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+//}}}
+//{{{ impl: PartialEq, PartialOrd for Var
+impl PartialEq for Var<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl PartialOrd for Var<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+//}}}
+//{{{ impl: arithmetic for Var
+impl Add for Var<'_> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        self.binary(rhs, Op::Add, self.value + rhs.value)
+    }
+}
+
+impl Sub for Var<'_> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self.binary(rhs, Op::Sub, self.value - rhs.value)
+    }
+}
+
+impl Mul for Var<'_> {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        self.binary(rhs, Op::Mul, self.value * rhs.value)
+    }
+}
+
+impl Div for Var<'_> {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        self.binary(rhs, Op::Div, self.value / rhs.value)
+    }
+}
+
+impl Neg for Var<'_> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        self.unary(Op::Neg, -self.value)
+    }
+}
+
+impl Add<f64> for Var<'_> {
+    type Output = Self;
+    fn add(self, rhs: f64) -> Self {
+        self + Self::constant(rhs)
+    }
+}
+
+impl Sub<f64> for Var<'_> {
+    type Output = Self;
+    fn sub(self, rhs: f64) -> Self {
+        self - Self::constant(rhs)
+    }
+}
+
+impl Mul<f64> for Var<'_> {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self {
+        self * Self::constant(rhs)
+    }
+}
+
+impl Div<f64> for Var<'_> {
+    type Output = Self;
+    fn div(self, rhs: f64) -> Self {
+        self / Self::constant(rhs)
+    }
+}
+
+impl<'t> Add<Var<'t>> for f64 {
+    type Output = Var<'t>;
+    fn add(self, rhs: Var<'t>) -> Var<'t> {
+        Var::constant(self) + rhs
+    }
+}
+
+impl<'t> Sub<Var<'t>> for f64 {
+    type Output = Var<'t>;
+    fn sub(self, rhs: Var<'t>) -> Var<'t> {
+        Var::constant(self) - rhs
+    }
+}
+
+impl<'t> Mul<Var<'t>> for f64 {
+    type Output = Var<'t>;
+    fn mul(self, rhs: Var<'t>) -> Var<'t> {
+        Var::constant(self) * rhs
+    }
+}
+
+impl<'t> Div<Var<'t>> for f64 {
+    type Output = Var<'t>;
+    fn div(self, rhs: Var<'t>) -> Var<'t> {
+        Var::constant(self) / rhs
+    }
+}
+//}}}
+//{{{ impl: Scalar for Var
+impl Scalar for Var<'_> {
+    fn from_f64(value: f64) -> Self {
+        Self::constant(value)
+    }
+
+    fn value(&self) -> f64 {
+        self.value
+    }
+
+    fn sqrt(self) -> Self {
+        self.unary(Op::Sqrt, self.value.sqrt())
+    }
+
+    fn exp(self) -> Self {
+        self.unary(Op::Exp, self.value.exp())
+    }
+
+    fn ln(self) -> Self {
+        self.unary(Op::Ln, self.value.ln())
+    }
+
+    fn sin(self) -> Self {
+        self.unary(Op::Sin, self.value.sin())
+    }
+
+    fn cos(self) -> Self {
+        self.unary(Op::Cos, self.value.cos())
+    }
+
+    fn tan(self) -> Self {
+        self.unary(Op::Tan, self.value.tan())
+    }
+
+    fn tanh(self) -> Self {
+        self.unary(Op::Tanh, self.value.tanh())
+    }
+
+    fn abs(self) -> Self {
+        self.unary(Op::Abs, self.value.abs())
+    }
+
+    fn powi(self, n: i32) -> Self {
+        let value = self.value.powi(n);
+        match self.tape {
+            Some(tape) => tape.push(Op::Powi(self.index.unwrap(), n), value),
+            None => Self::constant(value),
+        }
+    }
+
+    fn powf(self, p: f64) -> Self {
+        let value = self.value.powf(p);
+        match self.tape {
+            Some(tape) => tape.push(Op::Powf(self.index.unwrap(), p), value),
+            None => Self::constant(value),
+        }
+    }
+}
+//}}}
@@ -0,0 +1,129 @@
+//! Per-variable lower and upper bounds.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use thiserror::Error;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ enum: BoundsError
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Error)]
+pub enum BoundsError {
+    #[error("{lower} lower bounds given for {upper} upper bounds")]
+    LengthMismatch { lower: usize, upper: usize },
+    #[error("Lower bound of variable {index} exceeds its upper bound")]
+    Inverted { index: usize },
+    #[error("Bound of variable {index} is NaN")]
+    NaN { index: usize },
+}
+//}}}
+//{{{ enum: ActiveBound
+/// Bound a variable sits on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ActiveBound {
+    Lower,
+    Upper,
+}
+//}}}
+//{{{ struct: Bounds
+/// Box `l <= x <= u`, where an infinite bound leaves its side of a variable unconstrained.
+///
+/// A variable whose bounds are equal is fixed and is reported as sitting on its lower bound.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bounds {
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+}
+//}}}
+//{{{ impl: Bounds
+impl Bounds {
+    pub fn new(lower: Vec<f64>, upper: Vec<f64>) -> Result<Self, BoundsError> {
+        if lower.len() != upper.len() {
+            return Err(BoundsError::LengthMismatch {
+                lower: lower.len(),
+                upper: upper.len(),
+            });
+        }
+        for (index, (l, u)) in lower.iter().zip(&upper).enumerate() {
+            if l.is_nan() || u.is_nan() {
+                return Err(BoundsError::NaN { index });
+            }
+            if l > u {
+                return Err(BoundsError::Inverted { index });
+            }
+        }
+        Ok(Self { lower, upper })
+    }
+
+    /// `n` variables without any bounds.
+    pub fn unbounded(n: usize) -> Self {
+        Self {
+            lower: vec![f64::NEG_INFINITY; n],
+            upper: vec![f64::INFINITY; n],
+        }
+    }
+
+    /// The same `lower` and `upper` bound on each of `n` variables.
+    pub fn uniform(n: usize, lower: f64, upper: f64) -> Result<Self, BoundsError> {
+        Self::new(vec![lower; n], vec![upper; n])
+    }
+
+    pub fn len(&self) -> usize {
+        self.lower.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lower.is_empty()
+    }
+
+    pub fn lower(&self) -> &[f64] {
+        &self.lower
+    }
+
+    pub fn upper(&self) -> &[f64] {
+        &self.upper
+    }
+
+    /// Clamps `x` into the box.
+    pub fn project(&self, x: &mut [f64]) {
+        for ((xi, l), u) in x.iter_mut().zip(&self.lower).zip(&self.upper) {
+            *xi = xi.clamp(*l, *u);
+        }
+    }
+
+    pub fn contains(&self, x: &[f64]) -> bool {
+        x.iter()
+            .zip(&self.lower)
+            .zip(&self.upper)
+            .all(|((xi, l), u)| l <= xi && xi <= u)
+    }
+
+    /// Projected gradient `P(x − g) − x`, which vanishes exactly at the first-order stationary
+    /// points of the bound-constrained problem.
+    pub fn projected_gradient(&self, x: &[f64], grad: &[f64]) -> Vec<f64> {
+        (0..x.len())
+            .map(|i| (x[i] - grad[i]).clamp(self.lower[i], self.upper[i]) - x[i])
+            .collect()
+    }
+
+    /// The bound each variable of `x` sits on, if any.
+    pub fn active(&self, x: &[f64]) -> Vec<Option<ActiveBound>> {
+        (0..x.len())
+            .map(|i| {
+                if x[i] <= self.lower[i] {
+                    Some(ActiveBound::Lower)
+                } else if x[i] >= self.upper[i] {
+                    Some(ActiveBound::Upper)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+//}}}
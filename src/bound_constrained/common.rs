@@ -0,0 +1,120 @@
+//! Returns and errors shared by the bound-constrained minimizers.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::bounds::ActiveBound;
+use crate::line_search::LineSearchError;
+use crate::unconstrained::{ConvergedReason, IterationState};
+use crate::Budget;
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+use std::time::Duration;
+//}}}
+//{{{ dep imports
+use thiserror::Error;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Returns
+#[derive(Clone, Debug)]
+pub struct Returns<Vector> {
+    pub xmin: Vector,
+    pub fmin: f64,
+    /// Infinity norm of the projected gradient at `xmin`.
+    pub proj_grad_norm: f64,
+    pub reason: ConvergedReason,
+    pub num_iterations: usize,
+    pub num_fun_evals: usize,
+    pub num_grad_evals: usize,
+    /// The bound each variable of `xmin` sits on, if any.
+    pub active: Vec<Option<ActiveBound>>,
+}
+//}}}
+//{{{ impl: Returns
+impl<Vector> Returns<Vector> {
+    /// Indices of the variables sitting on one of their bounds.
+    pub fn active_set(&self) -> Vec<usize> {
+        (0..self.active.len())
+            .filter(|&i| self.active[i].is_some())
+            .collect()
+    }
+}
+//}}}
+//{{{ enum: Error
+/// Failure of a bound-constrained minimizer.
+///
+/// As for the unconstrained minimizers, every variant carries the last accepted iterate as a
+/// partial [`Returns`] with reason [`ConvergedReason::NotConverged`].
+#[derive(Error, Debug)]
+pub enum Error<Vector: Debug> {
+    #[error("Linear search failed with error {source}")]
+    LineSearch {
+        source: LineSearchError,
+        partial: Box<Returns<Vector>>,
+    },
+    #[error("Maximum iterations of {max_iter} reached")]
+    MaxIterations {
+        max_iter: usize,
+        partial: Box<Returns<Vector>>,
+    },
+    #[error("Cancelled")]
+    Cancelled { partial: Box<Returns<Vector>> },
+    #[error("Time limit of {time_limit:?} reached")]
+    TimeLimit {
+        time_limit: Duration,
+        partial: Box<Returns<Vector>>,
+    },
+    #[error("Evaluation budget {budget:?} exhausted")]
+    BudgetExhausted {
+        budget: Budget,
+        partial: Box<Returns<Vector>>,
+    },
+    /// The value or gradient at the (projected) starting point `x` is not finite.
+    #[error("Non-finite value or gradient at the starting point")]
+    NonFiniteValue {
+        x: Vector,
+        partial: Box<Returns<Vector>>,
+    },
+}
+//}}}
+//{{{ impl: Error
+impl<Vector: Debug> Error<Vector> {
+    /// The best point found before the failure.
+    pub fn partial(&self) -> &Returns<Vector> {
+        match self {
+            Error::LineSearch { partial, .. } => partial,
+            Error::MaxIterations { partial, .. } => partial,
+            Error::Cancelled { partial } => partial,
+            Error::TimeLimit { partial, .. } => partial,
+            Error::BudgetExhausted { partial, .. } => partial,
+            Error::NonFiniteValue { partial, .. } => partial,
+        }
+    }
+
+    /// Consumes the error, returning the best point found before the failure.
+    pub fn into_partial(self) -> Returns<Vector> {
+        match self {
+            Error::LineSearch { partial, .. } => *partial,
+            Error::MaxIterations { partial, .. } => *partial,
+            Error::Cancelled { partial } => *partial,
+            Error::TimeLimit { partial, .. } => *partial,
+            Error::BudgetExhausted { partial, .. } => *partial,
+            Error::NonFiniteValue { partial, .. } => *partial,
+        }
+    }
+}
+//}}}
+//{{{ enum: Step
+/// Outcome of a single step of a bound-constrained minimizer, as for
+/// [`crate::unconstrained::Step`].
+#[derive(Debug)]
+pub enum Step<Vector: Debug> {
+    /// An iteration has been completed, `grad_norm` being the infinity norm of the projected
+    /// gradient.
+    Iteration(IterationState<Vector>),
+    /// The minimization has finished with this result, further steps repeat it.
+    Done(Result<Returns<Vector>, Error<Vector>>),
+}
+//}}}
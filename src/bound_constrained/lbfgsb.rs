@@ -0,0 +1,828 @@
+//! Limited-memory BFGS for bound-constrained problems (L-BFGS-B).
+//!
+//! Follows Byrd, Lu, Nocedal and Zhu, "A limited memory algorithm for bound constrained
+//! optimization" (1995). Each iteration finds the generalized Cauchy point along the projected
+//! steepest descent path of the compact L-BFGS model, minimizes the model over the variables
+//! which are still free there, and searches along the resulting feasible direction.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::bounds::Bounds;
+use super::common::{Error, Returns, Step};
+use crate::dense::{dot, norm, norm_inf, Lu, Matrix};
+use crate::line_search::{LineSearchError, LineSearchOptions};
+use crate::unconstrained::{
+    ConvergedReason, ConvergenceState, Criterion, IterationState, Observer, ObserverAction,
+};
+use crate::{Budget, CancelToken, DenseVector, RealFn};
+//}}}
+//{{{ std imports
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Options
+/// Options of L-BFGS-B, whose stopping rules, cancellation, time limit and budget behave as
+/// those of [`crate::unconstrained::UnonstrainedOptions`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Options {
+    /// Number of correction pairs `(s, y)` kept, `m` in Byrd et al.
+    pub memory: usize,
+    /// Stops once `‖P(x − ∇f) − x‖_∞ <= proj_grad_tol`, checked ahead of `criteria`.
+    pub proj_grad_tol: f64,
+    pub max_iter: usize,
+    /// Options of the projected backtracking search, of which `c1` and `step_min` are used.
+    pub ls_opts: LineSearchOptions,
+    /// Maximum number of step reductions in a single line search.
+    pub max_backtracks: usize,
+    /// Stopping criteria checked in addition to `proj_grad_tol`, in which the gradient norms are
+    /// those of the projected gradient. The default is the relative reduction test
+    /// [`Criterion::FRtol`] of Byrd et al.
+    pub criteria: Option<Criterion>,
+    /// Token checked before every evaluation, ending the run with [`Error::Cancelled`] once it
+    /// is cancelled.
+    #[serde(skip)]
+    pub cancel: Option<CancelToken>,
+    /// Wall-clock budget checked before every evaluation, ending the run with
+    /// [`Error::TimeLimit`] once it is used up.
+    pub time_limit: Option<Duration>,
+    /// Limits on function and gradient evaluations, checked before every evaluation. The run
+    /// ends with [`Error::BudgetExhausted`].
+    pub budget: Budget,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            memory: 10,
+            proj_grad_tol: 1e-5,
+            max_iter: 1000,
+            ls_opts: LineSearchOptions::default(),
+            max_backtracks: 20,
+            criteria: Some(Criterion::FRtol(1e7 * f64::EPSILON)),
+            cancel: None,
+            time_limit: None,
+            budget: Budget::default(),
+        }
+    }
+}
+//}}}
+//{{{ struct: Memory
+/// The last few correction pairs and the scaling `θ` of the compact representation
+/// `B = θI − W M Wᵀ`, where `W = [Y, θS]`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Memory {
+    s: VecDeque<Vec<f64>>,
+    y: VecDeque<Vec<f64>>,
+    theta: f64,
+}
+//}}}
+//{{{ impl: Memory
+impl Memory {
+    fn new() -> Self {
+        Self {
+            s: VecDeque::new(),
+            y: VecDeque::new(),
+            theta: 1.0,
+        }
+    }
+
+    /// Number of correction pairs stored.
+    pub fn len(&self) -> usize {
+        self.s.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.s.is_empty()
+    }
+
+    fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Stores the pair unless it would spoil the positive definiteness of `B`, in which case
+    /// `false` is returned.
+    fn push(&mut self, s: Vec<f64>, y: Vec<f64>, capacity: usize) -> bool {
+        let sy = dot(&s, &y);
+        let yy = dot(&y, &y);
+        if sy <= f64::EPSILON * yy || capacity == 0 {
+            return false;
+        }
+        if self.len() == capacity {
+            self.s.pop_front();
+            self.y.pop_front();
+        }
+        self.s.push_back(s);
+        self.y.push_back(y);
+        self.theta = yy / sy;
+        true
+    }
+
+    /// Row `i` of `W`.
+    fn w_row(&self, i: usize) -> Vec<f64> {
+        let theta = self.theta;
+        self.y
+            .iter()
+            .map(|y| y[i])
+            .chain(self.s.iter().map(|s| theta * s[i]))
+            .collect()
+    }
+
+    /// Factorization of `M⁻¹ = [[−D, Lᵀ], [L, θSᵀS]]`, where `D` holds `sᵢᵀyᵢ` and `L` the
+    /// `sᵢᵀyⱼ` with `i > j`.
+    fn middle(&self) -> Option<Lu> {
+        let k = self.len();
+        let mut m = Matrix::zeros(2 * k, 2 * k);
+        for i in 0..k {
+            for j in 0..k {
+                let sy = dot(&self.s[i], &self.y[j]);
+                if i == j {
+                    m[(i, i)] = -sy;
+                } else if i > j {
+                    m[(k + i, j)] = sy;
+                    m[(j, k + i)] = sy;
+                }
+                m[(k + i, k + j)] = self.theta * dot(&self.s[i], &self.s[j]);
+            }
+        }
+        Lu::new(m)
+    }
+}
+//}}}
+//{{{ struct: Cauchy
+/// Generalized Cauchy point `xc` and `c = Wᵀ(xc − x)`.
+struct Cauchy {
+    xc: Vec<f64>,
+    c: Vec<f64>,
+}
+//}}}
+//{{{ struct: Accepted
+/// Point accepted by the line search, with its value and gradient.
+struct Accepted {
+    x: Vec<f64>,
+    f: f64,
+    grad: Vec<f64>,
+    alpha: f64,
+}
+//}}}
+//{{{ struct: State
+/// Snapshot of an L-BFGS-B run taken between two iterations.
+///
+/// Passing it to [`Lbfgsb::resume_from`] together with the same function, bounds and options
+/// continues the run exactly where it stopped, correction pairs included.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    /// Number of completed iterations.
+    pub iteration: usize,
+    pub x: Vec<f64>,
+    pub x_prev: Vec<f64>,
+    pub f: f64,
+    pub f_prev: f64,
+    pub grad: Vec<f64>,
+    /// Euclidean norm of the projected gradient at the starting point.
+    pub grad_norm_init: f64,
+    pub memory: Memory,
+    pub num_fun_evals: usize,
+    pub num_grad_evals: usize,
+    /// Wall-clock time spent up to the snapshot.
+    pub elapsed: Duration,
+}
+//}}}
+//{{{ struct: Iterate
+struct Iterate {
+    iteration: usize,
+    x: Vec<f64>,
+    x_prev: Vec<f64>,
+    f: f64,
+    f_prev: f64,
+    grad: Vec<f64>,
+    grad_norm_init: f64,
+    memory: Memory,
+    /// Step length accepted in the last iteration.
+    alpha: f64,
+    /// Whether the memory was cleared during the last iteration.
+    restarted: bool,
+}
+//}}}
+//{{{ enum: Outcome
+#[derive(Copy, Clone, Debug)]
+enum Outcome {
+    Converged(ConvergedReason),
+    LineSearch(LineSearchError),
+    MaxIterations,
+    Cancelled,
+    TimeLimit(Duration),
+    BudgetExhausted,
+    NonFiniteValue,
+}
+//}}}
+//{{{ struct: Lbfgsb
+/// L-BFGS-B minimizer of a [`RealFn`] subject to [`Bounds`].
+///
+/// The starting point is projected onto the bounds and every point evaluated afterwards lies
+/// within them, so the function never needs to clamp its arguments.
+pub struct Lbfgsb<F: RealFn> {
+    fcn: F,
+    x0: Vec<f64>,
+    bounds: Bounds,
+    opts: Options,
+    observer: Option<Box<dyn Observer<F::Vector>>>,
+    iterate: Option<Iterate>,
+    outcome: Option<Outcome>,
+    num_fun_evals: usize,
+    num_grad_evals: usize,
+    /// Set by the first step, so that the time before it is not charged against the run.
+    start: Option<Instant>,
+    elapsed_init: Duration,
+}
+//}}}
+//{{{ impl: Lbfgsb
+impl<F: RealFn> Lbfgsb<F>
+where
+    F::Vector: DenseVector + Debug,
+{
+    /// # Panics
+    ///
+    /// If `x0` and `bounds` have different lengths.
+    pub fn new(fcn: F, x0: F::Vector, bounds: Bounds, opts: Options) -> Self {
+        assert_eq!(x0.len(), bounds.len(), "starting point and bounds differ in length");
+        let mut x0 = x0.to_vec();
+        bounds.project(&mut x0);
+        Self {
+            fcn,
+            x0,
+            bounds,
+            opts,
+            observer: None,
+            iterate: None,
+            outcome: None,
+            num_fun_evals: 0,
+            num_grad_evals: 0,
+            start: None,
+            elapsed_init: Duration::ZERO,
+        }
+    }
+
+    /// Creates a minimizer which continues the run captured in `state`.
+    ///
+    /// # Panics
+    ///
+    /// If `state.x` and `bounds` have different lengths.
+    pub fn resume_from(fcn: F, state: State, bounds: Bounds, opts: Options) -> Self {
+        let mut lbfgsb = Self::new(fcn, F::Vector::from_slice(&state.x), bounds, opts);
+        lbfgsb.num_fun_evals = state.num_fun_evals;
+        lbfgsb.num_grad_evals = state.num_grad_evals;
+        lbfgsb.elapsed_init = state.elapsed;
+        lbfgsb.iterate = Some(Iterate {
+            iteration: state.iteration,
+            x: state.x,
+            x_prev: state.x_prev,
+            f: state.f,
+            f_prev: state.f_prev,
+            grad: state.grad,
+            grad_norm_init: state.grad_norm_init,
+            memory: state.memory,
+            alpha: 0.0,
+            restarted: false,
+        });
+        lbfgsb
+    }
+
+    /// Snapshot of the last completed iteration, `None` before the starting point has been
+    /// evaluated.
+    pub fn state(&self) -> Option<State> {
+        let it = self.iterate.as_ref()?;
+        Some(State {
+            iteration: it.iteration,
+            x: it.x.clone(),
+            x_prev: it.x_prev.clone(),
+            f: it.f,
+            f_prev: it.f_prev,
+            grad: it.grad.clone(),
+            grad_norm_init: it.grad_norm_init,
+            memory: it.memory.clone(),
+            num_fun_evals: self.num_fun_evals,
+            num_grad_evals: self.num_grad_evals,
+            elapsed: self.elapsed(),
+        })
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    /// Options which can be changed between steps, taking effect from the next iteration.
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    /// Installs an observer which is called after every iteration, replacing any previous one.
+    pub fn set_observer(&mut self, observer: Box<dyn Observer<F::Vector>>) {
+        self.observer = Some(observer);
+    }
+
+    /// Runs a single iteration, continuing from the current state if there is one.
+    pub fn step(&mut self) -> Step<F::Vector> {
+        self.start.get_or_insert_with(Instant::now);
+        if self.outcome.is_none() && self.iterate.is_none() {
+            self.outcome = self.initialize().err();
+        }
+        if let Some(outcome) = self.outcome {
+            return Step::Done(self.result(outcome));
+        }
+        match self.iterate_once() {
+            Ok(state) => Step::Iteration(state),
+            Err(outcome) => {
+                self.outcome = Some(outcome);
+                Step::Done(self.result(outcome))
+            }
+        }
+    }
+
+    /// Runs the minimization to the end, continuing from the current state if there is one.
+    pub fn minimize(&mut self) -> Result<Returns<F::Vector>, Error<F::Vector>> {
+        //{{{ trace
+        error!(target: "lbfgsb", "--- Entering minimize ---");
+        //}}}
+        loop {
+            if let Step::Done(result) = self.step() {
+                return result;
+            }
+        }
+    }
+
+    /// Evaluates the starting point, failing if its value or gradient is not finite or ending
+    /// the run if it is already stationary.
+    fn initialize(&mut self) -> Result<(), Outcome> {
+        let x = self.x0.clone();
+        let (f, grad) = self.evaluate(&x);
+        let proj_grad = self.bounds.projected_gradient(&x, &grad);
+        self.iterate = Some(Iterate {
+            iteration: 0,
+            x_prev: x.clone(),
+            x,
+            f,
+            f_prev: f,
+            grad_norm_init: norm(&proj_grad),
+            grad,
+            memory: Memory::new(),
+            alpha: 0.0,
+            restarted: false,
+        });
+        let it = self.iterate.as_ref().unwrap();
+        if !f.is_finite() || !norm(&it.grad).is_finite() {
+            //{{{ trace
+            info!(target: "lbfgsb", "Non-finite value or gradient at the starting point");
+            //}}}
+            return Err(Outcome::NonFiniteValue);
+        }
+        if norm_inf(&proj_grad) <= self.opts.proj_grad_tol {
+            return Err(Outcome::Converged(ConvergedReason::ProjGrad));
+        }
+        Ok(())
+    }
+
+    /// Wall-clock time spent on the run so far.
+    fn elapsed(&self) -> Duration {
+        self.elapsed_init + self.start.map_or(Duration::ZERO, |start| start.elapsed())
+    }
+
+    /// Checks that the run may go on with `fevals` more function and `gevals` more gradient
+    /// evaluations.
+    fn admit(&self, fevals: usize, gevals: usize) -> Result<(), Outcome> {
+        if self.opts.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Err(Outcome::Cancelled);
+        }
+        if let Some(time_limit) = self.opts.time_limit.filter(|t| self.elapsed() >= *t) {
+            return Err(Outcome::TimeLimit(time_limit));
+        }
+        let budget = self.opts.budget;
+        if !budget.allows(self.num_fun_evals, self.num_grad_evals, fevals, gevals) {
+            //{{{ trace
+            info!(target: "lbfgsb", "Evaluation budget {budget:?} exhausted");
+            //}}}
+            return Err(Outcome::BudgetExhausted);
+        }
+        Ok(())
+    }
+
+    /// Runs a single iteration, recording the outcome if it ends the run.
+    fn iterate_once(&mut self) -> Result<IterationState<F::Vector>, Outcome> {
+        let mut it = self.iterate.take().unwrap();
+        //{{{ trace
+        info!(target: "lbfgsb", "i = {} f = {:1.4e}", it.iteration, it.f);
+        //}}}
+        let stepped = if it.iteration >= self.opts.max_iter {
+            Err(Outcome::MaxIterations)
+        } else {
+            self.iterate(&it.x, it.f, &it.grad, &mut it.memory)
+        };
+        let (accepted, restarted) = match stepped {
+            Ok(stepped) => stepped,
+            Err(outcome) => {
+                self.iterate = Some(it);
+                return Err(outcome);
+            }
+        };
+
+        let s: Vec<f64> = (0..it.x.len()).map(|i| accepted.x[i] - it.x[i]).collect();
+        let y: Vec<f64> = (0..it.x.len()).map(|i| accepted.grad[i] - it.grad[i]).collect();
+        if !it.memory.push(s, y, self.opts.memory) {
+            //{{{ trace
+            debug!(target: "lbfgsb", "Skipping update without positive curvature");
+            //}}}
+        }
+        it.iteration += 1;
+        it.x_prev = std::mem::replace(&mut it.x, accepted.x);
+        it.f_prev = std::mem::replace(&mut it.f, accepted.f);
+        it.grad = accepted.grad;
+        it.alpha = accepted.alpha;
+        it.restarted = restarted;
+        let reason = self.check(&it);
+        let state = IterationState {
+            iteration: it.iteration,
+            x: F::Vector::from_slice(&it.x),
+            f: it.f,
+            grad_norm: norm_inf(&self.bounds.projected_gradient(&it.x, &it.grad)),
+            alpha: it.alpha,
+            direction: None,
+            restarted,
+        };
+        self.iterate = Some(it);
+        self.outcome = reason.map(Outcome::Converged);
+        Ok(state)
+    }
+
+    /// Informs the observer of an iteration and checks the stopping criteria, in which the
+    /// projected gradient stands for the gradient.
+    fn check(&mut self, it: &Iterate) -> Option<ConvergedReason> {
+        let proj_grad = self.bounds.projected_gradient(&it.x, &it.grad);
+        let proj_grad_norm = norm_inf(&proj_grad);
+        if let Some(observer) = self.observer.as_mut() {
+            let state = IterationState {
+                iteration: it.iteration,
+                x: F::Vector::from_slice(&it.x),
+                f: it.f,
+                grad_norm: proj_grad_norm,
+                alpha: it.alpha,
+                direction: None,
+                restarted: it.restarted,
+            };
+            if observer.observe(&state) == ObserverAction::Stop {
+                //{{{ trace
+                info!(target: "lbfgsb", "Stopping at the request of the observer");
+                //}}}
+                return Some(ConvergedReason::ObserverStop);
+            }
+        }
+        if proj_grad_norm <= self.opts.proj_grad_tol {
+            return Some(ConvergedReason::ProjGrad);
+        }
+        let step: Vec<f64> = (0..it.x.len()).map(|i| it.x[i] - it.x_prev[i]).collect();
+        let state = ConvergenceState {
+            iteration: it.iteration,
+            f: it.f,
+            f_prev: it.f_prev,
+            grad_norm: norm(&proj_grad),
+            grad_norm_init: it.grad_norm_init,
+            grad_inf_norm: proj_grad_norm,
+            step_norm: norm(&step),
+            x_norm: norm(&it.x),
+            num_fun_evals: self.num_fun_evals,
+            elapsed: self.elapsed(),
+        };
+        let reason = self.opts.criteria.as_ref().and_then(|c| c.check(&state));
+        //{{{ trace
+        if let Some(reason) = reason {
+            info!(target: "lbfgsb", "Converging with reason {reason:?}");
+        }
+        //}}}
+        reason
+    }
+
+    fn result(&self, outcome: Outcome) -> Result<Returns<F::Vector>, Error<F::Vector>> {
+        let reason = match outcome {
+            Outcome::Converged(reason) => reason,
+            _ => ConvergedReason::NotConverged,
+        };
+        let it = self.iterate.as_ref().unwrap();
+        let returns = self.returns(&it.x, it.f, &it.grad, it.iteration, reason);
+        match outcome {
+            Outcome::Converged(_) => Ok(returns),
+            Outcome::LineSearch(source) => Err(Error::LineSearch {
+                source,
+                partial: Box::new(returns),
+            }),
+            Outcome::MaxIterations => Err(Error::MaxIterations {
+                max_iter: self.opts.max_iter,
+                partial: Box::new(returns),
+            }),
+            Outcome::Cancelled => Err(Error::Cancelled {
+                partial: Box::new(returns),
+            }),
+            Outcome::TimeLimit(time_limit) => Err(Error::TimeLimit {
+                time_limit,
+                partial: Box::new(returns),
+            }),
+            Outcome::BudgetExhausted => Err(Error::BudgetExhausted {
+                budget: self.opts.budget,
+                partial: Box::new(returns),
+            }),
+            Outcome::NonFiniteValue => Err(Error::NonFiniteValue {
+                x: F::Vector::from_slice(&self.x0),
+                partial: Box::new(returns),
+            }),
+        }
+    }
+
+    /// Takes one step from `x`, retrying from a cleared memory (i.e. along the projected
+    /// steepest descent path) if the quasi-Newton direction fails. Whether the memory was
+    /// cleared is returned with the accepted point.
+    fn iterate(
+        &mut self,
+        x: &[f64],
+        f: f64,
+        g: &[f64],
+        memory: &mut Memory,
+    ) -> Result<(Accepted, bool), Outcome> {
+        let mut restarted = false;
+        loop {
+            let result = match self.direction(x, g, memory) {
+                Some(d) => self.search(x, f, g, &d, memory.is_empty()),
+                None => Err(Outcome::LineSearch(LineSearchError::NotDecreasing)),
+            };
+            match result {
+                Err(Outcome::LineSearch(err)) if !memory.is_empty() => {
+                    //{{{ trace
+                    info!(target: "lbfgsb", "Step failed with {err:?}, resetting the memory");
+                    //}}}
+                    memory.clear();
+                    restarted = true;
+                }
+                result => return result.map(|accepted| (accepted, restarted)),
+            }
+        }
+    }
+
+    /// Feasible descent direction `x̄ − x` from the subspace minimizer `x̄` of the model, or
+    /// `None` if the model offers no descent.
+    fn direction(&self, x: &[f64], g: &[f64], memory: &Memory) -> Option<Vec<f64>> {
+        let middle = if memory.is_empty() {
+            None
+        } else {
+            Some(memory.middle()?)
+        };
+        let cauchy = self.cauchy_point(x, g, memory, middle.as_ref());
+        let xbar = self.subspace_min(x, g, memory, middle.as_ref(), cauchy)?;
+        let d: Vec<f64> = (0..x.len()).map(|i| xbar[i] - x[i]).collect();
+        let dg = dot(&d, g);
+        //{{{ trace
+        debug!(target: "lbfgsb", "Directional derivative {dg:1.4e}");
+        //}}}
+        (dg < 0.0).then_some(d)
+    }
+
+    /// Generalized Cauchy point, the first local minimizer of the model along the projected
+    /// steepest descent path `P(x − t g)`, following algorithm CP of Byrd et al.
+    fn cauchy_point(&self, x: &[f64], g: &[f64], memory: &Memory, middle: Option<&Lu>) -> Cauchy {
+        let n = x.len();
+        let theta = memory.theta;
+        let lower = self.bounds.lower();
+        let upper = self.bounds.upper();
+        let apply_m = |v: &[f64]| middle.map_or_else(Vec::new, |lu| lu.solve(v));
+
+        let mut breaks = vec![f64::INFINITY; n];
+        let mut d = vec![0.0; n];
+        for i in 0..n {
+            if g[i] < 0.0 && upper[i] < f64::INFINITY {
+                breaks[i] = (x[i] - upper[i]) / g[i];
+            } else if g[i] > 0.0 && lower[i] > f64::NEG_INFINITY {
+                breaks[i] = (x[i] - lower[i]) / g[i];
+            }
+            if breaks[i] > 0.0 {
+                d[i] = -g[i];
+            }
+        }
+        let mut order: Vec<usize> = (0..n)
+            .filter(|&i| breaks[i] > 0.0 && breaks[i] < f64::INFINITY)
+            .collect();
+        order.sort_by(|&i, &j| breaks[i].total_cmp(&breaks[j]));
+
+        let mut xc = x.to_vec();
+        let mut p = vec![0.0; 2 * memory.len()];
+        for (i, di) in d.iter().enumerate().filter(|(_, di)| **di != 0.0) {
+            for (pj, wj) in p.iter_mut().zip(memory.w_row(i)) {
+                *pj += wj * di;
+            }
+        }
+        let mut c = vec![0.0; p.len()];
+        let mut fp = -dot(&d, &d);
+        let fpp0 = -theta * fp;
+        let mut fpp = fpp0 - dot(&p, &apply_m(&p));
+        let mut dt_min = if fpp > 0.0 { -fp / fpp } else { 0.0 };
+        let mut t_old = 0.0;
+        //{{{ trace
+        debug!(target: "lbfgsb", "{} breakpoints, f' = {fp:1.4e} f'' = {fpp:1.4e}", order.len());
+        //}}}
+
+        for b in order {
+            let dt = breaks[b] - t_old;
+            if dt_min < dt {
+                break;
+            }
+            xc[b] = if d[b] > 0.0 { upper[b] } else { lower[b] };
+            let zb = xc[b] - x[b];
+            let gb = g[b];
+            let wb = memory.w_row(b);
+            for (cj, pj) in c.iter_mut().zip(&p) {
+                *cj += dt * pj;
+            }
+            let wmc = dot(&wb, &apply_m(&c));
+            let wmp = dot(&wb, &apply_m(&p));
+            let wmw = dot(&wb, &apply_m(&wb));
+            fp += dt * fpp + gb * gb + theta * gb * zb - gb * wmc;
+            fpp -= theta * gb * gb + 2.0 * gb * wmp + gb * gb * wmw;
+            fpp = fpp.max(f64::EPSILON * fpp0);
+            for (pj, wj) in p.iter_mut().zip(&wb) {
+                *pj += gb * wj;
+            }
+            d[b] = 0.0;
+            dt_min = if fpp > 0.0 { -fp / fpp } else { 0.0 };
+            t_old = breaks[b];
+        }
+
+        let dt_min = dt_min.max(0.0);
+        let t = t_old + dt_min;
+        for i in 0..n {
+            if d[i] != 0.0 {
+                xc[i] = (x[i] + t * d[i]).clamp(lower[i], upper[i]);
+            }
+        }
+        for (cj, pj) in c.iter_mut().zip(&p) {
+            *cj += dt_min * pj;
+        }
+        Cauchy { xc, c }
+    }
+
+    /// Minimizes the model over the variables free at the Cauchy point by the direct primal
+    /// method of Byrd et al., backtracking the unconstrained minimizer into the box.
+    fn subspace_min(
+        &self,
+        x: &[f64],
+        g: &[f64],
+        memory: &Memory,
+        middle: Option<&Lu>,
+        cauchy: Cauchy,
+    ) -> Option<Vec<f64>> {
+        let Cauchy { mut xc, c } = cauchy;
+        let lower = self.bounds.lower();
+        let upper = self.bounds.upper();
+        let free: Vec<usize> = (0..x.len())
+            .filter(|&i| lower[i] < xc[i] && xc[i] < upper[i])
+            .collect();
+        if free.is_empty() {
+            return Some(xc);
+        }
+        let theta = memory.theta;
+        let rows: Vec<Vec<f64>> = free.iter().map(|&i| memory.w_row(i)).collect();
+
+        // reduced gradient of the model at the Cauchy point
+        let mc = middle.map_or_else(Vec::new, |lu| lu.solve(&c));
+        let r: Vec<f64> = free
+            .iter()
+            .zip(&rows)
+            .map(|(&i, w)| g[i] + theta * (xc[i] - x[i]) - dot(w, &mc))
+            .collect();
+
+        let du: Vec<f64> = match middle {
+            None => r.iter().map(|ri| -ri / theta).collect(),
+            Some(lu) => {
+                let k2 = 2 * memory.len();
+                let mut v = vec![0.0; k2];
+                let mut a = Matrix::zeros(k2, k2);
+                for (w, ri) in rows.iter().zip(&r) {
+                    for p in 0..k2 {
+                        v[p] += w[p] * ri;
+                        for q in 0..k2 {
+                            a[(p, q)] += w[p] * w[q];
+                        }
+                    }
+                }
+                let v = lu.solve(&v);
+                let mut n_mat = Matrix::identity(k2);
+                for q in 0..k2 {
+                    let col: Vec<f64> = (0..k2).map(|p| a[(p, q)]).collect();
+                    for (p, mp) in lu.solve(&col).into_iter().enumerate() {
+                        n_mat[(p, q)] -= mp / theta;
+                    }
+                }
+                let v = Lu::new(n_mat)?.solve(&v);
+                r.iter()
+                    .zip(&rows)
+                    .map(|(ri, w)| -ri / theta - dot(w, &v) / (theta * theta))
+                    .collect()
+            }
+        };
+
+        let mut alpha: f64 = 1.0;
+        for (&i, dui) in free.iter().zip(&du) {
+            if *dui > 0.0 {
+                alpha = alpha.min((upper[i] - xc[i]) / dui);
+            } else if *dui < 0.0 {
+                alpha = alpha.min((lower[i] - xc[i]) / dui);
+            }
+        }
+        //{{{ trace
+        debug!(target: "lbfgsb", "{} free variables, subspace step {alpha:1.4e}", free.len());
+        //}}}
+        for (&i, dui) in free.iter().zip(&du) {
+            xc[i] += alpha * dui;
+        }
+        Some(xc)
+    }
+
+    /// Backtracking search along `x + α d`, which stays within the bounds for `α ∈ [0, 1]`,
+    /// for a step satisfying the Armijo condition.
+    fn search(
+        &mut self,
+        x: &[f64],
+        f: f64,
+        g: &[f64],
+        d: &[f64],
+        steepest: bool,
+    ) -> Result<Accepted, Outcome> {
+        let dg = dot(d, g);
+        let c1 = self.opts.ls_opts.c1;
+        let mut alpha = if steepest { (1.0 / norm(d)).min(1.0) } else { 1.0 };
+        for _ in 0..=self.opts.max_backtracks {
+            let mut xt: Vec<f64> = (0..x.len()).map(|i| x[i] + alpha * d[i]).collect();
+            self.bounds.project(&mut xt);
+            self.admit(1, 1)?;
+            let (ft, gt) = self.evaluate(&xt);
+            //{{{ trace
+            trace!(target: "lbfgsb", "alpha = {alpha:1.4e} f = {ft:1.4e}");
+            //}}}
+            if ft <= f + c1 * alpha * dg && norm(&gt).is_finite() {
+                return Ok(Accepted {
+                    x: xt,
+                    f: ft,
+                    grad: gt,
+                    alpha,
+                });
+            }
+            // minimizer of the quadratic through f, dg and ft, kept within [0.1α, 0.5α]
+            let alpha_q = -dg * alpha * alpha / (2.0 * (ft - f - dg * alpha));
+            alpha = if alpha_q.is_finite() {
+                alpha_q.clamp(0.1 * alpha, 0.5 * alpha)
+            } else {
+                0.5 * alpha
+            };
+            if alpha < self.opts.ls_opts.step_min {
+                return Err(Outcome::LineSearch(LineSearchError::StepSizeSmall));
+            }
+        }
+        Err(Outcome::LineSearch(LineSearchError::MaxIterations))
+    }
+
+    fn evaluate(&mut self, x: &[f64]) -> (f64, Vec<f64>) {
+        self.num_fun_evals += 1;
+        self.num_grad_evals += 1;
+        let (f, g) = self.fcn.eval_with_grad(&F::Vector::from_slice(x));
+        (f, g.to_vec())
+    }
+
+    fn returns(
+        &self,
+        x: &[f64],
+        f: f64,
+        g: &[f64],
+        iter: usize,
+        reason: ConvergedReason,
+    ) -> Returns<F::Vector> {
+        //{{{ trace
+        error!(target: "lbfgsb", "--- Leaving minimize with {reason:?} ---");
+        //}}}
+        Returns {
+            xmin: F::Vector::from_slice(x),
+            fmin: f,
+            proj_grad_norm: norm_inf(&self.bounds.projected_gradient(x, g)),
+            reason,
+            num_iterations: iter,
+            num_fun_evals: self.num_fun_evals,
+            num_grad_evals: self.num_grad_evals,
+            active: self.bounds.active(x),
+        }
+    }
+}
+//}}}
@@ -0,0 +1,22 @@
+//! Minimizers for problems with lower and upper bounds on the variables.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+mod bounds;
+mod common;
+mod lbfgsb;
+
+pub use bounds::{ActiveBound, Bounds, BoundsError};
+pub use common::{
+    Error as BoundConstrainedError, Returns as BoundConstrainedReturns,
+    Step as BoundConstrainedStep,
+};
+pub use lbfgsb::{Lbfgsb, Memory as LbfgsbMemory, Options as LbfgsbOptions, State as LbfgsbState};
@@ -8,10 +8,19 @@
 //{{{ std imports 
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::fmt::Debug;
+use std::ops::{Index, IndexMut, Sub};
+use std::collections::VecDeque;
 //}}}
 //{{{ dep imports 
+use topohedral_linalg::{
+    dvector::{DVector, VecType},
+    scvector::SCVector,
+    VectorOps,
+};
+use serde::{Deserialize, Serialize};
 //}}}
 //--------------------------------------------------------------------------------------------------
 
@@ -21,6 +30,14 @@ pub trait RealFn1 {
 
     fn eval(&mut self, x: f64) -> f64;
     fn diff(&mut self, x: f64) -> f64;
+
+    /// Evaluates the function and its derivative at the same point.
+    ///
+    /// The default calls `eval` and `diff` separately, implementors which can share work between
+    /// the two should override it.
+    fn eval_with_diff(&mut self, x: f64) -> (f64, f64) {
+        (self.eval(x), self.diff(x))
+    }
 }
 //}}}
 //{{{ trait: RealFn
@@ -31,6 +48,69 @@ pub trait RealFn: Clone + Debug {
     fn eval(&mut self, x: &Self::Vector) -> f64;
     fn grad(&mut self, x: &Self::Vector) -> Self::Vector;
 
+    /// Evaluates the function and its gradient at the same point.
+    ///
+    /// The default calls `eval` and `grad` separately, implementors which can share work between
+    /// the two (e.g. a simulation whose adjoint reuses the forward solve) should override it.
+    fn eval_with_grad(&mut self, x: &Self::Vector) -> (f64, Self::Vector) {
+        (self.eval(x), self.grad(x))
+    }
+}
+//}}}
+//{{{ trait: DenseVector
+/// Component-wise access to a dense vector of `f64` values.
+///
+/// Algorithms which need to touch individual entries of a vector (finite differences, bounds,
+/// projections) are written against this trait rather than a concrete vector type.
+pub trait DenseVector: Clone + Index<usize, Output = f64> + IndexMut<usize> {
+
+    /// Creates a vector holding `values`.
+    fn from_slice(values: &[f64]) -> Self;
+
+    /// Number of components in the vector.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copies the components into a `Vec<f64>`.
+    fn to_vec(&self) -> Vec<f64> {
+        (0..self.len()).map(|i| self[i]).collect()
+    }
+
+    /// Overwrites the components with the values in `values`, which must have length `len()`.
+    fn copy_from_slice(&mut self, values: &[f64]) {
+        debug_assert_eq!(values.len(), self.len());
+        for (i, v) in values.iter().enumerate() {
+            self[i] = *v;
+        }
+    }
+}
+//}}}
+//{{{ impl: DenseVector for SCVector<f64, N>
+impl<const N: usize> DenseVector for SCVector<f64, N>
+where
+    [(); N * 1]:,
+{
+    fn from_slice(values: &[f64]) -> Self {
+        SCVector::from_col_slice(values)
+    }
+
+    fn len(&self) -> usize {
+        N
+    }
+}
+//}}}
+//{{{ impl: DenseVector for DVector<f64>
+impl DenseVector for DVector<f64> {
+    fn from_slice(values: &[f64]) -> Self {
+        DVector::from_slice(values, VecType::Col)
+    }
+
+    fn len(&self) -> usize {
+        DVector::len(self)
+    }
 }
 //}}}
 //{{{ impl: RealFn for Rc<RefCell<T>> 
@@ -47,6 +127,10 @@ where
     fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
         self.borrow_mut().grad(x)
     }
+
+    fn eval_with_grad(&mut self, x: &Self::Vector) -> (f64, Self::Vector) {
+        self.borrow_mut().eval_with_grad(x)
+    }
 }
 //}}}
 //{{{ impl: RealFn for Arc<Mutex<T>> 
@@ -63,41 +147,456 @@ where
     fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
         self.lock().unwrap().grad(x)
     }
+
+    fn eval_with_grad(&mut self, x: &Self::Vector) -> (f64, Self::Vector) {
+        self.lock().unwrap().eval_with_grad(x)
+    }
 }
 //}}}
-//{{{ struct: CountingRealFcn
+//{{{ struct: Budget
+/// Optional limits on the number of function and gradient (or derivative) evaluations.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Budget {
+    pub max_fevals: Option<usize>,
+    pub max_gevals: Option<usize>,
+}
+//}}}
+//{{{ impl: Budget
+impl Budget {
+    pub fn new(max_fevals: Option<usize>, max_gevals: Option<usize>) -> Self {
+        Self {
+            max_fevals,
+            max_gevals,
+        }
+    }
+
+    /// Whether `fevals` more function and `gevals` more gradient evaluations fit in the budget
+    /// after `num_fevals` and `num_gevals` have been spent.
+    pub fn allows(&self, num_fevals: usize, num_gevals: usize, fevals: usize, gevals: usize) -> bool {
+        self.max_fevals.is_none_or(|max| num_fevals + fevals <= max)
+            && self.max_gevals.is_none_or(|max| num_gevals + gevals <= max)
+    }
+
+    /// What is left of the budget after `num_fevals` and `num_gevals` have been spent.
+    pub fn remaining(&self, num_fevals: usize, num_gevals: usize) -> Budget {
+        Budget {
+            max_fevals: self.max_fevals.map(|max| max.saturating_sub(num_fevals)),
+            max_gevals: self.max_gevals.map(|max| max.saturating_sub(num_gevals)),
+        }
+    }
+}
+//}}}
+//{{{ struct: CountingRealFn
+/// Wrapper which counts the evaluations of a [`RealFn`] against an optional [`Budget`].
+///
+/// Since [`RealFn`] has no way to refuse an evaluation, the wrapper panics on one which would
+/// exceed the budget. Solvers given the budget check [`allows`](Self::allows) before each
+/// evaluation and stop first.
 #[derive(Clone, Debug)]
-pub(crate) struct CountingRealFn <F: RealFn> {
-    fcn: F, 
-    pub num_func_evals: usize, 
-    pub num_grad_evals: usize, 
+pub struct CountingRealFn<F: RealFn> {
+    fcn: F,
+    pub num_func_evals: usize,
+    pub num_grad_evals: usize,
+    pub budget: Budget,
 }
 //}}}
-//{{{ impl: RealFn for CountingRealFcn
+//{{{ impl: RealFn for CountingRealFn
 impl<F: RealFn> RealFn for CountingRealFn<F> {
 
     type Vector = F::Vector;
 
     fn eval(&mut self, x: &Self::Vector) -> f64 {
-        self.num_func_evals += 1;
+        self.charge(1, 0);
         self.fcn.eval(x)
     }
 
     fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
-        self.num_grad_evals += 1;
+        self.charge(0, 1);
         self.fcn.grad(x)
     }
+
+    fn eval_with_grad(&mut self, x: &Self::Vector) -> (f64, Self::Vector) {
+        self.charge(1, 1);
+        self.fcn.eval_with_grad(x)
+    }
 }
 //}}}
-//{{{ impl: CountingRealFcn
+//{{{ impl: CountingRealFn
 impl<F: RealFn> CountingRealFn<F> {
 
     pub fn new(fcn: F) -> Self  {
-        Self{
-            fcn: fcn, 
-            num_func_evals: 0, 
-            num_grad_evals: 0
+        Self {
+            fcn,
+            num_func_evals: 0,
+            num_grad_evals: 0,
+            budget: Budget::default(),
+        }
+    }
+
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.fcn
+    }
+
+    pub fn into_inner(self) -> F {
+        self.fcn
+    }
+
+    /// Whether `fevals` more function and `gevals` more gradient evaluations fit in the budget.
+    pub fn allows(&self, fevals: usize, gevals: usize) -> bool {
+        self.budget
+            .allows(self.num_func_evals, self.num_grad_evals, fevals, gevals)
+    }
+
+    /// What is left of the budget.
+    pub fn remaining(&self) -> Budget {
+        self.budget.remaining(self.num_func_evals, self.num_grad_evals)
+    }
+
+    /// Counts `fevals` function and `gevals` gradient evaluations, panicking if the budget does
+    /// not allow them.
+    fn charge(&mut self, fevals: usize, gevals: usize) {
+        assert!(self.allows(fevals, gevals), "evaluation budget {:?} exceeded", self.budget);
+        self.num_func_evals += fevals;
+        self.num_grad_evals += gevals;
+    }
+}
+//}}}
+//{{{ struct: CountingRealFn1
+/// [`CountingRealFn`] for a [`RealFn1`], counting values and derivatives against an optional
+/// [`Budget`], in which gradient evaluations stand for derivatives.
+///
+/// Unlike [`CountingRealFn`] it does not panic on an evaluation beyond the budget but returns
+/// NaN without evaluating, which the line searches treat as a failed trial step.
+#[derive(Clone, Debug)]
+pub struct CountingRealFn1<F: RealFn1> {
+    fcn: F,
+    pub num_evals: usize,
+    pub num_diffs: usize,
+    pub budget: Budget,
+}
+//}}}
+//{{{ impl: RealFn1 for CountingRealFn1
+impl<F: RealFn1> RealFn1 for CountingRealFn1<F> {
+    fn eval(&mut self, x: f64) -> f64 {
+        if !self.charge(1, 0) {
+            return f64::NAN;
+        }
+        self.fcn.eval(x)
+    }
+
+    fn diff(&mut self, x: f64) -> f64 {
+        if !self.charge(0, 1) {
+            return f64::NAN;
+        }
+        self.fcn.diff(x)
+    }
+
+    fn eval_with_diff(&mut self, x: f64) -> (f64, f64) {
+        if !self.charge(1, 1) {
+            return (f64::NAN, f64::NAN);
+        }
+        self.fcn.eval_with_diff(x)
+    }
+}
+//}}}
+//{{{ impl: CountingRealFn1
+impl<F: RealFn1> CountingRealFn1<F> {
+    pub fn new(fcn: F) -> Self {
+        Self {
+            fcn,
+            num_evals: 0,
+            num_diffs: 0,
+            budget: Budget::default(),
+        }
+    }
+
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.fcn
+    }
+
+    pub fn into_inner(self) -> F {
+        self.fcn
+    }
+
+    /// Whether `evals` more values and `diffs` more derivatives fit in the budget.
+    pub fn allows(&self, evals: usize, diffs: usize) -> bool {
+        self.budget.allows(self.num_evals, self.num_diffs, evals, diffs)
+    }
+
+    /// What is left of the budget.
+    pub fn remaining(&self) -> Budget {
+        self.budget.remaining(self.num_evals, self.num_diffs)
+    }
+
+    /// Counts `evals` values and `diffs` derivatives if the budget allows them.
+    fn charge(&mut self, evals: usize, diffs: usize) -> bool {
+        if !self.allows(evals, diffs) {
+            return false;
+        }
+        self.num_evals += evals;
+        self.num_diffs += diffs;
+        true
+    }
+}
+//}}}
+//{{{ enum: CachePolicy
+/// Decides whether a requested point matches a cached one.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CachePolicy {
+    /// The points must be identical, `‖x - y‖ = 0`.
+    Exact,
+    /// The points match if `‖x - y‖ <= tol * max(1, ‖x‖)`.
+    Tolerance(f64),
+}
+//}}}
+//{{{ struct: CachedPoint
+/// Serializable copy of an entry of a [`CachingRealFn`], see [`CachingRealFn::entries`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CachedPoint {
+    pub x: Vec<f64>,
+    pub f: Option<f64>,
+    pub grad: Option<Vec<f64>>,
+}
+//}}}
+//{{{ struct: CacheEntry
+#[derive(Clone)]
+struct CacheEntry<V> {
+    x: V,
+    f: Option<f64>,
+    grad: Option<V>,
+}
+//}}}
+//{{{ struct: CachingRealFn
+/// Memoizes the last few `(x, f, ∇f)` triples of the wrapped function.
+///
+/// Entries are kept most-recently-used first and the oldest one is dropped once `capacity` is
+/// exceeded. A capacity of zero disables the cache. Values and gradients are stored
+/// independently, so a request for `∇f` at a point whose value is already known only
+/// evaluates the gradient.
+#[derive(Clone)]
+pub struct CachingRealFn<F: RealFn>
+where
+    F::Vector: Clone,
+{
+    fcn: F,
+    capacity: usize,
+    policy: CachePolicy,
+    entries: VecDeque<CacheEntry<F::Vector>>,
+    /// Number of function values served from the cache.
+    pub num_func_hits: usize,
+    /// Number of gradients served from the cache.
+    pub num_grad_hits: usize,
+}
+//}}}
+//{{{ impl: CachingRealFn
+impl<F: RealFn> CachingRealFn<F>
+where
+    F::Vector: VectorOps<ScalarType = f64> + Sub<Output = F::Vector> + Clone,
+{
+    pub fn new(fcn: F, capacity: usize, policy: CachePolicy) -> Self {
+        Self {
+            fcn,
+            capacity,
+            policy,
+            entries: VecDeque::with_capacity(capacity),
+            num_func_hits: 0,
+            num_grad_hits: 0,
+        }
+    }
+
+    /// The wrapped function.
+    pub fn inner(&self) -> &F {
+        &self.fcn
+    }
+
+    pub fn inner_mut(&mut self) -> &mut F {
+        &mut self.fcn
+    }
+
+    /// Total number of requests served from the cache.
+    pub fn num_hits(&self) -> usize {
+        self.num_func_hits + self.num_grad_hits
+    }
+
+    /// Drops every cached entry.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn matches(&self, x: &F::Vector, y: &F::Vector) -> bool {
+        let dist = (x.clone() - y.clone()).norm();
+        match self.policy {
+            CachePolicy::Exact => dist == 0.0,
+            CachePolicy::Tolerance(tol) => dist <= tol * x.norm().max(1.0),
+        }
+    }
+
+    /// Moves the entry matching `x` to the front, inserting an empty one if there is none, and
+    /// returns it. Must not be called with a zero capacity.
+    fn entry(&mut self, x: &F::Vector) -> &mut CacheEntry<F::Vector> {
+        match self.entries.iter().position(|e| self.matches(x, &e.x)) {
+            Some(pos) => {
+                let entry = self.entries.remove(pos).unwrap();
+                self.entries.push_front(entry);
+            }
+            None => {
+                self.entries.push_front(CacheEntry {
+                    x: x.clone(),
+                    f: None,
+                    grad: None,
+                });
+                self.entries.truncate(self.capacity);
+            }
         }
+        &mut self.entries[0]
+    }
+}
+
+impl<F: RealFn> CachingRealFn<F>
+where
+    F::Vector: DenseVector,
+{
+    /// Copies of the cached entries, most recently used first.
+    pub fn entries(&self) -> Vec<CachedPoint> {
+        self.entries
+            .iter()
+            .map(|e| CachedPoint {
+                x: e.x.to_vec(),
+                f: e.f,
+                grad: e.grad.as_ref().map(DenseVector::to_vec),
+            })
+            .collect()
+    }
+
+    /// Replaces the cached entries with `entries`, most recently used first, of which at most
+    /// `capacity` are kept.
+    pub fn restore(&mut self, entries: &[CachedPoint]) {
+        self.entries = entries
+            .iter()
+            .take(self.capacity)
+            .map(|e| CacheEntry {
+                x: F::Vector::from_slice(&e.x),
+                f: e.f,
+                grad: e.grad.as_deref().map(F::Vector::from_slice),
+            })
+            .collect();
+    }
+}
+//}}}
+//{{{ impl: Debug for CachingRealFn
+impl<F: RealFn> Debug for CachingRealFn<F>
+where
+    F::Vector: Clone,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachingRealFn")
+            .field("fcn", &self.fcn)
+            .field("capacity", &self.capacity)
+            .field("policy", &self.policy)
+            .field("len", &self.entries.len())
+            .field("num_func_hits", &self.num_func_hits)
+            .field("num_grad_hits", &self.num_grad_hits)
+            .finish()
+    }
+}
+//}}}
+//{{{ impl: RealFn for CachingRealFn
+impl<F: RealFn> RealFn for CachingRealFn<F>
+where
+    F::Vector: VectorOps<ScalarType = f64> + Sub<Output = F::Vector> + Clone,
+{
+    type Vector = F::Vector;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        if self.capacity == 0 {
+            return self.fcn.eval(x);
+        }
+        if let Some(f) = self.entry(x).f {
+            self.num_func_hits += 1;
+            return f;
+        }
+        let f = self.fcn.eval(x);
+        self.entries[0].f = Some(f);
+        f
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        if self.capacity == 0 {
+            return self.fcn.grad(x);
+        }
+        if let Some(grad) = self.entry(x).grad.clone() {
+            self.num_grad_hits += 1;
+            return grad;
+        }
+        let grad = self.fcn.grad(x);
+        self.entries[0].grad = Some(grad.clone());
+        grad
+    }
+
+    fn eval_with_grad(&mut self, x: &Self::Vector) -> (f64, Self::Vector) {
+        if self.capacity == 0 {
+            return self.fcn.eval_with_grad(x);
+        }
+        let entry = self.entry(x);
+        let (f, grad) = (entry.f, entry.grad.clone());
+        let (f, grad) = match (f, grad) {
+            (Some(f), Some(grad)) => {
+                self.num_func_hits += 1;
+                self.num_grad_hits += 1;
+                (f, grad)
+            }
+            (Some(f), None) => {
+                self.num_func_hits += 1;
+                (f, self.fcn.grad(x))
+            }
+            (None, Some(grad)) => {
+                self.num_grad_hits += 1;
+                (self.fcn.eval(x), grad)
+            }
+            (None, None) => self.fcn.eval_with_grad(x),
+        };
+        self.entries[0].f = Some(f);
+        self.entries[0].grad = Some(grad.clone());
+        (f, grad)
+    }
+}
+//}}}
+//{{{ struct: CancelToken
+/// Shared flag through which a running minimizer or line search is asked to stop.
+///
+/// Clones share the flag, so a clone kept by e.g. a UI thread can cancel a run started with
+/// another. Solvers check it between evaluations and stop with the best point so far.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+//}}}
+//{{{ impl: CancelToken
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every solver holding a clone of this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clears the flag so that the token can be used for another run.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
     }
 }
 //}}}
@@ -0,0 +1,325 @@
+//! Augmented Lagrangian method.
+//!
+//! Follows the method of multipliers with the Powell-Hestenes-Rockafellar treatment of
+//! inequalities, as in Birgin and Martínez, "Practical augmented Lagrangian methods for
+//! constrained optimization" (2014). Each outer iteration minimizes
+//!
+//! `L_A(x) = f − λᵀc_E + ρ/2 ‖c_E‖² + 1/(2ρ) Σᵢ (max(0, μᵢ − ρ c_I,ᵢ)² − μᵢ²)`
+//!
+//! with a [`Subsolver`], then updates the multipliers from the constraint values at the new
+//! point and increases the penalty `ρ` if the constraints did not improve enough.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::{check_interruptions, Error, KktResiduals, Returns};
+use super::problem::{kkt_residuals, ConstrainedProblem};
+use super::subsolver::Subsolver;
+use crate::dense::{dot, norm_inf};
+use crate::{CancelToken, DenseVector, RealFn};
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Options {
+    /// Stops once the stationarity and complementarity residuals fall below `opt_tol` and the
+    /// constraint violation below `feas_tol`.
+    pub opt_tol: f64,
+    pub feas_tol: f64,
+    /// Maximum number of outer iterations.
+    pub max_iter: usize,
+    /// Initial penalty parameter `ρ`.
+    pub penalty_init: f64,
+    /// Factor by which `ρ` grows when the constraints do not improve enough.
+    pub penalty_factor: f64,
+    /// The method fails once `ρ` would exceed this.
+    pub penalty_max: f64,
+    /// `ρ` is kept if the constraint measure falls by at least this factor in an iteration.
+    pub violation_decrease: f64,
+    /// Gradient tolerance of the first subproblem.
+    pub inner_tol_init: f64,
+    /// Factor by which the subproblem tolerance falls in each iteration, down to `opt_tol`.
+    pub inner_tol_factor: f64,
+    /// Token checked before every outer iteration and handed to the subsolver, ending the run
+    /// with [`Error::Cancelled`] once it is cancelled.
+    #[serde(skip)]
+    pub cancel: Option<CancelToken>,
+    /// Wall-clock budget checked before every outer iteration, whatever is left of it being
+    /// handed to the subsolver. The run ends with [`Error::TimeLimit`].
+    pub time_limit: Option<Duration>,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            opt_tol: 1e-6,
+            feas_tol: 1e-6,
+            max_iter: 50,
+            penalty_init: 10.0,
+            penalty_factor: 10.0,
+            penalty_max: 1e12,
+            violation_decrease: 0.25,
+            inner_tol_init: 1e-2,
+            inner_tol_factor: 0.1,
+            cancel: None,
+            time_limit: None,
+        }
+    }
+}
+//}}}
+//{{{ struct: AugmentedLagrangianFn
+/// The augmented Lagrangian of a problem for fixed multipliers and penalty, as a [`RealFn`].
+#[derive(Clone, Debug)]
+pub struct AugmentedLagrangianFn<P: ConstrainedProblem> {
+    problem: P,
+    lambda: Vec<f64>,
+    mu: Vec<f64>,
+    penalty: f64,
+}
+//}}}
+//{{{ impl: AugmentedLagrangianFn
+impl<P: ConstrainedProblem> AugmentedLagrangianFn<P> {
+    pub fn new(problem: P, lambda: Vec<f64>, mu: Vec<f64>, penalty: f64) -> Self {
+        Self {
+            problem,
+            lambda,
+            mu,
+            penalty,
+        }
+    }
+
+    pub fn problem(&self) -> &P {
+        &self.problem
+    }
+
+    /// Value given the objective and constraint values.
+    fn value(&self, f: f64, eq: &[f64], ineq: &[f64]) -> f64 {
+        let rho = self.penalty;
+        let eq_term = -dot(&self.lambda, eq) + 0.5 * rho * dot(eq, eq);
+        let ineq_term: f64 = ineq
+            .iter()
+            .zip(&self.mu)
+            .map(|(c, m)| (m - rho * c).max(0.0).powi(2) - m * m)
+            .sum();
+        f + eq_term + ineq_term / (2.0 * rho)
+    }
+
+    /// Shifted multipliers `λ − ρ c_E` and `max(0, μ − ρ c_I)`, which are also the first-order
+    /// multiplier estimates at the minimizer of the function.
+    fn shifted(&self, eq: &[f64], ineq: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let rho = self.penalty;
+        let lambda = self.lambda.iter().zip(eq).map(|(l, c)| l - rho * c).collect();
+        let mu = self.mu.iter().zip(ineq).map(|(m, c)| (m - rho * c).max(0.0)).collect();
+        (lambda, mu)
+    }
+}
+//}}}
+//{{{ impl: RealFn for AugmentedLagrangianFn
+impl<P: ConstrainedProblem> RealFn for AugmentedLagrangianFn<P>
+where
+    P::Vector: DenseVector,
+{
+    type Vector = P::Vector;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        let f = self.problem.eval(x);
+        let eq = self.problem.eq(x);
+        let ineq = self.problem.ineq(x);
+        self.value(f, &eq, &ineq)
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        self.eval_with_grad(x).1
+    }
+
+    fn eval_with_grad(&mut self, x: &Self::Vector) -> (f64, Self::Vector) {
+        let f = self.problem.eval(x);
+        let eq = self.problem.eq(x);
+        let ineq = self.problem.ineq(x);
+        let (lambda, mu) = self.shifted(&eq, &ineq);
+        let mut grad = self.problem.grad(x);
+        let jac_eq = self.problem.eq_jac_tr(x, &lambda);
+        let jac_ineq = self.problem.ineq_jac_tr(x, &mu);
+        for i in 0..grad.len() {
+            grad[i] -= jac_eq[i] + jac_ineq[i];
+        }
+        (self.value(f, &eq, &ineq), grad)
+    }
+}
+//}}}
+//{{{ struct: AugmentedLagrangian
+/// Augmented Lagrangian minimizer of a [`ConstrainedProblem`].
+///
+/// Bounds on the variables are best left to a bound-constrained [`Subsolver`], which keeps every
+/// iterate within them, rather than being posed as inequality constraints.
+pub struct AugmentedLagrangian<P: ConstrainedProblem, S> {
+    problem: P,
+    x0: P::Vector,
+    subsolver: S,
+    opts: Options,
+}
+//}}}
+//{{{ impl: AugmentedLagrangian
+impl<P, S> AugmentedLagrangian<P, S>
+where
+    P: ConstrainedProblem,
+    P::Vector: DenseVector + Debug,
+    S: Subsolver<AugmentedLagrangianFn<P>>,
+{
+    pub fn new(problem: P, x0: P::Vector, subsolver: S, opts: Options) -> Self {
+        Self {
+            problem,
+            x0,
+            subsolver,
+            opts,
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    pub fn subsolver(&self) -> &S {
+        &self.subsolver
+    }
+
+    pub fn minimize(&mut self) -> Result<Returns<P::Vector>, Error<P::Vector>> {
+        //{{{ trace
+        error!(target: "al", "--- Entering minimize ---");
+        //}}}
+        let opts = self.opts.clone();
+        let start = Instant::now();
+        let mut x = self.x0.clone();
+        if let Some(bounds) = self.subsolver.bounds() {
+            let mut xv = x.to_vec();
+            bounds.project(&mut xv);
+            x = P::Vector::from_slice(&xv);
+        }
+        let mut ret = Returns {
+            xmin: x.clone(),
+            fmin: self.problem.eval(&x),
+            lambda: vec![0.0; self.problem.num_eq()],
+            mu: vec![0.0; self.problem.num_ineq()],
+            kkt: KktResiduals::default(),
+            penalty: opts.penalty_init,
+            num_iterations: 0,
+            num_inner_iterations: 0,
+            num_fun_evals: 1,
+            num_grad_evals: 0,
+        };
+        let eq = self.problem.eq(&x);
+        let ineq = self.problem.ineq(&x);
+        ret.kkt = self.residuals(&mut ret, &eq, &ineq);
+        let finite = ret.fmin.is_finite()
+            && eq.iter().chain(&ineq).all(|c| c.is_finite())
+            && ret.kkt.stationarity.is_finite();
+        if !finite {
+            //{{{ trace
+            info!(target: "al", "Non-finite value or constraint at the starting point");
+            //}}}
+            return Err(Error::NonFiniteValue {
+                x,
+                partial: Box::new(ret),
+            });
+        }
+
+        let mut measure_prev = f64::INFINITY;
+        let mut inner_tol = opts.inner_tol_init.max(opts.opt_tol);
+        loop {
+            if ret.num_iterations >= opts.max_iter {
+                //{{{ trace
+                info!(target: "al", "Did not converge within {} iterations", opts.max_iter);
+                //}}}
+                return Err(Error::MaxIterations {
+                    max_iter: opts.max_iter,
+                    partial: Box::new(ret),
+                });
+            }
+            check_interruptions(opts.cancel.as_ref(), opts.time_limit, start, &ret)?;
+            let fcn = AugmentedLagrangianFn::new(
+                self.problem.clone(),
+                ret.lambda.clone(),
+                ret.mu.clone(),
+                ret.penalty,
+            );
+            let time_left = opts.time_limit.map(|t| t.saturating_sub(start.elapsed()));
+            self.subsolver.set_interruptions(opts.cancel.as_ref(), time_left);
+            let sub = self.subsolver.minimize(fcn.clone(), ret.xmin.clone(), inner_tol);
+            ret.num_iterations += 1;
+            ret.num_inner_iterations += sub.num_iterations;
+            ret.num_fun_evals += sub.num_fun_evals + 1;
+            ret.num_grad_evals += sub.num_grad_evals;
+            ret.xmin = sub.x;
+            ret.fmin = self.problem.eval(&ret.xmin);
+
+            // the multiplier estimates at the new point replace the old ones
+            let eq = self.problem.eq(&ret.xmin);
+            let ineq = self.problem.ineq(&ret.xmin);
+            let measure = ineq
+                .iter()
+                .zip(&ret.mu)
+                .fold(norm_inf(&eq), |acc: f64, (c, m)| {
+                    acc.max(c.min(m / ret.penalty).abs())
+                });
+            (ret.lambda, ret.mu) = fcn.shifted(&eq, &ineq);
+            ret.kkt = self.residuals(&mut ret, &eq, &ineq);
+            //{{{ trace
+            info!(target: "al", "i = {} f = {:1.4e} ρ = {:1.4e} {:?} inner converged? {}",
+                ret.num_iterations, ret.fmin, ret.penalty, ret.kkt, sub.converged);
+            //}}}
+            let kkt = ret.kkt;
+            if kkt.stationarity <= opts.opt_tol
+                && kkt.complementarity <= opts.opt_tol
+                && kkt.feasibility <= opts.feas_tol
+            {
+                //{{{ trace
+                error!(target: "al", "--- Leaving minimize ---");
+                //}}}
+                return Ok(ret);
+            }
+            if measure > opts.violation_decrease * measure_prev {
+                let penalty = ret.penalty * opts.penalty_factor;
+                if penalty > opts.penalty_max {
+                    //{{{ trace
+                    info!(target: "al", "Penalty limit reached with violation {:1.4e}", kkt.feasibility);
+                    //}}}
+                    return Err(Error::PenaltyLimit {
+                        penalty: opts.penalty_max,
+                        partial: Box::new(ret),
+                    });
+                }
+                ret.penalty = penalty;
+            }
+            measure_prev = measure;
+            inner_tol = (inner_tol * opts.inner_tol_factor).max(opts.opt_tol);
+        }
+    }
+
+    /// KKT residuals at the point and multipliers of `ret`, counting the gradient evaluation.
+    fn residuals(&mut self, ret: &mut Returns<P::Vector>, eq: &[f64], ineq: &[f64]) -> KktResiduals {
+        ret.num_grad_evals += 1;
+        kkt_residuals(
+            &mut self.problem,
+            &ret.xmin,
+            eq,
+            ineq,
+            &ret.lambda,
+            &ret.mu,
+            self.subsolver.bounds(),
+        )
+    }
+}
+//}}}
@@ -0,0 +1,143 @@
+//! Returns and errors shared by the constrained minimizers.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use crate::CancelToken;
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: KktResiduals
+/// Residuals of the first-order (Karush-Kuhn-Tucker) optimality conditions.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct KktResiduals {
+    /// Infinity norm of the gradient of the Lagrangian `∇f − J_Eᵀλ − J_Iᵀμ`, projected onto the
+    /// bounds if there are any.
+    pub stationarity: f64,
+    /// Largest constraint violation `max(‖c_E‖_∞, ‖max(0, −c_I)‖_∞)`.
+    pub feasibility: f64,
+    /// Largest product `|μᵢ c_I,ᵢ|`.
+    pub complementarity: f64,
+}
+//}}}
+//{{{ struct: Returns
+#[derive(Clone, Debug)]
+pub struct Returns<Vector> {
+    pub xmin: Vector,
+    pub fmin: f64,
+    /// Multipliers `λ` of the equality constraints.
+    pub lambda: Vec<f64>,
+    /// Multipliers `μ >= 0` of the inequality constraints.
+    pub mu: Vec<f64>,
+    pub kkt: KktResiduals,
+    /// Penalty parameter in use at the end.
+    pub penalty: f64,
+    /// Number of outer iterations.
+    pub num_iterations: usize,
+    /// Total number of iterations of the subproblem solver.
+    pub num_inner_iterations: usize,
+    pub num_fun_evals: usize,
+    pub num_grad_evals: usize,
+}
+//}}}
+//{{{ enum: Error
+/// Failure of a constrained minimizer.
+///
+/// Every variant carries the last outer iterate as a partial [`Returns`].
+#[derive(Error, Debug)]
+pub enum Error<Vector: Debug> {
+    #[error("Maximum iterations of {max_iter} reached")]
+    MaxIterations {
+        max_iter: usize,
+        partial: Box<Returns<Vector>>,
+    },
+    /// The penalty parameter grew beyond its limit without the constraints being satisfied,
+    /// which usually means that the problem is locally infeasible.
+    #[error("Penalty parameter exceeded {penalty:e} without reaching feasibility")]
+    PenaltyLimit {
+        penalty: f64,
+        partial: Box<Returns<Vector>>,
+    },
+    /// No step along the search direction decreased the merit function enough.
+    #[error("Line search on the merit function failed")]
+    LineSearch { partial: Box<Returns<Vector>> },
+    /// The subproblem giving the step, a quadratic program or a linear system, could not be
+    /// solved, even with the Hessian approximation reset. For the penalty method, the subsolver
+    /// did not converge at a feasible point where neither the penalty nor its smoothing changes.
+    #[error("Step subproblem could not be solved")]
+    Subproblem { partial: Box<Returns<Vector>> },
+    #[error("Cancelled")]
+    Cancelled { partial: Box<Returns<Vector>> },
+    #[error("Time limit of {time_limit:?} reached")]
+    TimeLimit {
+        time_limit: Duration,
+        partial: Box<Returns<Vector>>,
+    },
+    /// The value, gradient or constraints at the starting point `x` are not finite.
+    #[error("Non-finite value or constraint at the starting point")]
+    NonFiniteValue {
+        x: Vector,
+        partial: Box<Returns<Vector>>,
+    },
+}
+//}}}
+//{{{ impl: Error
+impl<Vector: Debug> Error<Vector> {
+    /// The last iterate before the failure.
+    pub fn partial(&self) -> &Returns<Vector> {
+        match self {
+            Error::MaxIterations { partial, .. } => partial,
+            Error::PenaltyLimit { partial, .. } => partial,
+            Error::LineSearch { partial } => partial,
+            Error::Subproblem { partial } => partial,
+            Error::Cancelled { partial } => partial,
+            Error::TimeLimit { partial, .. } => partial,
+            Error::NonFiniteValue { partial, .. } => partial,
+        }
+    }
+
+    /// Consumes the error, returning the last iterate before the failure.
+    pub fn into_partial(self) -> Returns<Vector> {
+        match self {
+            Error::MaxIterations { partial, .. } => *partial,
+            Error::PenaltyLimit { partial, .. } => *partial,
+            Error::LineSearch { partial } => *partial,
+            Error::Subproblem { partial } => *partial,
+            Error::Cancelled { partial } => *partial,
+            Error::TimeLimit { partial, .. } => *partial,
+            Error::NonFiniteValue { partial, .. } => *partial,
+        }
+    }
+}
+//}}}
+//{{{ fun: check_interruptions
+/// Checks `cancel` and then `time_limit` for a run started at `start`, ending the run with `ret`
+/// as its partial result once either has been reached.
+pub(super) fn check_interruptions<Vector: Clone + Debug>(
+    cancel: Option<&CancelToken>,
+    time_limit: Option<Duration>,
+    start: Instant,
+    ret: &Returns<Vector>,
+) -> Result<(), Error<Vector>> {
+    if cancel.is_some_and(CancelToken::is_cancelled) {
+        return Err(Error::Cancelled {
+            partial: Box::new(ret.clone()),
+        });
+    }
+    if let Some(time_limit) = time_limit.filter(|t| start.elapsed() >= *t) {
+        return Err(Error::TimeLimit {
+            time_limit,
+            partial: Box::new(ret.clone()),
+        });
+    }
+    Ok(())
+}
+//}}}
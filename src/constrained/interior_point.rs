@@ -0,0 +1,509 @@
+//! Primal-dual interior point method for small dense nonlinear programs.
+//!
+//! Following IPOPT (Wächter and Biegler, 2006), the inequalities are turned into equalities with
+//! slacks, `c_I(x) − s = 0`, and the bounds `s >= 0` into the log barrier, giving the subproblem
+//!
+//! `min f(x) − τ Σ ln sᵢ` subject to `c_E(x) = 0` and `c_I(x) − s = 0`
+//!
+//! whose primal-dual optimality conditions, `Sz = τe` standing in for complementarity, are
+//! solved approximately by Newton steps. The barrier parameter `τ` then decreases by the monotone
+//! Fiacco-McCormick rule `τ ← max(τ_min, min(κτ, τ^θ))`. Steps keep the slacks and their
+//! multipliers `z` strictly positive by the fraction-to-boundary rule and are globalized by a
+//! backtracking line search on the l1 merit function
+//! `f − τ Σ ln sᵢ + ν (‖c_E‖₁ + ‖c_I − s‖₁)`.
+//!
+//! The Hessian of the Lagrangian is approximated by damped BFGS updates, so that the Newton
+//! matrix has the correct inertia whenever the Jacobian of the equalities has full rank, and is
+//! otherwise regularized. As in [`super::Sqp`], the Jacobians are formed from one product with
+//! the transposed Jacobian per constraint, so the method is meant for few variables and
+//! constraints.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::{check_interruptions, Error, KktResiduals, Returns};
+use super::problem::{violation, ConstrainedProblem};
+use super::sqp::{column_dot, damped_bfgs_update, jacobian, reduce_step};
+use crate::dense::{dot, norm_inf, Lu, Matrix};
+use crate::line_search::utils::satisfies_armijo;
+use crate::{CancelToken, DenseVector};
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+/// Relative distance by which the initial slacks are pushed away from zero.
+const SLACK_PUSH: f64 = 1e-2;
+/// Largest ratio between `zᵢ sᵢ` and the barrier parameter kept by the multiplier safeguard.
+const MULTIPLIER_SAFEGUARD: f64 = 1e10;
+/// Number of increases of the regularization of a singular Newton matrix.
+const MAX_REGULARIZATIONS: usize = 4;
+
+//{{{ struct: Options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Options {
+    /// Stops once the stationarity and complementarity residuals fall below `opt_tol` and the
+    /// constraint violation below `feas_tol`.
+    pub opt_tol: f64,
+    pub feas_tol: f64,
+    /// Maximum number of iterations.
+    pub max_iter: usize,
+    /// Initial barrier parameter.
+    pub barrier_init: f64,
+    /// Factor `κ` of the linear decrease of the barrier parameter.
+    pub barrier_factor: f64,
+    /// Power `θ` of the superlinear decrease of the barrier parameter.
+    pub barrier_power: f64,
+    /// The barrier parameter decreases once the error of its subproblem is below
+    /// `barrier_tol_factor` times its value.
+    pub barrier_tol_factor: f64,
+    /// Smallest fraction of the distance to the boundary a step may cover, used once the barrier
+    /// parameter falls below `1 − boundary_fraction`.
+    pub boundary_fraction: f64,
+    /// Sufficient decrease constant of the Armijo condition on the merit function.
+    pub c1: f64,
+    /// Maximum number of step reductions in a line search.
+    pub max_backtracks: usize,
+    /// Token checked before every iteration and every trial point of the line search, ending
+    /// the run with [`Error::Cancelled`] once it is cancelled.
+    #[serde(skip)]
+    pub cancel: Option<CancelToken>,
+    /// Wall-clock budget checked before every iteration and every trial point of the line
+    /// search, ending the run with [`Error::TimeLimit`].
+    pub time_limit: Option<Duration>,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            opt_tol: 1e-6,
+            feas_tol: 1e-6,
+            max_iter: 200,
+            barrier_init: 0.1,
+            barrier_factor: 0.2,
+            barrier_power: 1.5,
+            barrier_tol_factor: 10.0,
+            boundary_fraction: 0.99,
+            c1: 1e-4,
+            max_backtracks: 40,
+            cancel: None,
+            time_limit: None,
+        }
+    }
+}
+//}}}
+//{{{ struct: Point
+/// Values at a primal iterate.
+#[derive(Clone, Debug)]
+struct Point {
+    x: Vec<f64>,
+    s: Vec<f64>,
+    f: f64,
+    eq: Vec<f64>,
+    ineq: Vec<f64>,
+}
+//}}}
+//{{{ impl: Point
+impl Point {
+    /// The l1 norm of the residuals `c_E` and `c_I − s` of the barrier subproblem.
+    fn infeasibility(&self) -> f64 {
+        let eq: f64 = self.eq.iter().map(|c| c.abs()).sum();
+        let ineq: f64 = self.ineq.iter().zip(&self.s).map(|(c, s)| (c - s).abs()).sum();
+        eq + ineq
+    }
+
+    /// Merit function of the barrier subproblem with barrier parameter `tau` and penalty `nu`.
+    fn merit(&self, tau: f64, nu: f64) -> f64 {
+        let barrier: f64 = self.s.iter().map(|s| s.ln()).sum();
+        self.f - tau * barrier + nu * self.infeasibility()
+    }
+}
+//}}}
+//{{{ struct: Step
+/// Newton step of the primal-dual system.
+#[derive(Clone, Debug)]
+struct Step {
+    dx: Vec<f64>,
+    ds: Vec<f64>,
+    dlambda: Vec<f64>,
+    dy: Vec<f64>,
+    dz: Vec<f64>,
+    /// `dxᵀ(B + J_IᵀΣJ_I)dx`, the curvature of the condensed Newton matrix along the step.
+    curvature: f64,
+}
+//}}}
+//{{{ struct: InteriorPoint
+/// Primal-dual interior point minimizer of a [`ConstrainedProblem`].
+///
+/// The inequalities are only met in the limit, through the slacks, so bounds on the variables
+/// are posed as inequality constraints. The inner iterations counted in [`Returns`] are the
+/// factorizations of the Newton matrix and the penalty is the weight `ν` of the merit function.
+pub struct InteriorPoint<P: ConstrainedProblem> {
+    problem: P,
+    x0: P::Vector,
+    opts: Options,
+}
+//}}}
+//{{{ impl: InteriorPoint
+impl<P> InteriorPoint<P>
+where
+    P: ConstrainedProblem,
+    P::Vector: DenseVector + Debug,
+{
+    pub fn new(problem: P, x0: P::Vector, opts: Options) -> Self {
+        Self { problem, x0, opts }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    pub fn minimize(&mut self) -> Result<Returns<P::Vector>, Error<P::Vector>> {
+        //{{{ trace
+        error!(target: "ipm", "--- Entering minimize ---");
+        //}}}
+        let opts = self.opts.clone();
+        let start = Instant::now();
+        let n = self.x0.len();
+        let tau_min = opts.opt_tol.min(opts.feas_tol) / 10.0;
+        let mut point = self.point(self.x0.to_vec(), Vec::new());
+        point.s = point.ineq.iter().map(|c| c.max(SLACK_PUSH * c.abs().max(1.0))).collect();
+        let mut grad = self.problem.grad(&P::Vector::from_slice(&point.x)).to_vec();
+        let num_ineq = point.ineq.len();
+        let mut lambda = vec![0.0; point.eq.len()];
+        let mut y = vec![1.0; num_ineq];
+        let mut z = vec![1.0; num_ineq];
+        let mut ret = Returns {
+            xmin: P::Vector::from_slice(&point.x),
+            fmin: point.f,
+            lambda: lambda.clone(),
+            mu: z.clone(),
+            kkt: KktResiduals::default(),
+            penalty: 0.0,
+            num_iterations: 0,
+            num_inner_iterations: 0,
+            num_fun_evals: 1,
+            num_grad_evals: 1,
+        };
+        let finite = point.f.is_finite()
+            && point.eq.iter().chain(&point.ineq).all(|c| c.is_finite())
+            && grad.iter().all(|g| g.is_finite());
+        if !finite {
+            //{{{ trace
+            info!(target: "ipm", "Non-finite value or constraint at the starting point");
+            //}}}
+            return Err(Error::NonFiniteValue {
+                x: ret.xmin.clone(),
+                partial: Box::new(ret),
+            });
+        }
+
+        let mut tau = opts.barrier_init;
+        let mut nu = 0.0;
+        let mut hess = Matrix::identity(n);
+        //{{{ trace
+        info!(target: "ipm", "Initial values upon entry: ");
+        info!(target: "ipm", "f0 = {:1.4e} violation0 = {:1.4e} tau0 = {tau:1.4e}",
+            point.f, violation(&point.eq, &point.ineq));
+        //}}}
+        loop {
+            let x = P::Vector::from_slice(&point.x);
+            let jac_eq = jacobian(point.eq.len(), |e| self.problem.eq_jac_tr(&x, e).to_vec());
+            let jac_ineq = jacobian(num_ineq, |e| self.problem.ineq_jac_tr(&x, e).to_vec());
+            let lagrangian = |mult: &[f64]| -> Vec<f64> {
+                (0..n)
+                    .map(|j| grad[j] - column_dot(&jac_eq, j, &lambda) - column_dot(&jac_ineq, j, mult))
+                    .collect()
+            };
+            let r_x = lagrangian(&y);
+            ret.kkt = KktResiduals {
+                stationarity: norm_inf(&lagrangian(&z)),
+                feasibility: violation(&point.eq, &point.ineq),
+                complementarity: point
+                    .ineq
+                    .iter()
+                    .zip(&z)
+                    .fold(0.0, |acc: f64, (c, z)| acc.max((c * z).abs())),
+            };
+            ret.lambda = lambda.clone();
+            ret.mu = z.clone();
+            ret.penalty = nu;
+
+            // primal-dual error of the barrier subproblem, all but the complementarity being
+            // independent of the barrier parameter
+            let r_ineq: Vec<f64> = point.ineq.iter().zip(&point.s).map(|(c, s)| c - s).collect();
+            let fixed_error = norm_inf(&r_x)
+                .max(y.iter().zip(&z).fold(0.0, |acc: f64, (y, z)| acc.max((y - z).abs())))
+                .max(norm_inf(&point.eq))
+                .max(norm_inf(&r_ineq));
+            let barrier_error = |tau: f64| {
+                let compl = point.s.iter().zip(&z).fold(0.0, |acc: f64, (s, z)| acc.max((s * z - tau).abs()));
+                fixed_error.max(compl)
+            };
+            //{{{ trace
+            info!(target: "ipm", "======================================================================== i = {}", ret.num_iterations);
+            info!(target: "ipm", "Current values fk = {:1.4e} tau = {tau:1.4e} nu = {nu:1.4e}", point.f);
+            info!(target: "ipm","Convergence measures:");
+            info!(target: "ipm", "\tstationarity = {:1.4e}", ret.kkt.stationarity);
+            info!(target: "ipm", "\tfeasibility = {:1.4e}", ret.kkt.feasibility);
+            info!(target: "ipm", "\tcomplementarity = {:1.4e}", ret.kkt.complementarity);
+            info!(target: "ipm", "\tbarrier error = {:1.4e}", barrier_error(tau));
+            //}}}
+            let kkt = ret.kkt;
+            if kkt.stationarity <= opts.opt_tol
+                && kkt.complementarity <= opts.opt_tol
+                && kkt.feasibility <= opts.feas_tol
+            {
+                //{{{ trace
+                info!(target: "ipm", "Converging with residuals {kkt:?}");
+                error!(target: "ipm", "--- Leaving minimize ---");
+                //}}}
+                return Ok(ret);
+            }
+            if ret.num_iterations >= opts.max_iter {
+                //{{{ trace
+                info!(target: "ipm", "Did not converge within {} iterations", opts.max_iter);
+                //}}}
+                return Err(Error::MaxIterations {
+                    max_iter: opts.max_iter,
+                    partial: Box::new(ret),
+                });
+            }
+            check_interruptions(opts.cancel.as_ref(), opts.time_limit, start, &ret)?;
+            while tau > tau_min && barrier_error(tau) <= opts.barrier_tol_factor * tau {
+                tau = tau_min.max((opts.barrier_factor * tau).min(tau.powf(opts.barrier_power)));
+                //{{{ trace
+                info!(target: "ipm", "\tDecreasing the barrier parameter to tau = {tau:1.4e}");
+                //}}}
+            }
+
+            let Some(step) = self.newton_step(&mut ret, &hess, &point, &jac_eq, &jac_ineq, &r_x, &y, &z, tau)
+            else {
+                //{{{ trace
+                info!(target: "ipm", "Newton matrix is singular despite the regularization");
+                //}}}
+                return Err(Error::Subproblem {
+                    partial: Box::new(ret),
+                });
+            };
+            ret.num_iterations += 1;
+
+            // fraction-to-boundary rule for the slacks and their multipliers
+            let boundary = opts.boundary_fraction.max(1.0 - tau);
+            let alpha_max = max_step(&point.s, &step.ds, boundary);
+            let alpha_z = max_step(&z, &step.dz, boundary);
+
+            // the penalty makes the step a descent direction of the merit function
+            let theta = point.infeasibility();
+            let barrier_slope: f64 = point.s.iter().zip(&step.ds).map(|(s, ds)| ds / s).sum();
+            let dphi_barrier = dot(&grad, &step.dx) - tau * barrier_slope;
+            if theta > 0.0 {
+                let nu_trial = (dphi_barrier + 0.5 * step.curvature) / (0.9 * theta);
+                if nu < nu_trial {
+                    nu = 2.0 * nu_trial;
+                }
+            }
+            let phi0 = point.merit(tau, nu);
+            let dphi0 = dphi_barrier - nu * theta;
+            //{{{ trace
+            debug!(target: "ipm", "alpha_max = {alpha_max:1.4e} alpha_z = {alpha_z:1.4e} dphi0 = {dphi0:1.4e}");
+            //}}}
+            if dphi0 >= 0.0 {
+                if hess != Matrix::identity(n) {
+                    hess = Matrix::identity(n);
+                    continue;
+                }
+                //{{{ trace
+                info!(target: "ipm", "Step is not a descent direction of the merit function");
+                //}}}
+                return Err(Error::LineSearch {
+                    partial: Box::new(ret),
+                });
+            }
+
+            let mut alpha = alpha_max;
+            let mut accepted = None;
+            for _ in 0..=opts.max_backtracks {
+                check_interruptions(opts.cancel.as_ref(), opts.time_limit, start, &ret)?;
+                let xt = point.x.iter().zip(&step.dx).map(|(x, d)| x + alpha * d).collect();
+                let st = point.s.iter().zip(&step.ds).map(|(s, d)| s + alpha * d).collect();
+                let trial = self.point(xt, st);
+                ret.num_fun_evals += 1;
+                let phi = trial.merit(tau, nu);
+                //{{{ trace
+                trace!(target: "ipm", "alpha = {:1.4e} merit = {:1.4e}", alpha, phi);
+                //}}}
+                if phi.is_finite() && satisfies_armijo(opts.c1, alpha, phi0, dphi0, phi) {
+                    accepted = Some(trial);
+                    break;
+                }
+                alpha = reduce_step(alpha, phi0, dphi0, phi);
+            }
+            let Some(trial) = accepted else {
+                if hess != Matrix::identity(n) {
+                    hess = Matrix::identity(n);
+                    continue;
+                }
+                //{{{ trace
+                info!(target: "ipm", "Line search failed after {} step reductions", opts.max_backtracks);
+                //}}}
+                return Err(Error::LineSearch {
+                    partial: Box::new(ret),
+                });
+            };
+
+            for (l, dl) in lambda.iter_mut().zip(&step.dlambda) {
+                *l += alpha * dl;
+            }
+            for (yi, dy) in y.iter_mut().zip(&step.dy) {
+                *yi += alpha * dy;
+            }
+            // the safeguard keeps Σ = S⁻¹Z from drifting far from its value at the central path
+            for ((zi, dz), s) in z.iter_mut().zip(&step.dz).zip(&trial.s) {
+                let z_new = *zi + alpha_z * dz;
+                *zi = z_new.clamp(tau / (MULTIPLIER_SAFEGUARD * s), MULTIPLIER_SAFEGUARD * tau / s);
+            }
+
+            // damped BFGS update from the change in the gradient of the Lagrangian
+            let xt = P::Vector::from_slice(&trial.x);
+            let grad_t = self.problem.grad(&xt).to_vec();
+            ret.num_grad_evals += 1;
+            let jac_eq_t = self.problem.eq_jac_tr(&xt, &lambda);
+            let jac_ineq_t = self.problem.ineq_jac_tr(&xt, &y);
+            let s: Vec<f64> = trial.x.iter().zip(&point.x).map(|(a, b)| a - b).collect();
+            let yk: Vec<f64> = (0..n)
+                .map(|i| {
+                    let lag_t = grad_t[i] - jac_eq_t[i] - jac_ineq_t[i];
+                    let lag = grad[i] - column_dot(&jac_eq, i, &lambda) - column_dot(&jac_ineq, i, &y);
+                    lag_t - lag
+                })
+                .collect();
+            damped_bfgs_update(&mut hess, &s, &yk);
+
+            point = trial;
+            grad = grad_t;
+            ret.xmin = xt;
+            ret.fmin = point.f;
+        }
+    }
+
+    /// Objective and constraint values at `x`, with slacks `s`.
+    fn point(&mut self, x: Vec<f64>, s: Vec<f64>) -> Point {
+        let xv = P::Vector::from_slice(&x);
+        Point {
+            f: self.problem.eval(&xv),
+            eq: self.problem.eq(&xv),
+            ineq: self.problem.ineq(&xv),
+            x,
+            s,
+        }
+    }
+
+    /// Newton step of the primal-dual system with barrier parameter `tau`.
+    ///
+    /// Eliminating the slacks and the multipliers of the inequalities leaves the symmetric system
+    ///
+    /// `[B + J_IᵀΣJ_I, J_Eᵀ; J_E, −δI] [dx; −dλ] = [−r_x − J_Iᵀ(y − τS⁻¹e + Σr_I); −c_E]`
+    ///
+    /// with `Σ = S⁻¹Z`, `r_x` the gradient of the Lagrangian and `r_I = c_I − s`. The
+    /// regularization `δ` is only introduced when the matrix is singular, which happens when the
+    /// Jacobian of the equalities is rank deficient. Returns `None` if it stays singular.
+    #[allow(clippy::too_many_arguments)]
+    fn newton_step(
+        &self,
+        ret: &mut Returns<P::Vector>,
+        hess: &Matrix,
+        point: &Point,
+        jac_eq: &Matrix,
+        jac_ineq: &Matrix,
+        r_x: &[f64],
+        y: &[f64],
+        z: &[f64],
+        tau: f64,
+    ) -> Option<Step> {
+        let n = point.x.len();
+        let num_eq = point.eq.len();
+        let num_ineq = point.ineq.len();
+        let sigma: Vec<f64> = z.iter().zip(&point.s).map(|(z, s)| z / s).collect();
+        let r_ineq: Vec<f64> = point.ineq.iter().zip(&point.s).map(|(c, s)| c - s).collect();
+        let v: Vec<f64> = (0..num_ineq)
+            .map(|k| y[k] - tau / point.s[k] + sigma[k] * r_ineq[k])
+            .collect();
+
+        let mut condensed = hess.clone();
+        for i in 0..n {
+            for j in 0..n {
+                condensed[(i, j)] += (0..num_ineq)
+                    .map(|k| jac_ineq[(k, i)] * sigma[k] * jac_ineq[(k, j)])
+                    .sum::<f64>();
+            }
+        }
+        let mut kkt = Matrix::zeros(n + num_eq, n + num_eq);
+        for i in 0..n {
+            for j in 0..n {
+                kkt[(i, j)] = condensed[(i, j)];
+            }
+            for k in 0..num_eq {
+                kkt[(i, n + k)] = jac_eq[(k, i)];
+                kkt[(n + k, i)] = jac_eq[(k, i)];
+            }
+        }
+        let mut rhs: Vec<f64> = (0..n).map(|j| -r_x[j] - column_dot(jac_ineq, j, &v)).collect();
+        rhs.extend(point.eq.iter().map(|c| -c));
+
+        let mut delta = 0.0;
+        let mut lu = None;
+        for _ in 0..=MAX_REGULARIZATIONS {
+            let mut reg = kkt.clone();
+            for k in 0..num_eq {
+                reg[(n + k, n + k)] = -delta;
+            }
+            ret.num_inner_iterations += 1;
+            lu = Lu::new(reg);
+            if lu.is_some() {
+                break;
+            }
+            delta = if delta == 0.0 { 1e-8 * tau.powf(0.25) } else { 100.0 * delta };
+            //{{{ trace
+            debug!(target: "ipm", "Singular Newton matrix, regularizing with delta = {delta:1.4e}");
+            //}}}
+        }
+        let sol = lu?.solve(&rhs);
+        let dx = sol[..n].to_vec();
+        let dlambda: Vec<f64> = sol[n..].iter().map(|u| -u).collect();
+        let ds: Vec<f64> = jac_ineq.mul_vec(&dx).iter().zip(&r_ineq).map(|(j, r)| j + r).collect();
+        let dz: Vec<f64> = (0..num_ineq)
+            .map(|k| tau / point.s[k] - z[k] - sigma[k] * ds[k])
+            .collect();
+        let dy: Vec<f64> = (0..num_ineq).map(|k| dz[k] + z[k] - y[k]).collect();
+        let curvature = dot(&dx, &condensed.mul_vec(&dx));
+        Some(Step {
+            dx,
+            ds,
+            dlambda,
+            dy,
+            dz,
+            curvature,
+        })
+    }
+}
+//}}}
+//{{{ fun: max_step
+/// Largest step `α <= 1` keeping `v + α dv >= (1 − fraction) v`, for `v > 0`.
+fn max_step(v: &[f64], dv: &[f64], fraction: f64) -> f64 {
+    v.iter()
+        .zip(dv)
+        .filter(|(_, d)| **d < 0.0)
+        .fold(1.0, |alpha: f64, (v, d)| alpha.min(-fraction * v / d))
+}
+//}}}
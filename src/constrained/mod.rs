@@ -0,0 +1,32 @@
+//! Minimizers for problems with nonlinear equality and inequality constraints.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+mod augmented_lagrangian;
+mod common;
+mod interior_point;
+mod penalty;
+mod problem;
+mod sqp;
+mod subsolver;
+
+pub use augmented_lagrangian::{
+    AugmentedLagrangian, AugmentedLagrangianFn, Options as AugmentedLagrangianOptions,
+};
+pub use common::{Error as ConstrainedError, KktResiduals, Returns as ConstrainedReturns};
+pub use interior_point::{InteriorPoint, Options as InteriorPointOptions};
+pub use penalty::{Options as PenaltyOptions, Penalty, PenaltyFn, PenaltyMethod};
+pub use problem::ConstrainedProblem;
+pub use sqp::{Options as SqpOptions, Sqp};
+pub use subsolver::{
+    BoundConstrainedSubsolver, ConjugateGradientSubsolver, Subsolver, SubsolverReturns,
+    UnconstrainedSubsolver,
+};
@@ -0,0 +1,348 @@
+//! Quadratic and l1 penalty methods.
+//!
+//! Each outer iteration minimizes the objective plus a penalty on the constraint violation with
+//! a [`Subsolver`], starting from the previous solution, and increases the penalty parameter `ρ`
+//! while the constraints are violated. The quadratic penalty is smooth but only satisfies the
+//! constraints as `ρ → ∞`. The l1 penalty is exact, its minimizer solves the problem once `ρ`
+//! exceeds the largest multiplier, but it is not differentiable on the constraints, so by default
+//! its absolute values are smoothed with a parameter `ε` which shrinks once `ρ` is large enough.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::{check_interruptions, Error, KktResiduals, Returns};
+use super::problem::{kkt_residuals, ConstrainedProblem};
+use super::subsolver::Subsolver;
+use crate::{CancelToken, DenseVector, RealFn};
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ enum: Penalty
+/// Penalty on the constraint violation.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Penalty {
+    /// `ρ/2 (‖c_E‖² + ‖max(0, −c_I)‖²)`.
+    Quadratic,
+    /// `ρ (‖c_E‖₁ + ‖max(0, −c_I)‖₁)`, with each `|c|` replaced by `√(c² + ε²)` for a smoothing
+    /// `ε > 0`. A smoothing of zero gives the exact nonsmooth penalty.
+    L1 { smoothing: f64 },
+}
+//}}}
+//{{{ struct: Options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Options {
+    pub penalty: Penalty,
+    /// Stops once the constraint violation falls below `feas_tol` and the subproblem has been
+    /// solved to `opt_tol`.
+    pub feas_tol: f64,
+    /// Gradient tolerance of the subproblems.
+    pub opt_tol: f64,
+    /// Maximum number of outer iterations.
+    pub max_iter: usize,
+    /// Initial penalty parameter `ρ`.
+    pub penalty_init: f64,
+    /// Factor by which `ρ` grows while the constraints are violated, for the l1 penalty by more
+    /// than its smoothing.
+    pub penalty_factor: f64,
+    /// The method fails once `ρ` would exceed this.
+    pub penalty_max: f64,
+    /// Factor by which the smoothing of the l1 penalty shrinks in each iteration which keeps `ρ`.
+    pub smoothing_factor: f64,
+    /// Token checked before every outer iteration and handed to the subsolver, ending the run
+    /// with [`Error::Cancelled`] once it is cancelled.
+    #[serde(skip)]
+    pub cancel: Option<CancelToken>,
+    /// Wall-clock budget checked before every outer iteration, whatever is left of it being
+    /// handed to the subsolver. The run ends with [`Error::TimeLimit`].
+    pub time_limit: Option<Duration>,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            penalty: Penalty::Quadratic,
+            feas_tol: 1e-6,
+            opt_tol: 1e-6,
+            max_iter: 30,
+            penalty_init: 1.0,
+            penalty_factor: 10.0,
+            penalty_max: 1e12,
+            smoothing_factor: 0.1,
+            cancel: None,
+            time_limit: None,
+        }
+    }
+}
+//}}}
+//{{{ struct: PenaltyFn
+/// The objective of a problem plus a penalty on its constraints, as a [`RealFn`].
+#[derive(Clone, Debug)]
+pub struct PenaltyFn<P: ConstrainedProblem> {
+    problem: P,
+    penalty: Penalty,
+    rho: f64,
+}
+//}}}
+//{{{ impl: PenaltyFn
+impl<P: ConstrainedProblem> PenaltyFn<P> {
+    pub fn new(problem: P, penalty: Penalty, rho: f64) -> Self {
+        Self {
+            problem,
+            penalty,
+            rho,
+        }
+    }
+
+    pub fn problem(&self) -> &P {
+        &self.problem
+    }
+
+    /// Value of the penalty term given the constraint values.
+    fn value(&self, eq: &[f64], ineq: &[f64]) -> f64 {
+        let rho = self.rho;
+        match self.penalty {
+            Penalty::Quadratic => {
+                let eq_sq: f64 = eq.iter().map(|c| c * c).sum();
+                let ineq_sq: f64 = ineq.iter().map(|c| c.min(0.0).powi(2)).sum();
+                0.5 * rho * (eq_sq + ineq_sq)
+            }
+            Penalty::L1 { smoothing } => {
+                let abs = |c: f64| c.hypot(smoothing);
+                let eq_abs: f64 = eq.iter().map(|c| abs(*c)).sum();
+                let ineq_abs: f64 = ineq.iter().map(|c| 0.5 * (abs(*c) - c)).sum();
+                rho * (eq_abs + ineq_abs)
+            }
+        }
+    }
+
+    /// Multiplier estimates `λ` and `μ`, for which the gradient of the function is that of the
+    /// Lagrangian `∇f − J_Eᵀλ − J_Iᵀμ`.
+    fn multipliers(&self, eq: &[f64], ineq: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let rho = self.rho;
+        match self.penalty {
+            Penalty::Quadratic => (
+                eq.iter().map(|c| -rho * c).collect(),
+                ineq.iter().map(|c| -rho * c.min(0.0)).collect(),
+            ),
+            Penalty::L1 { smoothing } => {
+                // derivative of the smoothed |c|, the sign of c without smoothing
+                let sign = |c: f64| if c == 0.0 { 0.0 } else { c / c.hypot(smoothing) };
+                (
+                    eq.iter().map(|c| -rho * sign(*c)).collect(),
+                    ineq.iter().map(|c| 0.5 * rho * (1.0 - sign(*c))).collect(),
+                )
+            }
+        }
+    }
+}
+//}}}
+//{{{ impl: RealFn for PenaltyFn
+impl<P: ConstrainedProblem> RealFn for PenaltyFn<P>
+where
+    P::Vector: DenseVector,
+{
+    type Vector = P::Vector;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        let f = self.problem.eval(x);
+        let eq = self.problem.eq(x);
+        let ineq = self.problem.ineq(x);
+        f + self.value(&eq, &ineq)
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        self.eval_with_grad(x).1
+    }
+
+    fn eval_with_grad(&mut self, x: &Self::Vector) -> (f64, Self::Vector) {
+        let f = self.problem.eval(x);
+        let eq = self.problem.eq(x);
+        let ineq = self.problem.ineq(x);
+        let (lambda, mu) = self.multipliers(&eq, &ineq);
+        let mut grad = self.problem.grad(x);
+        let jac_eq = self.problem.eq_jac_tr(x, &lambda);
+        let jac_ineq = self.problem.ineq_jac_tr(x, &mu);
+        for i in 0..grad.len() {
+            grad[i] -= jac_eq[i] + jac_ineq[i];
+        }
+        (f + self.value(&eq, &ineq), grad)
+    }
+}
+//}}}
+//{{{ struct: PenaltyMethod
+/// Penalty minimizer of a [`ConstrainedProblem`].
+///
+/// The reported multipliers are the estimates implied by the penalty at the final point.
+pub struct PenaltyMethod<P: ConstrainedProblem, S> {
+    problem: P,
+    x0: P::Vector,
+    subsolver: S,
+    opts: Options,
+}
+//}}}
+//{{{ impl: PenaltyMethod
+impl<P, S> PenaltyMethod<P, S>
+where
+    P: ConstrainedProblem,
+    P::Vector: DenseVector + Debug,
+    S: Subsolver<PenaltyFn<P>>,
+{
+    pub fn new(problem: P, x0: P::Vector, subsolver: S, opts: Options) -> Self {
+        Self {
+            problem,
+            x0,
+            subsolver,
+            opts,
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    pub fn subsolver(&self) -> &S {
+        &self.subsolver
+    }
+
+    pub fn minimize(&mut self) -> Result<Returns<P::Vector>, Error<P::Vector>> {
+        //{{{ trace
+        error!(target: "penalty", "--- Entering minimize ---");
+        //}}}
+        let opts = self.opts.clone();
+        let start = Instant::now();
+        let mut x = self.x0.clone();
+        if let Some(bounds) = self.subsolver.bounds() {
+            let mut xv = x.to_vec();
+            bounds.project(&mut xv);
+            x = P::Vector::from_slice(&xv);
+        }
+        let mut ret = Returns {
+            xmin: x.clone(),
+            fmin: self.problem.eval(&x),
+            lambda: vec![0.0; self.problem.num_eq()],
+            mu: vec![0.0; self.problem.num_ineq()],
+            kkt: KktResiduals::default(),
+            penalty: opts.penalty_init,
+            num_iterations: 0,
+            num_inner_iterations: 0,
+            num_fun_evals: 1,
+            num_grad_evals: 0,
+        };
+        let eq = self.problem.eq(&x);
+        let ineq = self.problem.ineq(&x);
+        ret.kkt = self.residuals(&mut ret, &eq, &ineq);
+        let finite = ret.fmin.is_finite()
+            && eq.iter().chain(&ineq).all(|c| c.is_finite())
+            && ret.kkt.stationarity.is_finite();
+        if !finite {
+            //{{{ trace
+            info!(target: "penalty", "Non-finite value or constraint at the starting point");
+            //}}}
+            return Err(Error::NonFiniteValue {
+                x,
+                partial: Box::new(ret),
+            });
+        }
+
+        let mut penalty = opts.penalty;
+        loop {
+            if ret.num_iterations >= opts.max_iter {
+                //{{{ trace
+                info!(target: "penalty", "Did not converge within {} iterations", opts.max_iter);
+                //}}}
+                return Err(Error::MaxIterations {
+                    max_iter: opts.max_iter,
+                    partial: Box::new(ret),
+                });
+            }
+            check_interruptions(opts.cancel.as_ref(), opts.time_limit, start, &ret)?;
+            let fcn = PenaltyFn::new(self.problem.clone(), penalty, ret.penalty);
+            let time_left = opts.time_limit.map(|t| t.saturating_sub(start.elapsed()));
+            self.subsolver.set_interruptions(opts.cancel.as_ref(), time_left);
+            let sub = self.subsolver.minimize(fcn.clone(), ret.xmin.clone(), opts.opt_tol);
+            ret.num_iterations += 1;
+            ret.num_inner_iterations += sub.num_iterations;
+            ret.num_fun_evals += sub.num_fun_evals + 1;
+            ret.num_grad_evals += sub.num_grad_evals;
+            ret.xmin = sub.x;
+            ret.fmin = self.problem.eval(&ret.xmin);
+
+            let eq = self.problem.eq(&ret.xmin);
+            let ineq = self.problem.ineq(&ret.xmin);
+            (ret.lambda, ret.mu) = fcn.multipliers(&eq, &ineq);
+            ret.kkt = self.residuals(&mut ret, &eq, &ineq);
+            //{{{ trace
+            info!(target: "penalty", "i = {} f = {:1.4e} ρ = {:1.4e} {:?} inner converged? {}",
+                ret.num_iterations, ret.fmin, ret.penalty, ret.kkt, sub.converged);
+            //}}}
+            if ret.kkt.feasibility <= opts.feas_tol && sub.converged {
+                //{{{ trace
+                error!(target: "penalty", "--- Leaving minimize ---");
+                //}}}
+                return Ok(ret);
+            }
+            // the smoothed l1 penalty leaves a violation below its smoothing once ρ exceeds the
+            // multipliers, a larger one means that ρ is too small
+            let violation_max = match penalty {
+                Penalty::Quadratic => opts.feas_tol,
+                Penalty::L1 { smoothing } => smoothing.max(opts.feas_tol),
+            };
+            if ret.kkt.feasibility > violation_max {
+                let rho = ret.penalty * opts.penalty_factor;
+                if rho > opts.penalty_max {
+                    //{{{ trace
+                    info!(target: "penalty", "Penalty limit reached with violation {:1.4e}", ret.kkt.feasibility);
+                    //}}}
+                    return Err(Error::PenaltyLimit {
+                        penalty: opts.penalty_max,
+                        partial: Box::new(ret),
+                    });
+                }
+                ret.penalty = rho;
+            } else {
+                match &mut penalty {
+                    Penalty::L1 { smoothing } if *smoothing > 0.0 => {
+                        *smoothing *= opts.smoothing_factor;
+                    }
+                    _ => {
+                        // feasible but the subsolver gave up, and rerunning the same subproblem
+                        // would only repeat that
+                        //{{{ trace
+                        info!(target: "penalty", "Subproblem not solved at a feasible point");
+                        //}}}
+                        return Err(Error::Subproblem {
+                            partial: Box::new(ret),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// KKT residuals at the point and multipliers of `ret`, counting the gradient evaluation.
+    fn residuals(&mut self, ret: &mut Returns<P::Vector>, eq: &[f64], ineq: &[f64]) -> KktResiduals {
+        ret.num_grad_evals += 1;
+        kkt_residuals(
+            &mut self.problem,
+            &ret.xmin,
+            eq,
+            ineq,
+            &ret.lambda,
+            &ret.mu,
+            self.subsolver.bounds(),
+        )
+    }
+}
+//}}}
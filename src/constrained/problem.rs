@@ -0,0 +1,106 @@
+//! Nonlinear programs `min f(x)` subject to `c_E(x) = 0` and `c_I(x) >= 0`.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::KktResiduals;
+use crate::bound_constrained::Bounds;
+use crate::dense::norm_inf;
+use crate::DenseVector;
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ trait: ConstrainedProblem
+/// Objective and constraints of a nonlinear program.
+///
+/// The constraints are only accessed through their values and through products of their
+/// transposed Jacobians with multiplier vectors, so the Jacobians never need to be formed.
+/// Multipliers follow the sign convention of the Lagrangian
+/// `L(x, λ, μ) = f(x) − λᵀc_E(x) − μᵀc_I(x)`, with `μ >= 0` at a solution.
+pub trait ConstrainedProblem: Clone + Debug {
+    type Vector;
+
+    /// Number of equality constraints.
+    fn num_eq(&self) -> usize;
+
+    /// Number of inequality constraints.
+    fn num_ineq(&self) -> usize;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64;
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector;
+
+    /// Values of the equality constraints `c_E(x)`, of length [`num_eq`](Self::num_eq).
+    fn eq(&mut self, x: &Self::Vector) -> Vec<f64>;
+
+    /// Values of the inequality constraints `c_I(x)`, of length [`num_ineq`](Self::num_ineq).
+    fn ineq(&mut self, x: &Self::Vector) -> Vec<f64>;
+
+    /// Product `J_E(x)ᵀ λ` of the transposed Jacobian of the equality constraints.
+    fn eq_jac_tr(&mut self, x: &Self::Vector, lambda: &[f64]) -> Self::Vector;
+
+    /// Product `J_I(x)ᵀ μ` of the transposed Jacobian of the inequality constraints.
+    fn ineq_jac_tr(&mut self, x: &Self::Vector, mu: &[f64]) -> Self::Vector;
+}
+//}}}
+//{{{ fun: lagrangian_grad
+/// Gradient `∇f − J_Eᵀλ − J_Iᵀμ` of the Lagrangian.
+pub(crate) fn lagrangian_grad<P>(problem: &mut P, x: &P::Vector, lambda: &[f64], mu: &[f64]) -> Vec<f64>
+where
+    P: ConstrainedProblem,
+    P::Vector: DenseVector,
+{
+    let mut grad = problem.grad(x).to_vec();
+    let jac_eq = problem.eq_jac_tr(x, lambda);
+    let jac_ineq = problem.ineq_jac_tr(x, mu);
+    for (i, gi) in grad.iter_mut().enumerate() {
+        *gi -= jac_eq[i] + jac_ineq[i];
+    }
+    grad
+}
+//}}}
+//{{{ fun: violation
+/// Largest violation `max(‖c_E‖_∞, ‖max(0, −c_I)‖_∞)` of the constraints.
+pub(crate) fn violation(eq: &[f64], ineq: &[f64]) -> f64 {
+    let ineq = ineq.iter().fold(0.0, |acc: f64, c| acc.max(-c));
+    norm_inf(eq).max(ineq)
+}
+//}}}
+//{{{ fun: kkt_residuals
+/// Residuals of the first-order optimality conditions at `x`, given the constraint values.
+///
+/// With `bounds` the stationarity residual is that of the Lagrangian projected onto them.
+pub(crate) fn kkt_residuals<P>(
+    problem: &mut P,
+    x: &P::Vector,
+    eq: &[f64],
+    ineq: &[f64],
+    lambda: &[f64],
+    mu: &[f64],
+    bounds: Option<&Bounds>,
+) -> KktResiduals
+where
+    P: ConstrainedProblem,
+    P::Vector: DenseVector,
+{
+    let grad = lagrangian_grad(problem, x, lambda, mu);
+    let stationarity = match bounds {
+        Some(bounds) => norm_inf(&bounds.projected_gradient(&x.to_vec(), &grad)),
+        None => norm_inf(&grad),
+    };
+    let complementarity = ineq
+        .iter()
+        .zip(mu)
+        .fold(0.0, |acc: f64, (c, m)| acc.max((c * m).abs()));
+    KktResiduals {
+        stationarity,
+        feasibility: violation(eq, ineq),
+        complementarity,
+    }
+}
+//}}}
@@ -0,0 +1,490 @@
+//! Sequential quadratic programming for small dense problems.
+//!
+//! Each iteration solves the quadratic subproblem
+//!
+//! `min ½dᵀBd + ∇fᵀd` subject to `c_E + J_E d = 0`, `c_I + J_I d >= 0` and `l <= x + d <= u`
+//!
+//! with the dual active-set method of Goldfarb and Idnani, where `B` is a damped BFGS
+//! approximation of the Hessian of the Lagrangian (Powell, 1978), which stays positive definite.
+//! The step is then globalized by a backtracking line search on the l1 merit function
+//! `f + Σ νᵢ |c_E,ᵢ| + Σ νⱼ max(0, −c_I,ⱼ)`. As in SLSQP, when the linearized constraints are
+//! inconsistent, the violated ones are relaxed by a factor `1 − ξ` and `ξ ∈ [0, 1]` is penalized
+//! in the subproblem.
+//!
+//! The Jacobians of the constraints are formed from one product with the transposed Jacobian per
+//! constraint, so the method is meant for problems with few variables and constraints.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::{check_interruptions, Error, KktResiduals, Returns};
+use super::problem::{kkt_residuals, ConstrainedProblem};
+use crate::bound_constrained::Bounds;
+use crate::dense::{dot, norm_inf, Matrix};
+use crate::line_search::utils::{quadmin, satisfies_armijo};
+use crate::quadratic::dual::{self, QpError};
+use crate::{CancelToken, DenseVector};
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Options {
+    /// Stops once the stationarity and complementarity residuals fall below `opt_tol` and the
+    /// constraint violation below `feas_tol`.
+    pub opt_tol: f64,
+    pub feas_tol: f64,
+    /// Maximum number of iterations.
+    pub max_iter: usize,
+    /// Sufficient decrease constant of the Armijo condition on the merit function.
+    pub c1: f64,
+    /// Maximum number of step reductions in a line search.
+    pub max_backtracks: usize,
+    /// Weight of `½ξ²` in the relaxed subproblem.
+    pub relaxation_weight: f64,
+    /// Maximum number of steps of the quadratic subproblem solver.
+    pub qp_max_iter: usize,
+    /// Token checked before every iteration and every trial point of the line search, ending
+    /// the run with [`Error::Cancelled`] once it is cancelled.
+    #[serde(skip)]
+    pub cancel: Option<CancelToken>,
+    /// Wall-clock budget checked before every iteration and every trial point of the line
+    /// search, ending the run with [`Error::TimeLimit`].
+    pub time_limit: Option<Duration>,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            opt_tol: 1e-6,
+            feas_tol: 1e-6,
+            max_iter: 100,
+            c1: 1e-4,
+            max_backtracks: 30,
+            relaxation_weight: 1e4,
+            qp_max_iter: 1000,
+            cancel: None,
+            time_limit: None,
+        }
+    }
+}
+//}}}
+//{{{ struct: Point
+/// Values at an iterate.
+#[derive(Clone, Debug)]
+struct Point {
+    x: Vec<f64>,
+    f: f64,
+    eq: Vec<f64>,
+    ineq: Vec<f64>,
+}
+//}}}
+//{{{ struct: Step
+/// Solution of a quadratic subproblem.
+#[derive(Clone, Debug)]
+struct Step {
+    d: Vec<f64>,
+    lambda: Vec<f64>,
+    mu: Vec<f64>,
+    /// Relaxation of the violated constraints, zero unless the linearization was inconsistent.
+    xi: f64,
+    num_iterations: usize,
+}
+//}}}
+//{{{ struct: Sqp
+/// Sequential quadratic programming minimizer of a [`ConstrainedProblem`] subject to bounds.
+///
+/// The iterates always satisfy the bounds, while the constraints of the problem are only met in
+/// the limit.
+pub struct Sqp<P: ConstrainedProblem> {
+    problem: P,
+    x0: P::Vector,
+    bounds: Bounds,
+    opts: Options,
+}
+//}}}
+//{{{ impl: Sqp
+impl<P> Sqp<P>
+where
+    P: ConstrainedProblem,
+    P::Vector: DenseVector + Debug,
+{
+    /// Pass [`Bounds::unbounded`] for a problem without bounds.
+    pub fn new(problem: P, x0: P::Vector, bounds: Bounds, opts: Options) -> Self {
+        assert_eq!(x0.len(), bounds.len(), "starting point and bounds differ in length");
+        Self {
+            problem,
+            x0,
+            bounds,
+            opts,
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    pub fn minimize(&mut self) -> Result<Returns<P::Vector>, Error<P::Vector>> {
+        //{{{ trace
+        error!(target: "sqp", "--- Entering minimize ---");
+        //}}}
+        let opts = self.opts.clone();
+        let start = Instant::now();
+        let n = self.x0.len();
+        let mut xv = self.x0.to_vec();
+        self.bounds.project(&mut xv);
+        let mut point = self.point(xv);
+        let mut grad = self.problem.grad(&P::Vector::from_slice(&point.x)).to_vec();
+        let mut ret = Returns {
+            xmin: P::Vector::from_slice(&point.x),
+            fmin: point.f,
+            lambda: vec![0.0; self.problem.num_eq()],
+            mu: vec![0.0; self.problem.num_ineq()],
+            kkt: KktResiduals::default(),
+            penalty: 0.0,
+            num_iterations: 0,
+            num_inner_iterations: 0,
+            num_fun_evals: 1,
+            num_grad_evals: 1,
+        };
+        ret.kkt = self.residuals(&mut ret, &point);
+        let finite = point.f.is_finite()
+            && point.eq.iter().chain(&point.ineq).all(|c| c.is_finite())
+            && grad.iter().all(|g| g.is_finite());
+        if !finite {
+            //{{{ trace
+            info!(target: "sqp", "Non-finite value or constraint at the starting point");
+            //}}}
+            return Err(Error::NonFiniteValue {
+                x: ret.xmin.clone(),
+                partial: Box::new(ret),
+            });
+        }
+
+        let mut hess = Matrix::identity(n);
+        let mut weights_eq = vec![0.0; point.eq.len()];
+        let mut weights_ineq = vec![0.0; point.ineq.len()];
+        loop {
+            if ret.num_iterations >= opts.max_iter {
+                //{{{ trace
+                info!(target: "sqp", "Did not converge within {} iterations", opts.max_iter);
+                //}}}
+                return Err(Error::MaxIterations {
+                    max_iter: opts.max_iter,
+                    partial: Box::new(ret),
+                });
+            }
+            check_interruptions(opts.cancel.as_ref(), opts.time_limit, start, &ret)?;
+            let x = P::Vector::from_slice(&point.x);
+            let jac_eq = jacobian(point.eq.len(), |e| self.problem.eq_jac_tr(&x, e).to_vec());
+            let jac_ineq = jacobian(point.ineq.len(), |e| self.problem.ineq_jac_tr(&x, e).to_vec());
+            let step = match self.subproblem(&hess, &grad, &point, &jac_eq, &jac_ineq) {
+                Ok(step) => step,
+                Err(err) if hess != Matrix::identity(n) => {
+                    //{{{ trace
+                    debug!(target: "sqp", "Subproblem failed with {:?}, resetting the Hessian", err);
+                    //}}}
+                    hess = Matrix::identity(n);
+                    continue;
+                }
+                Err(err) => {
+                    //{{{ trace
+                    info!(target: "sqp", "Subproblem failed with {:?}", err);
+                    //}}}
+                    return Err(Error::Subproblem {
+                        partial: Box::new(ret),
+                    });
+                }
+            };
+            ret.num_iterations += 1;
+            ret.num_inner_iterations += step.num_iterations;
+            ret.lambda = step.lambda.clone();
+            ret.mu = step.mu.clone();
+            ret.kkt = self.residuals(&mut ret, &point);
+            //{{{ trace
+            info!(target: "sqp", "i = {} f = {:1.4e} ‖d‖ = {:1.4e} ξ = {:1.4e} {:?}",
+                ret.num_iterations, point.f, norm_inf(&step.d), step.xi, ret.kkt);
+            //}}}
+            let kkt = ret.kkt;
+            if kkt.stationarity <= opts.opt_tol
+                && kkt.complementarity <= opts.opt_tol
+                && kkt.feasibility <= opts.feas_tol
+            {
+                //{{{ trace
+                error!(target: "sqp", "--- Leaving minimize ---");
+                //}}}
+                return Ok(ret);
+            }
+
+            // Powell's update keeps each weight above the magnitude of its multiplier
+            for (w, l) in weights_eq.iter_mut().zip(&step.lambda) {
+                *w = l.abs().max(0.5 * (*w + l.abs()));
+            }
+            for (w, m) in weights_ineq.iter_mut().zip(&step.mu) {
+                *w = m.abs().max(0.5 * (*w + m.abs()));
+            }
+            ret.penalty = norm_inf(&weights_eq).max(norm_inf(&weights_ineq));
+            let merit = |p: &Point| {
+                let eq: f64 = p.eq.iter().zip(&weights_eq).map(|(c, w)| w * c.abs()).sum();
+                let ineq: f64 = p.ineq.iter().zip(&weights_ineq).map(|(c, w)| w * (-c).max(0.0)).sum();
+                p.f + eq + ineq
+            };
+            let phi0 = merit(&point);
+            let dphi0 = dot(&grad, &step.d) - (1.0 - step.xi) * (phi0 - point.f);
+            if dphi0 >= 0.0 {
+                // only possible for a relaxed subproblem, whose step need not descend
+                if hess != Matrix::identity(n) {
+                    hess = Matrix::identity(n);
+                    continue;
+                }
+                //{{{ trace
+                info!(target: "sqp", "Step is not a descent direction of the merit function");
+                //}}}
+                return Err(Error::LineSearch {
+                    partial: Box::new(ret),
+                });
+            }
+
+            let mut alpha = 1.0;
+            let mut accepted = None;
+            for _ in 0..=opts.max_backtracks {
+                check_interruptions(opts.cancel.as_ref(), opts.time_limit, start, &ret)?;
+                let mut xt: Vec<f64> = point.x.iter().zip(&step.d).map(|(x, d)| x + alpha * d).collect();
+                self.bounds.project(&mut xt);
+                let trial = self.point(xt);
+                ret.num_fun_evals += 1;
+                let phi = merit(&trial);
+                //{{{ trace
+                trace!(target: "sqp", "alpha = {:1.4e} merit = {:1.4e}", alpha, phi);
+                //}}}
+                if phi.is_finite() && satisfies_armijo(opts.c1, alpha, phi0, dphi0, phi) {
+                    accepted = Some(trial);
+                    break;
+                }
+                alpha = reduce_step(alpha, phi0, dphi0, phi);
+            }
+            let Some(trial) = accepted else {
+                //{{{ trace
+                info!(target: "sqp", "Line search failed after {} step reductions", opts.max_backtracks);
+                //}}}
+                return Err(Error::LineSearch {
+                    partial: Box::new(ret),
+                });
+            };
+
+            // damped BFGS update from the change in the gradient of the Lagrangian
+            let xt = P::Vector::from_slice(&trial.x);
+            let grad_t = self.problem.grad(&xt).to_vec();
+            ret.num_grad_evals += 1;
+            let jac_eq_t = self.problem.eq_jac_tr(&xt, &step.lambda);
+            let jac_ineq_t = self.problem.ineq_jac_tr(&xt, &step.mu);
+            let s: Vec<f64> = trial.x.iter().zip(&point.x).map(|(a, b)| a - b).collect();
+            let y: Vec<f64> = (0..n)
+                .map(|i| {
+                    let lag_t = grad_t[i] - jac_eq_t[i] - jac_ineq_t[i];
+                    let lag = grad[i]
+                        - column_dot(&jac_eq, i, &step.lambda)
+                        - column_dot(&jac_ineq, i, &step.mu);
+                    lag_t - lag
+                })
+                .collect();
+            damped_bfgs_update(&mut hess, &s, &y);
+
+            point = trial;
+            grad = grad_t;
+            ret.xmin = xt;
+            ret.fmin = point.f;
+        }
+    }
+
+    /// Objective and constraint values at `x`.
+    fn point(&mut self, x: Vec<f64>) -> Point {
+        let xv = P::Vector::from_slice(&x);
+        Point {
+            f: self.problem.eval(&xv),
+            eq: self.problem.eq(&xv),
+            ineq: self.problem.ineq(&xv),
+            x,
+        }
+    }
+
+    /// Solves the quadratic subproblem at `point`, relaxing the constraints if it is infeasible.
+    fn subproblem(
+        &self,
+        hess: &Matrix,
+        grad: &[f64],
+        point: &Point,
+        jac_eq: &Matrix,
+        jac_ineq: &Matrix,
+    ) -> Result<Step, QpError> {
+        let n = grad.len();
+        let (num_eq, num_ineq) = (point.eq.len(), point.ineq.len());
+        let b_eq: Vec<f64> = point.eq.iter().map(|c| -c).collect();
+        let mut rows_ineq: Vec<Vec<f64>> = (0..num_ineq).map(|j| jac_ineq.row(j).to_vec()).collect();
+        let mut b_ineq: Vec<f64> = point.ineq.iter().map(|c| -c).collect();
+        for j in 0..n {
+            let (lower, upper) = (self.bounds.lower()[j], self.bounds.upper()[j]);
+            if lower.is_finite() {
+                let mut row = vec![0.0; n];
+                row[j] = 1.0;
+                rows_ineq.push(row);
+                b_ineq.push(lower - point.x[j]);
+            }
+            if upper.is_finite() {
+                let mut row = vec![0.0; n];
+                row[j] = -1.0;
+                rows_ineq.push(row);
+                b_ineq.push(point.x[j] - upper);
+            }
+        }
+        let max_iter = self.opts.qp_max_iter;
+        let a_ineq = Matrix::from_rows(&rows_ineq, n);
+        match dual::solve(hess, grad, jac_eq, &b_eq, &a_ineq, &b_ineq, max_iter) {
+            Ok(sol) => {
+                return Ok(Step {
+                    d: sol.x,
+                    lambda: sol.lambda,
+                    mu: sol.mu[..num_ineq].to_vec(),
+                    xi: 0.0,
+                    num_iterations: sol.num_iterations,
+                })
+            }
+            Err(QpError::Infeasible) => {}
+            Err(err) => return Err(err),
+        }
+        //{{{ trace
+        debug!(target: "sqp", "Inconsistent linearization, relaxing the constraints");
+        //}}}
+
+        // variables (d, ξ), with the constraints violated at the point scaled by 1 − ξ
+        let mut hess_r = Matrix::zeros(n + 1, n + 1);
+        for i in 0..n {
+            for j in 0..n {
+                hess_r[(i, j)] = hess[(i, j)];
+            }
+        }
+        hess_r[(n, n)] = self.opts.relaxation_weight;
+        let mut grad_r = grad.to_vec();
+        grad_r.push(0.0);
+        let rows_eq: Vec<Vec<f64>> = (0..num_eq)
+            .map(|i| [jac_eq.row(i), &[-point.eq[i]]].concat())
+            .collect();
+        let mut rows_ineq_r: Vec<Vec<f64>> = rows_ineq
+            .iter()
+            .enumerate()
+            .map(|(j, row)| {
+                let violated = j < num_ineq && point.ineq[j] < 0.0;
+                let xi_coeff = if violated { -point.ineq[j] } else { 0.0 };
+                [row.as_slice(), &[xi_coeff]].concat()
+            })
+            .collect();
+        let mut xi_row = vec![0.0; n + 1];
+        xi_row[n] = 1.0;
+        rows_ineq_r.push(xi_row.clone());
+        b_ineq.push(0.0);
+        xi_row[n] = -1.0;
+        rows_ineq_r.push(xi_row);
+        b_ineq.push(-1.0);
+        let a_eq = Matrix::from_rows(&rows_eq, n + 1);
+        let a_ineq = Matrix::from_rows(&rows_ineq_r, n + 1);
+        let sol = dual::solve(&hess_r, &grad_r, &a_eq, &b_eq, &a_ineq, &b_ineq, max_iter)?;
+        Ok(Step {
+            xi: sol.x[n],
+            d: sol.x[..n].to_vec(),
+            lambda: sol.lambda,
+            mu: sol.mu[..num_ineq].to_vec(),
+            num_iterations: sol.num_iterations,
+        })
+    }
+
+    /// KKT residuals at `point` and the multipliers of `ret`, counting the gradient evaluation.
+    fn residuals(&mut self, ret: &mut Returns<P::Vector>, point: &Point) -> KktResiduals {
+        ret.num_grad_evals += 1;
+        kkt_residuals(
+            &mut self.problem,
+            &P::Vector::from_slice(&point.x),
+            &point.eq,
+            &point.ineq,
+            &ret.lambda,
+            &ret.mu,
+            Some(&self.bounds),
+        )
+    }
+}
+//}}}
+//{{{ fun: jacobian
+/// Jacobian with `m` rows, row `i` being the product of the transposed Jacobian with `eᵢ`.
+pub(super) fn jacobian(m: usize, mut jac_tr: impl FnMut(&[f64]) -> Vec<f64>) -> Matrix {
+    let mut e = vec![0.0; m];
+    let rows: Vec<Vec<f64>> = (0..m)
+        .map(|i| {
+            e[i] = 1.0;
+            let row = jac_tr(&e);
+            e[i] = 0.0;
+            row
+        })
+        .collect();
+    let cols = rows.first().map_or(0, |r| r.len());
+    Matrix::from_rows(&rows, cols)
+}
+//}}}
+//{{{ fun: column_dot
+/// Dot product of column `j` of `a` with `v`.
+pub(super) fn column_dot(a: &Matrix, j: usize, v: &[f64]) -> f64 {
+    (0..a.rows()).map(|i| a[(i, j)] * v[i]).sum()
+}
+//}}}
+//{{{ fun: reduce_step
+/// Next trial step after `alpha` failed, the minimizer of the quadratic interpolating the merit
+/// function kept within `[0.1α, 0.5α]`, or half the step for a non-finite value.
+pub(super) fn reduce_step(alpha: f64, phi0: f64, dphi0: f64, phi: f64) -> f64 {
+    if !phi.is_finite() {
+        return 0.5 * alpha;
+    }
+    match quadmin(0.0, phi0, dphi0, alpha, phi) {
+        Some(step) => step.clamp(0.1 * alpha, 0.5 * alpha),
+        None => 0.5 * alpha,
+    }
+}
+//}}}
+//{{{ fun: damped_bfgs_update
+/// Powell's damped BFGS update of `hess` with the step `s` and gradient change `y`, which mixes
+/// `y` with `Bs` so that the update keeps `hess` positive definite.
+pub(super) fn damped_bfgs_update(hess: &mut Matrix, s: &[f64], y: &[f64]) {
+    let bs = hess.mul_vec(s);
+    let sbs = dot(s, &bs);
+    if sbs <= f64::EPSILON * dot(s, s) {
+        return;
+    }
+    let sy = dot(s, y);
+    let theta = if sy >= 0.2 * sbs {
+        1.0
+    } else {
+        0.8 * sbs / (sbs - sy)
+    };
+    let r: Vec<f64> = y.iter().zip(&bs).map(|(y, b)| theta * y + (1.0 - theta) * b).collect();
+    let sr = dot(s, &r);
+    let n = s.len();
+    for i in 0..n {
+        for j in 0..n {
+            hess[(i, j)] += r[i] * r[j] / sr - bs[i] * bs[j] / sbs;
+        }
+    }
+}
+//}}}
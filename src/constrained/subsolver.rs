@@ -0,0 +1,219 @@
+//! Solvers for the unconstrained or bound-constrained subproblems of the constrained methods.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use crate::bound_constrained::{Bounds, Lbfgsb, LbfgsbOptions};
+use crate::unconstrained::{ConjugateGradient, ConjugateGradientOptions, UnconstrainedMinimizer};
+use crate::{CancelToken, DenseVector, RealFn};
+//}}}
+//{{{ std imports
+use std::fmt::{Debug, Display};
+use std::ops::{Add, Mul, Neg, Sub};
+use std::time::Duration;
+//}}}
+//{{{ dep imports
+use topohedral_linalg::VectorOps;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: SubsolverReturns
+/// Outcome of a single subproblem.
+#[derive(Clone, Debug)]
+pub struct SubsolverReturns<Vector> {
+    pub x: Vector,
+    pub f: f64,
+    /// Whether the subproblem was solved to the requested tolerance, failed subproblems still
+    /// give their best point.
+    pub converged: bool,
+    pub num_iterations: usize,
+    pub num_fun_evals: usize,
+    pub num_grad_evals: usize,
+}
+//}}}
+//{{{ trait: Subsolver
+/// Minimizes the penalized functions built by the constrained methods.
+pub trait Subsolver<F: RealFn> {
+    /// Minimizes `fcn` from `x0` until its (projected) gradient norm falls below `tol`.
+    fn minimize(&mut self, fcn: F, x0: F::Vector, tol: f64) -> SubsolverReturns<F::Vector>;
+
+    /// Bounds kept explicitly by the subsolver, which the constrained method then leaves to it.
+    fn bounds(&self) -> Option<&Bounds> {
+        None
+    }
+
+    /// Hands over the cancellation token and the time left to the constrained method before
+    /// every subproblem, so that an interruption also stops the subproblem. Subsolvers which
+    /// ignore them are only interrupted between subproblems.
+    fn set_interruptions(&mut self, _cancel: Option<&CancelToken>, _time_left: Option<Duration>) {}
+}
+//}}}
+//{{{ fun: earliest
+/// The shorter of two optional time limits.
+fn earliest(a: Option<Duration>, b: Option<Duration>) -> Option<Duration> {
+    a.into_iter().chain(b).min()
+}
+//}}}
+//{{{ fun: run_unconstrained
+/// Runs `minimizer` to the end, keeping the partial result of a failed run.
+fn run_unconstrained<M: UnconstrainedMinimizer>(mut minimizer: M) -> SubsolverReturns<M::Vector> {
+    let (ret, converged) = match minimizer.minimize() {
+        Ok(ret) => (ret, true),
+        Err(err) => (err.into_partial(), false),
+    };
+    SubsolverReturns {
+        x: ret.xmin,
+        f: ret.fmin,
+        converged,
+        num_iterations: ret.num_iterations,
+        num_fun_evals: ret.num_fun_evals,
+        num_grad_evals: ret.num_grad_evals,
+    }
+}
+//}}}
+//{{{ struct: UnconstrainedSubsolver
+/// Solves each subproblem with an [`UnconstrainedMinimizer`] built by a closure
+/// `FnMut(F, F::Vector, f64) -> M` from the function, the starting point and the gradient
+/// tolerance.
+///
+/// The closure is expected to carry the tolerance over into the minimizer's options, e.g. as
+/// `grad_atol`. It does not see the cancellation token or time limit of the constrained method,
+/// which therefore only interrupts the run between subproblems.
+pub struct UnconstrainedSubsolver<B> {
+    build: B,
+}
+//}}}
+//{{{ impl: UnconstrainedSubsolver
+impl<B> UnconstrainedSubsolver<B> {
+    pub fn new(build: B) -> Self {
+        Self { build }
+    }
+}
+//}}}
+//{{{ impl: Subsolver for UnconstrainedSubsolver
+impl<F, B, M> Subsolver<F> for UnconstrainedSubsolver<B>
+where
+    F: RealFn,
+    F::Vector: Debug,
+    B: FnMut(F, F::Vector, f64) -> M,
+    M: UnconstrainedMinimizer<Vector = F::Vector>,
+{
+    fn minimize(&mut self, fcn: F, x0: F::Vector, tol: f64) -> SubsolverReturns<F::Vector> {
+        run_unconstrained((self.build)(fcn, x0, tol))
+    }
+}
+//}}}
+//{{{ struct: ConjugateGradientSubsolver
+/// Solves each subproblem with [`ConjugateGradient`], with `grad_atol` set to the requested
+/// tolerance and no relative gradient test.
+///
+/// The cancellation token of the constrained method replaces any in `opts`, and the time left to
+/// it caps any time limit there.
+#[derive(Clone)]
+pub struct ConjugateGradientSubsolver {
+    pub opts: ConjugateGradientOptions,
+    cancel: Option<CancelToken>,
+    time_left: Option<Duration>,
+}
+//}}}
+//{{{ impl: ConjugateGradientSubsolver
+impl ConjugateGradientSubsolver {
+    pub fn new(opts: ConjugateGradientOptions) -> Self {
+        Self {
+            opts,
+            cancel: None,
+            time_left: None,
+        }
+    }
+}
+//}}}
+//{{{ impl: Subsolver for ConjugateGradientSubsolver
+impl<F: RealFn> Subsolver<F> for ConjugateGradientSubsolver
+where
+    F::Vector: VectorOps<ScalarType = f64>
+        + Add<Output = F::Vector>
+        + Sub<Output = F::Vector>
+        + Neg<Output = F::Vector>
+        + Clone
+        + DenseVector
+        + Debug
+        + Display,
+    f64: Mul<F::Vector, Output = F::Vector>,
+{
+    fn minimize(&mut self, fcn: F, x0: F::Vector, tol: f64) -> SubsolverReturns<F::Vector> {
+        let mut opts = self.opts.clone();
+        opts.uncon_opts.grad_atol = tol;
+        opts.uncon_opts.grad_rtol = 0.0;
+        opts.uncon_opts.cancel = self.cancel.clone().or(opts.uncon_opts.cancel);
+        opts.uncon_opts.time_limit = earliest(opts.uncon_opts.time_limit, self.time_left);
+        run_unconstrained(ConjugateGradient::new(fcn, x0, opts))
+    }
+
+    fn set_interruptions(&mut self, cancel: Option<&CancelToken>, time_left: Option<Duration>) {
+        self.cancel = cancel.cloned();
+        self.time_left = time_left;
+    }
+}
+//}}}
+//{{{ struct: BoundConstrainedSubsolver
+/// Solves each subproblem with [`Lbfgsb`] subject to `bounds`, with `proj_grad_tol` set to the
+/// requested tolerance.
+///
+/// The cancellation token of the constrained method replaces any in `opts`, and the time left to
+/// it caps any time limit there.
+#[derive(Clone, Debug)]
+pub struct BoundConstrainedSubsolver {
+    pub bounds: Bounds,
+    pub opts: LbfgsbOptions,
+    cancel: Option<CancelToken>,
+    time_left: Option<Duration>,
+}
+//}}}
+//{{{ impl: BoundConstrainedSubsolver
+impl BoundConstrainedSubsolver {
+    pub fn new(bounds: Bounds, opts: LbfgsbOptions) -> Self {
+        Self {
+            bounds,
+            opts,
+            cancel: None,
+            time_left: None,
+        }
+    }
+}
+//}}}
+//{{{ impl: Subsolver for BoundConstrainedSubsolver
+impl<F: RealFn> Subsolver<F> for BoundConstrainedSubsolver
+where
+    F::Vector: DenseVector + Debug,
+{
+    fn minimize(&mut self, fcn: F, x0: F::Vector, tol: f64) -> SubsolverReturns<F::Vector> {
+        let opts = LbfgsbOptions {
+            proj_grad_tol: tol,
+            cancel: self.cancel.clone().or_else(|| self.opts.cancel.clone()),
+            time_limit: earliest(self.opts.time_limit, self.time_left),
+            ..self.opts.clone()
+        };
+        let (ret, converged) = match Lbfgsb::new(fcn, x0, self.bounds.clone(), opts).minimize() {
+            Ok(ret) => (ret, true),
+            Err(err) => (err.into_partial(), false),
+        };
+        SubsolverReturns {
+            x: ret.xmin,
+            f: ret.fmin,
+            converged,
+            num_iterations: ret.num_iterations,
+            num_fun_evals: ret.num_fun_evals,
+            num_grad_evals: ret.num_grad_evals,
+        }
+    }
+
+    fn bounds(&self) -> Option<&Bounds> {
+        Some(&self.bounds)
+    }
+
+    fn set_interruptions(&mut self, cancel: Option<&CancelToken>, time_left: Option<Duration>) {
+        self.cancel = cancel.cloned();
+        self.time_left = time_left;
+    }
+}
+//}}}
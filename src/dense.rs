@@ -0,0 +1,232 @@
+//! Dense linear algebra on `f64` slices used internally by the solvers.
+//!
+//! The solvers which need small dense systems (compact quasi-Newton matrices and the like) work
+//! on plain `Vec<f64>` storage rather than on the user's vector type, these are the helpers
+//! they share.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+//}}}
+//{{{ std imports
+use std::ops::{Index, IndexMut};
+//}}}
+//{{{ dep imports
+use topohedral_linalg::dmatrix::DMatrix;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ fun: dot
+pub(crate) fn dot(a: &[f64], b: &[f64]) -> f64 {
+    debug_assert_eq!(a.len(), b.len());
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+//}}}
+//{{{ fun: norm
+pub(crate) fn norm(a: &[f64]) -> f64 {
+    dot(a, a).sqrt()
+}
+//}}}
+//{{{ fun: norm_inf
+pub(crate) fn norm_inf(a: &[f64]) -> f64 {
+    a.iter().fold(0.0, |acc, v| acc.max(v.abs()))
+}
+//}}}
+//{{{ struct: Matrix
+/// Row-major dense matrix.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>,
+}
+//}}}
+//{{{ impl: Matrix
+impl Matrix {
+    pub(crate) fn zeros(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            data: vec![0.0; rows * cols],
+        }
+    }
+
+    pub(crate) fn identity(n: usize) -> Self {
+        let mut out = Self::zeros(n, n);
+        for i in 0..n {
+            out[(i, i)] = 1.0;
+        }
+        out
+    }
+
+    /// Matrix whose rows are `rows`, each of length `cols`.
+    pub(crate) fn from_rows(rows: &[Vec<f64>], cols: usize) -> Self {
+        let mut data = Vec::with_capacity(rows.len() * cols);
+        for row in rows {
+            debug_assert_eq!(row.len(), cols);
+            data.extend_from_slice(row);
+        }
+        Self {
+            rows: rows.len(),
+            cols,
+            data,
+        }
+    }
+
+    pub(crate) fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub(crate) fn row(&self, i: usize) -> &[f64] {
+        &self.data[i * self.cols..(i + 1) * self.cols]
+    }
+
+    /// Product `A x`.
+    pub(crate) fn mul_vec(&self, x: &[f64]) -> Vec<f64> {
+        (0..self.rows).map(|i| dot(self.row(i), x)).collect()
+    }
+}
+//}}}
+//{{{ impl: Index for Matrix
+impl Index<(usize, usize)> for Matrix {
+    type Output = f64;
+
+    fn index(&self, (i, j): (usize, usize)) -> &f64 {
+        &self.data[i * self.cols + j]
+    }
+}
+//}}}
+//{{{ impl: IndexMut for Matrix
+impl IndexMut<(usize, usize)> for Matrix {
+    fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut f64 {
+        &mut self.data[i * self.cols + j]
+    }
+}
+//}}}
+//{{{ struct: Lu
+/// LU factorization with partial pivoting, `P A = L U`.
+#[derive(Clone, Debug)]
+pub(crate) struct Lu {
+    lu: Matrix,
+    perm: Vec<usize>,
+}
+//}}}
+//{{{ impl: Lu
+impl Lu {
+    /// Factorizes the square matrix `a`, returning `None` if it is numerically singular.
+    pub(crate) fn new(mut a: Matrix) -> Option<Self> {
+        debug_assert_eq!(a.rows, a.cols);
+        let n = a.rows;
+        let scale = norm_inf(&a.data).max(f64::MIN_POSITIVE);
+        let mut perm: Vec<usize> = (0..n).collect();
+        for k in 0..n {
+            let p = (k..n).max_by(|&i, &j| a[(i, k)].abs().total_cmp(&a[(j, k)].abs()))?;
+            if a[(p, k)].abs() <= f64::EPSILON * scale {
+                return None;
+            }
+            if p != k {
+                for j in 0..n {
+                    a.data.swap(k * n + j, p * n + j);
+                }
+                perm.swap(k, p);
+            }
+            for i in k + 1..n {
+                let l = a[(i, k)] / a[(k, k)];
+                a[(i, k)] = l;
+                for j in k + 1..n {
+                    a[(i, j)] -= l * a[(k, j)];
+                }
+            }
+        }
+        Some(Self { lu: a, perm })
+    }
+
+    /// Solves `A x = b`.
+    pub(crate) fn solve(&self, b: &[f64]) -> Vec<f64> {
+        let n = self.lu.rows;
+        let mut x: Vec<f64> = self.perm.iter().map(|&p| b[p]).collect();
+        for i in 0..n {
+            x[i] -= dot(&self.lu.row(i)[..i], &x[..i]);
+        }
+        for i in (0..n).rev() {
+            x[i] = (x[i] - dot(&self.lu.row(i)[i + 1..], &x[i + 1..])) / self.lu[(i, i)];
+        }
+        x
+    }
+
+    /// Solves `Aᵀ x = b`.
+    pub(crate) fn solve_transpose(&self, b: &[f64]) -> Vec<f64> {
+        let n = self.lu.rows;
+        let mut w = b.to_vec();
+        for i in 0..n {
+            let head: f64 = (0..i).map(|k| self.lu[(k, i)] * w[k]).sum();
+            w[i] = (w[i] - head) / self.lu[(i, i)];
+        }
+        for i in (0..n).rev() {
+            let tail: f64 = (i + 1..n).map(|k| self.lu[(k, i)] * w[k]).sum();
+            w[i] -= tail;
+        }
+        let mut x = vec![0.0; n];
+        for (i, &p) in self.perm.iter().enumerate() {
+            x[p] = w[i];
+        }
+        x
+    }
+}
+//}}}
+//{{{ struct: Cholesky
+/// Cholesky factorization `A = L Lᵀ` of a symmetric positive definite matrix.
+#[derive(Clone, Debug)]
+pub(crate) struct Cholesky {
+    l: Matrix,
+}
+//}}}
+//{{{ impl: Cholesky
+impl Cholesky {
+    /// Factorizes the symmetric matrix `a`, of which only the lower triangle is read, returning
+    /// `None` if it is not numerically positive definite.
+    pub(crate) fn new(a: &Matrix) -> Option<Self> {
+        debug_assert_eq!(a.rows, a.cols);
+        let n = a.rows;
+        let scale = (0..n).fold(0.0, |acc: f64, i| acc.max(a[(i, i)].abs()));
+        let mut l = Matrix::zeros(n, n);
+        for j in 0..n {
+            let pivot = a[(j, j)] - dot(&l.row(j)[..j], &l.row(j)[..j]);
+            if pivot.is_nan() || pivot <= f64::EPSILON * scale {
+                return None;
+            }
+            let pivot = pivot.sqrt();
+            l[(j, j)] = pivot;
+            for i in j + 1..n {
+                l[(i, j)] = (a[(i, j)] - dot(&l.row(i)[..j], &l.row(j)[..j])) / pivot;
+            }
+        }
+        Some(Self { l })
+    }
+
+    /// Solves `A x = b`.
+    pub(crate) fn solve(&self, b: &[f64]) -> Vec<f64> {
+        let n = self.l.rows;
+        let mut x = b.to_vec();
+        for i in 0..n {
+            x[i] = (x[i] - dot(&self.l.row(i)[..i], &x[..i])) / self.l[(i, i)];
+        }
+        for i in (0..n).rev() {
+            let tail: f64 = (i + 1..n).map(|k| self.l[(k, i)] * x[k]).sum();
+            x[i] = (x[i] - tail) / self.l[(i, i)];
+        }
+        x
+    }
+}
+//}}}
+//{{{ fun: to_dense
+/// Copies `a` into the internal row-major storage.
+pub(crate) fn to_dense(a: &DMatrix<f64>) -> Matrix {
+    let mut out = Matrix::zeros(a.nrows(), a.ncols());
+    for i in 0..a.nrows() {
+        for j in 0..a.ncols() {
+            out[(i, j)] = a[(i, j)];
+        }
+    }
+    out
+}
+//}}}
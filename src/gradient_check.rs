@@ -0,0 +1,284 @@
+//! Finite-difference checks of hand-written derivatives.
+//!
+//! A wrong gradient usually shows up much later as a line search which cannot find a decreasing
+//! step. The functions in this module compare `RealFn::grad` and `RealFn1::diff` against finite
+//! difference approximations so that such errors can be caught at the source. For vectors with
+//! many components the component-wise check is replaced by a small number of directional
+//! derivative checks, each of which costs only two function evaluations.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use crate::common::DenseVector;
+use crate::{RealFn, RealFn1};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ enum: Scheme
+/// The finite difference formula used to approximate a derivative.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Scheme {
+    /// (f(x + h) - f(x)) / h, first order accurate.
+    Forward,
+    /// (f(x + h) - f(x - h)) / 2h, second order accurate.
+    Central,
+}
+//}}}
+//{{{ struct: Options
+/// Options for configuring a derivative check.
+///
+/// The step used for component `i` is `step * max(1, |x_i|)`. A component passes if either its
+/// absolute error is below `atol` or its relative error is below `rtol`. Vectors with more than
+/// `max_components` entries are checked along `num_directions` pseudo-random directions only.
+#[derive(Debug, Copy, Clone)]
+pub struct Options {
+    pub step: f64,
+    pub atol: f64,
+    pub rtol: f64,
+    pub scheme: Scheme,
+    pub max_components: usize,
+    pub num_directions: usize,
+    pub seed: u64,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            step: 1e-6,
+            atol: 1e-6,
+            rtol: 1e-4,
+            scheme: Scheme::Central,
+            max_components: 100,
+            num_directions: 4,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+}
+//}}}
+//{{{ struct: ComponentError
+/// Comparison of a single derivative component against its finite difference approximation.
+#[derive(Debug, Copy, Clone)]
+pub struct ComponentError {
+    pub index: usize,
+    pub analytic: f64,
+    pub approx: f64,
+    pub abs_error: f64,
+    pub rel_error: f64,
+    pub passed: bool,
+}
+//}}}
+//{{{ struct: DirectionalError
+/// Comparison of the directional derivative `∇f(x)·d` against its finite difference
+/// approximation along the unit direction `d`.
+#[derive(Debug, Clone)]
+pub struct DirectionalError {
+    pub direction: Vec<f64>,
+    pub analytic: f64,
+    pub approx: f64,
+    pub abs_error: f64,
+    pub rel_error: f64,
+    pub passed: bool,
+}
+//}}}
+//{{{ struct: Report
+/// The results of a derivative check.
+///
+/// `components` is empty when the vector was too large for a component-wise check, in which case
+/// `directions` holds the directional checks instead. `worst` is the index into `components` with
+/// the largest absolute error.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub components: Vec<ComponentError>,
+    pub directions: Vec<DirectionalError>,
+    pub worst: Option<usize>,
+    pub max_abs_error: f64,
+    pub max_rel_error: f64,
+    pub passed: bool,
+}
+//}}}
+//{{{ impl: Report
+impl Report {
+    /// Returns the component with the largest absolute error, if a component check was done.
+    pub fn worst_component(&self) -> Option<&ComponentError> {
+        self.worst.map(|i| &self.components[i])
+    }
+
+    fn from_errors(components: Vec<ComponentError>, directions: Vec<DirectionalError>) -> Self {
+        let worst = components
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.abs_error.total_cmp(&b.1.abs_error))
+            .map(|(i, _)| i);
+        let abs_errors = components
+            .iter()
+            .map(|c| c.abs_error)
+            .chain(directions.iter().map(|d| d.abs_error));
+        let rel_errors = components
+            .iter()
+            .map(|c| c.rel_error)
+            .chain(directions.iter().map(|d| d.rel_error));
+        let max_abs_error = abs_errors.fold(0.0, f64::max);
+        let max_rel_error = rel_errors.fold(0.0, f64::max);
+        let passed = components.iter().all(|c| c.passed) && directions.iter().all(|d| d.passed);
+        Self {
+            components,
+            directions,
+            worst,
+            max_abs_error,
+            max_rel_error,
+            passed,
+        }
+    }
+}
+//}}}
+//{{{ fun: check_gradient
+/// Compares `fcn.grad(x)` against a finite difference approximation of the gradient.
+pub fn check_gradient<F>(fcn: &mut F, x: &F::Vector, opts: &Options) -> Report
+where
+    F: RealFn,
+    F::Vector: DenseVector,
+{
+    //{{{ trace
+    info!(target: "gc", "--- Entering check_gradient ---");
+    //}}}
+    let n = x.len();
+    let grad = fcn.grad(x);
+    let fx = fcn.eval(x);
+
+    let mut components = Vec::new();
+    let mut directions = Vec::new();
+    if n <= opts.max_components {
+        for i in 0..n {
+            let h = opts.step * x[i].abs().max(1.0);
+            let mut xp = x.clone();
+            xp[i] += h;
+            let fp = fcn.eval(&xp);
+            let approx = match opts.scheme {
+                Scheme::Forward => (fp - fx) / h,
+                Scheme::Central => {
+                    let mut xm = x.clone();
+                    xm[i] -= h;
+                    (fp - fcn.eval(&xm)) / (2.0 * h)
+                }
+            };
+            let (abs_error, rel_error, passed) = compare(grad[i], approx, opts);
+            //{{{ trace
+            debug!(target: "gc", "i = {i} analytic = {:1.4e} approx = {approx:1.4e} abs_error = {abs_error:1.4e}", grad[i]);
+            //}}}
+            components.push(ComponentError {
+                index: i,
+                analytic: grad[i],
+                approx,
+                abs_error,
+                rel_error,
+                passed,
+            });
+        }
+    } else {
+        let scale = (0..n).map(|i| x[i].abs()).fold(1.0, f64::max);
+        let h = opts.step * scale;
+        let mut rng = XorShift::new(opts.seed);
+        for _ in 0..opts.num_directions {
+            let dir = rng.direction(n);
+            let analytic: f64 = (0..n).map(|i| grad[i] * dir[i]).sum();
+            let mut xp = x.clone();
+            for (i, d) in dir.iter().enumerate() {
+                xp[i] += h * d;
+            }
+            let fp = fcn.eval(&xp);
+            let approx = match opts.scheme {
+                Scheme::Forward => (fp - fx) / h,
+                Scheme::Central => {
+                    let mut xm = x.clone();
+                    for (i, d) in dir.iter().enumerate() {
+                        xm[i] -= h * d;
+                    }
+                    (fp - fcn.eval(&xm)) / (2.0 * h)
+                }
+            };
+            let (abs_error, rel_error, passed) = compare(analytic, approx, opts);
+            //{{{ trace
+            debug!(target: "gc", "directional analytic = {analytic:1.4e} approx = {approx:1.4e} abs_error = {abs_error:1.4e}");
+            //}}}
+            directions.push(DirectionalError {
+                direction: dir,
+                analytic,
+                approx,
+                abs_error,
+                rel_error,
+                passed,
+            });
+        }
+    }
+    let report = Report::from_errors(components, directions);
+    //{{{ trace
+    info!(target: "gc", "max_abs_error = {:1.4e} max_rel_error = {:1.4e} passed = {}", report.max_abs_error, report.max_rel_error, report.passed);
+    info!(target: "gc", "--- Leaving check_gradient ---");
+    //}}}
+    report
+}
+//}}}
+//{{{ fun: check_derivative
+/// Compares `fcn.diff(x)` against a finite difference approximation of the derivative.
+pub fn check_derivative<F: RealFn1>(fcn: &mut F, x: f64, opts: &Options) -> Report {
+    let h = opts.step * x.abs().max(1.0);
+    let analytic = fcn.diff(x);
+    let approx = match opts.scheme {
+        Scheme::Forward => (fcn.eval(x + h) - fcn.eval(x)) / h,
+        Scheme::Central => (fcn.eval(x + h) - fcn.eval(x - h)) / (2.0 * h),
+    };
+    let (abs_error, rel_error, passed) = compare(analytic, approx, opts);
+    let component = ComponentError {
+        index: 0,
+        analytic,
+        approx,
+        abs_error,
+        rel_error,
+        passed,
+    };
+    Report::from_errors(vec![component], Vec::new())
+}
+//}}}
+//{{{ fun: compare
+fn compare(analytic: f64, approx: f64, opts: &Options) -> (f64, f64, bool) {
+    let abs_error = (analytic - approx).abs();
+    let scale = analytic.abs().max(approx.abs());
+    let rel_error = if scale > 0.0 { abs_error / scale } else { 0.0 };
+    let passed = abs_error <= opts.atol || rel_error <= opts.rtol;
+    (abs_error, rel_error, passed)
+}
+//}}}
+//{{{ struct: XorShift
+/// Small deterministic generator so that directional checks are reproducible.
+struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    fn next(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    /// Returns a unit vector with entries of equal magnitude and random sign.
+    fn direction(&mut self, n: usize) -> Vec<f64> {
+        let mag = 1.0 / (n as f64).sqrt();
+        (0..n)
+            .map(|_| if self.next() & 1 == 0 { mag } else { -mag })
+            .collect()
+    }
+}
+//}}}
@@ -15,6 +15,18 @@
 #![feature(impl_trait_in_assoc_type)]
 
 mod common;
-pub use common::{RealFn, RealFn1};
+mod dense;
+pub use common::{
+    Budget, CachePolicy, CachedPoint, CachingRealFn, CancelToken, CountingRealFn, CountingRealFn1,
+    DenseVector, RealFn, RealFn1,
+};
+pub mod autodiff;
+pub mod bound_constrained;
+pub mod constrained;
+pub mod gradient_check;
 pub mod line_search;
+pub mod linprog;
+pub mod nonlinear_solve;
+pub mod projected;
+pub mod quadratic;
 pub mod unconstrained;
\ No newline at end of file
@@ -0,0 +1,325 @@
+//! Backtracking line search on the Armijo condition alone.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common as com;
+use super::common::{AskTell, Error, LineSearch, Returns, Stats, Task};
+use super::utils::{quadmin, satisfies_armijo};
+use crate::{Budget, CancelToken, RealFn1};
+//}}}
+//{{{ std imports
+use std::time::Instant;
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Options
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Options {
+    /// Of the common options only `c1`, `step_init`, `step_min` and `max_nonfinite` are used.
+    pub ls_opts: com::Options,
+    /// Each reduction multiplies the step by a factor in `[shrink_min, shrink_max]`.
+    pub shrink_min: f64,
+    pub shrink_max: f64,
+    /// Maximum number of step reductions.
+    pub maxiter: usize,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            ls_opts: com::Options::default(),
+            shrink_min: 0.1,
+            shrink_max: 0.5,
+            maxiter: 30,
+        }
+    }
+}
+//}}}
+//{{{ enum: Phase
+/// What the search is waiting for.
+#[derive(Clone, Debug)]
+enum Phase {
+    Idle,
+    /// `φ` at the current step.
+    Eval,
+    Done(Result<Returns, Error>),
+}
+//}}}
+//{{{ struct: BacktrackingAskTell
+/// The backtracking line search as an [`AskTell`] state machine.
+///
+/// Starting from `step_init`, the step is reduced to the minimizer of the quadratic interpolating
+/// `φ(0)`, `φ'(0)` and `φ(α)`, kept within `[shrink_min α, shrink_max α]`, until it satisfies the
+/// Armijo condition. A non-finite value reduces the step by `shrink_max`. No derivative is ever
+/// requested, so the `dphi_alpha` of the returns is NaN.
+#[derive(Clone, Debug)]
+pub struct BacktrackingAskTell {
+    pub opts: Options,
+    phi0: f64,
+    dphi0: f64,
+    alpha: f64,
+    iter: usize,
+    phase: Phase,
+    stats: Stats,
+}
+//}}}
+//{{{ impl: BacktrackingAskTell
+impl BacktrackingAskTell {
+    pub fn new(opts: Options) -> Self {
+        Self {
+            opts,
+            phi0: 0.0,
+            dphi0: 0.0,
+            alpha: opts.ls_opts.step_init,
+            iter: 0,
+            phase: Phase::Idle,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Reduces the step after `phi` failed at the current one.
+    fn reduce(&mut self, phi: f64) {
+        self.stats.num_rejected += 1;
+        self.iter += 1;
+        if self.iter > self.opts.maxiter {
+            self.phase = Phase::Done(Err(Error::MaxIterations));
+            return;
+        }
+        let (lo, hi) = (
+            self.opts.shrink_min * self.alpha,
+            self.opts.shrink_max * self.alpha,
+        );
+        self.alpha = match quadmin(0.0, self.phi0, self.dphi0, self.alpha, phi) {
+            Some(step) if phi.is_finite() => step.clamp(lo, hi),
+            _ => hi,
+        };
+        //{{{ trace
+        debug!(target: "ls", "Backtracking to alpha = {:1.4e}", self.alpha);
+        //}}}
+        self.phase = if self.alpha < self.opts.ls_opts.step_min {
+            Phase::Done(Err(Error::StepSizeSmall))
+        } else {
+            Phase::Eval
+        };
+    }
+}
+//}}}
+//{{{ impl: AskTell for BacktrackingAskTell
+impl AskTell for BacktrackingAskTell {
+    fn start(&mut self, phi0: f64, dphi0: f64) {
+        //{{{ trace
+        error!(target: "ls", "--- Entering backtracking search ---");
+        info!(target: "ls", "phi0={phi0} dphi0={dphi0}");
+        //}}}
+        let phase = if !phi0.is_finite() || !dphi0.is_finite() {
+            Phase::Done(Err(Error::NonFiniteValue { alpha: 0.0 }))
+        } else if dphi0 >= 0.0 {
+            Phase::Done(Err(Error::NotDecreasing))
+        } else {
+            Phase::Eval
+        };
+        *self = Self {
+            phi0,
+            dphi0,
+            phase,
+            stats: Stats {
+                num_searches: 1,
+                ..Stats::default()
+            },
+            ..Self::new(self.opts)
+        };
+    }
+
+    fn ask(&self) -> Task {
+        match &self.phase {
+            Phase::Idle => Task::Failed(Error::NoStepFound),
+            Phase::Eval => Task::Value(self.alpha),
+            Phase::Done(Ok(returns)) => Task::Converged(*returns),
+            Phase::Done(Err(err)) => Task::Failed(*err),
+        }
+    }
+
+    fn tell(&mut self, value: f64) {
+        if !matches!(self.phase, Phase::Eval) {
+            return;
+        }
+        self.stats.num_evals += 1;
+        if !value.is_finite() {
+            self.stats.num_nonfinite += 1;
+            //{{{ trace
+            info!(target: "ls", "Non-finite value at alpha = {:1.4e}", self.alpha);
+            //}}}
+            if self.stats.num_nonfinite > self.opts.ls_opts.max_nonfinite {
+                self.phase = Phase::Done(Err(Error::NonFiniteValue { alpha: self.alpha }));
+                return;
+            }
+        } else if satisfies_armijo(
+            self.opts.ls_opts.c1,
+            self.alpha,
+            self.phi0,
+            self.dphi0,
+            value,
+        ) {
+            //{{{ trace
+            info!(target: "ls", "--- leaving backtracking search() ----");
+            //}}}
+            self.phase = Phase::Done(Ok(Returns {
+                alpha: self.alpha,
+                phi_alpha: value,
+                dphi_alpha: f64::NAN,
+            }));
+            return;
+        }
+        self.reduce(value);
+    }
+
+    /// Only values are ever requested, the derivative is ignored.
+    fn tell_value_and_diff(&mut self, value: f64, _diff: f64) {
+        self.tell(value);
+    }
+
+    fn stats(&self) -> Stats {
+        self.stats
+    }
+}
+//}}}
+//{{{ struct: Backtracking
+/// The backtracking line search driving a [`BacktrackingAskTell`] on a [`RealFn1`].
+///
+/// Cancellation, the deadline and the budgets are checked before every evaluation, as in
+/// [`super::Interp`].
+pub struct Backtracking<F: RealFn1> {
+    pub opts: Options,
+    pub(crate) f: F,
+    cancel: Option<CancelToken>,
+    deadline: Option<Instant>,
+    budget: Budget,
+    total_budget: Budget,
+    /// Values and derivatives evaluated by the searches before the current one.
+    num_evals_spent: usize,
+    num_diffs_spent: usize,
+    stats: Stats,
+}
+//}}}
+//{{{ impl: Backtracking
+impl<F: RealFn1> Backtracking<F> {
+    pub fn new(f: F, opts: Options) -> Self {
+        Self {
+            opts,
+            f,
+            cancel: None,
+            deadline: None,
+            budget: Budget::default(),
+            total_budget: Budget::default(),
+            num_evals_spent: 0,
+            num_diffs_spent: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Stops the search with [`Error::Cancelled`] once `token` is cancelled.
+    pub fn with_cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Stops the search with [`Error::TimeLimit`] once `deadline` has passed.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Stops each search with [`Error::BudgetExhausted`] rather than exceed `budget`. As for
+    /// [`super::Interp::with_budget`], the limit applies to each search separately.
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Stops a search with [`Error::BudgetExhausted`] rather than let the searches made so far
+    /// together exceed `budget`, as [`super::Interp::with_total_budget`] does.
+    pub fn with_total_budget(mut self, budget: Budget) -> Self {
+        self.total_budget = budget;
+        self
+    }
+
+    /// Why the search must stop before the next evaluation, if it must.
+    fn interrupted(&self) -> Option<Error> {
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Some(Error::Cancelled);
+        }
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Some(Error::TimeLimit);
+        }
+        None
+    }
+
+    /// Whether `evals` more values and `diffs` more derivatives fit in both budgets.
+    fn allows(&self, evals: usize, diffs: usize) -> bool {
+        let (num_evals, num_diffs) = (self.stats.num_evals, self.stats.num_diffs);
+        let (spent_evals, spent_diffs) = (
+            self.num_evals_spent + num_evals,
+            self.num_diffs_spent + num_diffs,
+        );
+        self.budget.allows(num_evals, num_diffs, evals, diffs)
+            && self.total_budget.allows(spent_evals, spent_diffs, evals, diffs)
+    }
+
+    /// Runs a single search, stopping before an evaluation which is not allowed.
+    fn run(&mut self, phi0: f64, dphi0: f64) -> Result<Returns, Error> {
+        let mut search = BacktrackingAskTell::new(self.opts);
+        search.start(phi0, dphi0);
+        loop {
+            let task = search.ask();
+            self.stats = search.stats();
+            if let (Task::Value(_) | Task::ValueAndDiff(_), Some(err)) = (task, self.interrupted())
+            {
+                return Err(err);
+            }
+            let (evals, diffs) = match task {
+                Task::Value(_) => (1, 0),
+                Task::ValueAndDiff(_) => (1, 1),
+                _ => (0, 0),
+            };
+            if !self.allows(evals, diffs) {
+                return Err(Error::BudgetExhausted);
+            }
+            match task {
+                Task::Value(alpha) => search.tell(self.f.eval(alpha)),
+                Task::ValueAndDiff(alpha) => {
+                    let (phi, dphi) = self.f.eval_with_diff(alpha);
+                    search.tell_value_and_diff(phi, dphi);
+                }
+                Task::Converged(returns) => return Ok(returns),
+                Task::Failed(err) => return Err(err),
+            }
+        }
+    }
+}
+//}}}
+//{{{ impl: LineSearch for Backtracking
+impl<F: RealFn1> LineSearch for Backtracking<F> {
+    type Function = F;
+
+    fn search(&mut self, phi0: f64, dphi0: f64) -> Result<Returns, Error> {
+        let result = self.run(phi0, dphi0);
+        self.num_evals_spent += self.stats.num_evals;
+        self.num_diffs_spent += self.stats.num_diffs;
+        result
+    }
+
+    fn update_fcn(&mut self, fcn: Self::Function) {
+        self.f = fcn;
+    }
+
+    fn stats(&self) -> Stats {
+        self.stats
+    }
+}
+//}}}
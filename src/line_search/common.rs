@@ -11,6 +11,7 @@ use std::ops::{Add, Mul};
 //}}}
 //{{{ dep imports 
 use topohedral_linalg::VectorOps;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 //}}}
 //--------------------------------------------------------------------------------------------------
@@ -49,10 +50,16 @@ where
         let grad = self.f.grad(&x);
         grad.dot(&self.dir)
     }
+
+    fn eval_with_diff(&mut self, alpha: f64) -> (f64, f64) {
+        let x = self.x.clone() + alpha * self.dir.clone();
+        let (fx, grad) = self.f.eval_with_grad(&x);
+        (fx, grad.dot(&self.dir))
+    }
 }
 //}}}
 //{{{ enum: Error
-#[derive(PartialEq, Error, Debug)]
+#[derive(Copy, Clone, PartialEq, Error, Debug)]
 pub enum Error {
     #[error("Not decreasing")]
     NotDecreasing,
@@ -68,6 +75,14 @@ pub enum Error {
     StepSizeLarge,
     #[error("No step found")]
     NoStepFound,
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("Deadline passed")]
+    TimeLimit,
+    #[error("Evaluation budget exhausted")]
+    BudgetExhausted,
+    #[error("Non-finite value at step {alpha}")]
+    NonFiniteValue { alpha: f64 },
 }
 //}}}
 //{{{ struct: Options 
@@ -78,13 +93,16 @@ pub enum Error {
 /// and the Armijo and curvature conditions (`c1` and `c2`). The `method` field
 /// specifies the line search method to use, which can be one of `FixedStep`, `Quadratic`,
 /// or `Inexact`.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Options {
     pub c1: f64,
     pub c2: f64,
     pub step_min: f64,
     pub step_max: f64,
     pub step_init: f64,
+    /// Number of non-finite trial values tolerated per search, each one making the search
+    /// back off towards smaller steps, before it fails with [`Error::NonFiniteValue`].
+    pub max_nonfinite: usize,
 }
 //}}}
 //{{{ impl: Default for Options
@@ -96,6 +114,7 @@ impl Default for Options {
             step_min: 1e-8,
             step_max: 50.0,
             step_init: 1.0,
+            max_nonfinite: 10,
         }
     }
 }
@@ -115,10 +134,81 @@ pub struct Returns {
     pub dphi_alpha: f64,
 }
 //}}}
+//{{{ struct: Stats
+/// Evaluation statistics of one or more line searches.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stats {
+    pub num_searches: usize,
+    /// Number of values `φ(α)` evaluated.
+    pub num_evals: usize,
+    /// Number of derivatives `φ'(α)` evaluated.
+    pub num_diffs: usize,
+    /// Number of trial steps which failed the Wolfe conditions.
+    pub num_rejected: usize,
+    /// Number of trial steps at which `φ` or `φ'` was not finite.
+    #[serde(default)]
+    pub num_nonfinite: usize,
+}
+//}}}
+//{{{ impl: Stats
+impl Stats {
+    /// Average number of values and derivatives evaluated per search.
+    pub fn evals_per_search(&self) -> f64 {
+        (self.num_evals + self.num_diffs) as f64 / self.num_searches.max(1) as f64
+    }
+
+    /// Adds the statistics of further searches.
+    pub fn accumulate(&mut self, other: &Stats) {
+        self.num_searches += other.num_searches;
+        self.num_evals += other.num_evals;
+        self.num_diffs += other.num_diffs;
+        self.num_rejected += other.num_rejected;
+        self.num_nonfinite += other.num_nonfinite;
+    }
+}
+//}}}
 //{{{ trait: LineSearch
 pub trait LineSearch {
     type Function: RealFn1;
     fn search(&mut self, phi0: f64, dphi0: f64) ->  Result<Returns, Error>;
     fn update_fcn(&mut self, fcn: Self::Function);
+
+    /// Statistics of the last search.
+    fn stats(&self) -> Stats {
+        Stats::default()
+    }
+}
+//}}}
+//{{{ enum: Task
+/// Request made by a line search driven through [`AskTell`].
+#[derive(Debug, Copy, Clone)]
+pub enum Task {
+    /// Evaluate `φ(alpha)` and pass it to [`AskTell::tell`].
+    Value(f64),
+    /// Evaluate `φ(alpha)` and `φ'(alpha)` together and pass them to
+    /// [`AskTell::tell_value_and_diff`].
+    ValueAndDiff(f64),
+    /// An acceptable step has been found.
+    Converged(Returns),
+    /// The search has failed.
+    Failed(Error),
+}
+//}}}
+//{{{ trait: AskTell
+/// Reverse-communication form of a line search, in the style of MINPACK's `dcsrch`.
+///
+/// Rather than calling a function, the search is asked what it needs next and told the result,
+/// so that `φ` can be evaluated wherever the caller likes, e.g. by an external job scheduler.
+pub trait AskTell {
+    /// Starts a new search from `φ(0) = phi0` and `φ'(0) = dphi0`.
+    fn start(&mut self, phi0: f64, dphi0: f64);
+    /// Returns the current request, repeated calls without a `tell` return the same one.
+    fn ask(&self) -> Task;
+    /// Supplies the value requested by the last [`AskTell::ask`].
+    fn tell(&mut self, value: f64);
+    /// Supplies the value and derivative requested by the last [`AskTell::ask`].
+    fn tell_value_and_diff(&mut self, value: f64, diff: f64);
+    /// Statistics of the current search.
+    fn stats(&self) -> Stats;
 }
 //}}}
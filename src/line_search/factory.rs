@@ -8,30 +8,46 @@ use topohedral_linalg::VectorOps;
 //{{{ crate imports 
 use crate::RealFn1;
 use super::common::*;
+use super::backtracking;
 use super::interp;
 //}}}
 //{{{ std imports 
 use std::ops::{Mul, Add};
 //}}}
 //{{{ dep imports 
+use serde::{Deserialize, Serialize};
 //}}}
 //--------------------------------------------------------------------------------------------------
 
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub enum Method{
-    Interp(interp::Options)
+    Interp(interp::Options),
+    /// Armijo backtracking, which only evaluates values and suits directions whose full step is
+    /// the natural one, as Newton's.
+    Backtracking(backtracking::Options),
 } 
 
+impl Default for Method {
+    fn default() -> Self {
+        Method::Interp(interp::Options::default())
+    }
+}
+
 pub fn create<'a, F: RealFn1 + 'a>(fcn: F, method: Method) 
 -> Box<dyn LineSearch<Function = F> + 'a>
 {
     match method {
         Method::Interp(opts) => {
-            Box::new(interp::Interp{
-                opts: opts, 
-                f: fcn 
-            })
+            Box::new(interp::Interp::new(fcn, opts))
         }
+        Method::Backtracking(opts) => Box::new(backtracking::Backtracking::new(fcn, opts)),
+    }
+}
+/// Creates the [`AskTell`] form of the line search selected by `method`.
+pub fn create_ask_tell(method: Method) -> Box<dyn AskTell> {
+    match method {
+        Method::Interp(opts) => Box::new(interp::InterpAskTell::new(opts)),
+        Method::Backtracking(opts) => Box::new(backtracking::BacktrackingAskTell::new(opts)),
     }
-}
\ No newline at end of file
+}
@@ -5,23 +5,23 @@
 
 //{{{ crate imports
 use super::common as com;
-use super::common::{
-    Error, Error as LineSearchError, LineSearchFcn, LineSearch, Options as LineSearchOptions,
-    Returns,
-};
+use super::common::{AskTell, Error, LineSearch, Returns, Stats, Task};
 use super::utils::{cubicmin, quadmin};
-use crate::line_search::utils::{quadcubmin, satisfies_armijo, satisfies_wolfe};
-use crate::RealFn1;
+use crate::line_search::utils::satisfies_wolfe;
+use crate::{Budget, CancelToken, RealFn1};
 //}}}
 //{{{ std imports
-use std::ops::{Add, Mul};
-use topohedral_linalg::VectorOps;
+use std::time::Instant;
 //}}}
 //{{{ dep imports
+use serde::{Deserialize, Serialize};
 use topohedral_tracing::*;
 //}}}
 //--------------------------------------------------------------------------------------------------
 
+/// Factor by which the bracket shrinks after a non-finite value at one of its steps.
+const BACKTRACK: f64 = 0.5;
+
 #[derive(Copy, Clone, Debug)]
 struct GuessData {
     a: f64,
@@ -33,7 +33,7 @@ struct GuessData {
     phi_c: f64,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Options {
     pub ls_opts: com::Options,
     pub step1: f64,
@@ -42,20 +42,101 @@ pub struct Options {
     pub maxiter: usize,
 }
 
-pub struct Interp<F: RealFn1> {
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            ls_opts: com::Options::default(),
+            step1: 0.5,
+            step2: 1.0,
+            scale_factor: 1.5,
+            maxiter: 10,
+        }
+    }
+}
+
+//{{{ enum: Bracket
+/// Which pair of trial steps `b`, `c` the search is interpolating from.
+#[derive(Copy, Clone, Debug)]
+enum Bracket {
+    Initial,
+    Low,
+    High,
+}
+//}}}
+//{{{ enum: Phase
+/// What the search is waiting for.
+#[derive(Clone, Debug)]
+enum Phase {
+    Idle,
+    /// `φ(b)` for the current bracket.
+    EvalB,
+    /// `φ(c)` for the current bracket.
+    EvalC { phi_b: f64 },
+    /// `φ` at `candidates[next]`, the best candidate so far being `best`.
+    Candidate {
+        guess: GuessData,
+        candidates: [Option<f64>; 3],
+        next: usize,
+        best: Option<(f64, f64)>,
+    },
+    /// `φ` and `φ'` together at the best candidate.
+    Diff { guess: GuessData, alpha: f64 },
+    Done(Result<Returns, Error>),
+}
+//}}}
+//{{{ struct: InterpAskTell
+/// The interpolating line search as an [`AskTell`] state machine.
+///
+/// Starting from the steps `step1` and `step2` it tries the minimizers of the cubic and
+/// quadratic interpolants of `φ`, and on failure shrinks and grows the pair of steps by
+/// `scale_factor` in turn. Non-finite values are treated as infeasible: the bracket backs off
+/// towards zero, and an interpolated candidate or its derivative is passed over.
+#[derive(Clone, Debug)]
+pub struct InterpAskTell {
     pub opts: Options,
-    pub(crate) f: F,
+    phi0: f64,
+    dphi0: f64,
+    b_low: f64,
+    c_low: f64,
+    b_high: f64,
+    c_high: f64,
+    iter: usize,
+    bracket: Bracket,
+    phase: Phase,
+    stats: Stats,
 }
+//}}}
+//{{{ impl: InterpAskTell
+impl InterpAskTell {
+    pub fn new(opts: Options) -> Self {
+        Self {
+            opts,
+            phi0: 0.0,
+            dphi0: 0.0,
+            b_low: opts.step1,
+            c_low: opts.step2,
+            b_high: opts.step1,
+            c_high: opts.step2,
+            iter: 0,
+            bracket: Bracket::Initial,
+            phase: Phase::Idle,
+            stats: Stats::default(),
+        }
+    }
 
-impl<F: RealFn1> Interp<F> {
-    pub fn new(f: F, opts: Options) -> Self {
-        Self { opts: opts, f: f }
+    /// The pair of steps of the current bracket.
+    fn bracket_steps(&self) -> (f64, f64) {
+        match self.bracket {
+            Bracket::Initial | Bracket::High => (self.b_high, self.c_high),
+            Bracket::Low => (self.b_low, self.c_low),
+        }
     }
 
-    fn guess_is_ok(&mut self, guess_data: GuessData) -> Option<(f64, f64, f64)> {
+    /// Forms the interpolated candidates for a guess and asks for the first of them.
+    fn begin_guess(&mut self, guess: GuessData) {
         //{{{ trace
-        info!(target: "ls", "--- Entering guess_is_ok ---");
-        trace!(target: "ls", "Data: {:?}", guess_data);
+        info!(target: "ls", "--- Entering guess ---");
+        trace!(target: "ls", "Data: {:?}", guess);
         //}}}
         let GuessData {
             a,
@@ -65,148 +146,374 @@ impl<F: RealFn1> Interp<F> {
             phi_b,
             c,
             phi_c,
-        } = guess_data;
-
-        let c1 = self.opts.ls_opts.c1;
-        let c2 = self.opts.ls_opts.c2;
-        let best_guess_opt = quadcubmin(&mut self.f, a, phi_a, dphi_a, b, phi_b, c, phi_c);
+        } = guess;
+        let candidates = [
+            cubicmin(a, phi_a, dphi_a, b, phi_b, c, phi_c),
+            quadmin(a, phi_a, dphi_a, b, phi_b),
+            quadmin(a, phi_a, dphi_a, c, phi_c),
+        ];
+        self.next_candidate(guess, candidates, 0, None);
+    }
 
-        if best_guess_opt.is_none() {
-            //{{{ trace
-            info!(target: "ls", "No guess found");
-            info!(target: "ls", "--- leaving guess_is_ok ---");
-            //}}}
-            return None;
+    /// Asks for the next candidate from `from` on, or moves on to the best one.
+    fn next_candidate(
+        &mut self,
+        guess: GuessData,
+        candidates: [Option<f64>; 3],
+        from: usize,
+        best: Option<(f64, f64)>,
+    ) {
+        if let Some(next) = (from..candidates.len()).find(|&i| candidates[i].is_some()) {
+            self.phase = Phase::Candidate {
+                guess,
+                candidates,
+                next,
+                best,
+            };
+            return;
+        }
+        match best {
+            Some((alpha, _)) => {
+                //{{{ trace
+                trace!(target: "ls", "best candidate alpha = {alpha:1.4e}");
+                //}}}
+                self.phase = Phase::Diff { guess, alpha };
+            }
+            None => {
+                //{{{ trace
+                info!(target: "ls", "No guess found");
+                //}}}
+                self.next_bracket();
+            }
         }
+    }
 
-        let (alpha, phi_alpha) = best_guess_opt.unwrap();
-        let dphi_alpha = self.f.diff(alpha);
+    /// Moves on to the next bracket after a failed guess.
+    fn next_bracket(&mut self) {
         //{{{ trace
-        info!("Checking wolfe for alpha = {alpha} phi_alpha = {phi_alpha} dphi_alpha = {dphi_alpha}");
+        info!(target: "ls", "--- leaving guess ---");
         //}}}
-        if satisfies_wolfe(c1, c2, phi_a, dphi_a, alpha, phi_alpha, dphi_alpha).is_ok() {
-            //{{{ trace
-            info!("Satisfies wolfe!");
-            info!(target: "ls", "--- leaving guess_is_ok ---");
-            //}}}
-            return Some((alpha, phi_alpha, dphi_alpha));
+        let scale_factor = self.opts.scale_factor;
+        let inv_scale_factor = 1.0 / scale_factor;
+        let next = match self.bracket {
+            Bracket::Initial => (self.iter < self.opts.maxiter).then_some(Bracket::Low),
+            Bracket::Low => Some(Bracket::High),
+            Bracket::High => {
+                self.iter += 1;
+                (self.iter < self.opts.maxiter).then_some(Bracket::Low)
+            }
+        };
+        match next {
+            Some(Bracket::Low) => {
+                //{{{ trace
+                info!(target: "ls", "---------------------------------- i = {}", self.iter);
+                //}}}
+                self.b_low *= inv_scale_factor;
+                self.c_low *= inv_scale_factor;
+                self.bracket = Bracket::Low;
+                self.phase = Phase::EvalB;
+            }
+            Some(_) => {
+                self.b_high *= scale_factor;
+                self.c_high *= scale_factor;
+                self.bracket = Bracket::High;
+                self.phase = Phase::EvalB;
+            }
+            None => self.phase = Phase::Done(Err(Error::MaxIterations)),
         }
+    }
+
+    /// Treats the non-finite value told in `phase` at step `alpha` as infeasible, failing once
+    /// more than `max_nonfinite` have been seen in the search.
+    fn reject_nonfinite(&mut self, phase: Phase, alpha: f64) {
+        self.stats.num_nonfinite += 1;
         //{{{ trace
-        info!(target: "ls", "--- leaving guess_is_ok ---");
+        info!(target: "ls", "Non-finite value at alpha = {alpha:1.4e}");
         //}}}
-        None
+        if self.stats.num_nonfinite > self.opts.ls_opts.max_nonfinite {
+            self.phase = Phase::Done(Err(Error::NonFiniteValue { alpha }));
+            return;
+        }
+        match phase {
+            Phase::EvalB | Phase::EvalC { .. } => {
+                match self.bracket {
+                    Bracket::Initial | Bracket::High => {
+                        self.b_high *= BACKTRACK;
+                        self.c_high *= BACKTRACK;
+                    }
+                    Bracket::Low => {
+                        self.b_low *= BACKTRACK;
+                        self.c_low *= BACKTRACK;
+                    }
+                }
+                self.phase = Phase::EvalB;
+            }
+            Phase::Candidate {
+                guess,
+                candidates,
+                next,
+                best,
+            } => self.next_candidate(guess, candidates, next + 1, best),
+            Phase::Diff { .. } => self.next_bracket(),
+            Phase::Idle | Phase::Done(_) => unreachable!("no evaluation requested"),
+        }
     }
 }
-
-impl<F: RealFn1> LineSearch for Interp<F> {
-    type Function = F;
-
-    fn search(&mut self, phi0: f64, dphi0: f64) -> Result<Returns, Error> {
+//}}}
+//{{{ impl: AskTell for InterpAskTell
+impl AskTell for InterpAskTell {
+    fn start(&mut self, phi0: f64, dphi0: f64) {
         //{{{ trace
         error!(target: "ls", "--- Entering search ---");
         info!(target: "ls", "phi0={phi0} dphi0={dphi0}");
         //}}}
-        let Options {
-            ls_opts: _,
-            step1,
-            step2,
-            scale_factor,
-            maxiter,
-        } = self.opts;
-
-        let a = 0.0;
-        let mut b_low = step1;
-        let mut c_low = step2;
-        let mut b_high = step1;
-        let mut c_high = step2;
-        let phi_a = phi0;
-        let dphi_a = dphi0;
-        let mut phi_b_low = self.f.eval(b_high);
-        let mut phi_c_low = self.f.eval(c_high);
-        let mut phi_b_high = phi_b_low;
-        let mut phi_c_high = phi_b_high;
-
-        if let Some((alpha, phi_alpha, dphi_alpha)) = self.guess_is_ok(GuessData {
-            a: a,
-            phi_a: phi_a,
-            dphi_a: dphi_a,
-            b: b_low,
-            phi_b: phi_b_low,
-            c: c_low,
-            phi_c: phi_c_low,
-        }) {
-            return Ok(Returns {
-                alpha: alpha,
-                phi_alpha: phi_alpha,
-                dphi_alpha: dphi_alpha,
-            });
+        let phase = if phi0.is_finite() && dphi0.is_finite() {
+            Phase::EvalB
+        } else {
+            Phase::Done(Err(Error::NonFiniteValue { alpha: 0.0 }))
+        };
+        *self = Self {
+            phi0,
+            dphi0,
+            phase,
+            stats: Stats {
+                num_searches: 1,
+                ..Stats::default()
+            },
+            ..Self::new(self.opts)
+        };
+    }
+
+    fn ask(&self) -> Task {
+        match &self.phase {
+            Phase::Idle => Task::Failed(Error::NoStepFound),
+            Phase::EvalB => Task::Value(self.bracket_steps().0),
+            Phase::EvalC { .. } => Task::Value(self.bracket_steps().1),
+            Phase::Candidate {
+                candidates, next, ..
+            } => Task::Value(candidates[*next].unwrap()),
+            Phase::Diff { alpha, .. } => Task::ValueAndDiff(*alpha),
+            Phase::Done(Ok(returns)) => Task::Converged(*returns),
+            Phase::Done(Err(err)) => Task::Failed(*err),
         }
+    }
 
-        let inv_scale_factor = 1.0 / scale_factor;
-        for i in 0..maxiter {
-            //{{{ trace
-            info!(target: "ls", "---------------------------------- i = {i}");
-            //}}}
-            b_low = b_low * inv_scale_factor;
-            phi_b_low = self.f.eval(b_low);
-            c_low = c_low * inv_scale_factor;
-            phi_c_low = self.f.eval(c_low);
-            let guess_data_low = GuessData {
-                a: a,
-                phi_a: phi_a,
-                dphi_a: dphi_a,
-                b: b_low,
-                phi_b: phi_b_low,
-                c: c_low,
-                phi_c: phi_c_low,
-            };
-            //{{{ trace
-            debug!(target: "ls", "Looking low:\n{guess_data_low:?}");
-            //}}}
-            if let Some((alpha, phi_alpha, dphi_alpha)) = self.guess_is_ok(guess_data_low) {
+    fn tell(&mut self, value: f64) {
+        match self.phase {
+            Phase::EvalB | Phase::EvalC { .. } | Phase::Candidate { .. } => self.stats.num_evals += 1,
+            Phase::Idle | Phase::Diff { .. } | Phase::Done(_) => return,
+        }
+        if let (false, Task::Value(alpha)) = (value.is_finite(), self.ask()) {
+            let phase = std::mem::replace(&mut self.phase, Phase::Idle);
+            self.reject_nonfinite(phase, alpha);
+            return;
+        }
+        match std::mem::replace(&mut self.phase, Phase::Idle) {
+            Phase::EvalB => self.phase = Phase::EvalC { phi_b: value },
+            Phase::EvalC { phi_b } => {
+                let (b, c) = self.bracket_steps();
+                let guess = GuessData {
+                    a: 0.0,
+                    phi_a: self.phi0,
+                    dphi_a: self.dphi0,
+                    b,
+                    phi_b,
+                    c,
+                    phi_c: value,
+                };
+                //{{{ trace
+                debug!(target: "ls", "Looking {:?}:\n{guess:?}", self.bracket);
+                //}}}
+                self.begin_guess(guess);
+            }
+            Phase::Candidate {
+                guess,
+                candidates,
+                next,
+                best,
+            } => {
+                let alpha = candidates[next].unwrap();
                 //{{{ trace
-                info!(target: "ls", "Low guess found acceptable step: alpha = {alpha}, phi_alpha = {phi_alpha}, dphi_alpha = {dphi_alpha}");
-                info!(target: "ls", "--- leaving search() ----");
+                trace!(target: "ls", "alpha-falpha pair: ({alpha:1.4e}, {value:1.4e})");
                 //}}}
-                return Ok(Returns {
-                    alpha: alpha,
-                    phi_alpha: phi_alpha,
-                    dphi_alpha: dphi_alpha,
-                });
+                // the first of several equal minima is kept
+                let best = match best {
+                    Some((_, phi_best)) if value < phi_best => Some((alpha, value)),
+                    None => Some((alpha, value)),
+                    best => best,
+                };
+                self.next_candidate(guess, candidates, next + 1, best);
             }
+            phase => self.phase = phase,
+        }
+    }
 
-            b_high = b_high * scale_factor;
-            phi_b_high = self.f.eval(b_high);
-            c_high = c_high * scale_factor;
-            phi_c_high = self.f.eval(c_high);
-            let guess_data_high = GuessData {
-                a: a,
-                phi_a: phi_a,
-                dphi_a: dphi_a,
-                b: b_high,
-                phi_b: phi_b_high,
-                c: c_high,
-                phi_c: phi_c_high,
-            };
+    fn tell_value_and_diff(&mut self, value: f64, diff: f64) {
+        let Phase::Diff { guess, alpha } = self.phase else {
+            return;
+        };
+        self.stats.num_evals += 1;
+        self.stats.num_diffs += 1;
+        if !value.is_finite() || !diff.is_finite() {
+            let phase = std::mem::replace(&mut self.phase, Phase::Idle);
+            self.reject_nonfinite(phase, alpha);
+            return;
+        }
+        let (phi_alpha, dphi_alpha) = (value, diff);
+        let c1 = self.opts.ls_opts.c1;
+        let c2 = self.opts.ls_opts.c2;
+        //{{{ trace
+        info!("Checking wolfe for alpha = {alpha} phi_alpha = {phi_alpha} dphi_alpha = {dphi_alpha}");
+        //}}}
+        if satisfies_wolfe(c1, c2, guess.phi_a, guess.dphi_a, alpha, phi_alpha, dphi_alpha).is_ok() {
             //{{{ trace
-            debug!(target: "ls", "Looking high:\n{guess_data_high:?}");
+            info!("Satisfies wolfe!");
+            info!(target: "ls", "--- leaving search() ----");
             //}}}
-            if let Some((alpha, phi_alpha, dphi_alpha)) = self.guess_is_ok(guess_data_high) {
-                //{{{ trace
-                info!(target: "ls", "High guess found acceptable step: alpha = {alpha}, phi_alpha = {phi_alpha}, dphi_alpha = {dphi_alpha}");
-                info!(target: "ls", "--- leaving search() ----");
-                //}}}
-                return Ok(Returns {
-                    alpha: alpha,
-                    phi_alpha: phi_alpha,
-                    dphi_alpha: dphi_alpha,
-                });
+            self.phase = Phase::Done(Ok(Returns {
+                alpha,
+                phi_alpha,
+                dphi_alpha,
+            }));
+        } else {
+            self.stats.num_rejected += 1;
+            self.next_bracket();
+        }
+    }
+
+    fn stats(&self) -> Stats {
+        self.stats
+    }
+}
+//}}}
+//{{{ struct: Interp
+pub struct Interp<F: RealFn1> {
+    pub opts: Options,
+    pub(crate) f: F,
+    cancel: Option<CancelToken>,
+    deadline: Option<Instant>,
+    budget: Budget,
+    total_budget: Budget,
+    /// Values and derivatives evaluated by the searches before the current one.
+    num_evals_spent: usize,
+    num_diffs_spent: usize,
+    stats: Stats,
+}
+//}}}
+//{{{ impl: Interp
+impl<F: RealFn1> Interp<F> {
+    pub fn new(f: F, opts: Options) -> Self {
+        Self {
+            opts,
+            f,
+            cancel: None,
+            deadline: None,
+            budget: Budget::default(),
+            total_budget: Budget::default(),
+            num_evals_spent: 0,
+            num_diffs_spent: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Stops the search with [`Error::Cancelled`] once `token` is cancelled.
+    pub fn with_cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Stops the search with [`Error::TimeLimit`] once `deadline` has passed.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Stops each search with [`Error::BudgetExhausted`] rather than exceed `budget`, in which
+    /// gradient evaluations stand for derivatives. The limit applies to each search separately,
+    /// see [`with_total_budget`](Self::with_total_budget) for one on all the searches together.
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Stops a search with [`Error::BudgetExhausted`] rather than let the searches made so far
+    /// together exceed `budget`. It is checked alongside any per-search budget.
+    pub fn with_total_budget(mut self, budget: Budget) -> Self {
+        self.total_budget = budget;
+        self
+    }
+
+    /// Why the search must stop before the next evaluation, if it must.
+    fn interrupted(&self) -> Option<Error> {
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Some(Error::Cancelled);
+        }
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Some(Error::TimeLimit);
+        }
+        None
+    }
+
+    /// Whether `evals` more values and `diffs` more derivatives fit in both budgets.
+    fn allows(&self, evals: usize, diffs: usize) -> bool {
+        let (num_evals, num_diffs) = (self.stats.num_evals, self.stats.num_diffs);
+        let (spent_evals, spent_diffs) = (
+            self.num_evals_spent + num_evals,
+            self.num_diffs_spent + num_diffs,
+        );
+        self.budget.allows(num_evals, num_diffs, evals, diffs)
+            && self.total_budget.allows(spent_evals, spent_diffs, evals, diffs)
+    }
+
+    /// Runs a single search, stopping before an evaluation which is not allowed.
+    fn run(&mut self, phi0: f64, dphi0: f64) -> Result<Returns, Error> {
+        let mut search = InterpAskTell::new(self.opts);
+        search.start(phi0, dphi0);
+        loop {
+            let task = search.ask();
+            self.stats = search.stats();
+            if let (Task::Value(_) | Task::ValueAndDiff(_), Some(err)) = (task, self.interrupted())
+            {
+                return Err(err);
+            }
+            let (evals, diffs) = match task {
+                Task::Value(_) => (1, 0),
+                Task::ValueAndDiff(_) => (1, 1),
+                _ => (0, 0),
+            };
+            if !self.allows(evals, diffs) {
+                return Err(Error::BudgetExhausted);
+            }
+            match task {
+                Task::Value(alpha) => search.tell(self.f.eval(alpha)),
+                Task::ValueAndDiff(alpha) => {
+                    let (phi, dphi) = self.f.eval_with_diff(alpha);
+                    search.tell_value_and_diff(phi, dphi);
+                }
+                Task::Converged(returns) => return Ok(returns),
+                Task::Failed(err) => return Err(err),
             }
         }
-        Err(LineSearchError::MaxIterations)
+    }
+}
+//}}}
+//{{{ impl: LineSearch for Interp
+impl<F: RealFn1> LineSearch for Interp<F> {
+    type Function = F;
+
+    fn search(&mut self, phi0: f64, dphi0: f64) -> Result<Returns, Error> {
+        let result = self.run(phi0, dphi0);
+        self.num_evals_spent += self.stats.num_evals;
+        self.num_diffs_spent += self.stats.num_diffs;
+        result
     }
 
     fn update_fcn(&mut self, fcn: Self::Function) {
         self.f = fcn;
     }
+
+    fn stats(&self) -> Stats {
+        self.stats
+    }
 }
+//}}}
@@ -14,16 +14,21 @@ use topohedral_linalg::VectorOps;
 //}}}
 //--------------------------------------------------------------------------------------------------
 
+mod backtracking;
 mod common;
 mod factory;
 mod interp;
 mod thuente;
-mod utils;
+pub(crate) mod utils;
 
+pub use backtracking::{
+    Backtracking, BacktrackingAskTell, Options as BacktrackingOptions,
+};
 pub use common::{
-    Error as LineSearchError, LineSearchFcn, LineSearch, Options as LineSearchOptions,
-    Returns as LineSearchReturns,
+    AskTell as LineSearchAskTell, Error as LineSearchError, LineSearchFcn, LineSearch,
+    Options as LineSearchOptions, Returns as LineSearchReturns, Stats as LineSearchStats,
+    Task as LineSearchTask,
 };
-pub use factory::{create, Method as LineSearchMethod};
-pub use interp::Interp;
+pub use factory::{create, create_ask_tell, Method as LineSearchMethod};
+pub use interp::{Interp, InterpAskTell};
 pub use interp::Options as InterpOptions;
@@ -10,7 +10,7 @@ use super::common::{
     Returns,
 };
 use super::utils::{cubicmin, quadmin};
-use crate::line_search::utils::{quadcubmin, satisfies_armijo, satisfies_wolfe};
+use crate::line_search::utils::{satisfies_armijo, satisfies_wolfe};
 use crate::RealFn1;
 //}}}
 //{{{ std imports
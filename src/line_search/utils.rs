@@ -5,7 +5,6 @@
 
 //{{{ crate imports
 use super::common::Error;
-use crate::common::RealFn1;
 //}}}
 //{{{ std imports
 //}}}
@@ -63,6 +62,9 @@ pub fn quadmin(a: f64, phi_a: f64, dphi_a: f64, b: f64, phi_b: f64) -> Option<f6
     error!(target: "ls", "Returning alpha_min = {:1.4e}", alpha_min);
     error!(target: "ls", "--- Leaving quadmin ---");
     //}}}
+    if !alpha_min.is_finite() {
+        return None;
+    }
     return Some(alpha_min);
 }
 //}}}
@@ -130,9 +132,9 @@ pub fn cubicmin(
     error!(target: "ls", "Returning alpha_min = {:1.4e}", alpha_min);
     error!(target: "ls", "--- Leaving cubicmin ---");
     //}}}
-    if alpha_min.is_nan() {
+    if !alpha_min.is_finite() {
         //{{{ trace
-        error!("Final result is Nan, returning None");
+        error!("Final result is not finite, returning None");
         error!(target: "ls", "--- Leaving cubicmin ---");
         //}}}
         return None;
@@ -140,57 +142,6 @@ pub fn cubicmin(
     return Some(alpha_min);
 }
 //}}}
-//{{{ fun: quadcubmin
-pub fn quadcubmin<F: RealFn1>(
-    f: &mut F, 
-    a: f64,
-    phi_a: f64,
-    dphi_a: f64,
-    b: f64,
-    phi_b: f64,
-    c: f64,
-    phi_c: f64,
-) -> Option<(f64, f64)> {
-
-    //{{{ trace
-    info!(target: "ls", "--- entering quadcubmin ---");
-    //}}}
-    let to_pair = |x: &Option<f64>| -> Option<(f64, f64)> {
-        match x {
-            None => None,
-            Some(alpha) => Some((*alpha, f.eval(*alpha))),
-        }
-    };
-    let cubic_min_alpha = cubicmin(a, phi_a, dphi_a, b, phi_b, c, phi_c);
-    let quad_min1_alpha = quadmin(a, phi_a, dphi_a, b, phi_b);
-    let quad_min2_alpha = quadmin(a, phi_a, dphi_a, c, phi_c);
-    let opt_min_value = [cubic_min_alpha, quad_min1_alpha, quad_min2_alpha]
-        .iter()
-        .map(to_pair)
-        .filter_map(|x| {
-            //{{{ trace
-            trace!(target: "ls", "alpha-falpha pair: {:?}", x);
-            //}}}
-            x
-        })
-        .min_by(|x, y| x.1.partial_cmp(&y.1).unwrap());
-
-    if opt_min_value.is_none() {
-        //{{{ trace
-        info!(target: "ls", "No value found");
-        info!(target: "ls", "--- leaving quadcubmin ---");
-        //}}}
-        return None
-    }
-
-    let (alpha_min, fmin) = opt_min_value.unwrap();
-    //{{{ trace
-    trace!(target: "ls", "returning alpha ={alpha_min:1.4e}");
-    info!(target: "ls", "--- leaving quadcubmin ---");
-    //}}}
-    Some((alpha_min, fmin))
-}
-//}}}
 //{{{ fun: satisfies_armijo
 pub fn satisfies_armijo(c1: f64, alpha: f64, phi0: f64, dphi0: f64, phi1: f64) -> bool {
     //{{{ trace
@@ -0,0 +1,43 @@
+//! Returns and errors shared by the linear programming methods.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use thiserror::Error;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Returns
+/// Solution of a linear program with its dual values, following the convention
+/// `c = A_Eᵀλ + A_Iᵀμ + z` of the Lagrangian `cᵀx − λᵀ(A_E x − b_E) − μᵀ(A_I x − b_I)`.
+#[derive(Clone, Debug)]
+pub struct Returns {
+    pub x: Vec<f64>,
+    pub objective: f64,
+    /// Multipliers `λ` of the equality constraints.
+    pub lambda: Vec<f64>,
+    /// Multipliers `μ >= 0` of the inequality constraints.
+    pub mu: Vec<f64>,
+    /// Reduced costs `z`, nonnegative at a lower bound and nonpositive at an upper bound.
+    pub reduced_costs: Vec<f64>,
+    pub num_iterations: usize,
+}
+//}}}
+//{{{ enum: Error
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum Error {
+    #[error("The constraints are infeasible")]
+    Infeasible,
+    #[error("The objective is unbounded below on the feasible set")]
+    Unbounded,
+    /// A basis or the normal equations became numerically singular.
+    #[error("The linear system of an iteration is numerically singular")]
+    Singular,
+    #[error("Maximum iterations of {max_iter} reached")]
+    MaxIterations { max_iter: usize },
+}
+//}}}
@@ -0,0 +1,447 @@
+//! Mehrotra predictor-corrector interior point method.
+//!
+//! The program is first brought to the standard form `min cᵀx` subject to `Ax = b` and `x >= 0`,
+//! shifting variables by their finite bounds, adding a row with a slack for each variable bounded
+//! on both sides, surplus variables for the inequalities and splitting free variables into their
+//! positive and negative parts. The method then works on the homogeneous self-dual model
+//! (Xu, Hung and Ye, 1996)
+//!
+//! `Ax = bτ`, `Aᵀy + s = cτ`, `bᵀy − cᵀx = κ` with `x, s, τ, κ >= 0`,
+//!
+//! whose iterates approach either a solution `(x, y, s) / τ` or, with `τ` going to zero, a
+//! certificate of primal or dual infeasibility. Each iteration takes Mehrotra's predictor step
+//! towards `XSe = 0` and `τκ = 0`, from whose progress the centering parameter is chosen, and then
+//! a corrector step, both solved through the same normal equations `AXS⁻¹Aᵀ`.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::{Error, Returns};
+use super::problem::LinearProgram;
+use crate::dense::{dot, norm_inf, to_dense, Cholesky, Matrix};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+/// Number of increases of the regularization of singular normal equations.
+const MAX_REGULARIZATIONS: usize = 6;
+
+//{{{ struct: Options
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Options {
+    /// Stops once the relative primal and dual residuals and the relative duality gap fall below
+    /// `tol`, which is also the tolerance of the infeasibility certificates. These are only
+    /// accepted once `τ <= tol·κ`.
+    pub tol: f64,
+    /// Maximum number of iterations.
+    pub max_iter: usize,
+    /// Fraction of the distance to the boundary covered by a step.
+    pub step_fraction: f64,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            tol: 1e-8,
+            max_iter: 100,
+            step_fraction: 0.99,
+        }
+    }
+}
+//}}}
+//{{{ enum: Column
+/// Recovery of a variable of the program from the standard form.
+#[derive(Copy, Clone, Debug)]
+enum Column {
+    /// `x = lower + x'`.
+    Shifted { col: usize, lower: f64 },
+    /// `x = upper − x'`.
+    Reflected { col: usize, upper: f64 },
+    /// `x = x⁺ − x⁻`.
+    Split { pos: usize, neg: usize },
+}
+//}}}
+//{{{ struct: StandardForm
+/// The program as `min cᵀx` subject to `Ax = b` and `x >= 0`.
+struct StandardForm {
+    a: Matrix,
+    b: Vec<f64>,
+    c: Vec<f64>,
+    columns: Vec<Column>,
+}
+//}}}
+//{{{ impl: StandardForm
+impl StandardForm {
+    fn new(problem: &LinearProgram) -> Self {
+        let (n, num_eq, num_ineq) = (problem.dim(), problem.num_eq(), problem.num_ineq());
+        let a_eq = to_dense(problem.a_eq());
+        let a_ineq = to_dense(problem.a_ineq());
+        let rows: Vec<&[f64]> = (0..num_eq)
+            .map(|i| a_eq.row(i))
+            .chain((0..num_ineq).map(|i| a_ineq.row(i)))
+            .collect();
+        let mut b: Vec<f64> = problem.b_eq().iter().chain(problem.b_ineq()).copied().collect();
+        let (lower, upper) = (problem.bounds().lower(), problem.bounds().upper());
+
+        // columns as (coefficients of the constraint rows, cost), bound rows being added after
+        let mut cols: Vec<(Vec<f64>, f64)> = Vec::new();
+        let mut columns = Vec::with_capacity(n);
+        let mut bound_rows: Vec<(usize, f64)> = Vec::new();
+        for j in 0..n {
+            let col: Vec<f64> = rows.iter().map(|row| row[j]).collect();
+            let cost = problem.cost()[j];
+            if lower[j].is_finite() {
+                for (bi, a) in b.iter_mut().zip(&col) {
+                    *bi -= a * lower[j];
+                }
+                columns.push(Column::Shifted {
+                    col: cols.len(),
+                    lower: lower[j],
+                });
+                if upper[j].is_finite() {
+                    bound_rows.push((cols.len(), upper[j] - lower[j]));
+                }
+                cols.push((col, cost));
+            } else if upper[j].is_finite() {
+                for (bi, a) in b.iter_mut().zip(&col) {
+                    *bi -= a * upper[j];
+                }
+                columns.push(Column::Reflected {
+                    col: cols.len(),
+                    upper: upper[j],
+                });
+                cols.push((col.iter().map(|a| -a).collect(), -cost));
+            } else {
+                columns.push(Column::Split {
+                    pos: cols.len(),
+                    neg: cols.len() + 1,
+                });
+                let neg = col.iter().map(|a| -a).collect();
+                cols.push((col, cost));
+                cols.push((neg, -cost));
+            }
+        }
+        for i in 0..num_ineq {
+            let mut col = vec![0.0; num_eq + num_ineq];
+            col[num_eq + i] = -1.0;
+            cols.push((col, 0.0));
+        }
+
+        let m = num_eq + num_ineq + bound_rows.len();
+        let num_vars = cols.len() + bound_rows.len();
+        let mut a = Matrix::zeros(m, num_vars);
+        for (j, (col, _)) in cols.iter().enumerate() {
+            for (i, v) in col.iter().enumerate() {
+                a[(i, j)] = *v;
+            }
+        }
+        for (k, (j, width)) in bound_rows.iter().enumerate() {
+            let i = num_eq + num_ineq + k;
+            a[(i, *j)] = 1.0;
+            a[(i, cols.len() + k)] = 1.0;
+            b.push(*width);
+        }
+        let mut c: Vec<f64> = cols.iter().map(|(_, cost)| *cost).collect();
+        c.resize(num_vars, 0.0);
+        Self { a, b, c, columns }
+    }
+
+    /// Product `Ax`.
+    fn mul(&self, x: &[f64]) -> Vec<f64> {
+        self.a.mul_vec(x)
+    }
+
+    /// Product `Aᵀy`.
+    fn mul_tr(&self, y: &[f64]) -> Vec<f64> {
+        (0..self.c.len())
+            .map(|j| (0..self.b.len()).map(|i| self.a[(i, j)] * y[i]).sum())
+            .collect()
+    }
+
+    /// Factorization of the normal equations `A diag(d) Aᵀ`, regularized if they are singular.
+    fn normal_equations(&self, d: &[f64]) -> Option<Cholesky> {
+        let m = self.b.len();
+        let mut normal = Matrix::zeros(m, m);
+        for i in 0..m {
+            for k in 0..=i {
+                normal[(i, k)] = (0..d.len()).map(|j| self.a[(i, j)] * d[j] * self.a[(k, j)]).sum();
+            }
+        }
+        let scale = (0..m).fold(1.0, |acc: f64, i| acc.max(normal[(i, i)]));
+        let mut delta = 0.0;
+        for _ in 0..=MAX_REGULARIZATIONS {
+            let mut reg = normal.clone();
+            for i in 0..m {
+                reg[(i, i)] += delta;
+            }
+            if let Some(chol) = Cholesky::new(&reg) {
+                return Some(chol);
+            }
+            delta = if delta == 0.0 { 1e-14 * scale } else { 100.0 * delta };
+            //{{{ trace
+            debug!(target: "lp", "Singular normal equations, regularizing with delta = {delta:1.4e}");
+            //}}}
+        }
+        None
+    }
+}
+//}}}
+//{{{ struct: Iterate
+/// Point of the homogeneous self-dual model.
+#[derive(Clone, Debug)]
+struct Iterate {
+    x: Vec<f64>,
+    y: Vec<f64>,
+    s: Vec<f64>,
+    tau: f64,
+    kappa: f64,
+}
+//}}}
+//{{{ impl: Iterate
+impl Iterate {
+    /// Largest step `α <= 1` keeping the iterate along `dir` nonnegative.
+    fn max_step(&self, dir: &Iterate) -> f64 {
+        let pairs = self
+            .x
+            .iter()
+            .zip(&dir.x)
+            .chain(self.s.iter().zip(&dir.s))
+            .chain([(&self.tau, &dir.tau), (&self.kappa, &dir.kappa)]);
+        pairs
+            .filter(|(_, d)| **d < 0.0)
+            .fold(1.0, |alpha: f64, (v, d)| alpha.min(-v / d))
+    }
+
+    /// The iterate moved by `alpha` along `dir`.
+    fn step(&self, alpha: f64, dir: &Iterate) -> Iterate {
+        let add = |v: &[f64], d: &[f64]| v.iter().zip(d).map(|(v, d)| v + alpha * d).collect();
+        Iterate {
+            x: add(&self.x, &dir.x),
+            y: add(&self.y, &dir.y),
+            s: add(&self.s, &dir.s),
+            tau: self.tau + alpha * dir.tau,
+            kappa: self.kappa + alpha * dir.kappa,
+        }
+    }
+
+    /// Average complementarity `(xᵀs + τκ) / (n + 1)`.
+    fn centrality(&self) -> f64 {
+        (dot(&self.x, &self.s) + self.tau * self.kappa) / (self.x.len() + 1) as f64
+    }
+}
+//}}}
+//{{{ struct: Residuals
+/// Right-hand side of the Newton equations of the homogeneous model.
+struct Residuals {
+    /// `bτ − Ax`.
+    primal: Vec<f64>,
+    /// `cτ − Aᵀy − s`.
+    dual: Vec<f64>,
+    /// `bᵀy − cᵀx − κ`.
+    gap: f64,
+    /// Targets of `S dx + X ds` and `κ dτ + τ dκ`.
+    compl: Vec<f64>,
+    compl_tau: f64,
+}
+//}}}
+//{{{ struct: PredictorCorrector
+/// Mehrotra predictor-corrector solver of a [`LinearProgram`].
+///
+/// Solutions lie in the relative interior of the optimal face rather than at a vertex.
+pub struct PredictorCorrector {
+    problem: LinearProgram,
+    opts: Options,
+}
+//}}}
+//{{{ impl: PredictorCorrector
+impl PredictorCorrector {
+    pub fn new(problem: LinearProgram, opts: Options) -> Self {
+        Self { problem, opts }
+    }
+
+    pub fn problem(&self) -> &LinearProgram {
+        &self.problem
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    pub fn minimize(&mut self) -> Result<Returns, Error> {
+        //{{{ trace
+        error!(target: "lp", "--- Entering minimize ---");
+        //}}}
+        let opts = self.opts;
+        let form = StandardForm::new(&self.problem);
+        let (m, n) = (form.b.len(), form.c.len());
+        let (b_norm, c_norm) = (norm_inf(&form.b), norm_inf(&form.c));
+        let mut it = Iterate {
+            x: vec![1.0; n],
+            y: vec![0.0; m],
+            s: vec![1.0; n],
+            tau: 1.0,
+            kappa: 1.0,
+        };
+        let mut num_iterations = 0;
+        loop {
+            let ax = form.mul(&it.x);
+            let aty = form.mul_tr(&it.y);
+            let primal: Vec<f64> = form.b.iter().zip(&ax).map(|(b, ax)| b * it.tau - ax).collect();
+            let dual: Vec<f64> = (0..n).map(|j| form.c[j] * it.tau - aty[j] - it.s[j]).collect();
+            let (cx, by) = (dot(&form.c, &it.x), dot(&form.b, &it.y));
+            let mu = it.centrality();
+
+            let primal_res = norm_inf(&primal) / it.tau / (1.0 + b_norm);
+            let dual_res = norm_inf(&dual) / it.tau / (1.0 + c_norm);
+            let gap = (cx - by).abs() / it.tau / (1.0 + (cx / it.tau).abs());
+            //{{{ trace
+            info!(target: "lp", "i = {} primal = {:1.4e} dual = {:1.4e} gap = {:1.4e} tau = {:1.4e} kappa = {:1.4e}",
+                num_iterations, primal_res, dual_res, gap, it.tau, it.kappa);
+            //}}}
+            if primal_res <= opts.tol && dual_res <= opts.tol && gap <= opts.tol {
+                //{{{ trace
+                error!(target: "lp", "--- Leaving minimize ---");
+                //}}}
+                return Ok(self.returns(&form, &it, num_iterations));
+            }
+
+            // certificates: Aᵀy <= 0 with bᵀy > 0, or Ax = 0 with x >= 0 and cᵀx < 0, only
+            // trusted once τ has vanished against κ, since the iterates of a feasible problem
+            // with a zero cost and a distant solution can pass the tests while τ is still large
+            let aty_s: Vec<f64> = aty.iter().zip(&it.s).map(|(a, s)| a + s).collect();
+            let vanishing_tau = it.tau <= opts.tol * it.kappa;
+            if vanishing_tau && by > 0.0 && norm_inf(&aty_s) <= opts.tol * by {
+                //{{{ trace
+                info!(target: "lp", "Certificate of primal infeasibility found");
+                //}}}
+                return Err(Error::Infeasible);
+            }
+            if vanishing_tau && cx < 0.0 && norm_inf(&ax) <= opts.tol * -cx {
+                //{{{ trace
+                info!(target: "lp", "Certificate of dual infeasibility found");
+                //}}}
+                return Err(Error::Unbounded);
+            }
+            if num_iterations >= opts.max_iter {
+                //{{{ trace
+                info!(target: "lp", "Did not converge within {} iterations", opts.max_iter);
+                //}}}
+                return Err(Error::MaxIterations {
+                    max_iter: opts.max_iter,
+                });
+            }
+
+            let d: Vec<f64> = it.x.iter().zip(&it.s).map(|(x, s)| x / s).collect();
+            let chol = form.normal_equations(&d).ok_or(Error::Singular)?;
+
+            // predictor, the affine scaling step towards the solution of the model
+            let affine = Residuals {
+                primal: primal.clone(),
+                dual: dual.clone(),
+                gap: by - cx - it.kappa,
+                compl: it.x.iter().zip(&it.s).map(|(x, s)| -x * s).collect(),
+                compl_tau: -it.tau * it.kappa,
+            };
+            let dir_aff = newton(&form, &chol, &d, &it, &affine);
+            let alpha_aff = it.max_step(&dir_aff);
+            let sigma = (it.step(alpha_aff, &dir_aff).centrality() / mu).powi(3);
+
+            // corrector, aiming at the central path and compensating the second-order term
+            let eta = 1.0 - sigma;
+            let corrector = Residuals {
+                primal: primal.iter().map(|r| eta * r).collect(),
+                dual: dual.iter().map(|r| eta * r).collect(),
+                gap: eta * (by - cx - it.kappa),
+                compl: (0..n)
+                    .map(|j| -it.x[j] * it.s[j] - dir_aff.x[j] * dir_aff.s[j] + sigma * mu)
+                    .collect(),
+                compl_tau: -it.tau * it.kappa - dir_aff.tau * dir_aff.kappa + sigma * mu,
+            };
+            let dir = newton(&form, &chol, &d, &it, &corrector);
+            let alpha = (opts.step_fraction * it.max_step(&dir)).min(1.0);
+            //{{{ trace
+            debug!(target: "lp", "alpha_aff = {alpha_aff:1.4e} sigma = {sigma:1.4e} alpha = {alpha:1.4e}");
+            //}}}
+            it = it.step(alpha, &dir);
+            num_iterations += 1;
+        }
+    }
+
+    /// Solution of the program from the iterate of the model on its standard form.
+    fn returns(&self, form: &StandardForm, it: &Iterate, num_iterations: usize) -> Returns {
+        let problem = &self.problem;
+        let xs: Vec<f64> = it.x.iter().map(|x| x / it.tau).collect();
+        let x: Vec<f64> = form
+            .columns
+            .iter()
+            .map(|col| match *col {
+                Column::Shifted { col, lower } => lower + xs[col],
+                Column::Reflected { col, upper } => upper - xs[col],
+                Column::Split { pos, neg } => xs[pos] - xs[neg],
+            })
+            .collect();
+        let num_eq = problem.num_eq();
+        let lambda: Vec<f64> = it.y[..num_eq].iter().map(|y| y / it.tau).collect();
+        let mu: Vec<f64> = it.y[num_eq..num_eq + problem.num_ineq()]
+            .iter()
+            .map(|y| y / it.tau)
+            .collect();
+        Returns {
+            objective: problem.objective(&x),
+            reduced_costs: problem.reduced_costs(&lambda, &mu),
+            x,
+            lambda,
+            mu,
+            num_iterations,
+        }
+    }
+}
+//}}}
+//{{{ fun: newton
+/// Newton direction of the homogeneous model for the right-hand side `rhs`.
+///
+/// Eliminating `ds` and `dκ` gives `dx = D(Aᵀdy − c dτ − r_d + X⁻¹r_xs)` with `D = XS⁻¹`, so that
+/// `dy = p + q dτ` for the solutions `p` and `q` of two systems with the normal equations, and
+/// the gap equation then gives `dτ`.
+fn newton(form: &StandardForm, chol: &Cholesky, d: &[f64], it: &Iterate, rhs: &Residuals) -> Iterate {
+    let n = form.c.len();
+    let r_dual: Vec<f64> = (0..n).map(|j| rhs.dual[j] - rhs.compl[j] / it.x[j]).collect();
+    let scaled = |v: &[f64]| -> Vec<f64> { v.iter().zip(d).map(|(v, d)| v * d).collect() };
+    let ad_r = form.mul(&scaled(&r_dual));
+    let p_rhs: Vec<f64> = rhs.primal.iter().zip(&ad_r).map(|(r, a)| r + a).collect();
+    let p = chol.solve(&p_rhs);
+    let ad_c = form.mul(&scaled(&form.c));
+    let q_rhs: Vec<f64> = ad_c.iter().zip(&form.b).map(|(a, b)| a + b).collect();
+    let q = chol.solve(&q_rhs);
+
+    let atp = form.mul_tr(&p);
+    let atq = form.mul_tr(&q);
+    let u: Vec<f64> = (0..n).map(|j| d[j] * (atp[j] - r_dual[j])).collect();
+    let v: Vec<f64> = (0..n).map(|j| d[j] * (atq[j] - form.c[j])).collect();
+    let r_gap = rhs.gap - rhs.compl_tau / it.tau;
+    let numer = r_gap - dot(&form.c, &u) + dot(&form.b, &p);
+    let denom = dot(&form.c, &v) - dot(&form.b, &q) - it.kappa / it.tau;
+    let dtau = numer / denom;
+
+    let dy: Vec<f64> = p.iter().zip(&q).map(|(p, q)| p + q * dtau).collect();
+    let dx: Vec<f64> = u.iter().zip(&v).map(|(u, v)| u + v * dtau).collect();
+    let ds: Vec<f64> = (0..n).map(|j| (rhs.compl[j] - it.s[j] * dx[j]) / it.x[j]).collect();
+    let dkappa = (rhs.compl_tau - it.kappa * dtau) / it.tau;
+    Iterate {
+        x: dx,
+        y: dy,
+        s: ds,
+        tau: dtau,
+        kappa: dkappa,
+    }
+}
+//}}}
@@ -0,0 +1,24 @@
+//! Dense linear programming.
+//!
+//! Minimizes `cᵀx` subject to linear equality and inequality constraints and bounds on the
+//! variables, either with a revised simplex method or with a primal-dual interior point method,
+//! reporting the dual values in both cases.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+mod common;
+mod interior_point;
+mod problem;
+mod simplex;
+
+pub use common::{Error as LinprogError, Returns as LinprogReturns};
+pub use interior_point::{Options as PredictorCorrectorOptions, PredictorCorrector};
+pub use problem::LinearProgram;
+pub use simplex::{Options as SimplexOptions, Simplex};
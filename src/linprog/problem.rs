@@ -0,0 +1,130 @@
+//! Linear programs `min cᵀx` subject to `A_E x = b_E`, `A_I x >= b_I` and `l <= x <= u`.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use crate::bound_constrained::Bounds;
+use crate::dense::{dot, to_dense};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use topohedral_linalg::dmatrix::DMatrix;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: LinearProgram
+/// A dense linear program, the rows of `A_E` and `A_I` being the normals of the constraints.
+///
+/// Variables are nonnegative unless other bounds are given, as in the standard form.
+#[derive(Clone, Debug)]
+pub struct LinearProgram {
+    cost: Vec<f64>,
+    a_eq: DMatrix<f64>,
+    b_eq: Vec<f64>,
+    a_ineq: DMatrix<f64>,
+    b_ineq: Vec<f64>,
+    bounds: Bounds,
+}
+//}}}
+//{{{ impl: LinearProgram
+impl LinearProgram {
+    /// The program with cost `cost` over the nonnegative orthant.
+    pub fn new(cost: Vec<f64>) -> Self {
+        let n = cost.len();
+        let bounds = Bounds::new(vec![0.0; n], vec![f64::INFINITY; n]).unwrap();
+        Self {
+            cost,
+            a_eq: DMatrix::zeros(0, n),
+            b_eq: Vec::new(),
+            a_ineq: DMatrix::zeros(0, n),
+            b_ineq: Vec::new(),
+            bounds,
+        }
+    }
+
+    /// Replaces the equality constraints by `A_E x = b_E`.
+    pub fn with_equalities(mut self, a_eq: DMatrix<f64>, b_eq: Vec<f64>) -> Self {
+        assert!(
+            a_eq.nrows() == b_eq.len() && a_eq.ncols() == self.dim(),
+            "equality constraints do not match the problem size"
+        );
+        self.a_eq = a_eq;
+        self.b_eq = b_eq;
+        self
+    }
+
+    /// Replaces the inequality constraints by `A_I x >= b_I`.
+    pub fn with_inequalities(mut self, a_ineq: DMatrix<f64>, b_ineq: Vec<f64>) -> Self {
+        assert!(
+            a_ineq.nrows() == b_ineq.len() && a_ineq.ncols() == self.dim(),
+            "inequality constraints do not match the problem size"
+        );
+        self.a_ineq = a_ineq;
+        self.b_ineq = b_ineq;
+        self
+    }
+
+    /// Replaces the bounds on the variables, [`Bounds::unbounded`] making them all free.
+    pub fn with_bounds(mut self, bounds: Bounds) -> Self {
+        assert_eq!(bounds.len(), self.dim(), "bounds do not match the problem size");
+        self.bounds = bounds;
+        self
+    }
+
+    /// Number of variables.
+    pub fn dim(&self) -> usize {
+        self.cost.len()
+    }
+
+    pub fn num_eq(&self) -> usize {
+        self.b_eq.len()
+    }
+
+    pub fn num_ineq(&self) -> usize {
+        self.b_ineq.len()
+    }
+
+    pub fn cost(&self) -> &[f64] {
+        &self.cost
+    }
+
+    pub fn a_eq(&self) -> &DMatrix<f64> {
+        &self.a_eq
+    }
+
+    pub fn b_eq(&self) -> &[f64] {
+        &self.b_eq
+    }
+
+    pub fn a_ineq(&self) -> &DMatrix<f64> {
+        &self.a_ineq
+    }
+
+    pub fn b_ineq(&self) -> &[f64] {
+        &self.b_ineq
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    /// Value `cᵀx` of the objective.
+    pub fn objective(&self, x: &[f64]) -> f64 {
+        dot(&self.cost, x)
+    }
+
+    /// Reduced costs `c − A_Eᵀλ − A_Iᵀμ`, the multipliers of the bounds.
+    pub(crate) fn reduced_costs(&self, lambda: &[f64], mu: &[f64]) -> Vec<f64> {
+        let a_eq = to_dense(&self.a_eq);
+        let a_ineq = to_dense(&self.a_ineq);
+        (0..self.dim())
+            .map(|j| {
+                let eq: f64 = (0..self.num_eq()).map(|i| a_eq[(i, j)] * lambda[i]).sum();
+                let ineq: f64 = (0..self.num_ineq()).map(|i| a_ineq[(i, j)] * mu[i]).sum();
+                self.cost[j] - eq - ineq
+            })
+            .collect()
+    }
+}
+//}}}
@@ -0,0 +1,303 @@
+//! Bounded-variable revised simplex method.
+//!
+//! The inequalities are turned into equalities with nonnegative surplus variables, so that the
+//! program reads `min cᵀx` subject to `Ax = b` and `l <= x <= u`. Nonbasic variables sit at one of
+//! their bounds, or at zero if they are free, and an entering variable moves until a basic
+//! variable reaches a bound or it reaches its own other bound (Chvátal, "Linear Programming",
+//! 1983, ch. 8). Phase 1 starts from a basis of artificial variables and minimizes their sum,
+//! after which the artificial variables are fixed at zero for phase 2. Both the entering and the
+//! leaving variables are chosen by Bland's rule, the smallest index among the candidates, which
+//! prevents cycling on degenerate vertices. The basis is refactorized at every iteration, so the
+//! method is meant for small dense programs.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::{Error, Returns};
+use super::problem::LinearProgram;
+use crate::dense::{norm_inf, to_dense, Lu, Matrix};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Options
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Options {
+    /// Tolerance of the tests on reduced costs and pivots.
+    pub tol: f64,
+    /// The program is infeasible if the sum of the artificial variables after phase 1 exceeds
+    /// `feas_tol (1 + ‖b‖_∞)`.
+    pub feas_tol: f64,
+    /// Maximum number of iterations of both phases together.
+    pub max_iter: usize,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            tol: 1e-9,
+            feas_tol: 1e-7,
+            max_iter: 10_000,
+        }
+    }
+}
+//}}}
+//{{{ struct: Tableau
+/// Program in equality form with the current basis and values of all variables.
+struct Tableau {
+    a: Matrix,
+    b: Vec<f64>,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+    x: Vec<f64>,
+    basis: Vec<usize>,
+    is_basic: Vec<bool>,
+}
+//}}}
+//{{{ impl: Tableau
+impl Tableau {
+    /// Runs simplex iterations with cost `cost` from the current basis, returning the simplex
+    /// multipliers `B⁻ᵀc_B` at the optimum.
+    fn optimize(
+        &mut self,
+        cost: &[f64],
+        opts: &Options,
+        phase: usize,
+        num_iterations: &mut usize,
+    ) -> Result<Vec<f64>, Error> {
+        let m = self.basis.len();
+        loop {
+            let mut basis_matrix = Matrix::zeros(m, m);
+            for (k, &j) in self.basis.iter().enumerate() {
+                for i in 0..m {
+                    basis_matrix[(i, k)] = self.a[(i, j)];
+                }
+            }
+            let lu = Lu::new(basis_matrix).ok_or(Error::Singular)?;
+
+            // basic values recomputed from the nonbasic ones, so that rounding does not build up
+            let rhs: Vec<f64> = (0..m)
+                .map(|i| {
+                    let nonbasic: f64 = (0..self.x.len())
+                        .filter(|&j| !self.is_basic[j])
+                        .map(|j| self.a[(i, j)] * self.x[j])
+                        .sum();
+                    self.b[i] - nonbasic
+                })
+                .collect();
+            for (&j, v) in self.basis.iter().zip(lu.solve(&rhs)) {
+                self.x[j] = v;
+            }
+            let cost_basic: Vec<f64> = self.basis.iter().map(|&j| cost[j]).collect();
+            let pi = lu.solve_transpose(&cost_basic);
+
+            // Bland's rule, the first nonbasic variable whose move decreases the cost
+            let entering = (0..self.x.len()).filter(|&j| !self.is_basic[j]).find_map(|j| {
+                let d = cost[j] - (0..m).map(|i| self.a[(i, j)] * pi[i]).sum::<f64>();
+                if d < -opts.tol && self.x[j] < self.upper[j] {
+                    Some((j, 1.0))
+                } else if d > opts.tol && self.x[j] > self.lower[j] {
+                    Some((j, -1.0))
+                } else {
+                    None
+                }
+            });
+            let Some((entering, dir)) = entering else {
+                //{{{ trace
+                info!(target: "lp", "Phase {phase} optimal after {num_iterations} iterations");
+                //}}}
+                return Ok(pi);
+            };
+            if *num_iterations >= opts.max_iter {
+                //{{{ trace
+                info!(target: "lp", "Did not converge within {} iterations", opts.max_iter);
+                //}}}
+                return Err(Error::MaxIterations {
+                    max_iter: opts.max_iter,
+                });
+            }
+            let column: Vec<f64> = (0..m).map(|i| self.a[(i, entering)]).collect();
+            let w = lu.solve(&column);
+
+            // ratio test, a flip of the entering variable winning ties, then the smallest index
+            let mut step = self.upper[entering] - self.lower[entering];
+            let mut leaving: Option<usize> = None;
+            for (r, &j) in self.basis.iter().enumerate() {
+                let rate = dir * w[r];
+                let ratio = if rate > opts.tol {
+                    (self.x[j] - self.lower[j]) / rate
+                } else if rate < -opts.tol {
+                    (self.upper[j] - self.x[j]) / -rate
+                } else {
+                    continue;
+                };
+                let ratio = ratio.max(0.0);
+                let smaller = ratio < step - opts.tol;
+                let tied = ratio <= step + opts.tol && leaving.is_some_and(|l| j < self.basis[l]);
+                if smaller || tied {
+                    step = ratio;
+                    leaving = Some(r);
+                }
+            }
+            if step.is_infinite() {
+                //{{{ trace
+                info!(target: "lp", "Unbounded along variable {entering}");
+                //}}}
+                return Err(Error::Unbounded);
+            }
+
+            self.x[entering] += dir * step;
+            for (r, &j) in self.basis.iter().enumerate() {
+                self.x[j] -= dir * step * w[r];
+            }
+            let left = leaving.map(|r| self.basis[r]);
+            if let Some(r) = leaving {
+                let j = self.basis[r];
+                self.x[j] = if dir * w[r] > 0.0 {
+                    self.lower[j]
+                } else {
+                    self.upper[j]
+                };
+                self.is_basic[j] = false;
+                self.is_basic[entering] = true;
+                self.basis[r] = entering;
+            }
+            *num_iterations += 1;
+            //{{{ trace
+            info!(target: "lp", "i = {} phase = {} entering = {} leaving = {:?} step = {:1.4e}",
+                num_iterations, phase, entering, left, step);
+            //}}}
+        }
+    }
+}
+//}}}
+//{{{ struct: Simplex
+/// Revised simplex solver of a [`LinearProgram`], which finds an optimal vertex.
+pub struct Simplex {
+    problem: LinearProgram,
+    opts: Options,
+}
+//}}}
+//{{{ impl: Simplex
+impl Simplex {
+    pub fn new(problem: LinearProgram, opts: Options) -> Self {
+        Self { problem, opts }
+    }
+
+    pub fn problem(&self) -> &LinearProgram {
+        &self.problem
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    pub fn minimize(&mut self) -> Result<Returns, Error> {
+        //{{{ trace
+        error!(target: "lp", "--- Entering minimize ---");
+        //}}}
+        let problem = &self.problem;
+        let (n, num_eq, num_ineq) = (problem.dim(), problem.num_eq(), problem.num_ineq());
+        let m = num_eq + num_ineq;
+        let num_vars = n + num_ineq + m;
+        let a_eq = to_dense(problem.a_eq());
+        let a_ineq = to_dense(problem.a_ineq());
+        let b: Vec<f64> = problem.b_eq().iter().chain(problem.b_ineq()).copied().collect();
+
+        // columns of the variables, then of the surplus variables, then of the artificial ones
+        let mut a = Matrix::zeros(m, num_vars);
+        for j in 0..n {
+            for i in 0..num_eq {
+                a[(i, j)] = a_eq[(i, j)];
+            }
+            for i in 0..num_ineq {
+                a[(num_eq + i, j)] = a_ineq[(i, j)];
+            }
+        }
+        for i in 0..num_ineq {
+            a[(num_eq + i, n + i)] = -1.0;
+        }
+        let mut lower = problem.bounds().lower().to_vec();
+        let mut upper = problem.bounds().upper().to_vec();
+        lower.resize(num_vars, 0.0);
+        upper.resize(num_vars, f64::INFINITY);
+        let mut x: Vec<f64> = (0..n + num_ineq)
+            .map(|j| {
+                if lower[j].is_finite() {
+                    lower[j]
+                } else if upper[j].is_finite() {
+                    upper[j]
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let residual: Vec<f64> = (0..m)
+            .map(|i| b[i] - (0..n + num_ineq).map(|j| a[(i, j)] * x[j]).sum::<f64>())
+            .collect();
+        for (i, r) in residual.iter().enumerate() {
+            a[(i, n + num_ineq + i)] = if *r >= 0.0 { 1.0 } else { -1.0 };
+            x.push(r.abs());
+        }
+        let mut is_basic = vec![false; num_vars];
+        for flag in &mut is_basic[n + num_ineq..] {
+            *flag = true;
+        }
+        let mut tableau = Tableau {
+            a,
+            b: b.clone(),
+            lower,
+            upper,
+            x,
+            basis: (n + num_ineq..num_vars).collect(),
+            is_basic,
+        };
+
+        let mut num_iterations = 0;
+        let mut cost = vec![0.0; num_vars];
+        for c in &mut cost[n + num_ineq..] {
+            *c = 1.0;
+        }
+        tableau.optimize(&cost, &self.opts, 1, &mut num_iterations)?;
+        let infeasibility: f64 = tableau.x[n + num_ineq..].iter().sum();
+        if infeasibility > self.opts.feas_tol * (1.0 + norm_inf(&b)) {
+            //{{{ trace
+            info!(target: "lp", "Phase 1 ended with infeasibility {:1.4e}", infeasibility);
+            //}}}
+            return Err(Error::Infeasible);
+        }
+
+        // artificial variables left in the basis are degenerate and held at zero
+        for j in n + num_ineq..num_vars {
+            tableau.upper[j] = 0.0;
+        }
+        let mut cost = problem.cost().to_vec();
+        cost.resize(num_vars, 0.0);
+        let pi = tableau.optimize(&cost, &self.opts, 2, &mut num_iterations)?;
+
+        let x = tableau.x[..n].to_vec();
+        let lambda = pi[..num_eq].to_vec();
+        let mu = pi[num_eq..].to_vec();
+        //{{{ trace
+        error!(target: "lp", "--- Leaving minimize ---");
+        //}}}
+        Ok(Returns {
+            objective: problem.objective(&x),
+            reduced_costs: problem.reduced_costs(&lambda, &mu),
+            x,
+            lambda,
+            mu,
+            num_iterations,
+        })
+    }
+}
+//}}}
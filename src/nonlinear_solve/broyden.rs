@@ -0,0 +1,257 @@
+//! Broyden's quasi-Newton methods.
+//!
+//! The Jacobian is computed once at the starting point and then replaced by rank-one secant
+//! updates, either of the Jacobian itself (the "good" method) or of its inverse (the "bad" one).
+//! The steps are searched on the merit function `½‖F‖²`, whose slope along the quasi-Newton
+//! direction is taken by a directional difference of `F`. Whenever the direction fails to decrease
+//! the merit function, or the updated matrix becomes singular, the Jacobian is recomputed.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::{
+    evaluate, initial_returns, jacobian, jacobian_product, search, Error, Residual, Returns,
+};
+use crate::dense::{dot, norm_inf, Lu, Matrix};
+use crate::line_search::{self as ls, BacktrackingOptions, LineSearchError, LineSearchMethod};
+use crate::DenseVector;
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ enum: Update
+/// Secant update applied after each step `s` with change `y` in the residual.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Update {
+    /// `B ← B + (y − B s) sᵀ / sᵀs` on the Jacobian, which is factorized at every iteration.
+    Good,
+    /// `H ← H + (s − H y) yᵀ / yᵀy` on the inverse Jacobian, which is applied directly.
+    Bad,
+}
+//}}}
+//{{{ struct: Options
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Options {
+    pub update: Update,
+    /// Stops once `‖F‖_∞ <= f_tol`.
+    pub f_tol: f64,
+    /// Maximum number of iterations.
+    pub max_iter: usize,
+    /// Relative step of the finite differences.
+    pub fd_step: f64,
+    pub ls_method: LineSearchMethod,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            update: Update::Good,
+            f_tol: 1e-10,
+            max_iter: 200,
+            fd_step: f64::EPSILON.sqrt(),
+            ls_method: LineSearchMethod::Backtracking(BacktrackingOptions::default()),
+        }
+    }
+}
+//}}}
+//{{{ struct: Broyden
+/// Broyden solver of a [`Residual`].
+pub struct Broyden<R: Residual> {
+    residual: R,
+    x0: R::Vector,
+    opts: Options,
+}
+//}}}
+//{{{ impl: Broyden
+impl<R> Broyden<R>
+where
+    R: Residual,
+    R::Vector: DenseVector + Debug,
+{
+    pub fn new(residual: R, x0: R::Vector, opts: Options) -> Self {
+        Self { residual, x0, opts }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    pub fn solve(&mut self) -> Result<Returns<R::Vector>, Error<R::Vector>> {
+        //{{{ trace
+        error!(target: "nls", "--- Entering broyden ---");
+        //}}}
+        let opts = self.opts;
+        let (mut fx, mut ret) = initial_returns(&mut self.residual, &self.x0)?;
+        let mut x = self.x0.to_vec();
+        let mut line_search = ls::create(
+            super::MeritFn::new(
+                self.residual.clone(),
+                self.x0.clone(),
+                self.x0.clone(),
+                opts.fd_step,
+            ),
+            opts.ls_method,
+        );
+        // B for the good update and H for the bad one, `None` when it must be recomputed
+        let mut mat: Option<Matrix> = None;
+        let mut fresh = false;
+        loop {
+            //{{{ trace
+            info!(target: "nls", "i = {} ‖F‖ = {:1.4e}", ret.num_iterations, ret.residual_norm);
+            //}}}
+            if ret.residual_norm <= opts.f_tol {
+                //{{{ trace
+                error!(target: "nls", "--- Leaving broyden ---");
+                //}}}
+                return Ok(ret);
+            }
+            if ret.num_iterations >= opts.max_iter {
+                //{{{ trace
+                info!(target: "nls", "Did not converge within {} iterations", opts.max_iter);
+                //}}}
+                return Err(Error::MaxIterations {
+                    max_iter: opts.max_iter,
+                    partial: Box::new(ret),
+                });
+            }
+            if mat.is_none() {
+                //{{{ trace
+                debug!(target: "nls", "Computing the Jacobian");
+                //}}}
+                let jac = jacobian(&mut self.residual, &x, &fx, opts.fd_step, &mut ret);
+                mat = match opts.update {
+                    Update::Good => Some(jac),
+                    Update::Bad => inverse(jac),
+                };
+                fresh = true;
+            }
+            let rhs: Vec<f64> = fx.iter().map(|f| -f).collect();
+            let dir = match (opts.update, &mat) {
+                (Update::Good, Some(b)) => Lu::new(b.clone()).map(|lu| lu.solve(&rhs)),
+                (Update::Bad, Some(h)) => Some(h.mul_vec(&rhs)),
+                (_, None) => None,
+            };
+            let Some(dir) = dir else {
+                if fresh {
+                    //{{{ trace
+                    info!(target: "nls", "Singular Jacobian");
+                    //}}}
+                    return Err(Error::SingularJacobian {
+                        partial: Box::new(ret),
+                    });
+                }
+                mat = None;
+                continue;
+            };
+            let jd = jacobian_product(
+                &mut self.residual,
+                None,
+                &x,
+                &fx,
+                &dir,
+                opts.fd_step,
+                &mut ret,
+            );
+            let dphi0 = dot(&fx, &jd);
+            let result = if dphi0 < 0.0 {
+                let res = &self.residual;
+                search(
+                    &mut *line_search,
+                    res,
+                    &x,
+                    &fx,
+                    &dir,
+                    dphi0,
+                    opts.fd_step,
+                    &mut ret,
+                )
+            } else {
+                Err(LineSearchError::NotDecreasing)
+            };
+            let alpha = match result {
+                Ok(alpha) => alpha,
+                Err(source) if fresh => {
+                    //{{{ trace
+                    info!(target: "nls", "Line search failed with {source:?}");
+                    //}}}
+                    return Err(Error::LineSearch {
+                        source,
+                        partial: Box::new(ret),
+                    });
+                }
+                Err(_) => {
+                    //{{{ trace
+                    debug!(target: "nls", "No descent along the quasi-Newton direction");
+                    //}}}
+                    mat = None;
+                    continue;
+                }
+            };
+            //{{{ trace
+            debug!(target: "nls", "alpha = {:1.4e} ‖d‖ = {:1.4e}", alpha, norm_inf(&dir));
+            //}}}
+            let s: Vec<f64> = dir.iter().map(|d| alpha * d).collect();
+            for (x, s) in x.iter_mut().zip(&s) {
+                *x += s;
+            }
+            let fx_new = evaluate(&mut self.residual, &x, &mut ret);
+            let y: Vec<f64> = fx_new.iter().zip(&fx).map(|(a, b)| a - b).collect();
+            if let Some(mat) = mat.as_mut() {
+                match opts.update {
+                    Update::Good => rank_one_update(mat, &s, &y),
+                    Update::Bad => rank_one_update(mat, &y, &s),
+                }
+            }
+            fx = fx_new;
+            fresh = false;
+            ret.num_iterations += 1;
+            ret.x = R::Vector::from_slice(&x);
+            ret.residual_norm = norm_inf(&fx);
+        }
+    }
+}
+//}}}
+//{{{ fun: rank_one_update
+/// Secant update `M ← M + (v − M u) uᵀ / uᵀu`, so that `M u = v` afterwards, skipped for a
+/// negligible `u`.
+fn rank_one_update(mat: &mut Matrix, u: &[f64], v: &[f64]) {
+    let uu = dot(u, u);
+    if uu <= f64::MIN_POSITIVE {
+        return;
+    }
+    let mu = mat.mul_vec(u);
+    for i in 0..mat.rows() {
+        let ri = (v[i] - mu[i]) / uu;
+        for (j, uj) in u.iter().enumerate() {
+            mat[(i, j)] += ri * uj;
+        }
+    }
+}
+//}}}
+//{{{ fun: inverse
+/// Inverse of a square matrix, or `None` if it is numerically singular.
+fn inverse(a: Matrix) -> Option<Matrix> {
+    let n = a.rows();
+    let lu = Lu::new(a)?;
+    let mut inv = Matrix::zeros(n, n);
+    let mut e = vec![0.0; n];
+    for j in 0..n {
+        e[j] = 1.0;
+        for (i, v) in lu.solve(&e).into_iter().enumerate() {
+            inv[(i, j)] = v;
+        }
+        e[j] = 0.0;
+    }
+    Some(inv)
+}
+//}}}
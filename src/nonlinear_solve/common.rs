@@ -0,0 +1,240 @@
+//! Residual trait, returns and errors shared by the nonlinear equation solvers.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::merit::MeritFn;
+use crate::dense::{norm_inf, to_dense, Matrix};
+use crate::line_search::{LineSearch, LineSearchError, LineSearchStats};
+use crate::DenseVector;
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+//}}}
+//{{{ dep imports
+use thiserror::Error;
+use topohedral_linalg::dmatrix::DMatrix;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ trait: Residual
+/// Square system of equations `F(x) = 0`.
+pub trait Residual: Clone + Debug {
+    type Vector;
+
+    /// Value `F(x)`, with as many components as `x`.
+    fn eval(&mut self, x: &Self::Vector) -> Self::Vector;
+
+    /// Jacobian `∂Fᵢ/∂xⱼ` at `x`.
+    ///
+    /// The default returns `None`, upon which the solvers approximate it by forward differences,
+    /// or its products by directional differences.
+    fn jacobian(&mut self, _x: &Self::Vector) -> Option<DMatrix<f64>> {
+        None
+    }
+}
+//}}}
+//{{{ struct: Returns
+#[derive(Clone, Debug)]
+pub struct Returns<Vector> {
+    pub x: Vector,
+    /// Infinity norm of `F(x)`.
+    pub residual_norm: f64,
+    pub num_iterations: usize,
+    /// Total number of iterations of the linear solver, zero for the direct methods.
+    pub num_inner_iterations: usize,
+    /// Number of evaluations of `F`, counting each derivative of the merit function as one.
+    pub num_fun_evals: usize,
+    /// Number of evaluations of the Jacobian given by the [`Residual`].
+    pub num_jac_evals: usize,
+    pub line_search: LineSearchStats,
+}
+//}}}
+//{{{ enum: Error
+/// Failure of a nonlinear equation solver.
+///
+/// Every variant carries the last iterate as a partial [`Returns`].
+#[derive(Error, Debug)]
+pub enum Error<Vector: Debug> {
+    #[error("Maximum iterations of {max_iter} reached")]
+    MaxIterations {
+        max_iter: usize,
+        partial: Box<Returns<Vector>>,
+    },
+    /// No step along the direction decreased `½‖F‖²` enough, as happens near a local minimum of
+    /// the merit function which is not a root.
+    #[error("Line search on the merit function failed with error {source}")]
+    LineSearch {
+        source: LineSearchError,
+        partial: Box<Returns<Vector>>,
+    },
+    #[error("The Jacobian is numerically singular")]
+    SingularJacobian { partial: Box<Returns<Vector>> },
+    /// The residual at the starting point `x` is not finite.
+    #[error("Non-finite residual at the starting point")]
+    NonFiniteValue {
+        x: Vector,
+        partial: Box<Returns<Vector>>,
+    },
+}
+//}}}
+//{{{ impl: Error
+impl<Vector: Debug> Error<Vector> {
+    /// The last iterate before the failure.
+    pub fn partial(&self) -> &Returns<Vector> {
+        match self {
+            Error::MaxIterations { partial, .. } => partial,
+            Error::LineSearch { partial, .. } => partial,
+            Error::SingularJacobian { partial } => partial,
+            Error::NonFiniteValue { partial, .. } => partial,
+        }
+    }
+
+    /// Consumes the error, returning the last iterate before the failure.
+    pub fn into_partial(self) -> Returns<Vector> {
+        match self {
+            Error::MaxIterations { partial, .. } => *partial,
+            Error::LineSearch { partial, .. } => *partial,
+            Error::SingularJacobian { partial } => *partial,
+            Error::NonFiniteValue { partial, .. } => *partial,
+        }
+    }
+}
+//}}}
+//{{{ fun: evaluate
+/// `F(x)`, counting the evaluation.
+pub(super) fn evaluate<R>(residual: &mut R, x: &[f64], ret: &mut Returns<R::Vector>) -> Vec<f64>
+where
+    R: Residual,
+    R::Vector: DenseVector,
+{
+    ret.num_fun_evals += 1;
+    let fx = residual.eval(&R::Vector::from_slice(x)).to_vec();
+    assert_eq!(fx.len(), x.len(), "residual and variables differ in length");
+    fx
+}
+//}}}
+//{{{ fun: jacobian
+/// Jacobian at `x`, where `F(x) = fx`, from the residual or else by forward differences with
+/// steps `fd_step max(1, |xⱼ|)`.
+pub(super) fn jacobian<R>(
+    residual: &mut R,
+    x: &[f64],
+    fx: &[f64],
+    fd_step: f64,
+    ret: &mut Returns<R::Vector>,
+) -> Matrix
+where
+    R: Residual,
+    R::Vector: DenseVector,
+{
+    if let Some(jac) = residual.jacobian(&R::Vector::from_slice(x)) {
+        ret.num_jac_evals += 1;
+        return to_dense(&jac);
+    }
+    let n = x.len();
+    let mut jac = Matrix::zeros(n, n);
+    let mut xh = x.to_vec();
+    for j in 0..n {
+        let h = fd_step * x[j].abs().max(1.0);
+        xh[j] = x[j] + h;
+        let fh = evaluate(residual, &xh, ret);
+        xh[j] = x[j];
+        for i in 0..n {
+            jac[(i, j)] = (fh[i] - fx[i]) / h;
+        }
+    }
+    jac
+}
+//}}}
+//{{{ fun: jacobian_product
+/// Product `J(x) v`, where `F(x) = fx`, with the dense Jacobian `jac` when given and otherwise by
+/// a forward difference along `v` with step `fd_step max(1, ‖x‖_∞) / ‖v‖_∞`.
+pub(super) fn jacobian_product<R>(
+    residual: &mut R,
+    jac: Option<&Matrix>,
+    x: &[f64],
+    fx: &[f64],
+    v: &[f64],
+    fd_step: f64,
+    ret: &mut Returns<R::Vector>,
+) -> Vec<f64>
+where
+    R: Residual,
+    R::Vector: DenseVector,
+{
+    if let Some(jac) = jac {
+        return jac.mul_vec(v);
+    }
+    let v_norm = norm_inf(v);
+    if v_norm == 0.0 {
+        return vec![0.0; fx.len()];
+    }
+    let h = fd_step * norm_inf(x).max(1.0) / v_norm;
+    let xh: Vec<f64> = x.iter().zip(v).map(|(x, v)| x + h * v).collect();
+    let fh = evaluate(residual, &xh, ret);
+    fh.iter().zip(fx).map(|(a, b)| (a - b) / h).collect()
+}
+//}}}
+//{{{ fun: initial_returns
+/// Returns at the starting point `x0` with value `fx0`, or the error for a non-finite value.
+#[allow(clippy::type_complexity)]
+pub(super) fn initial_returns<R>(
+    residual: &mut R,
+    x0: &R::Vector,
+) -> Result<(Vec<f64>, Returns<R::Vector>), Error<R::Vector>>
+where
+    R: Residual,
+    R::Vector: DenseVector + Debug,
+{
+    let mut ret = Returns {
+        x: x0.clone(),
+        residual_norm: 0.0,
+        num_iterations: 0,
+        num_inner_iterations: 0,
+        num_fun_evals: 0,
+        num_jac_evals: 0,
+        line_search: LineSearchStats::default(),
+    };
+    let fx = evaluate(residual, &x0.to_vec(), &mut ret);
+    ret.residual_norm = norm_inf(&fx);
+    if !fx.iter().all(|f| f.is_finite()) {
+        return Err(Error::NonFiniteValue {
+            x: x0.clone(),
+            partial: Box::new(ret),
+        });
+    }
+    Ok((fx, ret))
+}
+//}}}
+//{{{ fun: search
+/// Searches along `dir` from `x`, where `F(x) = fx`, returning the accepted step.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn search<R>(
+    line_search: &mut dyn LineSearch<Function = MeritFn<R>>,
+    residual: &R,
+    x: &[f64],
+    fx: &[f64],
+    dir: &[f64],
+    dphi0: f64,
+    fd_step: f64,
+    ret: &mut Returns<R::Vector>,
+) -> Result<f64, LineSearchError>
+where
+    R: Residual,
+    R::Vector: DenseVector,
+{
+    let phi0 = 0.5 * fx.iter().map(|f| f * f).sum::<f64>();
+    line_search.update_fcn(MeritFn::new(
+        residual.clone(),
+        R::Vector::from_slice(x),
+        R::Vector::from_slice(dir),
+        fd_step,
+    ));
+    let result = line_search.search(phi0, dphi0);
+    let stats = line_search.stats();
+    ret.line_search.accumulate(&stats);
+    ret.num_fun_evals += stats.num_evals + stats.num_diffs;
+    result.map(|ls_ret| ls_ret.alpha)
+}
+//}}}
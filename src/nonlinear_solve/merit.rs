@@ -0,0 +1,95 @@
+//! Merit function `½‖F‖²` along a search direction.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::Residual;
+use crate::dense::{dot, norm_inf};
+use crate::{DenseVector, RealFn1};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: MeritFn
+/// `φ(α) = ½‖F(x + α dir)‖²`, through which the solvers drive any [`crate::line_search::LineSearch`].
+///
+/// The derivative `φ'(α) = F(y)ᵀJ(y) dir` at `y = x + α dir` uses the Jacobian of the residual
+/// when it has one, and a forward difference of `F` along `dir` with step `fd_step` relative to
+/// `y` otherwise.
+#[derive(Debug, Clone)]
+pub struct MeritFn<R: Residual> {
+    pub residual: R,
+    pub x: R::Vector,
+    pub dir: R::Vector,
+    pub fd_step: f64,
+}
+//}}}
+//{{{ impl: MeritFn
+impl<R> MeritFn<R>
+where
+    R: Residual,
+    R::Vector: DenseVector,
+{
+    pub fn new(residual: R, x: R::Vector, dir: R::Vector, fd_step: f64) -> Self {
+        Self {
+            residual,
+            x,
+            dir,
+            fd_step,
+        }
+    }
+
+    fn point(&self, alpha: f64) -> Vec<f64> {
+        let (x, dir) = (self.x.to_vec(), self.dir.to_vec());
+        x.iter().zip(&dir).map(|(x, d)| x + alpha * d).collect()
+    }
+
+    /// Derivative `F(y)ᵀJ(y) dir` at the point `y` of step `alpha`, where `F(y) = fy`.
+    fn slope(&mut self, alpha: f64, fy: &[f64]) -> f64 {
+        let y = self.point(alpha);
+        let dir = self.dir.to_vec();
+        let jd = match self.residual.jacobian(&R::Vector::from_slice(&y)) {
+            Some(jac) => (0..fy.len())
+                .map(|i| (0..dir.len()).map(|j| jac[(i, j)] * dir[j]).sum())
+                .collect(),
+            None => {
+                let h =
+                    self.fd_step * norm_inf(&y).max(1.0) / norm_inf(&dir).max(f64::MIN_POSITIVE);
+                let yh: Vec<f64> = y.iter().zip(&dir).map(|(y, d)| y + h * d).collect();
+                let fh = self.residual.eval(&R::Vector::from_slice(&yh)).to_vec();
+                fh.iter()
+                    .zip(fy)
+                    .map(|(a, b)| (a - b) / h)
+                    .collect::<Vec<f64>>()
+            }
+        };
+        dot(fy, &jd)
+    }
+}
+//}}}
+//{{{ impl: RealFn1 for MeritFn
+impl<R> RealFn1 for MeritFn<R>
+where
+    R: Residual,
+    R::Vector: DenseVector,
+{
+    fn eval(&mut self, alpha: f64) -> f64 {
+        let y = R::Vector::from_slice(&self.point(alpha));
+        let fy = self.residual.eval(&y).to_vec();
+        0.5 * dot(&fy, &fy)
+    }
+
+    fn diff(&mut self, alpha: f64) -> f64 {
+        self.eval_with_diff(alpha).1
+    }
+
+    fn eval_with_diff(&mut self, alpha: f64) -> (f64, f64) {
+        let y = R::Vector::from_slice(&self.point(alpha));
+        let fy = self.residual.eval(&y).to_vec();
+        (0.5 * dot(&fy, &fy), self.slope(alpha, &fy))
+    }
+}
+//}}}
@@ -0,0 +1,25 @@
+//! Solvers of square systems of nonlinear equations `F(x) = 0`.
+//!
+//! All of them are globalized by a line search on the merit function `½‖F‖²`, which goes through
+//! the [`crate::line_search::LineSearch`] trait by way of the [`MeritFn`] adapter.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+mod broyden;
+mod common;
+mod merit;
+mod newton;
+mod newton_krylov;
+
+pub use broyden::{Broyden, Options as BroydenOptions, Update as BroydenUpdate};
+pub use common::{Error as NonlinearSolveError, Residual, Returns as NonlinearSolveReturns};
+pub use merit::MeritFn;
+pub use newton::{Newton, Options as NewtonOptions};
+pub use newton_krylov::{NewtonKrylov, Options as NewtonKrylovOptions};
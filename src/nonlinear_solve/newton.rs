@@ -0,0 +1,157 @@
+//! Damped Newton method.
+//!
+//! Each iteration solves `J(x) d = −F(x)` with a dense LU factorization, the Jacobian coming from
+//! the residual or from forward differences, and searches along `d` on the merit function
+//! `½‖F‖²`, of which it is a descent direction with `φ'(0) = −‖F‖²`. Near a regular root the
+//! full step is accepted and convergence is quadratic.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::{evaluate, initial_returns, jacobian, search, Error, Residual, Returns};
+use crate::dense::{dot, norm_inf, Lu};
+use crate::line_search::{self as ls, BacktrackingOptions, LineSearchMethod};
+use crate::DenseVector;
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Options
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Options {
+    /// Stops once `‖F‖_∞ <= f_tol`.
+    pub f_tol: f64,
+    /// Maximum number of iterations.
+    pub max_iter: usize,
+    /// Relative step of the finite differences.
+    pub fd_step: f64,
+    pub ls_method: LineSearchMethod,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            f_tol: 1e-10,
+            max_iter: 100,
+            fd_step: f64::EPSILON.sqrt(),
+            ls_method: LineSearchMethod::Backtracking(BacktrackingOptions::default()),
+        }
+    }
+}
+//}}}
+//{{{ struct: Newton
+/// Damped Newton solver of a [`Residual`].
+pub struct Newton<R: Residual> {
+    residual: R,
+    x0: R::Vector,
+    opts: Options,
+}
+//}}}
+//{{{ impl: Newton
+impl<R> Newton<R>
+where
+    R: Residual,
+    R::Vector: DenseVector + Debug,
+{
+    pub fn new(residual: R, x0: R::Vector, opts: Options) -> Self {
+        Self { residual, x0, opts }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    pub fn solve(&mut self) -> Result<Returns<R::Vector>, Error<R::Vector>> {
+        //{{{ trace
+        error!(target: "nls", "--- Entering newton ---");
+        //}}}
+        let opts = self.opts;
+        let (mut fx, mut ret) = initial_returns(&mut self.residual, &self.x0)?;
+        let mut x = self.x0.to_vec();
+        let mut line_search = ls::create(
+            super::MeritFn::new(
+                self.residual.clone(),
+                self.x0.clone(),
+                self.x0.clone(),
+                opts.fd_step,
+            ),
+            opts.ls_method,
+        );
+        loop {
+            //{{{ trace
+            info!(target: "nls", "i = {} ‖F‖ = {:1.4e}", ret.num_iterations, ret.residual_norm);
+            //}}}
+            if ret.residual_norm <= opts.f_tol {
+                //{{{ trace
+                error!(target: "nls", "--- Leaving newton ---");
+                //}}}
+                return Ok(ret);
+            }
+            if ret.num_iterations >= opts.max_iter {
+                //{{{ trace
+                info!(target: "nls", "Did not converge within {} iterations", opts.max_iter);
+                //}}}
+                return Err(Error::MaxIterations {
+                    max_iter: opts.max_iter,
+                    partial: Box::new(ret),
+                });
+            }
+            let jac = jacobian(&mut self.residual, &x, &fx, opts.fd_step, &mut ret);
+            let Some(lu) = Lu::new(jac) else {
+                //{{{ trace
+                info!(target: "nls", "Singular Jacobian");
+                //}}}
+                return Err(Error::SingularJacobian {
+                    partial: Box::new(ret),
+                });
+            };
+            let rhs: Vec<f64> = fx.iter().map(|f| -f).collect();
+            let dir = lu.solve(&rhs);
+            let dphi0 = -dot(&fx, &fx);
+            let res = &self.residual;
+            let result = search(
+                &mut *line_search,
+                res,
+                &x,
+                &fx,
+                &dir,
+                dphi0,
+                opts.fd_step,
+                &mut ret,
+            );
+            let alpha = match result {
+                Ok(alpha) => alpha,
+                Err(source) => {
+                    //{{{ trace
+                    info!(target: "nls", "Line search failed with {source:?}");
+                    //}}}
+                    return Err(Error::LineSearch {
+                        source,
+                        partial: Box::new(ret),
+                    });
+                }
+            };
+            //{{{ trace
+            debug!(target: "nls", "alpha = {:1.4e} ‖d‖ = {:1.4e}", alpha, norm_inf(&dir));
+            //}}}
+            for (x, d) in x.iter_mut().zip(&dir) {
+                *x += alpha * d;
+            }
+            fx = evaluate(&mut self.residual, &x, &mut ret);
+            ret.num_iterations += 1;
+            ret.x = R::Vector::from_slice(&x);
+            ret.residual_norm = norm_inf(&fx);
+        }
+    }
+}
+//}}}
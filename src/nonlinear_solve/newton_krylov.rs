@@ -0,0 +1,295 @@
+//! Inexact Newton method with GMRES.
+//!
+//! The Newton system `J(x) d = −F(x)` is solved by restarted GMRES only to the relative
+//! accuracy `η ‖F‖₂`, the forcing term `η` following the second choice of Eisenstat and Walker.
+//! GMRES needs nothing but products `J v`, which come from the Jacobian of the residual when it
+//! has one, and otherwise from a directional difference costing one evaluation of `F` each, so
+//! the Jacobian is never formed. The steps are searched on the merit function `½‖F‖²`.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::{
+    evaluate, initial_returns, jacobian_product, search, Error, Residual, Returns,
+};
+use crate::dense::{dot, norm, norm_inf, to_dense, Matrix};
+use crate::line_search::{self as ls, BacktrackingOptions, LineSearchError, LineSearchMethod};
+use crate::DenseVector;
+//}}}
+//{{{ std imports
+use std::fmt::Debug;
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Options
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Options {
+    /// Stops once `‖F‖_∞ <= f_tol`.
+    pub f_tol: f64,
+    /// Maximum number of Newton iterations.
+    pub max_iter: usize,
+    /// Relative step of the directional differences.
+    pub fd_step: f64,
+    /// Dimension of the Krylov subspace before GMRES restarts.
+    pub krylov_dim: usize,
+    /// Maximum number of GMRES restarts per Newton iteration.
+    pub max_restarts: usize,
+    /// The forcing term is `gamma (‖F_k‖ / ‖F_{k−1}‖)^exponent`, safeguarded and capped at
+    /// `eta_max`, which is also the first one.
+    pub forcing_gamma: f64,
+    pub forcing_exponent: f64,
+    pub eta_max: f64,
+    pub ls_method: LineSearchMethod,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            f_tol: 1e-10,
+            max_iter: 100,
+            fd_step: f64::EPSILON.sqrt(),
+            krylov_dim: 30,
+            max_restarts: 10,
+            forcing_gamma: 0.9,
+            forcing_exponent: 2.0,
+            eta_max: 0.9,
+            ls_method: LineSearchMethod::Backtracking(BacktrackingOptions::default()),
+        }
+    }
+}
+//}}}
+//{{{ struct: NewtonKrylov
+/// Newton-GMRES solver of a [`Residual`].
+pub struct NewtonKrylov<R: Residual> {
+    residual: R,
+    x0: R::Vector,
+    opts: Options,
+}
+//}}}
+//{{{ impl: NewtonKrylov
+impl<R> NewtonKrylov<R>
+where
+    R: Residual,
+    R::Vector: DenseVector + Debug,
+{
+    pub fn new(residual: R, x0: R::Vector, opts: Options) -> Self {
+        Self { residual, x0, opts }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    pub fn solve(&mut self) -> Result<Returns<R::Vector>, Error<R::Vector>> {
+        //{{{ trace
+        error!(target: "nls", "--- Entering newton-krylov ---");
+        //}}}
+        let opts = self.opts;
+        let (mut fx, mut ret) = initial_returns(&mut self.residual, &self.x0)?;
+        let mut x = self.x0.to_vec();
+        let mut line_search = ls::create(
+            super::MeritFn::new(
+                self.residual.clone(),
+                self.x0.clone(),
+                self.x0.clone(),
+                opts.fd_step,
+            ),
+            opts.ls_method,
+        );
+        let mut eta = opts.eta_max;
+        loop {
+            //{{{ trace
+            info!(target: "nls", "i = {} ‖F‖ = {:1.4e}", ret.num_iterations, ret.residual_norm);
+            //}}}
+            if ret.residual_norm <= opts.f_tol {
+                //{{{ trace
+                error!(target: "nls", "--- Leaving newton-krylov ---");
+                //}}}
+                return Ok(ret);
+            }
+            if ret.num_iterations >= opts.max_iter {
+                //{{{ trace
+                info!(target: "nls", "Did not converge within {} iterations", opts.max_iter);
+                //}}}
+                return Err(Error::MaxIterations {
+                    max_iter: opts.max_iter,
+                    partial: Box::new(ret),
+                });
+            }
+            let jac = self
+                .residual
+                .jacobian(&R::Vector::from_slice(&x))
+                .map(|jac| {
+                    ret.num_jac_evals += 1;
+                    to_dense(&jac)
+                });
+            let rhs: Vec<f64> = fx.iter().map(|f| -f).collect();
+            let f_norm = norm(&fx);
+            let residual = &mut self.residual;
+            let (dir, num_inner) = gmres(
+                |v: &[f64]| {
+                    jacobian_product(residual, jac.as_ref(), &x, &fx, v, opts.fd_step, &mut ret)
+                },
+                &rhs,
+                eta * f_norm,
+                opts.krylov_dim,
+                opts.max_restarts,
+            );
+            ret.num_inner_iterations += num_inner;
+            let jd = jacobian_product(
+                &mut self.residual,
+                jac.as_ref(),
+                &x,
+                &fx,
+                &dir,
+                opts.fd_step,
+                &mut ret,
+            );
+            let dphi0 = dot(&fx, &jd);
+            let result = if dphi0 < 0.0 {
+                let res = &self.residual;
+                search(
+                    &mut *line_search,
+                    res,
+                    &x,
+                    &fx,
+                    &dir,
+                    dphi0,
+                    opts.fd_step,
+                    &mut ret,
+                )
+            } else {
+                Err(LineSearchError::NotDecreasing)
+            };
+            let alpha = match result {
+                Ok(alpha) => alpha,
+                Err(source) => {
+                    //{{{ trace
+                    info!(target: "nls", "Line search failed with {source:?}");
+                    //}}}
+                    return Err(Error::LineSearch {
+                        source,
+                        partial: Box::new(ret),
+                    });
+                }
+            };
+            //{{{ trace
+            debug!(target: "nls", "alpha = {:1.4e} eta = {:1.4e} inner = {}", alpha, eta, num_inner);
+            //}}}
+            for (x, d) in x.iter_mut().zip(&dir) {
+                *x += alpha * d;
+            }
+            fx = evaluate(&mut self.residual, &x, &mut ret);
+            ret.num_iterations += 1;
+            ret.x = R::Vector::from_slice(&x);
+            ret.residual_norm = norm_inf(&fx);
+
+            // Eisenstat-Walker forcing term, not allowed to drop much faster than the previous one
+            let eta_prev = eta;
+            let safeguard = opts.forcing_gamma * eta_prev.powf(opts.forcing_exponent);
+            eta = opts.forcing_gamma * (norm(&fx) / f_norm).powf(opts.forcing_exponent);
+            if safeguard > 0.1 {
+                eta = eta.max(safeguard);
+            }
+            eta = eta.min(opts.eta_max);
+        }
+    }
+}
+//}}}
+//{{{ fun: gmres
+/// Restarted GMRES for `A z = b` from `z = 0`, stopping once `‖b − A z‖₂ <= tol`.
+///
+/// Returns the last iterate, converged or not, with the number of products by `A`.
+fn gmres(
+    mut apply: impl FnMut(&[f64]) -> Vec<f64>,
+    b: &[f64],
+    tol: f64,
+    krylov_dim: usize,
+    max_restarts: usize,
+) -> (Vec<f64>, usize) {
+    let n = b.len();
+    let m = krylov_dim.clamp(1, n.max(1));
+    let mut z = vec![0.0; n];
+    let mut num_products = 0;
+    for restart in 0..=max_restarts {
+        let r: Vec<f64> = if restart == 0 {
+            b.to_vec()
+        } else {
+            num_products += 1;
+            apply(&z).iter().zip(b).map(|(az, b)| b - az).collect()
+        };
+        let beta = norm(&r);
+        if beta <= tol {
+            break;
+        }
+        let mut basis: Vec<Vec<f64>> = vec![r.iter().map(|r| r / beta).collect()];
+        // Hessenberg matrix reduced to upper triangular form by the Givens rotations (cs, sn)
+        let mut hess = Matrix::zeros(m + 1, m);
+        let (mut cs, mut sn) = (vec![0.0; m], vec![0.0; m]);
+        let mut g = vec![0.0; m + 1];
+        g[0] = beta;
+        let mut k = 0;
+        while k < m {
+            let mut w = apply(&basis[k]);
+            num_products += 1;
+            for (i, v) in basis.iter().enumerate() {
+                let h = dot(&w, v);
+                hess[(i, k)] = h;
+                for (w, v) in w.iter_mut().zip(v) {
+                    *w -= h * v;
+                }
+            }
+            let w_norm = norm(&w);
+            hess[(k + 1, k)] = w_norm;
+            for i in 0..k {
+                let (a, b) = (hess[(i, k)], hess[(i + 1, k)]);
+                hess[(i, k)] = cs[i] * a + sn[i] * b;
+                hess[(i + 1, k)] = -sn[i] * a + cs[i] * b;
+            }
+            let (a, b) = (hess[(k, k)], hess[(k + 1, k)]);
+            let rho = a.hypot(b);
+            (cs[k], sn[k]) = if rho == 0.0 {
+                (1.0, 0.0)
+            } else {
+                (a / rho, b / rho)
+            };
+            hess[(k, k)] = rho;
+            hess[(k + 1, k)] = 0.0;
+            g[k + 1] = -sn[k] * g[k];
+            g[k] *= cs[k];
+            k += 1;
+            if g[k].abs() <= tol || w_norm <= f64::EPSILON * beta {
+                break;
+            }
+            basis.push(w.iter().map(|w| w / w_norm).collect());
+        }
+        // Back substitution for the coefficients of the basis
+        let mut y = vec![0.0; k];
+        for i in (0..k).rev() {
+            let s: f64 = (i + 1..k).map(|j| hess[(i, j)] * y[j]).sum();
+            y[i] = if hess[(i, i)] == 0.0 {
+                0.0
+            } else {
+                (g[i] - s) / hess[(i, i)]
+            };
+        }
+        for (v, y) in basis.iter().zip(&y) {
+            for (z, v) in z.iter_mut().zip(v) {
+                *z += y * v;
+            }
+        }
+        if g[k].abs() <= tol {
+            break;
+        }
+    }
+    (z, num_products)
+}
+//}}}
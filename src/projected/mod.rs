@@ -0,0 +1,19 @@
+//! Minimizers over convex sets with cheap Euclidean projections.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+mod common;
+mod projection;
+mod spg;
+
+pub use common::{Error as ProjectedError, Returns as ProjectedReturns};
+pub use projection::{Ball, NonNegative, Projection, Simplex};
+pub use spg::{Options as SpgOptions, Spg};
@@ -0,0 +1,156 @@
+//! Euclidean projections onto simple convex sets.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use crate::bound_constrained::Bounds;
+use crate::dense::norm;
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ trait: Projection
+/// A closed convex set with a cheap Euclidean projection `P(x) = argmin_{z ∈ C} ‖z − x‖`.
+pub trait Projection {
+    /// Replaces `x` by its projection onto the set.
+    fn project(&self, x: &mut [f64]);
+
+    /// Projected gradient `P(x − g) − x`, which vanishes exactly at the first-order stationary
+    /// points of `f` over the set.
+    fn projected_gradient(&self, x: &[f64], grad: &[f64]) -> Vec<f64> {
+        let mut out: Vec<f64> = x.iter().zip(grad).map(|(x, g)| x - g).collect();
+        self.project(&mut out);
+        for (o, x) in out.iter_mut().zip(x) {
+            *o -= x;
+        }
+        out
+    }
+}
+//}}}
+//{{{ impl: Projection for Bounds
+/// The box `l <= x <= u`.
+impl Projection for Bounds {
+    fn project(&self, x: &mut [f64]) {
+        Bounds::project(self, x);
+    }
+}
+//}}}
+//{{{ struct: NonNegative
+/// The nonnegative orthant `x >= 0`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NonNegative;
+//}}}
+//{{{ impl: Projection for NonNegative
+impl Projection for NonNegative {
+    fn project(&self, x: &mut [f64]) {
+        for xi in x.iter_mut() {
+            *xi = xi.max(0.0);
+        }
+    }
+}
+//}}}
+//{{{ struct: Simplex
+/// The simplex `{x : x >= 0, Σ xᵢ = radius}`, the probability simplex for a radius of one.
+#[derive(Copy, Clone, Debug)]
+pub struct Simplex {
+    pub radius: f64,
+}
+//}}}
+//{{{ impl: Simplex
+impl Simplex {
+    /// The probability simplex.
+    pub fn new() -> Self {
+        Self { radius: 1.0 }
+    }
+
+    pub fn with_radius(radius: f64) -> Self {
+        Self { radius }
+    }
+}
+//}}}
+//{{{ impl: Default for Simplex
+impl Default for Simplex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+//}}}
+//{{{ impl: Projection for Simplex
+/// Sorts the components to find the shift `τ` with `Σ max(xᵢ − τ, 0) = radius`, as in Held,
+/// Wolfe and Crowder (1974).
+impl Projection for Simplex {
+    fn project(&self, x: &mut [f64]) {
+        if x.is_empty() {
+            return;
+        }
+        let mut sorted = x.to_vec();
+        sorted.sort_by(|a, b| b.total_cmp(a));
+        let mut cumsum = 0.0;
+        let mut tau = 0.0;
+        for (j, uj) in sorted.iter().enumerate() {
+            cumsum += uj;
+            let t = (cumsum - self.radius) / (j + 1) as f64;
+            if uj - t > 0.0 {
+                tau = t;
+            }
+        }
+        for xi in x.iter_mut() {
+            *xi = (*xi - tau).max(0.0);
+        }
+    }
+}
+//}}}
+//{{{ struct: Ball
+/// The Euclidean ball `‖x − center‖ <= radius`, centred on the origin when `center` is `None`.
+#[derive(Clone, Debug)]
+pub struct Ball {
+    pub center: Option<Vec<f64>>,
+    pub radius: f64,
+}
+//}}}
+//{{{ impl: Ball
+impl Ball {
+    /// The ball of `radius` about the origin.
+    pub fn new(radius: f64) -> Self {
+        Self {
+            center: None,
+            radius,
+        }
+    }
+
+    /// The ball of `radius` about `center`.
+    ///
+    /// # Panics
+    ///
+    /// Projecting a point whose length differs from that of `center`, which happens as soon as
+    /// a minimizer is created with a starting point of the wrong length.
+    pub fn centered(center: Vec<f64>, radius: f64) -> Self {
+        Self {
+            center: Some(center),
+            radius,
+        }
+    }
+}
+//}}}
+//{{{ impl: Projection for Ball
+impl Projection for Ball {
+    fn project(&self, x: &mut [f64]) {
+        if let Some(center) = &self.center {
+            assert_eq!(center.len(), x.len(), "ball centre and point differ in length");
+        }
+        let center = |i: usize| self.center.as_ref().map_or(0.0, |c| c[i]);
+        let offset: Vec<f64> = (0..x.len()).map(|i| x[i] - center(i)).collect();
+        let dist = norm(&offset);
+        if dist <= self.radius {
+            return;
+        }
+        let scale = self.radius / dist;
+        for (i, xi) in x.iter_mut().enumerate() {
+            *xi = center(i) + scale * offset[i];
+        }
+    }
+}
+//}}}
@@ -0,0 +1,309 @@
+//! Spectral projected gradient (SPG) minimizer.
+//!
+//! Follows algorithm SPG2 of Birgin, Martínez and Raydan, "Nonmonotone spectral projected
+//! gradient methods on convex sets" (2000). Each iteration projects a Barzilai-Borwein step
+//! along the negative gradient onto the feasible set and backtracks along the resulting
+//! direction until the value falls sufficiently below the largest of the last few values.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::{Error, Returns};
+use super::projection::Projection;
+use crate::dense::{dot, norm, norm_inf};
+use crate::line_search::{LineSearchError, LineSearchOptions};
+use crate::unconstrained::ConvergedReason;
+use crate::{Budget, CancelToken, DenseVector, RealFn};
+//}}}
+//{{{ std imports
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Options {
+    /// Stops once `‖P(x − ∇f) − x‖_∞ <= proj_grad_tol`.
+    pub proj_grad_tol: f64,
+    pub max_iter: usize,
+    /// Number of recent values the line search compares against, one makes it monotone.
+    pub memory: usize,
+    /// Safeguards on the Barzilai-Borwein step `sᵀs / sᵀy`.
+    pub spectral_min: f64,
+    pub spectral_max: f64,
+    /// Options of the nonmonotone backtracking search, of which `c1` and `step_min` are used.
+    pub ls_opts: LineSearchOptions,
+    /// Maximum number of step reductions in a single line search.
+    pub max_backtracks: usize,
+    /// Token checked before every evaluation, ending the run with [`Error::Cancelled`] once it
+    /// is cancelled.
+    #[serde(skip)]
+    pub cancel: Option<CancelToken>,
+    /// Wall-clock budget of a call to [`Spg::minimize`], checked before every evaluation and
+    /// ending the run with [`Error::TimeLimit`] once it is used up.
+    pub time_limit: Option<Duration>,
+    /// Limits on function and gradient evaluations, checked before every evaluation. The run
+    /// ends with [`Error::BudgetExhausted`].
+    pub budget: Budget,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            proj_grad_tol: 1e-5,
+            max_iter: 1000,
+            memory: 10,
+            spectral_min: 1e-30,
+            spectral_max: 1e30,
+            ls_opts: LineSearchOptions::default(),
+            max_backtracks: 30,
+            cancel: None,
+            time_limit: None,
+            budget: Budget::default(),
+        }
+    }
+}
+//}}}
+//{{{ enum: Stop
+/// Why a step could not be completed.
+#[derive(Copy, Clone, Debug)]
+enum Stop {
+    LineSearch(LineSearchError),
+    Cancelled,
+    TimeLimit(Duration),
+    BudgetExhausted,
+}
+//}}}
+//{{{ struct: Spg
+/// Spectral projected gradient minimizer of a [`RealFn`] over a set with a [`Projection`].
+///
+/// Only feasible points are evaluated, the starting point being projected first.
+pub struct Spg<F: RealFn, P: Projection> {
+    fcn: F,
+    x0: Vec<f64>,
+    set: P,
+    opts: Options,
+    num_fun_evals: usize,
+    num_grad_evals: usize,
+}
+//}}}
+//{{{ impl: Spg
+impl<F: RealFn, P: Projection> Spg<F, P>
+where
+    F::Vector: DenseVector + Debug,
+{
+    pub fn new(fcn: F, x0: F::Vector, set: P, opts: Options) -> Self {
+        let mut x0 = x0.to_vec();
+        set.project(&mut x0);
+        Self {
+            fcn,
+            x0,
+            set,
+            opts,
+            num_fun_evals: 0,
+            num_grad_evals: 0,
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    pub fn set(&self) -> &P {
+        &self.set
+    }
+
+    pub fn minimize(&mut self) -> Result<Returns<F::Vector>, Error<F::Vector>> {
+        //{{{ trace
+        error!(target: "spg", "--- Entering minimize ---");
+        //}}}
+        self.num_fun_evals = 0;
+        self.num_grad_evals = 0;
+        let start = Instant::now();
+        let mut x = self.x0.clone();
+        let mut f = self.eval(&x);
+        let mut g = self.grad(&x);
+        if !f.is_finite() || !norm(&g).is_finite() {
+            //{{{ trace
+            info!(target: "spg", "Non-finite value or gradient at the starting point");
+            //}}}
+            return Err(Error::NonFiniteValue {
+                x: F::Vector::from_slice(&x),
+                partial: Box::new(self.returns(&x, f, &g, 0, ConvergedReason::NotConverged)),
+            });
+        }
+        let (spectral_min, spectral_max) = (self.opts.spectral_min, self.opts.spectral_max);
+        let mut recent = VecDeque::from([f]);
+        let mut spectral = norm_inf(&self.set.projected_gradient(&x, &g))
+            .recip()
+            .clamp(spectral_min, spectral_max);
+        let mut iter = 0;
+        loop {
+            let proj_grad_norm = norm_inf(&self.set.projected_gradient(&x, &g));
+            //{{{ trace
+            info!(target: "spg", "i = {iter} f = {f:1.4e} ‖P(x − g) − x‖ = {proj_grad_norm:1.4e}");
+            //}}}
+            if proj_grad_norm <= self.opts.proj_grad_tol {
+                return Ok(self.returns(&x, f, &g, iter, ConvergedReason::ProjGrad));
+            }
+            if iter >= self.opts.max_iter {
+                return Err(Error::MaxIterations {
+                    max_iter: self.opts.max_iter,
+                    partial: Box::new(self.returns(&x, f, &g, iter, ConvergedReason::NotConverged)),
+                });
+            }
+
+            let mut d: Vec<f64> = (0..x.len()).map(|i| x[i] - spectral * g[i]).collect();
+            self.set.project(&mut d);
+            for (di, xi) in d.iter_mut().zip(&x) {
+                *di -= xi;
+            }
+            let f_max = recent.iter().fold(f64::NEG_INFINITY, |acc, v| acc.max(*v));
+            let stepped = self.search(start, &x, f, f_max, &g, &d).and_then(|(x_new, f_new)| {
+                self.admit(start, 0, 1)?;
+                let g_new = self.grad(&x_new);
+                Ok((x_new, f_new, g_new))
+            });
+            let (x_new, f_new, g_new) = match stepped {
+                Ok(accepted) => accepted,
+                Err(stop) => {
+                    let reason = ConvergedReason::NotConverged;
+                    let partial = Box::new(self.returns(&x, f, &g, iter, reason));
+                    return Err(match stop {
+                        Stop::LineSearch(source) => Error::LineSearch { source, partial },
+                        Stop::Cancelled => Error::Cancelled { partial },
+                        Stop::TimeLimit(time_limit) => Error::TimeLimit {
+                            time_limit,
+                            partial,
+                        },
+                        Stop::BudgetExhausted => Error::BudgetExhausted {
+                            budget: self.opts.budget,
+                            partial,
+                        },
+                    });
+                }
+            };
+            iter += 1;
+
+            let s: Vec<f64> = (0..x.len()).map(|i| x_new[i] - x[i]).collect();
+            let y: Vec<f64> = (0..x.len()).map(|i| g_new[i] - g[i]).collect();
+            let sy = dot(&s, &y);
+            spectral = if sy > 0.0 {
+                (dot(&s, &s) / sy).clamp(spectral_min, spectral_max)
+            } else {
+                spectral_max
+            };
+            //{{{ trace
+            debug!(target: "spg", "Spectral step {spectral:1.4e}");
+            //}}}
+            (x, f, g) = (x_new, f_new, g_new);
+            recent.push_back(f);
+            if recent.len() > self.opts.memory.max(1) {
+                recent.pop_front();
+            }
+        }
+    }
+
+    /// Backtracks along `x + α d` for a value sufficiently below `f_max`, the largest recent
+    /// value, trying the minimizer of the quadratic interpolant if it lies in `[0.1 α, 0.9 α]`
+    /// and halving otherwise.
+    fn search(
+        &mut self,
+        start: Instant,
+        x: &[f64],
+        f: f64,
+        f_max: f64,
+        g: &[f64],
+        d: &[f64],
+    ) -> Result<(Vec<f64>, f64), Stop> {
+        let dg = dot(g, d);
+        if dg >= 0.0 {
+            return Err(Stop::LineSearch(LineSearchError::NotDecreasing));
+        }
+        let c1 = self.opts.ls_opts.c1;
+        let mut alpha: f64 = 1.0;
+        for _ in 0..=self.opts.max_backtracks {
+            let xt: Vec<f64> = (0..x.len()).map(|i| x[i] + alpha * d[i]).collect();
+            self.admit(start, 1, 0)?;
+            let ft = self.eval(&xt);
+            //{{{ trace
+            trace!(target: "spg", "alpha = {alpha:1.4e} f = {ft:1.4e}");
+            //}}}
+            if ft <= f_max + c1 * alpha * dg {
+                return Ok((xt, ft));
+            }
+            let alpha_q = -0.5 * alpha * alpha * dg / (ft - f - alpha * dg);
+            alpha = if alpha_q.is_finite() && 0.1 * alpha <= alpha_q && alpha_q <= 0.9 * alpha {
+                alpha_q
+            } else {
+                0.5 * alpha
+            };
+            if alpha < self.opts.ls_opts.step_min {
+                return Err(Stop::LineSearch(LineSearchError::StepSizeSmall));
+            }
+        }
+        Err(Stop::LineSearch(LineSearchError::MaxIterations))
+    }
+
+    /// Checks that the run started at `start` may go on with `fevals` more function and
+    /// `gevals` more gradient evaluations.
+    fn admit(&self, start: Instant, fevals: usize, gevals: usize) -> Result<(), Stop> {
+        if self.opts.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Err(Stop::Cancelled);
+        }
+        if let Some(time_limit) = self.opts.time_limit.filter(|t| start.elapsed() >= *t) {
+            return Err(Stop::TimeLimit(time_limit));
+        }
+        let budget = self.opts.budget;
+        if !budget.allows(self.num_fun_evals, self.num_grad_evals, fevals, gevals) {
+            //{{{ trace
+            info!(target: "spg", "Evaluation budget {budget:?} exhausted");
+            //}}}
+            return Err(Stop::BudgetExhausted);
+        }
+        Ok(())
+    }
+
+    fn eval(&mut self, x: &[f64]) -> f64 {
+        self.num_fun_evals += 1;
+        self.fcn.eval(&F::Vector::from_slice(x))
+    }
+
+    fn grad(&mut self, x: &[f64]) -> Vec<f64> {
+        self.num_grad_evals += 1;
+        self.fcn.grad(&F::Vector::from_slice(x)).to_vec()
+    }
+
+    fn returns(
+        &self,
+        x: &[f64],
+        f: f64,
+        g: &[f64],
+        iter: usize,
+        reason: ConvergedReason,
+    ) -> Returns<F::Vector> {
+        //{{{ trace
+        error!(target: "spg", "--- Leaving minimize with {reason:?} ---");
+        //}}}
+        Returns {
+            xmin: F::Vector::from_slice(x),
+            fmin: f,
+            proj_grad_norm: norm_inf(&self.set.projected_gradient(x, g)),
+            reason,
+            num_iterations: iter,
+            num_fun_evals: self.num_fun_evals,
+            num_grad_evals: self.num_grad_evals,
+        }
+    }
+}
+//}}}
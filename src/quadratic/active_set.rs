@@ -0,0 +1,488 @@
+//! Primal active-set method for convex quadratic programs.
+//!
+//! Follows Nocedal and Wright, "Numerical Optimization" (2006), §16.5. A feasible starting point
+//! is found first as the feasible point nearest to the origin, with the dual method of
+//! Goldfarb and Idnani, which also detects inconsistent constraints. From there each iteration
+//! minimizes the objective on the working set of constraints held with equality, in the null
+//! space of their normals. As the Hessian need only be positive semidefinite, the reduced
+//! Hessian may be singular, in which case the method moves along a direction of zero curvature
+//! until it meets a constraint; if none blocks it the program is unbounded. Once the working set
+//! is optimal, an inequality with a negative multiplier is dropped, or the method stops.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::dual::{self, QpError};
+use super::problem::QuadraticProgram;
+use crate::dense::{dot, norm, norm_inf, to_dense, Matrix};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Options
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Options {
+    /// Relative tolerance of the tests on steps, multipliers, constraint activity and the
+    /// curvature of the reduced Hessian.
+    pub tol: f64,
+    /// Maximum number of iterations, including those spent finding a feasible point.
+    pub max_iter: usize,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            tol: 1e-9,
+            max_iter: 1000,
+        }
+    }
+}
+//}}}
+//{{{ struct: Returns
+#[derive(Clone, Debug)]
+pub struct Returns {
+    pub x: Vec<f64>,
+    /// Value of the objective at `x`.
+    pub objective: f64,
+    /// Multipliers `λ` of the equality constraints, with `Hx + c = A_Eᵀλ + A_Iᵀμ`.
+    pub lambda: Vec<f64>,
+    /// Multipliers `μ >= 0` of the inequality constraints.
+    pub mu: Vec<f64>,
+    /// Indices of the inequality constraints in the final working set, in increasing order.
+    pub active: Vec<usize>,
+    pub num_iterations: usize,
+}
+//}}}
+//{{{ enum: Error
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum Error {
+    #[error("The constraints are infeasible")]
+    Infeasible,
+    /// The objective decreases without bound along the ray `x + t direction`, `t >= 0`, which
+    /// stays feasible.
+    #[error("The objective is unbounded below on the feasible set")]
+    Unbounded { x: Vec<f64>, direction: Vec<f64> },
+    #[error("The Hessian is not positive semidefinite")]
+    NotConvex,
+    /// The constraints are too close to linearly dependent to find a feasible point.
+    #[error("The constraints are numerically degenerate")]
+    Degenerate,
+    #[error("Maximum iterations of {max_iter} reached")]
+    MaxIterations { max_iter: usize },
+}
+//}}}
+//{{{ enum: Row
+/// A constraint in the working set.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Row {
+    Eq(usize),
+    Ineq(usize),
+}
+//}}}
+//{{{ struct: Qr
+/// Householder factorization `A_Wᵀ = Q [R; 0]` of the transposed working set normals.
+struct Qr {
+    /// `Q`, stored by columns.
+    q: Vec<Vec<f64>>,
+    /// The upper triangular `R`.
+    r: Matrix,
+}
+//}}}
+//{{{ impl: Qr
+impl Qr {
+    fn new(normals: &[&[f64]], n: usize) -> Self {
+        let k = normals.len();
+        let mut b = Matrix::zeros(n, k);
+        for (j, a) in normals.iter().enumerate() {
+            for i in 0..n {
+                b[(i, j)] = a[i];
+            }
+        }
+        let mut reflectors = Vec::with_capacity(k);
+        for j in 0..k {
+            let mut v: Vec<f64> = (j..n).map(|i| b[(i, j)]).collect();
+            let alpha = -norm(&v).copysign(v[0]);
+            v[0] -= alpha;
+            let vv = dot(&v, &v);
+            if vv > 0.0 {
+                for c in j..k {
+                    let w = (j..n).map(|i| v[i - j] * b[(i, c)]).sum::<f64>() * 2.0 / vv;
+                    for i in j..n {
+                        b[(i, c)] -= w * v[i - j];
+                    }
+                }
+            }
+            reflectors.push((v, vv));
+        }
+        let mut r = Matrix::zeros(k, k);
+        for i in 0..k {
+            for j in i..k {
+                r[(i, j)] = b[(i, j)];
+            }
+        }
+        // Q = H₀H₁⋯H_{k−1}, applied to each unit vector from the last reflector
+        let q = (0..n)
+            .map(|c| {
+                let mut e = vec![0.0; n];
+                e[c] = 1.0;
+                for (j, (v, vv)) in reflectors.iter().enumerate().rev() {
+                    if *vv > 0.0 {
+                        let w = 2.0 * dot(v, &e[j..]) / vv;
+                        for (ei, vi) in e[j..].iter_mut().zip(v) {
+                            *ei -= w * vi;
+                        }
+                    }
+                }
+                e
+            })
+            .collect();
+        Self { q, r }
+    }
+
+    /// Columns of `Q` spanning the null space of the working set normals.
+    fn null_space(&self) -> &[Vec<f64>] {
+        &self.q[self.r.rows()..]
+    }
+
+    /// Whether the `j`-th normal, of length `norm`, is numerically dependent on the previous ones.
+    fn dependent(&self, j: usize, norm: f64, tol: f64) -> bool {
+        self.r[(j, j)].abs() <= tol * norm
+    }
+
+    /// Least squares solution `y` of `A_Wᵀy = g`.
+    fn multipliers(&self, g: &[f64]) -> Vec<f64> {
+        let k = self.r.rows();
+        let mut y: Vec<f64> = self.q[..k].iter().map(|q| dot(q, g)).collect();
+        for i in (0..k).rev() {
+            let tail: f64 = (i + 1..k).map(|j| self.r[(i, j)] * y[j]).sum();
+            y[i] = (y[i] - tail) / self.r[(i, i)];
+        }
+        y
+    }
+}
+//}}}
+//{{{ struct: SemidefiniteFactor
+/// Pivoted Cholesky factorization `PᵀMP = LLᵀ` of a positive semidefinite matrix, with `L` of
+/// numerical rank `rank`.
+struct SemidefiniteFactor {
+    l: Matrix,
+    /// Row `i` of `PᵀMP` is row `perm[i]` of `M`.
+    perm: Vec<usize>,
+    rank: usize,
+}
+//}}}
+//{{{ impl: SemidefiniteFactor
+impl SemidefiniteFactor {
+    /// Factorizes `m`, returning `None` if it is not positive semidefinite to within `tol`
+    /// relative to its largest diagonal entry.
+    fn new(m: &Matrix, tol: f64) -> Option<Self> {
+        let r = m.rows();
+        let mut a = m.clone();
+        let mut l = Matrix::zeros(r, r);
+        let mut perm: Vec<usize> = (0..r).collect();
+        let thresh = tol * (0..r).fold(0.0, |acc: f64, i| acc.max(a[(i, i)].abs()));
+        let mut rank = r;
+        for k in 0..r {
+            let p = (k..r).max_by(|&i, &j| a[(i, i)].total_cmp(&a[(j, j)]))?;
+            if a[(p, p)] <= thresh {
+                rank = k;
+                break;
+            }
+            if p != k {
+                for j in 0..r {
+                    let tmp = a[(k, j)];
+                    a[(k, j)] = a[(p, j)];
+                    a[(p, j)] = tmp;
+                }
+                for i in 0..r {
+                    let tmp = a[(i, k)];
+                    a[(i, k)] = a[(i, p)];
+                    a[(i, p)] = tmp;
+                }
+                for j in 0..k {
+                    let tmp = l[(k, j)];
+                    l[(k, j)] = l[(p, j)];
+                    l[(p, j)] = tmp;
+                }
+                perm.swap(k, p);
+            }
+            let pivot = a[(k, k)].sqrt();
+            l[(k, k)] = pivot;
+            for i in k + 1..r {
+                l[(i, k)] = a[(i, k)] / pivot;
+            }
+            for i in k + 1..r {
+                for j in k + 1..r {
+                    a[(i, j)] -= l[(i, k)] * l[(j, k)];
+                }
+            }
+        }
+        // what is left after the last pivot vanishes only for a semidefinite matrix
+        for i in rank..r {
+            for j in rank..r {
+                if a[(i, j)].abs() > thresh {
+                    return None;
+                }
+            }
+        }
+        Some(Self { l, perm, rank })
+    }
+
+    /// A solution of `M u = b`, assuming that `b` is in the range of `M`.
+    fn solve(&self, b: &[f64]) -> Vec<f64> {
+        let rank = self.rank;
+        let mut y: Vec<f64> = self.perm[..rank].iter().map(|&p| b[p]).collect();
+        for i in 0..rank {
+            y[i] = (y[i] - dot(&self.l.row(i)[..i], &y[..i])) / self.l[(i, i)];
+        }
+        for i in (0..rank).rev() {
+            let tail: f64 = (i + 1..rank).map(|k| self.l[(k, i)] * y[k]).sum();
+            y[i] = (y[i] - tail) / self.l[(i, i)];
+        }
+        let mut u = vec![0.0; b.len()];
+        for (i, yi) in y.into_iter().enumerate() {
+            u[self.perm[i]] = yi;
+        }
+        u
+    }
+
+    /// Orthonormal basis of the null space of `M`.
+    fn null_space(&self) -> Vec<Vec<f64>> {
+        let (r, rank) = (self.perm.len(), self.rank);
+        let mut basis: Vec<Vec<f64>> = Vec::with_capacity(r - rank);
+        for j in rank..r {
+            // (w, eⱼ) with L₁₁ᵀw = −lⱼ, lⱼ being the first rank entries of row j of L
+            let mut w: Vec<f64> = (0..rank).map(|i| -self.l[(j, i)]).collect();
+            for i in (0..rank).rev() {
+                let tail: f64 = (i + 1..rank).map(|k| self.l[(k, i)] * w[k]).sum();
+                w[i] = (w[i] - tail) / self.l[(i, i)];
+            }
+            let mut v = vec![0.0; r];
+            for (i, wi) in w.into_iter().enumerate() {
+                v[self.perm[i]] = wi;
+            }
+            v[self.perm[j]] = 1.0;
+            for _ in 0..2 {
+                for b in &basis {
+                    let c = dot(b, &v);
+                    for (vi, bi) in v.iter_mut().zip(b) {
+                        *vi -= c * bi;
+                    }
+                }
+            }
+            let nv = norm(&v);
+            basis.push(v.into_iter().map(|vi| vi / nv).collect());
+        }
+        basis
+    }
+}
+//}}}
+//{{{ struct: ActiveSet
+/// Primal active-set solver of a convex [`QuadraticProgram`].
+pub struct ActiveSet {
+    problem: QuadraticProgram,
+    opts: Options,
+}
+//}}}
+//{{{ impl: ActiveSet
+impl ActiveSet {
+    pub fn new(problem: QuadraticProgram, opts: Options) -> Self {
+        Self { problem, opts }
+    }
+
+    pub fn problem(&self) -> &QuadraticProgram {
+        &self.problem
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    pub fn minimize(&mut self) -> Result<Returns, Error> {
+        //{{{ trace
+        error!(target: "qp", "--- Entering minimize ---");
+        //}}}
+        let Options { tol, max_iter } = self.opts;
+        let n = self.problem.dim();
+        let h = to_dense(self.problem.hessian());
+        let c = self.problem.linear();
+        let a_eq = to_dense(self.problem.a_eq());
+        let a_ineq = to_dense(self.problem.a_ineq());
+        let (b_eq, b_ineq) = (self.problem.b_eq(), self.problem.b_ineq());
+
+        // the feasible point nearest to the origin
+        let zeros = vec![0.0; n];
+        let start = dual::solve(&Matrix::identity(n), &zeros, &a_eq, b_eq, &a_ineq, b_ineq, max_iter)
+            .map_err(|err| match err {
+                QpError::Infeasible => Error::Infeasible,
+                QpError::Degenerate => Error::Degenerate,
+                QpError::MaxIterations | QpError::NotConvex => Error::MaxIterations { max_iter },
+            })?;
+        let mut x = start.x;
+        let mut num_iterations = start.num_iterations;
+        //{{{ trace
+        info!(target: "qp", "Feasible point found after {} iterations", num_iterations);
+        //}}}
+
+        let normal = |row: Row| match row {
+            Row::Eq(i) => a_eq.row(i),
+            Row::Ineq(i) => a_ineq.row(i),
+        };
+        let mut working: Vec<Row> = Vec::new();
+        let candidates = (0..a_eq.rows()).map(Row::Eq).chain((0..a_ineq.rows()).filter_map(|i| {
+            let slack = dot(a_ineq.row(i), &x) - b_ineq[i];
+            (slack <= tol * (1.0 + b_ineq[i].abs())).then_some(Row::Ineq(i))
+        }));
+        for row in candidates.collect::<Vec<_>>() {
+            if working.len() == n {
+                break;
+            }
+            working.push(row);
+            let normals: Vec<&[f64]> = working.iter().map(|&r| normal(r)).collect();
+            let k = working.len() - 1;
+            if Qr::new(&normals, n).dependent(k, norm(normal(row)), tol) {
+                working.pop();
+            }
+        }
+
+        loop {
+            if num_iterations >= max_iter {
+                //{{{ trace
+                info!(target: "qp", "Did not converge within {} iterations", max_iter);
+                //}}}
+                return Err(Error::MaxIterations { max_iter });
+            }
+            num_iterations += 1;
+            let mut g = h.mul_vec(&x);
+            for (gi, ci) in g.iter_mut().zip(c) {
+                *gi += ci;
+            }
+            let normals: Vec<&[f64]> = working.iter().map(|&r| normal(r)).collect();
+            let qr = Qr::new(&normals, n);
+            let z = qr.null_space();
+
+            // reduced Hessian ZᵀHZ and gradient Zᵀg
+            let hz: Vec<Vec<f64>> = z.iter().map(|zj| h.mul_vec(zj)).collect();
+            let mut m = Matrix::zeros(z.len(), z.len());
+            for i in 0..z.len() {
+                for j in 0..z.len() {
+                    m[(i, j)] = dot(&z[i], &hz[j]);
+                }
+            }
+            let gr: Vec<f64> = z.iter().map(|zj| dot(zj, &g)).collect();
+            let factor = SemidefiniteFactor::new(&m, tol).ok_or(Error::NotConvex)?;
+
+            // a descent direction of zero curvature if the gradient has a component in the null
+            // space of the reduced Hessian, the minimizer on the working set otherwise
+            let mut u = vec![0.0; gr.len()];
+            for v in factor.null_space() {
+                let coeff = dot(&v, &gr);
+                for (ui, vi) in u.iter_mut().zip(&v) {
+                    *ui -= coeff * vi;
+                }
+            }
+            let newton = norm(&u) <= tol * (1.0 + norm(&g));
+            if newton {
+                let neg_gr: Vec<f64> = gr.iter().map(|v| -v).collect();
+                u = factor.solve(&neg_gr);
+            }
+            let mut p = vec![0.0; n];
+            for (zj, uj) in z.iter().zip(&u) {
+                for (pi, zi) in p.iter_mut().zip(zj) {
+                    *pi += uj * zi;
+                }
+            }
+            //{{{ trace
+            info!(target: "qp", "i = {} f = {:1.4e} |W| = {} ‖p‖ = {:1.4e} zero curvature? {}",
+                num_iterations, self.problem.objective(&x), working.len(), norm_inf(&p), !newton);
+            //}}}
+
+            if newton && norm_inf(&p) <= tol * (1.0 + norm_inf(&x)) {
+                let y = qr.multipliers(&g);
+                let drop = working
+                    .iter()
+                    .zip(&y)
+                    .enumerate()
+                    .filter(|(_, (row, _))| matches!(row, Row::Ineq(_)))
+                    .min_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b));
+                match drop {
+                    Some((j, (_, yj))) if *yj < -tol * (1.0 + norm_inf(&g)) => {
+                        //{{{ trace
+                        debug!(target: "qp", "Dropping {:?} with multiplier {:1.4e}", working[j], yj);
+                        //}}}
+                        working.remove(j);
+                        continue;
+                    }
+                    _ => {}
+                }
+                let mut lambda = vec![0.0; a_eq.rows()];
+                let mut mu = vec![0.0; a_ineq.rows()];
+                let mut active = Vec::new();
+                for (row, yj) in working.iter().zip(&y) {
+                    match *row {
+                        Row::Eq(i) => lambda[i] = *yj,
+                        Row::Ineq(i) => {
+                            mu[i] = yj.max(0.0);
+                            active.push(i);
+                        }
+                    }
+                }
+                active.sort_unstable();
+                //{{{ trace
+                error!(target: "qp", "--- Leaving minimize ---");
+                //}}}
+                return Ok(Returns {
+                    objective: self.problem.objective(&x),
+                    x,
+                    lambda,
+                    mu,
+                    active,
+                    num_iterations,
+                });
+            }
+
+            // the first inequality outside the working set met along p
+            let mut alpha = if newton { 1.0 } else { f64::INFINITY };
+            let mut blocking = None;
+            let norm_p = norm(&p);
+            for (i, b) in b_ineq.iter().enumerate() {
+                if working.contains(&Row::Ineq(i)) {
+                    continue;
+                }
+                let a = a_ineq.row(i);
+                let ap = dot(a, &p);
+                if ap < -tol * norm(a) * norm_p {
+                    let step = (dot(a, &x) - b).max(0.0) / -ap;
+                    if step < alpha {
+                        alpha = step;
+                        blocking = Some(i);
+                    }
+                }
+            }
+            if alpha == f64::INFINITY {
+                //{{{ trace
+                info!(target: "qp", "Unbounded along a direction of zero curvature");
+                //}}}
+                let direction = p.iter().map(|pi| pi / norm_p).collect();
+                return Err(Error::Unbounded { x, direction });
+            }
+            for (xi, pi) in x.iter_mut().zip(&p) {
+                *xi += alpha * pi;
+            }
+            if let Some(i) = blocking {
+                working.push(Row::Ineq(i));
+            }
+        }
+    }
+}
+//}}}
@@ -0,0 +1,224 @@
+//! Dual active-set solver for strictly convex quadratic programs, which solves the subproblems of
+//! [`Sqp`](crate::constrained::Sqp) and finds the starting point of [`ActiveSet`](super::ActiveSet).
+//!
+//! Follows Goldfarb and Idnani, "A numerically stable dual method for solving strictly convex
+//! quadratic programs" (1983). Starting from the unconstrained minimizer, the method adds one
+//! violated constraint at a time, dropping active inequalities whose multipliers would turn
+//! negative, so that every iterate is optimal for the constraints in its active set. Unlike a
+//! primal method it needs no feasible starting point, and it finds out that the constraints are
+//! inconsistent when a violated constraint can no longer be added.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use crate::dense::{dot, Cholesky, Matrix};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+/// Relative violation below which a constraint counts as satisfied.
+const FEAS_TOL: f64 = 1e-10;
+/// Relative size of the step direction below which a constraint normal counts as linearly
+/// dependent on the active ones.
+const DEP_TOL: f64 = 1e-12;
+
+//{{{ struct: QpSolution
+#[derive(Clone, Debug)]
+pub(crate) struct QpSolution {
+    pub(crate) x: Vec<f64>,
+    /// Multipliers `λ` of the equality constraints, with `H x + c = A_Eᵀλ + A_Iᵀμ`.
+    pub(crate) lambda: Vec<f64>,
+    /// Multipliers `μ >= 0` of the inequality constraints.
+    pub(crate) mu: Vec<f64>,
+    pub(crate) num_iterations: usize,
+}
+//}}}
+//{{{ enum: QpError
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum QpError {
+    /// The Hessian is not positive definite.
+    NotConvex,
+    /// The constraints are inconsistent.
+    Infeasible,
+    /// The active constraints became numerically dependent.
+    Degenerate,
+    MaxIterations,
+}
+//}}}
+//{{{ struct: Constraint
+/// An active constraint `nᵀx >= b`, equalities being flipped so that they start out violated.
+struct Constraint {
+    normal: Vec<f64>,
+    /// `H⁻¹n`.
+    hinv_normal: Vec<f64>,
+    /// Multiplier of the constraint as written.
+    u: f64,
+    index: Index,
+}
+//}}}
+//{{{ enum: Index
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Index {
+    Eq(usize),
+    Ineq(usize),
+}
+//}}}
+//{{{ struct: DualActiveSet
+struct DualActiveSet {
+    chol: Cholesky,
+    x: Vec<f64>,
+    active: Vec<Constraint>,
+    num_iterations: usize,
+    max_iter: usize,
+}
+//}}}
+//{{{ impl: DualActiveSet
+impl DualActiveSet {
+    /// Adds the constraint `nᵀx >= b`, stepping `x` until it holds with equality.
+    ///
+    /// Returns `false` if the constraint is linearly dependent on the active ones and already
+    /// satisfied, in which case it is left out.
+    fn add(&mut self, normal: Vec<f64>, b: f64, index: Index) -> Result<bool, QpError> {
+        let hinv_normal = self.chol.solve(&normal);
+        let nn = dot(&normal, &hinv_normal);
+        let tol = FEAS_TOL * (1.0 + b.abs());
+        let mut u_new = 0.0;
+        loop {
+            self.num_iterations += 1;
+            if self.num_iterations > self.max_iter {
+                return Err(QpError::MaxIterations);
+            }
+            // dual step r = (NᵀH⁻¹N)⁻¹NᵀH⁻¹n and primal step z = H⁻¹n − H⁻¹N r
+            let k = self.active.len();
+            let mut r = vec![0.0; k];
+            if k > 0 {
+                let mut m = Matrix::zeros(k, k);
+                for i in 0..k {
+                    for j in 0..=i {
+                        m[(i, j)] = dot(&self.active[i].normal, &self.active[j].hinv_normal);
+                    }
+                }
+                let rhs: Vec<f64> = self.active.iter().map(|a| dot(&a.hinv_normal, &normal)).collect();
+                r = Cholesky::new(&m).ok_or(QpError::Degenerate)?.solve(&rhs);
+            }
+            let mut z = hinv_normal.clone();
+            for (a, rj) in self.active.iter().zip(&r) {
+                for (zi, vi) in z.iter_mut().zip(&a.hinv_normal) {
+                    *zi -= rj * vi;
+                }
+            }
+            let s = dot(&normal, &self.x) - b;
+            let zn = dot(&z, &normal);
+            let dependent = zn <= DEP_TOL * nn;
+            if dependent && s >= -tol {
+                return Ok(false);
+            }
+
+            // the largest step keeping the active inequality multipliers nonnegative
+            let mut t_dual = f64::INFINITY;
+            let mut drop = None;
+            for (j, (a, rj)) in self.active.iter().zip(&r).enumerate() {
+                if matches!(a.index, Index::Ineq(_)) && *rj > 0.0 && a.u / rj < t_dual {
+                    t_dual = a.u / rj;
+                    drop = Some(j);
+                }
+            }
+            let t_primal = if dependent { f64::INFINITY } else { -s / zn };
+            let t = t_dual.min(t_primal);
+            if t == f64::INFINITY {
+                return Err(QpError::Infeasible);
+            }
+            for (a, rj) in self.active.iter_mut().zip(&r) {
+                a.u -= t * rj;
+            }
+            u_new += t;
+            if t_primal.is_finite() {
+                for (xi, zi) in self.x.iter_mut().zip(&z) {
+                    *xi += t * zi;
+                }
+            }
+            if t == t_primal {
+                self.active.push(Constraint {
+                    normal,
+                    hinv_normal,
+                    u: u_new,
+                    index,
+                });
+                return Ok(true);
+            }
+            if let Some(j) = drop {
+                self.active.remove(j);
+            }
+        }
+    }
+}
+//}}}
+//{{{ fun: solve
+/// Minimizes `½xᵀHx + cᵀx` subject to `A_E x = b_E` and `A_I x >= b_I`, for a symmetric positive
+/// definite `H`, the rows of `a_eq` and `a_ineq` being the constraint normals.
+///
+/// `max_iter` bounds the total number of steps taken while adding constraints.
+pub(crate) fn solve(
+    h: &Matrix,
+    c: &[f64],
+    a_eq: &Matrix,
+    b_eq: &[f64],
+    a_ineq: &Matrix,
+    b_ineq: &[f64],
+    max_iter: usize,
+) -> Result<QpSolution, QpError> {
+    let chol = Cholesky::new(h).ok_or(QpError::NotConvex)?;
+    let neg_c: Vec<f64> = c.iter().map(|ci| -ci).collect();
+    let mut qp = DualActiveSet {
+        x: chol.solve(&neg_c),
+        chol,
+        active: Vec::new(),
+        num_iterations: 0,
+        max_iter,
+    };
+
+    let mut signs = vec![1.0; a_eq.rows()];
+    for (i, sign) in signs.iter_mut().enumerate() {
+        let normal = a_eq.row(i);
+        if dot(normal, &qp.x) > b_eq[i] {
+            *sign = -1.0;
+        }
+        let flipped = normal.iter().map(|v| *sign * v).collect();
+        qp.add(flipped, *sign * b_eq[i], Index::Eq(i))?;
+    }
+    loop {
+        // the most violated inequality relative to the length of its normal
+        let mut worst = None;
+        let mut worst_s = 0.0;
+        for (i, b) in b_ineq.iter().enumerate() {
+            let row = a_ineq.row(i);
+            let s = dot(row, &qp.x) - b;
+            let scaled = s / dot(row, row).sqrt().max(f64::MIN_POSITIVE);
+            let violated = s < -FEAS_TOL * (1.0 + b.abs());
+            if violated && scaled < worst_s && !qp.active.iter().any(|a| a.index == Index::Ineq(i)) {
+                worst = Some(i);
+                worst_s = scaled;
+            }
+        }
+        let Some(p) = worst else { break };
+        qp.add(a_ineq.row(p).to_vec(), b_ineq[p], Index::Ineq(p))?;
+    }
+
+    let mut lambda = vec![0.0; a_eq.rows()];
+    let mut mu = vec![0.0; a_ineq.rows()];
+    for a in &qp.active {
+        match a.index {
+            Index::Eq(i) => lambda[i] = signs[i] * a.u,
+            Index::Ineq(i) => mu[i] = a.u,
+        }
+    }
+    Ok(QpSolution {
+        x: qp.x,
+        lambda,
+        mu,
+        num_iterations: qp.num_iterations,
+    })
+}
+//}}}
@@ -0,0 +1,22 @@
+//! Dense convex quadratic programming.
+//!
+//! Minimizes `½xᵀHx + cᵀx` subject to linear equality and inequality constraints for a
+//! symmetric positive semidefinite `H`, reporting the multipliers and the active constraints.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+mod active_set;
+pub(crate) mod dual;
+mod problem;
+
+pub use active_set::{
+    ActiveSet, Error as QuadraticError, Options as ActiveSetOptions, Returns as QuadraticReturns,
+};
+pub use problem::QuadraticProgram;
@@ -0,0 +1,112 @@
+//! Quadratic programs `min ½xᵀHx + cᵀx` subject to `A_E x = b_E` and `A_I x >= b_I`.
+//!
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use crate::dense::{dot, to_dense};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use topohedral_linalg::dmatrix::DMatrix;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: QuadraticProgram
+/// A dense quadratic program with a symmetric Hessian `H`, the rows of `A_E` and `A_I` being the
+/// normals of the constraints.
+#[derive(Clone, Debug)]
+pub struct QuadraticProgram {
+    hessian: DMatrix<f64>,
+    linear: Vec<f64>,
+    a_eq: DMatrix<f64>,
+    b_eq: Vec<f64>,
+    a_ineq: DMatrix<f64>,
+    b_ineq: Vec<f64>,
+}
+//}}}
+//{{{ impl: QuadraticProgram
+impl QuadraticProgram {
+    /// The unconstrained program with Hessian `hessian` and linear term `linear`.
+    pub fn new(hessian: DMatrix<f64>, linear: Vec<f64>) -> Self {
+        let n = linear.len();
+        assert!(
+            hessian.nrows() == n && hessian.ncols() == n,
+            "Hessian and linear term differ in size"
+        );
+        Self {
+            hessian,
+            linear,
+            a_eq: DMatrix::zeros(0, n),
+            b_eq: Vec::new(),
+            a_ineq: DMatrix::zeros(0, n),
+            b_ineq: Vec::new(),
+        }
+    }
+
+    /// Replaces the equality constraints by `A_E x = b_E`.
+    pub fn with_equalities(mut self, a_eq: DMatrix<f64>, b_eq: Vec<f64>) -> Self {
+        assert!(
+            a_eq.nrows() == b_eq.len() && a_eq.ncols() == self.dim(),
+            "equality constraints do not match the problem size"
+        );
+        self.a_eq = a_eq;
+        self.b_eq = b_eq;
+        self
+    }
+
+    /// Replaces the inequality constraints by `A_I x >= b_I`.
+    pub fn with_inequalities(mut self, a_ineq: DMatrix<f64>, b_ineq: Vec<f64>) -> Self {
+        assert!(
+            a_ineq.nrows() == b_ineq.len() && a_ineq.ncols() == self.dim(),
+            "inequality constraints do not match the problem size"
+        );
+        self.a_ineq = a_ineq;
+        self.b_ineq = b_ineq;
+        self
+    }
+
+    /// Number of variables.
+    pub fn dim(&self) -> usize {
+        self.linear.len()
+    }
+
+    pub fn num_eq(&self) -> usize {
+        self.b_eq.len()
+    }
+
+    pub fn num_ineq(&self) -> usize {
+        self.b_ineq.len()
+    }
+
+    pub fn hessian(&self) -> &DMatrix<f64> {
+        &self.hessian
+    }
+
+    pub fn linear(&self) -> &[f64] {
+        &self.linear
+    }
+
+    pub fn a_eq(&self) -> &DMatrix<f64> {
+        &self.a_eq
+    }
+
+    pub fn b_eq(&self) -> &[f64] {
+        &self.b_eq
+    }
+
+    pub fn a_ineq(&self) -> &DMatrix<f64> {
+        &self.a_ineq
+    }
+
+    pub fn b_ineq(&self) -> &[f64] {
+        &self.b_ineq
+    }
+
+    /// Value `½xᵀHx + cᵀx` of the objective.
+    pub fn objective(&self, x: &[f64]) -> f64 {
+        let hx = to_dense(&self.hessian).mul_vec(x);
+        0.5 * dot(x, &hx) + dot(&self.linear, x)
+    }
+}
+//}}}
@@ -0,0 +1,562 @@
+//! Barzilai-Borwein gradient method.
+//!
+//! Steps along the negative gradient with the spectral step lengths of Barzilai and Borwein,
+//! "Two-point step size gradient methods" (1988). The step is taken as it is unless a nonmonotone
+//! globalization in the style of Raydan, "The Barzilai and Borwein gradient method for the large
+//! scale unconstrained minimization problem" (1997), is asked for, in which case it is shortened
+//! until the value falls sufficiently below the largest of the last few values.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::Options as UnonstrainedOptions;
+use super::common::{
+    ConvergedReason, Direction, Error, IterationState, Observer, ObserverAction, Returns, Step,
+    UnconstrainedMinimizer,
+};
+use super::criteria::ConvergenceState;
+use super::history::{History, IterationRecord};
+use crate::common::{CancelToken, CountingRealFn, DenseVector};
+use crate::line_search::{LineSearchError, LineSearchStats};
+use crate::RealFn;
+//}}}
+//{{{ std imports
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::time::{Duration, Instant};
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+use topohedral_linalg::VectorOps;
+use topohedral_tracing::*;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ enum: Variant
+/// Choice of spectral step from the last step `s` and gradient change `y`.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Variant {
+    /// The long step `sᵀs / sᵀy`, known as BB1.
+    Long,
+    /// The short step `sᵀy / yᵀy`, known as BB2.
+    Short,
+    /// BB1 on odd iterations and BB2 on even ones.
+    Alternating,
+    /// BB2 when `BB2 / BB1 < kappa` and BB1 otherwise, the adaptive rule of Zhou, Gao and Dai,
+    /// "Gradient methods with adaptive step-sizes" (2006).
+    Adaptive { kappa: f64 },
+}
+//}}}
+//{{{ struct: Nonmonotone
+/// Nonmonotone acceptance test `f(x + λd) <= max_{j < memory} f_{k−j} + c1 λ ∇fᵀd`.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Nonmonotone {
+    /// Number of recent values compared against, one makes the test monotone.
+    pub memory: usize,
+    pub c1: f64,
+}
+//}}}
+//{{{ impl: Default for Nonmonotone
+impl Default for Nonmonotone {
+    fn default() -> Self {
+        Self {
+            memory: 10,
+            c1: 1e-4,
+        }
+    }
+}
+//}}}
+//{{{ impl: Nonmonotone
+impl Nonmonotone {
+    /// Whether the value `ft` at step `alpha` passes the test against the largest recent value
+    /// `f_max`, with `dg` the directional derivative.
+    fn accepts(&self, f_max: f64, ft: f64, alpha: f64, dg: f64) -> bool {
+        ft <= f_max + self.c1 * alpha * dg
+    }
+}
+//}}}
+//{{{ struct: Options
+/// Options of the Barzilai-Borwein method.
+///
+/// The line search method, cache size and cache policy of `uncon_opts` are not used, every
+/// iteration evaluates the value and gradient at the new iterate exactly once when the step is
+/// accepted.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Options {
+    pub uncon_opts: UnonstrainedOptions,
+    pub variant: Variant,
+    /// Safeguards on the spectral step.
+    pub step_min: f64,
+    pub step_max: f64,
+    /// Globalization of the step, `None` takes every step in full.
+    pub nonmonotone: Option<Nonmonotone>,
+    /// Maximum number of step reductions in a single iteration, either to satisfy the
+    /// nonmonotone test or to back off from non-finite values.
+    pub max_backtracks: usize,
+}
+//}}}
+//{{{ impl: Default for Options
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            uncon_opts: UnonstrainedOptions::default(),
+            variant: Variant::Long,
+            step_min: 1e-30,
+            step_max: 1e30,
+            nonmonotone: Some(Nonmonotone::default()),
+            max_backtracks: 30,
+        }
+    }
+}
+//}}}
+//{{{ fun: reduce_step
+/// Shortened step after `alpha` gave the value `ft`, the minimizer of the quadratic interpolant
+/// of `f`, `dg` and `ft` if it lies within `[0.1α, 0.5α]` and half the step otherwise.
+fn reduce_step(alpha: f64, f: f64, ft: f64, dg: f64) -> f64 {
+    let alpha_q = -0.5 * alpha * alpha * dg / (ft - f - alpha * dg);
+    if alpha_q.is_finite() && 0.1 * alpha <= alpha_q && alpha_q <= 0.5 * alpha {
+        alpha_q
+    } else {
+        0.5 * alpha
+    }
+}
+//}}}
+//{{{ struct: Iterate
+struct Iterate<V> {
+    iteration: usize,
+    x: V,
+    x_prev: V,
+    f: f64,
+    f_prev: f64,
+    grad: V,
+    grad_norm_init: f64,
+    /// Spectral step for the next iteration.
+    spectral: f64,
+    /// Step length accepted in the last iteration.
+    alpha: f64,
+    /// Values of the last iterates, for the nonmonotone test.
+    recent: VecDeque<f64>,
+    elapsed: Duration,
+    history: Option<History>,
+}
+//}}}
+//{{{ enum: Outcome
+#[derive(Copy, Clone, Debug)]
+enum Outcome {
+    Converged(ConvergedReason),
+    LineSearch(LineSearchError),
+    MaxIterations,
+    Cancelled,
+    TimeLimit(Duration),
+    BudgetExhausted,
+    NonFiniteValue,
+}
+//}}}
+//{{{ struct: BarzilaiBorwein
+/// Barzilai-Borwein minimizer of a [`RealFn`].
+///
+/// The first step has unit length along the negative gradient in the infinity norm, as has any
+/// step following one with `sᵀy <= 0`, where the spectral steps are meaningless.
+pub struct BarzilaiBorwein<F: RealFn> {
+    fcn: CountingRealFn<F>,
+    x_init: F::Vector,
+    opts: Options,
+    observer: Option<Box<dyn Observer<F::Vector>>>,
+    iterate: Option<Iterate<F::Vector>>,
+    outcome: Option<Outcome>,
+    line_search: LineSearchStats,
+    /// Set by the first step, so that time spent before the run is not counted.
+    start: Option<Instant>,
+}
+//}}}
+//{{{ impl: BarzilaiBorwein
+impl<F: RealFn> BarzilaiBorwein<F>
+where
+    F::Vector: VectorOps<ScalarType = f64>
+        + Add<Output = F::Vector>
+        + Sub<Output = F::Vector>
+        + Neg<Output = F::Vector>
+        + Clone
+        + DenseVector
+        + fmt::Debug,
+    f64: Mul<F::Vector, Output = F::Vector>,
+{
+    pub fn new(fcn: F, x0: F::Vector, opts: Options) -> Self {
+        Self {
+            fcn: CountingRealFn::new(fcn),
+            x_init: x0,
+            opts,
+            observer: None,
+            iterate: None,
+            outcome: None,
+            line_search: LineSearchStats::default(),
+            start: None,
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    /// Options which can be changed between steps, taking effect from the next iteration.
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    /// Evaluates the starting point, returning `false` if its value or gradient is not finite.
+    fn initialize(&mut self) -> bool {
+        let (fk, grad_fk) = self.fcn.eval_with_grad(&self.x_init);
+        let grad_fk_norm = grad_fk.norm();
+        //{{{ trace
+        info!(target: "bb", "f0 = {fk:1.4e} norm_f0 = {grad_fk_norm:1.4e}");
+        //}}}
+        let spectral = self.unit_step(&grad_fk);
+        let history = self.opts.uncon_opts.record_history.then(|| {
+            let mut history = History::new();
+            history.push(self.record(0, &self.x_init, fk, grad_fk_norm));
+            history
+        });
+        self.iterate = Some(Iterate {
+            iteration: 0,
+            x: self.x_init.clone(),
+            x_prev: self.x_init.clone(),
+            f: fk,
+            f_prev: fk,
+            grad: grad_fk,
+            grad_norm_init: grad_fk_norm,
+            spectral,
+            alpha: 0.0,
+            recent: VecDeque::from([fk]),
+            elapsed: self.elapsed(),
+            history,
+        });
+        fk.is_finite() && grad_fk_norm.is_finite()
+    }
+
+    /// Wall-clock time spent on the run so far.
+    fn elapsed(&self) -> Duration {
+        self.start.map_or(Duration::ZERO, |start| start.elapsed())
+    }
+
+    /// Checks that the run may go on with `fevals` more function and `gevals` more gradient
+    /// evaluations.
+    fn admit(&self, fevals: usize, gevals: usize) -> Result<(), Outcome> {
+        let opts = &self.opts.uncon_opts;
+        if opts.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Err(Outcome::Cancelled);
+        }
+        if let Some(time_limit) = opts.time_limit.filter(|t| self.elapsed() >= *t) {
+            return Err(Outcome::TimeLimit(time_limit));
+        }
+        let (num_fevals, num_gevals) = (self.fcn.num_func_evals, self.fcn.num_grad_evals);
+        if !opts.budget.allows(num_fevals, num_gevals, fevals, gevals) {
+            //{{{ trace
+            info!(target: "bb", "Evaluation budget {:?} exhausted", opts.budget);
+            //}}}
+            return Err(Outcome::BudgetExhausted);
+        }
+        Ok(())
+    }
+
+    /// Takes the spectral step from the current iterate, returning the new point with its
+    /// value, gradient and step length.
+    ///
+    /// Without globalization any finite trial value is accepted and the value and gradient are
+    /// evaluated together. Otherwise the step is shortened, trying the minimizer of the
+    /// quadratic interpolant before halving, until the nonmonotone test holds.
+    fn search(
+        &mut self,
+        stats: &mut LineSearchStats,
+    ) -> Result<(F::Vector, f64, F::Vector, f64), Outcome> {
+        let it = self.iterate.as_ref().unwrap();
+        let (x, f, direction) = (it.x.clone(), it.f, -it.grad.clone());
+        let dg = it.grad.dot(&direction);
+        let f_max = it.recent.iter().fold(f64::NEG_INFINITY, |acc, v| acc.max(*v));
+        let nonmonotone = self.opts.nonmonotone;
+        stats.num_searches += 1;
+        let mut alpha = it.spectral;
+        let mut alpha_tried = alpha;
+        for _ in 0..=self.opts.max_backtracks {
+            alpha_tried = alpha;
+            let xt = x.clone() + alpha * direction.clone();
+            let (ft, gt) = if nonmonotone.is_some() {
+                self.admit(1, 0)?;
+                (self.fcn.eval(&xt), None)
+            } else {
+                self.admit(1, 1)?;
+                stats.num_diffs += 1;
+                let (ft, gt) = self.fcn.eval_with_grad(&xt);
+                (ft, Some(gt))
+            };
+            stats.num_evals += 1;
+            //{{{ trace
+            trace!(target: "bb", "alpha = {alpha:1.4e} f = {ft:1.4e}");
+            //}}}
+            if !ft.is_finite() {
+                stats.num_nonfinite += 1;
+                alpha = reduce_step(alpha, f, f64::INFINITY, dg);
+                continue;
+            }
+            if nonmonotone.is_none_or(|nm| nm.accepts(f_max, ft, alpha, dg)) {
+                let gt = match gt {
+                    Some(gt) => gt,
+                    None => {
+                        self.admit(0, 1)?;
+                        stats.num_diffs += 1;
+                        self.fcn.grad(&xt)
+                    }
+                };
+                return Ok((xt, ft, gt, alpha));
+            }
+            stats.num_rejected += 1;
+            alpha = reduce_step(alpha, f, ft, dg);
+        }
+        let source = if stats.num_rejected == 0 {
+            LineSearchError::NonFiniteValue { alpha: alpha_tried }
+        } else {
+            LineSearchError::MaxIterations
+        };
+        //{{{ trace
+        info!(target: "bb", "Step reduction failed with {source:?}");
+        //}}}
+        Err(Outcome::LineSearch(source))
+    }
+
+    /// Step of unit length along `−grad` in the infinity norm.
+    fn unit_step(&self, grad: &F::Vector) -> f64 {
+        (0..grad.len())
+            .fold(0.0, |m: f64, j| grad[j].abs().max(m))
+            .recip()
+            .clamp(self.opts.step_min, self.opts.step_max)
+    }
+
+    /// Next spectral step from the last step `s` and gradient change `y`, for iteration `i`,
+    /// with `grad` the current gradient.
+    fn spectral_step(&self, i: usize, s: &F::Vector, y: &F::Vector, grad: &F::Vector) -> f64 {
+        let sy = s.dot(y);
+        if sy <= 0.0 {
+            //{{{ trace
+            debug!(target: "bb", "Non-positive curvature sᵀy = {sy:1.4e}, resetting the step");
+            //}}}
+            return self.unit_step(grad);
+        }
+        let long = s.dot(s) / sy;
+        let short = sy / y.dot(y);
+        let step = match self.opts.variant {
+            Variant::Long => long,
+            Variant::Short => short,
+            Variant::Alternating if i % 2 == 1 => long,
+            Variant::Alternating => short,
+            Variant::Adaptive { kappa } if short / long < kappa => short,
+            Variant::Adaptive { .. } => long,
+        };
+        //{{{ trace
+        debug!(target: "bb", "BB1 = {long:1.4e} BB2 = {short:1.4e} step = {step:1.4e}");
+        //}}}
+        step.clamp(self.opts.step_min, self.opts.step_max)
+    }
+
+    /// Runs a single iteration, recording the outcome if it ends the run.
+    fn iterate_once(&mut self) -> Result<IterationState<F::Vector>, Outcome> {
+        let i = self.iterate.as_ref().unwrap().iteration + 1;
+        if i >= self.opts.uncon_opts.max_iter as usize {
+            //{{{ trace
+            info!(target: "bb", "Did not converge within {} iterations", self.opts.uncon_opts.max_iter);
+            //}}}
+            return Err(Outcome::MaxIterations);
+        }
+        let mut stats = LineSearchStats::default();
+        let searched = self.search(&mut stats);
+        self.line_search.accumulate(&stats);
+        let (xk, fk, grad_fk, alpha) = searched?;
+        let grad_fk_norm = grad_fk.norm();
+        if !grad_fk_norm.is_finite() {
+            return Err(Outcome::LineSearch(LineSearchError::NonFiniteValue { alpha }));
+        }
+
+        let mut it = self.iterate.take().unwrap();
+        let spectral = self.spectral_step(
+            i + 1,
+            &(xk.clone() - it.x.clone()),
+            &(grad_fk.clone() - it.grad.clone()),
+            &grad_fk,
+        );
+        if let Some(history) = it.history.as_mut() {
+            history.push(IterationRecord {
+                alpha,
+                step_norm: (xk.clone() - it.x.clone()).norm(),
+                line_search: stats,
+                ..self.record(i, &xk, fk, grad_fk_norm)
+            });
+        }
+        //{{{ trace
+        info!(target: "bb", "i = {i} f = {fk:1.4e} ‖∇f‖ = {grad_fk_norm:1.4e} alpha = {alpha:1.4e}");
+        //}}}
+        it.iteration = i;
+        it.x_prev = std::mem::replace(&mut it.x, xk);
+        it.f_prev = std::mem::replace(&mut it.f, fk);
+        it.grad = grad_fk;
+        it.spectral = spectral;
+        it.alpha = alpha;
+        it.recent.push_back(fk);
+        let memory = self.opts.nonmonotone.map_or(1, |nm| nm.memory.max(1));
+        while it.recent.len() > memory {
+            it.recent.pop_front();
+        }
+        it.elapsed = self.elapsed();
+        let reason = self.check(&it);
+        let state = IterationState {
+            iteration: i,
+            x: it.x.clone(),
+            f: fk,
+            grad_norm: grad_fk_norm,
+            alpha,
+            direction: Some(Direction::Steepest),
+            restarted: false,
+        };
+        self.iterate = Some(it);
+        self.outcome = reason.map(Outcome::Converged);
+        Ok(state)
+    }
+
+    /// Informs the observer of an iteration and checks the stopping criteria.
+    fn check(&mut self, it: &Iterate<F::Vector>) -> Option<ConvergedReason> {
+        let grad_fk_norm = it.grad.norm();
+        if let Some(observer) = self.observer.as_mut() {
+            let state = IterationState {
+                iteration: it.iteration,
+                x: it.x.clone(),
+                f: it.f,
+                grad_norm: grad_fk_norm,
+                alpha: it.alpha,
+                direction: Some(Direction::Steepest),
+                restarted: false,
+            };
+            if observer.observe(&state) == ObserverAction::Stop {
+                //{{{ trace
+                info!(target: "bb", "Stopping at the request of the observer");
+                //}}}
+                return Some(ConvergedReason::ObserverStop);
+            }
+        }
+
+        let state = ConvergenceState {
+            iteration: it.iteration,
+            f: it.f,
+            f_prev: it.f_prev,
+            grad_norm: grad_fk_norm,
+            grad_norm_init: it.grad_norm_init,
+            grad_inf_norm: (0..it.grad.len()).fold(0.0, |m, j| it.grad[j].abs().max(m)),
+            step_norm: (it.x.clone() - it.x_prev.clone()).norm(),
+            x_norm: it.x.norm(),
+            num_fun_evals: self.fcn.num_func_evals,
+            elapsed: it.elapsed,
+        };
+        let reason = self.opts.uncon_opts.check_convergence(&state);
+        //{{{ trace
+        if let Some(reason) = reason {
+            info!(target: "bb", "Converging with reason {reason:?}");
+        }
+        //}}}
+        reason
+    }
+
+    fn result(&self, outcome: Outcome) -> Result<Returns<F::Vector>, Error<F::Vector>> {
+        let reason = match outcome {
+            Outcome::Converged(reason) => reason,
+            _ => ConvergedReason::NotConverged,
+        };
+        let it = self.iterate.as_ref().unwrap();
+        let returns = Returns {
+            fmin: it.f,
+            xmin: it.x.clone(),
+            grad_norm: it.grad.norm(),
+            reason,
+            num_iterations: it.iteration,
+            num_fun_evals: self.fcn.num_func_evals,
+            num_grad_evals: self.fcn.num_grad_evals,
+            num_cache_hits: 0,
+            line_search: self.line_search,
+            history: it.history.clone(),
+        };
+        match outcome {
+            Outcome::Converged(_) => Ok(returns),
+            Outcome::LineSearch(source) => Err(Error::LineSearch {
+                source,
+                partial: Box::new(returns),
+            }),
+            Outcome::MaxIterations => Err(Error::MaxIterations {
+                max_iter: self.opts.uncon_opts.max_iter as usize,
+                partial: Box::new(returns),
+            }),
+            Outcome::Cancelled => Err(Error::Cancelled {
+                partial: Box::new(returns),
+            }),
+            Outcome::TimeLimit(time_limit) => Err(Error::TimeLimit {
+                time_limit,
+                partial: Box::new(returns),
+            }),
+            Outcome::BudgetExhausted => Err(Error::BudgetExhausted {
+                budget: self.opts.uncon_opts.budget,
+                partial: Box::new(returns),
+            }),
+            Outcome::NonFiniteValue => Err(Error::NonFiniteValue {
+                x: self.x_init.clone(),
+                partial: Box::new(returns),
+            }),
+        }
+    }
+
+    /// Builds the history record for iteration `iter`, with the evaluation counts so far.
+    fn record(&self, iter: usize, xk: &F::Vector, fk: f64, grad_norm: f64) -> IterationRecord {
+        IterationRecord {
+            iteration: iter,
+            f: fk,
+            grad_norm,
+            num_fun_evals: self.fcn.num_func_evals,
+            num_grad_evals: self.fcn.num_grad_evals,
+            x: self.opts.uncon_opts.record_x.then(|| DenseVector::to_vec(xk)),
+            ..IterationRecord::default()
+        }
+    }
+}
+//}}}
+//{{{ impl: UnconstrainedMinimizer for BarzilaiBorwein
+impl<F: RealFn> UnconstrainedMinimizer for BarzilaiBorwein<F>
+where
+    F::Vector: VectorOps<ScalarType = f64>
+        + Add<Output = F::Vector>
+        + Sub<Output = F::Vector>
+        + Neg<Output = F::Vector>
+        + Clone
+        + DenseVector
+        + fmt::Debug,
+    f64: Mul<F::Vector, Output = F::Vector>,
+{
+    type Vector = F::Vector;
+
+    fn step(&mut self) -> Step<Self::Vector> {
+        self.start.get_or_insert_with(Instant::now);
+        if self.outcome.is_none() && self.iterate.is_none() && !self.initialize() {
+            //{{{ trace
+            info!(target: "bb", "Non-finite value or gradient at the starting point");
+            //}}}
+            self.outcome = Some(Outcome::NonFiniteValue);
+        }
+        if let Some(outcome) = self.outcome {
+            return Step::Done(self.result(outcome));
+        }
+        match self.iterate_once() {
+            Ok(state) => Step::Iteration(state),
+            Err(outcome) => {
+                self.outcome = Some(outcome);
+                Step::Done(self.result(outcome))
+            }
+        }
+    }
+
+    fn set_observer(&mut self, observer: Box<dyn Observer<Self::Vector>>) {
+        self.observer = Some(observer);
+    }
+}
+//}}}
@@ -6,48 +6,321 @@
 //{{{ crate imports 
 use crate::line_search::LineSearchError;
 use crate::line_search::LineSearchMethod;
+use crate::line_search::LineSearchStats;
+use super::criteria::{ConvergenceState, Criterion};
+use super::history::History;
+use crate::{Budget, CachePolicy, CancelToken};
 //}}}
 //{{{ std imports 
+use std::fmt::Debug;
+use std::time::Duration;
 //}}}
 //{{{ dep imports 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 //}}}
 //--------------------------------------------------------------------------------------------------
 
-#[derive(Copy, Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Options {
+    /// Stops once `‖∇f_k‖ / ‖∇f_0‖ < grad_rtol`, checked ahead of and independently of
+    /// `criteria`. Zero turns the test off.
     pub grad_rtol: f64,
+    /// Stops once `‖∇f_k‖ < grad_atol`, checked ahead of and independently of `criteria`. Zero
+    /// turns the test off.
     pub grad_atol: f64,
     pub max_iter: u64,
     pub ls_method: LineSearchMethod,
+    /// Number of recent `(x, f, ∇f)` triples to memoize, zero disables the cache.
+    pub cache_size: usize,
+    /// How cached points are matched against requested ones.
+    pub cache_policy: CachePolicy,
+    /// Whether to record a per-iteration [`History`] in the returns.
+    pub record_history: bool,
+    /// Whether the recorded history also stores each iterate `x_k`.
+    pub record_x: bool,
+    /// Stopping criteria checked in addition to `grad_rtol` and `grad_atol`. Since either
+    /// tolerance alone stops the run, set both to zero for a composed criterion such as
+    /// [`Criterion::All`] to decide on its own.
+    pub criteria: Option<Criterion>,
+    /// Token checked before every evaluation, ending the run with [`Error::Cancelled`] once it
+    /// is cancelled.
+    #[serde(skip)]
+    pub cancel: Option<CancelToken>,
+    /// Wall-clock budget checked before every evaluation, ending the run with
+    /// [`Error::TimeLimit`] once it is used up. Unlike [`Criterion::TimeLimit`] this can stop a
+    /// run in the middle of a line search.
+    pub time_limit: Option<Duration>,
+    /// Limits on function and gradient evaluations, checked before every evaluation so that
+    /// line searches cannot overrun them. The run ends with [`Error::BudgetExhausted`]. A request
+    /// which the evaluation cache would answer is still charged against the budget.
+    pub budget: Budget,
+}
+
+impl Options {
+    /// Checks the gradient tolerances which are switched on and then the additional criteria,
+    /// shared by every minimizer so that they all stop under the same rules.
+    pub fn check_convergence(&self, state: &ConvergenceState) -> Option<ConvergedReason> {
+        let grad_rtol = (self.grad_rtol > 0.0).then_some(Criterion::GradRtol(self.grad_rtol));
+        let grad_atol = (self.grad_atol > 0.0).then_some(Criterion::GradAtol(self.grad_atol));
+        let reason = [grad_rtol.as_ref(), grad_atol.as_ref(), self.criteria.as_ref()]
+            .into_iter()
+            .flatten()
+            .find_map(|c| c.check(state));
+        reason
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            grad_rtol: 1e-6,
+            grad_atol: 1e-8,
+            max_iter: 1000,
+            ls_method: LineSearchMethod::default(),
+            cache_size: 4,
+            cache_policy: CachePolicy::Exact,
+            record_history: false,
+            record_x: false,
+            criteria: None,
+            cancel: None,
+            time_limit: None,
+            budget: Budget::default(),
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
 pub enum ConvergedReason {
     Rtol, 
     Atol,
+    /// The infinity norm of the projected gradient `P(x − ∇f) − x` of a bound-constrained
+    /// problem fell below its tolerance.
+    ProjGrad,
+    /// An [`Observer`] asked for early termination.
+    ObserverStop,
+    GradInfNorm,
+    FAtol,
+    FRtol,
+    XAtol,
+    XRtol,
+    TargetF,
+    MaxFunEvals,
+    TimeLimit,
+    /// The minimizer failed, only used for the partial results carried by [`Error`].
+    NotConverged,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct Returns<Vector> {
     pub xmin: Vector,
     pub fmin: f64,
+    /// Gradient norm at `xmin`.
+    pub grad_norm: f64,
     pub reason: ConvergedReason, 
     pub num_iterations: usize, 
     pub num_fun_evals: usize, 
     pub num_grad_evals: usize, 
+    /// Function values and gradients served from the evaluation cache.
+    pub num_cache_hits: usize,
+    /// Totals over all line searches.
+    pub line_search: LineSearchStats,
+    /// Per-iteration history, present when `Options::record_history` is set.
+    pub history: Option<History>,
 }
 
+/// Failure of a minimizer.
+///
+/// Every variant carries the last accepted iterate and the counters so far as a partial
+/// [`Returns`] with reason [`ConvergedReason::NotConverged`], so that the work done before the
+/// failure is not lost.
 #[derive(Error, Debug)] 
-pub enum Error {
-    #[error("Linear search failed with error {0}")]
-    LineSearch(#[from] LineSearchError),
-    #[error("Maximum iterations of {0} reached")]
-    MaxIterations(usize),
+pub enum Error<Vector: Debug> {
+    #[error("Linear search failed with error {source}")]
+    LineSearch {
+        source: LineSearchError,
+        partial: Box<Returns<Vector>>,
+    },
+    #[error("Maximum iterations of {max_iter} reached")]
+    MaxIterations {
+        max_iter: usize,
+        partial: Box<Returns<Vector>>,
+    },
+    #[error("Cancelled")]
+    Cancelled { partial: Box<Returns<Vector>> },
+    #[error("Time limit of {time_limit:?} reached")]
+    TimeLimit {
+        time_limit: Duration,
+        partial: Box<Returns<Vector>>,
+    },
+    #[error("Evaluation budget {budget:?} exhausted")]
+    BudgetExhausted {
+        budget: Budget,
+        partial: Box<Returns<Vector>>,
+    },
+    /// The value or gradient at the starting point `x` is not finite. Non-finite values at
+    /// trial points are handled by the line search, see [`LineSearchError::NonFiniteValue`].
+    #[error("Non-finite value or gradient at the starting point")]
+    NonFiniteValue {
+        x: Vector,
+        partial: Box<Returns<Vector>>,
+    },
+}
+
+impl<Vector: Debug> Error<Vector> {
+    /// The best point found before the failure.
+    pub fn partial(&self) -> &Returns<Vector> {
+        match self {
+            Error::LineSearch { partial, .. } => partial,
+            Error::MaxIterations { partial, .. } => partial,
+            Error::Cancelled { partial } => partial,
+            Error::TimeLimit { partial, .. } => partial,
+            Error::BudgetExhausted { partial, .. } => partial,
+            Error::NonFiniteValue { partial, .. } => partial,
+        }
+    }
+
+    pub(crate) fn partial_mut(&mut self) -> &mut Returns<Vector> {
+        match self {
+            Error::LineSearch { partial, .. } => partial,
+            Error::MaxIterations { partial, .. } => partial,
+            Error::Cancelled { partial } => partial,
+            Error::TimeLimit { partial, .. } => partial,
+            Error::BudgetExhausted { partial, .. } => partial,
+            Error::NonFiniteValue { partial, .. } => partial,
+        }
+    }
+
+    /// Consumes the error, returning the best point found before the failure.
+    pub fn into_partial(self) -> Returns<Vector> {
+        match self {
+            Error::LineSearch { partial, .. } => *partial,
+            Error::MaxIterations { partial, .. } => *partial,
+            Error::Cancelled { partial } => *partial,
+            Error::TimeLimit { partial, .. } => *partial,
+            Error::BudgetExhausted { partial, .. } => *partial,
+            Error::NonFiniteValue { partial, .. } => *partial,
+        }
+    }
+}
+
+/// Search direction taken by a minimizer, [`Direction::Steepest`] for methods which only ever
+/// follow the negative gradient.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    Steepest,
+    FletcherReeves,
+    PolakRibiere,
+}
+
+/// Snapshot of a minimizer taken after each accepted step.
+#[derive(Clone, Debug)]
+pub struct IterationState<Vector> {
+    pub iteration: usize,
+    pub x: Vector,
+    pub f: f64,
+    pub grad_norm: f64,
+    /// Step length accepted by the line search.
+    pub alpha: f64,
+    /// Search direction used for the step, `None` for methods without a choice of direction.
+    pub direction: Option<Direction>,
+    /// Whether the direction was reset to steepest descent for this step.
+    pub restarted: bool,
+}
+
+/// What a minimizer should do after an [`Observer`] has seen an iteration.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ObserverAction {
+    Continue,
+    Stop,
+}
+
+/// Receives the state of a minimizer after each iteration.
+///
+/// Returning [`ObserverAction::Stop`] ends the minimization with
+/// [`ConvergedReason::ObserverStop`] and the current iterate as the result. Closures of the form
+/// `FnMut(&IterationState<V>) -> ObserverAction` implement this trait.
+pub trait Observer<Vector> {
+    fn observe(&mut self, state: &IterationState<Vector>) -> ObserverAction;
+}
+
+impl<Vector, F> Observer<Vector> for F
+where
+    F: FnMut(&IterationState<Vector>) -> ObserverAction,
+{
+    fn observe(&mut self, state: &IterationState<Vector>) -> ObserverAction {
+        self(state)
+    }
+}
+
+/// Quantities a minimizer driven through `ask`/`tell` needs at a point.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Need {
+    Value,
+    Grad,
+    ValueAndGrad,
+}
+
+/// Request made by a minimizer driven through `ask`/`tell`.
+#[derive(Debug)]
+pub enum Task<Vector: Debug> {
+    /// Evaluate the quantities in `need` at `x` and pass them to `tell`.
+    Evaluate { x: Vector, need: Need },
+    /// The minimization has finished with this result.
+    Done(Result<Returns<Vector>, Error<Vector>>),
+}
+
+/// Outcome of [`UnconstrainedMinimizer::step`].
+#[derive(Debug)]
+pub enum Step<Vector: Debug> {
+    /// An iteration has been completed.
+    Iteration(IterationState<Vector>),
+    /// The minimization has finished with this result, further steps repeat it.
+    Done(Result<Returns<Vector>, Error<Vector>>),
 }
 
 pub trait UnconstrainedMinimizer {
 
-    type Vector;
-    fn minimize(&mut self) -> Result<Returns<Self::Vector>, Error>;
-}
\ No newline at end of file
+    type Vector: Debug;
+
+    /// Runs a single iteration, continuing from the current state if there is one.
+    fn step(&mut self) -> Step<Self::Vector>;
+
+    /// Runs the minimization to the end, continuing from the current state if there is one.
+    fn minimize(&mut self) -> Result<Returns<Self::Vector>, Error<Self::Vector>> {
+        loop {
+            if let Step::Done(result) = self.step() {
+                return result;
+            }
+        }
+    }
+
+    /// Installs an observer which is called after every iteration, replacing any previous one.
+    fn set_observer(&mut self, observer: Box<dyn Observer<Self::Vector>>);
+
+    /// Iterator over the remaining iterations, after which [`minimize`](Self::minimize)
+    /// returns the result.
+    fn iterations(&mut self) -> Iterations<'_, Self>
+    where
+        Self: Sized,
+    {
+        Iterations { minimizer: self }
+    }
+}
+
+/// Iterator over the states of the iterations of a minimizer, see
+/// [`UnconstrainedMinimizer::iterations`].
+pub struct Iterations<'a, M: UnconstrainedMinimizer> {
+    minimizer: &'a mut M,
+}
+
+impl<M: UnconstrainedMinimizer> Iterator for Iterations<'_, M> {
+    type Item = IterationState<M::Vector>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.minimizer.step() {
+            Step::Iteration(state) => Some(state),
+            Step::Done(_) => None,
+        }
+    }
+}
@@ -6,11 +6,20 @@
 
 //{{{ crate imports
 use super::common::Options as UnonstrainedOptions;
-use super::common::{Error, Returns, UnconstrainedMinimizer};
-use crate::common::{arc_real_fn, CountingRealFn};
+pub use super::common::Direction;
+use super::criteria::ConvergenceState;
+use super::common::{
+    Error, IterationState, Need, Observer, ObserverAction, Returns, Step, Task,
+    UnconstrainedMinimizer,
+};
+use super::history::{History, IterationRecord};
+use crate::common::{
+    arc_real_fn, CachedPoint, CachingRealFn, CancelToken, CountingRealFn, DenseVector,
+};
 use crate::line_search as ls;
-use crate::line_search::LineSearch;
-use crate::line_search::LineSearchFcn;
+use crate::line_search::{
+    LineSearchAskTell, LineSearchError, LineSearchReturns, LineSearchStats, LineSearchTask,
+};
 use crate::unconstrained::common::ConvergedReason;
 use crate::RealFn;
 //}}}
@@ -18,66 +27,450 @@ use crate::RealFn;
 use std::ops::{Add, Mul, Neg, Sub};
 use std::fmt;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 //}}}
 //{{{ dep imports
+use serde::{Deserialize, Serialize};
 use topohedral_linalg::VectorOps;
 use topohedral_tracing::*;
 //}}}
 //--------------------------------------------------------------------------------------------------
 
-#[derive(Copy, Clone)]
-pub enum Direction {
-    Steepest,
-    FletcherReeves,
-    PolakRibiere,
-}
-
-#[derive(Copy, Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Options {
     pub uncon_opts: UnonstrainedOptions,
     pub direction: Direction,
     pub restart: u64,
 }
 
-pub struct ConjugateGradient<F: RealFn> {
-    fcn: Arc<Mutex<CountingRealFn<F>>>,
-    x_init: F::Vector,
-    grad_fx_init: F::Vector,
+//{{{ struct: State
+/// Snapshot of a conjugate gradient run taken between two iterations.
+///
+/// Passing it to [`ConjugateGradient::resume_from`] together with the same function and options
+/// continues the run exactly where it stopped. The restart phase follows from `iteration`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    /// Number of completed iterations.
+    pub iteration: usize,
+    pub x: Vec<f64>,
+    pub x_prev: Vec<f64>,
+    pub f: f64,
+    pub f_prev: f64,
+    pub grad: Vec<f64>,
+    pub grad_norm_init: f64,
+    /// Search direction for the next iteration.
+    pub direction: Vec<f64>,
+    /// Conjugacy parameter used to build `direction`.
+    pub beta: f64,
+    pub num_fun_evals: usize,
+    pub num_grad_evals: usize,
+    /// Values and gradients served from the evaluation cache of [`ConjugateGradient`], always
+    /// zero in the state of a [`ConjugateGradientAskTell`], which has no cache.
+    pub num_func_hits: usize,
+    pub num_grad_hits: usize,
+    /// Entries of the evaluation cache, most recently used first, empty for a
+    /// [`ConjugateGradientAskTell`].
+    #[serde(default)]
+    pub cache: Vec<CachedPoint>,
+    /// Totals over the line searches so far.
+    #[serde(default)]
+    pub line_search: LineSearchStats,
+    /// Wall-clock time spent in the run so far.
+    pub elapsed: Duration,
+    pub history: Option<History>,
+}
+//}}}
+//{{{ struct: Iterate
+/// Working form of [`State`] held by the minimizer between iterations.
+#[derive(Clone)]
+struct Iterate<V> {
+    iteration: usize,
+    x: V,
+    x_prev: V,
+    f: f64,
+    f_prev: f64,
+    grad: V,
+    grad_norm_init: f64,
+    direction: V,
+    beta: f64,
+    elapsed: Duration,
+    history: Option<History>,
+}
+//}}}
+//{{{ struct: Accepted
+/// Step accepted in a single iteration, used to inform observers.
+#[derive(Copy, Clone)]
+struct Accepted {
+    alpha: f64,
+    direction: Direction,
+    restarted: bool,
+}
+//}}}
+//{{{ struct: Search
+/// Line search along `direction` in progress for the next iteration.
+struct Search<V> {
+    line_search: Box<dyn LineSearchAskTell>,
+    direction: V,
+    beta: f64,
+    direction_kind: Direction,
+    restarted: bool,
+    /// Step and gradient of the last trial point whose value and derivative were requested,
+    /// which become those of the iterate if the step is accepted.
+    trial_grad: Option<(f64, V)>,
+    /// Step accepted by a line search which did not request the gradient there, such as
+    /// backtracking or one accepting an earlier trial step, while that gradient is requested
+    /// separately.
+    accepted: Option<LineSearchReturns>,
+}
+//}}}
+//{{{ enum: Phase
+/// What the ask/tell minimizer is waiting for.
+enum Phase<V> {
+    /// Value and gradient at the starting point.
+    Start,
+    /// The next iteration, which is set up when it is first asked for so that the options can
+    /// be changed in between.
+    Next,
+    Search(Search<V>),
+    Finished(Outcome),
+}
+//}}}
+//{{{ enum: Outcome
+#[derive(Copy, Clone, Debug)]
+enum Outcome {
+    Converged(ConvergedReason),
+    LineSearch(LineSearchError),
+    MaxIterations,
+    Cancelled,
+    TimeLimit(Duration),
+    BudgetExhausted,
+    NonFiniteValue,
+}
+//}}}
+//{{{ struct: ConjugateGradientAskTell
+/// Conjugate gradient minimizer driven by the caller through [`ask`](Self::ask) and
+/// [`tell`](Self::tell).
+///
+/// The minimizer never evaluates the objective itself. [`ask`](Self::ask) returns the point
+/// and the quantities it needs next and the caller passes them back with
+/// [`tell`](Self::tell), which suits objectives evaluated asynchronously, e.g. by a job
+/// scheduler. [`ConjugateGradient`] is a thin loop over this type.
+pub struct ConjugateGradientAskTell<V> {
+    x_init: V,
     opts: Options,
+    observer: Option<Box<dyn Observer<V>>>,
+    iterate: Option<Iterate<V>>,
+    phase: Phase<V>,
+    accepted: Option<Accepted>,
+    num_fun_evals: usize,
+    num_grad_evals: usize,
+    line_search: LineSearchStats,
+    /// Set by the first [`ask`](Self::ask), so that time spent before the run is not counted.
+    start: Option<Instant>,
+    elapsed_init: Duration,
 }
-
-impl<F: RealFn> ConjugateGradient<F>
+//}}}
+//{{{ impl: ConjugateGradientAskTell
+impl<V> ConjugateGradientAskTell<V>
 where
-    F::Vector: VectorOps<ScalarType = f64>
-        + Add<Output = F::Vector>
-        + Sub<Output = F::Vector>
+    V: VectorOps<ScalarType = f64>
+        + Add<Output = V>
+        + Sub<Output = V>
+        + Neg<Output = V>
         + Clone
+        + DenseVector
+        + fmt::Debug
         + fmt::Display,
-    f64: Mul<F::Vector, Output = F::Vector>,
+    f64: Mul<V, Output = V>,
 {
-    pub fn new(mut fcn: F, x0: F::Vector, opts: Options) -> Self {
-        let grad_0 = fcn.grad(&x0);
-        let fcn_shared = arc_real_fn(CountingRealFn::new(fcn));
+    pub fn new(x0: V, opts: Options) -> Self {
         Self {
-            fcn: fcn_shared.clone(),
-            x_init: x0.clone(),
-            grad_fx_init: grad_0,
-            opts: opts,
+            x_init: x0,
+            opts,
+            observer: None,
+            iterate: None,
+            phase: Phase::Start,
+            accepted: None,
+            num_fun_evals: 0,
+            num_grad_evals: 0,
+            line_search: LineSearchStats::default(),
+            start: None,
+            elapsed_init: Duration::ZERO,
+        }
+    }
+
+    /// Creates a minimizer which continues the run captured in `state`.
+    ///
+    /// The run continues with the line search of iteration `state.iteration + 1`, so with the
+    /// same options the remaining requests are identical to those of the uninterrupted run.
+    pub fn resume_from(state: State, opts: Options) -> Self {
+        let x = V::from_slice(&state.x);
+        let mut cg = Self::new(x.clone(), opts);
+        cg.num_fun_evals = state.num_fun_evals;
+        cg.num_grad_evals = state.num_grad_evals;
+        cg.line_search = state.line_search;
+        cg.elapsed_init = state.elapsed;
+        cg.iterate = Some(Iterate {
+            iteration: state.iteration,
+            x,
+            x_prev: V::from_slice(&state.x_prev),
+            f: state.f,
+            f_prev: state.f_prev,
+            grad: V::from_slice(&state.grad),
+            grad_norm_init: state.grad_norm_init,
+            direction: V::from_slice(&state.direction),
+            beta: state.beta,
+            elapsed: state.elapsed,
+            history: state.history,
+        });
+        cg.phase = Phase::Next;
+        cg
+    }
+
+    /// Snapshot of the last completed iteration, `None` before the starting point has been
+    /// evaluated.
+    pub fn state(&self) -> Option<State> {
+        let it = self.iterate.as_ref()?;
+        Some(State {
+            iteration: it.iteration,
+            x: DenseVector::to_vec(&it.x),
+            x_prev: DenseVector::to_vec(&it.x_prev),
+            f: it.f,
+            f_prev: it.f_prev,
+            grad: DenseVector::to_vec(&it.grad),
+            grad_norm_init: it.grad_norm_init,
+            direction: DenseVector::to_vec(&it.direction),
+            beta: it.beta,
+            num_fun_evals: self.num_fun_evals,
+            num_grad_evals: self.num_grad_evals,
+            num_func_hits: 0,
+            num_grad_hits: 0,
+            cache: Vec::new(),
+            line_search: self.line_search,
+            elapsed: it.elapsed,
+            history: it.history.clone(),
+        })
+    }
+
+    /// Installs an observer which is called after every iteration, replacing any previous one.
+    pub fn set_observer(&mut self, observer: Box<dyn Observer<V>>) {
+        self.observer = Some(observer);
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    /// Options which can be changed between iterations, taking effect from the next one.
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.opts
+    }
+
+    /// Number of completed iterations.
+    pub fn iteration(&self) -> usize {
+        self.iterate.as_ref().map_or(0, |it| it.iteration)
+    }
+
+    /// State of the last completed iteration, `None` before the first one.
+    pub fn last_iteration(&self) -> Option<IterationState<V>> {
+        let it = self.iterate.as_ref()?;
+        let accepted = self.accepted?;
+        Some(IterationState {
+            iteration: it.iteration,
+            x: it.x.clone(),
+            f: it.f,
+            grad_norm: it.grad.norm(),
+            alpha: accepted.alpha,
+            direction: Some(accepted.direction),
+            restarted: accepted.restarted,
+        })
+    }
+
+    /// Returns the current request, repeated calls without a [`tell`](Self::tell) return the
+    /// same one.
+    ///
+    /// Once the starting point has been evaluated, a cancelled token, a used up time limit or
+    /// a request which would exceed the evaluation budget ends the run here, with the last
+    /// accepted iterate as the result.
+    pub fn ask(&mut self) -> Task<V> {
+        self.start.get_or_insert_with(Instant::now);
+        if let Phase::Start | Phase::Finished(_) = self.phase {
+            return self.request();
+        }
+        if let Some(outcome) = self.interrupted() {
+            //{{{ trace
+            info!(target: "cg", "Interrupted with {outcome:?}");
+            //}}}
+            self.phase = Phase::Finished(outcome);
+        }
+        let task = self.request();
+        if let Task::Evaluate { need, .. } = &task {
+            let fevals = (*need != Need::Grad) as usize;
+            let gevals = (*need != Need::Value) as usize;
+            let budget = self.opts.uncon_opts.budget;
+            if !budget.allows(self.num_fun_evals, self.num_grad_evals, fevals, gevals) {
+                //{{{ trace
+                info!(target: "cg", "Evaluation budget {budget:?} exhausted");
+                //}}}
+                self.phase = Phase::Finished(Outcome::BudgetExhausted);
+                return self.request();
+            }
+        }
+        task
+    }
+
+    /// The current request, setting up the next iteration if need be.
+    fn request(&mut self) -> Task<V> {
+        if let Phase::Next = self.phase {
+            self.begin_iteration();
+        }
+        match &self.phase {
+            Phase::Start => Task::Evaluate {
+                x: self.x_init.clone(),
+                need: Need::ValueAndGrad,
+            },
+            Phase::Search(search) => {
+                let x = &self.iterate.as_ref().unwrap().x;
+                let (alpha, need) = match (search.accepted, search.line_search.ask()) {
+                    (Some(ls_ret), _) => (ls_ret.alpha, Need::Grad),
+                    (None, LineSearchTask::Value(alpha)) => (alpha, Need::Value),
+                    (None, LineSearchTask::ValueAndDiff(alpha)) => (alpha, Need::ValueAndGrad),
+                    (None, task) => unreachable!("line search outcome {task:?} not handled"),
+                };
+                Task::Evaluate {
+                    x: x.clone() + alpha * search.direction.clone(),
+                    need,
+                }
+            }
+            Phase::Next => unreachable!("next iteration not set up"),
+            Phase::Finished(outcome) => Task::Done(self.result(*outcome)),
+        }
+    }
+
+    /// Supplies the quantities requested by the last [`ask`](Self::ask), others are ignored.
+    ///
+    /// # Panics
+    ///
+    /// If a requested quantity is missing.
+    pub fn tell(&mut self, value: Option<f64>, grad: Option<V>) {
+        self.tell_counted(value, grad, None);
+    }
+
+    /// As [`tell`](Self::tell), with the evaluation counts so far given by `counts` rather than
+    /// by the number of quantities told.
+    pub(crate) fn tell_counted(
+        &mut self,
+        value: Option<f64>,
+        grad: Option<V>,
+        counts: Option<(usize, usize)>,
+    ) {
+        let need = match self.request() {
+            Task::Evaluate { need, .. } => need,
+            Task::Done(_) => return,
+        };
+        let (num_fun_evals, num_grad_evals) = counts.unwrap_or((
+            self.num_fun_evals + (need != Need::Grad) as usize,
+            self.num_grad_evals + (need != Need::Value) as usize,
+        ));
+        self.num_fun_evals = num_fun_evals;
+        self.num_grad_evals = num_grad_evals;
+        match &mut self.phase {
+            Phase::Start => {
+                let fk = value.expect("value at the starting point requested");
+                let grad_fk = grad.expect("gradient at the starting point requested");
+                let finite = fk.is_finite() && grad_fk.norm().is_finite();
+                self.iterate = Some(self.initialize(fk, grad_fk));
+                self.phase = if finite {
+                    Phase::Next
+                } else {
+                    //{{{ trace
+                    info!(target: "cg", "Non-finite value or gradient at the starting point");
+                    //}}}
+                    Phase::Finished(Outcome::NonFiniteValue)
+                };
+            }
+            Phase::Search(search) => {
+                if let Some(ls_ret) = search.accepted.take() {
+                    let grad = grad.expect("gradient at the accepted step requested");
+                    search.trial_grad = Some((ls_ret.alpha, grad));
+                    self.finish_iteration(ls_ret);
+                    return;
+                }
+                match search.line_search.ask() {
+                    LineSearchTask::ValueAndDiff(alpha) => {
+                        let value = value.expect("value at the trial point requested");
+                        let grad = grad.expect("gradient at the trial point requested");
+                        let diff = grad.dot(&search.direction);
+                        search.line_search.tell_value_and_diff(value, diff);
+                        search.trial_grad = Some((alpha, grad));
+                    }
+                    _ => search
+                        .line_search
+                        .tell(value.expect("value at the trial point requested")),
+                }
+                self.advance();
+            }
+            Phase::Next | Phase::Finished(_) => {}
+        }
+    }
+
+    /// Wall-clock time spent on the run so far, including that before it was resumed.
+    fn elapsed(&self) -> Duration {
+        self.elapsed_init + self.start.map_or(Duration::ZERO, |start| start.elapsed())
+    }
+
+    /// Why the run must stop before the next evaluation, if it must.
+    fn interrupted(&self) -> Option<Outcome> {
+        let opts = &self.opts.uncon_opts;
+        if opts.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Some(Outcome::Cancelled);
+        }
+        let elapsed = self.elapsed();
+        opts.time_limit
+            .filter(|time_limit| elapsed >= *time_limit)
+            .map(Outcome::TimeLimit)
+    }
+
+    /// Handles the line search outcomes until an evaluation is needed or the run has finished.
+    fn advance(&mut self) {
+        while let Phase::Search(search) = &mut self.phase {
+            match search.line_search.ask() {
+                LineSearchTask::Value(_) | LineSearchTask::ValueAndDiff(_) => return,
+                LineSearchTask::Converged(ls_ret) => {
+                    let trial_alpha = search.trial_grad.as_ref().map(|(alpha, _)| *alpha);
+                    if trial_alpha == Some(ls_ret.alpha) {
+                        self.finish_iteration(ls_ret);
+                    } else {
+                        //{{{ trace
+                        debug!(target: "cg", "Requesting the gradient at the accepted step");
+                        //}}}
+                        search.accepted = Some(ls_ret);
+                        return;
+                    }
+                }
+                LineSearchTask::Failed(source) => {
+                    self.line_search.accumulate(&search.line_search.stats());
+                    //{{{ trace
+                    info!(target: "cg", "Line search failed with {source:?}");
+                    //}}}
+                    self.phase = Phase::Finished(Outcome::LineSearch(source));
+                }
+            }
         }
     }
 
     /// Updates the search direction for the conjugate gradient method based on the
     /// current and previous gradients, and the current search direction.
     /// The update formula used depends on the `DirectionMethod` specified in the
-    /// `Opts` struct.
+    /// `Opts` struct. Returns the new direction together with the conjugacy parameter beta.
     fn update_direction(
         &self,
-        grad_fk1: &F::Vector,
-        grad_fk: &F::Vector,
+        grad_fk1: &V,
+        grad_fk: &V,
         norm_grad_fk1: f64,
         norm_grad_fk: f64,
-        dir_k: &F::Vector,
-    ) -> F::Vector {
+        dir_k: &V,
+    ) -> (V, f64) {
         //{{{ trace
         debug!(target: "cg", "\t--- Entering update_direction ---");
         trace!(target: "cg", "\t\n\ngrad_fk1 = \n{grad_fk1}\n\ngrad_fk = \n{grad_fk}\n\n");
@@ -114,130 +507,388 @@ where
         debug!(target: "cg", "beta = {:1.4e}", beta);
         debug!(target: "cg", "--- Leaving update_direction ---");
         //}}}
-        new_dir_k
+        (new_dir_k, beta)
     }
 
-    fn is_converged(&self, grad_norm: f64, grad_norm_init: f64) -> Option<ConvergedReason> {
-        let rtol = self.opts.uncon_opts.grad_rtol;
-        let rtol_converged = (grad_norm / grad_norm_init) < rtol;
-        if rtol_converged {
-            return Some(ConvergedReason::Rtol);
+    /// Builds the first iterate from the starting point.
+    fn initialize(&self, fk: f64, grad_fk: V) -> Iterate<V> {
+        let xk = self.x_init.clone();
+        let grad_fk_norm = grad_fk.norm();
+        //{{{ trace
+        info!(target: "cg", "Initial values upon entry: ");
+        info!(target: "cg", "f0 = {fk:1.4e} norm_f0 = {grad_fk_norm:1.4e}");
+        trace!(target: "cg", "\n\nx0 = \n{xk}\n\ngrad_f0 = \n{grad_fk}\n\n");
+        //}}}
+        let history = self.opts.uncon_opts.record_history.then(|| {
+            let mut history = History::new();
+            history.push(self.record(0, &xk, fk, grad_fk_norm));
+            history
+        });
+        Iterate {
+            iteration: 0,
+            x_prev: xk.clone(),
+            x: xk,
+            f: fk,
+            f_prev: fk,
+            direction: -grad_fk.clone(),
+            grad: grad_fk,
+            grad_norm_init: grad_fk_norm,
+            beta: 0.0,
+            elapsed: self.elapsed(),
+            history,
         }
-        let atol_converged = grad_norm < self.opts.uncon_opts.grad_atol;
-        if atol_converged {
-            return Some(ConvergedReason::Atol);
+    }
+
+    /// Chooses the search direction of the next iteration and starts its line search.
+    fn begin_iteration(&mut self) {
+        let it = self.iterate.as_ref().unwrap();
+        let i = it.iteration + 1;
+        if i >= self.opts.uncon_opts.max_iter as usize {
+            //{{{ trace
+            let maxiter = self.opts.uncon_opts.max_iter;
+            info!("Did not converge within {maxiter} iterations");
+            //}}}
+            self.phase = Phase::Finished(Outcome::MaxIterations);
+            return;
+        }
+        let grad_fk_norm = it.grad.norm();
+        //{{{ trace
+        info!(target: "cg", "======================================================================== i = {i}");
+        info!(target: "cg", "Current values fk = {:1.4e} grad_fk_norm = {grad_fk_norm:1.4e}", it.f);
+        info!(target: "cg","Convergence measures:");
+        info!(target: "cg", "\t||∇f(k)|| / ||∇f(0)|| = {:1.4e} ", grad_fk_norm / it.grad_norm_init);
+        info!(target: "cg", "\t||x(k) - x(k-1)|| = {:1.4e}", (it.x.clone() - it.x_prev.clone()).norm());
+        trace!(target: "cg", "\n\nxk = \n{}\n\ndir = \n{}\n\n", it.x, it.direction);
+        //}}}
+        let phi0 = it.f;
+        let mut dphi0 = it.grad.dot(&it.direction);
+        let needs_restart = i as u64 % self.opts.restart == 0;
+        let not_decreaseing = dphi0 >= 0.0;
+        let restarted = needs_restart || not_decreaseing;
+        let direction_kind = if restarted || i == 1 {
+            Direction::Steepest
+        } else {
+            self.opts.direction
+        };
+        let mut direction = it.direction.clone();
+        let mut beta = it.beta;
+        if restarted {
+            info!(target: "cg", "\tDoing restart for reasons:  restart? {needs_restart} descent direction? {not_decreaseing}");
+            direction = -it.grad.clone();
+            dphi0 = it.grad.dot(&direction);
+            beta = 0.0;
         }
-        None
+
+        let mut line_search = ls::create_ask_tell(self.opts.uncon_opts.ls_method);
+        line_search.start(phi0, dphi0);
+        self.phase = Phase::Search(Search {
+            line_search,
+            direction,
+            beta,
+            direction_kind,
+            restarted,
+            trial_grad: None,
+            accepted: None,
+        });
     }
-}
 
-impl<F: RealFn> UnconstrainedMinimizer for ConjugateGradient<F>
+    /// Accepts the step found by the line search and checks for convergence.
+    fn finish_iteration(&mut self, ls_ret: LineSearchReturns) {
+        let Phase::Search(search) = std::mem::replace(&mut self.phase, Phase::Start) else {
+            unreachable!("no line search in progress");
+        };
+        let Some((_, grad_fk)) = search.trial_grad else {
+            unreachable!("gradient at the accepted step not requested");
+        };
+        let ls_stats = search.line_search.stats();
+        self.line_search.accumulate(&ls_stats);
+        let mut it = self.iterate.take().unwrap();
+        let i = it.iteration + 1;
+        let direction = search.direction;
+
+        let xk = it.x.clone() + ls_ret.alpha * direction.clone();
+        let fk = ls_ret.phi_alpha;
+        let grad_fk_prev_norm = it.grad.norm();
+        let grad_fk_norm = grad_fk.norm();
+
+        if let Some(history) = it.history.as_mut() {
+            history.push(IterationRecord {
+                alpha: ls_ret.alpha,
+                step_norm: (xk.clone() - it.x.clone()).norm(),
+                beta: Some(search.beta),
+                line_search: ls_stats,
+                ..self.record(i, &xk, fk, grad_fk_norm)
+            });
+        }
+
+        let (next_direction, next_beta) = self.update_direction(
+            &it.grad,
+            &grad_fk,
+            grad_fk_prev_norm,
+            grad_fk_norm,
+            &direction,
+        );
+        //{{{ trace
+        trace!(target: "cg", "fk_prev = {:1.4e}, fk = {fk:1.4e}", it.f);
+        trace!(target: "cg", "grad_fk_prev_norm = {grad_fk_prev_norm:1.4e}, fk = {grad_fk_norm:1.4e}");
+        //}}}
+
+        it.iteration = i;
+        it.x_prev = std::mem::replace(&mut it.x, xk);
+        it.f_prev = it.f;
+        it.f = fk;
+        it.grad = grad_fk;
+        it.direction = next_direction;
+        it.beta = next_beta;
+        it.elapsed = self.elapsed();
+        let accepted = Accepted {
+            alpha: ls_ret.alpha,
+            direction: search.direction_kind,
+            restarted: search.restarted,
+        };
+        self.accepted = Some(accepted);
+        let reason = self.check(&it, &accepted);
+        self.iterate = Some(it);
+        self.phase = match reason {
+            Some(reason) => Phase::Finished(Outcome::Converged(reason)),
+            None => Phase::Next,
+        };
+    }
+
+    /// Informs the observer of an iteration and checks the stopping criteria.
+    fn check(&mut self, it: &Iterate<V>, step: &Accepted) -> Option<ConvergedReason> {
+        let grad_fk_norm = it.grad.norm();
+        if let Some(observer) = self.observer.as_mut() {
+            let state = IterationState {
+                iteration: it.iteration,
+                x: it.x.clone(),
+                f: it.f,
+                grad_norm: grad_fk_norm,
+                alpha: step.alpha,
+                direction: Some(step.direction),
+                restarted: step.restarted,
+            };
+            if observer.observe(&state) == ObserverAction::Stop {
+                //{{{ trace
+                info!(target: "cg", "Stopping at the request of the observer");
+                //}}}
+                return Some(ConvergedReason::ObserverStop);
+            }
+        }
+
+        let state = ConvergenceState {
+            iteration: it.iteration,
+            f: it.f,
+            f_prev: it.f_prev,
+            grad_norm: grad_fk_norm,
+            grad_norm_init: it.grad_norm_init,
+            grad_inf_norm: (0..it.grad.len()).fold(0.0, |m, j| it.grad[j].abs().max(m)),
+            step_norm: (it.x.clone() - it.x_prev.clone()).norm(),
+            x_norm: it.x.norm(),
+            num_fun_evals: self.num_fun_evals,
+            elapsed: it.elapsed,
+        };
+        let reason = self.opts.uncon_opts.check_convergence(&state);
+        //{{{ trace
+        if let Some(reason) = reason {
+            info!(target: "cg", "Converging with reason {reason:?}");
+        }
+        //}}}
+        reason
+    }
+
+    fn result(&self, outcome: Outcome) -> Result<Returns<V>, Error<V>> {
+        let reason = match outcome {
+            Outcome::Converged(reason) => reason,
+            _ => ConvergedReason::NotConverged,
+        };
+        let it = self.iterate.as_ref().unwrap();
+        let returns = Returns {
+            fmin: it.f,
+            xmin: it.x.clone(),
+            grad_norm: it.grad.norm(),
+            reason,
+            num_iterations: it.iteration,
+            num_fun_evals: self.num_fun_evals,
+            num_grad_evals: self.num_grad_evals,
+            num_cache_hits: 0,
+            line_search: self.line_search,
+            history: it.history.clone(),
+        };
+        match outcome {
+            Outcome::Converged(_) => Ok(returns),
+            Outcome::LineSearch(source) => Err(Error::LineSearch {
+                source,
+                partial: Box::new(returns),
+            }),
+            Outcome::MaxIterations => Err(Error::MaxIterations {
+                max_iter: self.opts.uncon_opts.max_iter as usize,
+                partial: Box::new(returns),
+            }),
+            Outcome::Cancelled => Err(Error::Cancelled {
+                partial: Box::new(returns),
+            }),
+            Outcome::TimeLimit(time_limit) => Err(Error::TimeLimit {
+                time_limit,
+                partial: Box::new(returns),
+            }),
+            Outcome::BudgetExhausted => Err(Error::BudgetExhausted {
+                budget: self.opts.uncon_opts.budget,
+                partial: Box::new(returns),
+            }),
+            Outcome::NonFiniteValue => Err(Error::NonFiniteValue {
+                x: self.x_init.clone(),
+                partial: Box::new(returns),
+            }),
+        }
+    }
+
+    /// Builds the history record for iteration `iter`, with the evaluation counts so far.
+    fn record(&self, iter: usize, xk: &V, fk: f64, grad_norm: f64) -> IterationRecord {
+        IterationRecord {
+            iteration: iter,
+            f: fk,
+            grad_norm,
+            num_fun_evals: self.num_fun_evals,
+            num_grad_evals: self.num_grad_evals,
+            x: self.opts.uncon_opts.record_x.then(|| DenseVector::to_vec(xk)),
+            ..IterationRecord::default()
+        }
+    }
+}
+//}}}
+//{{{ struct: ConjugateGradient
+pub struct ConjugateGradient<F: RealFn>
+where
+    F::Vector: Clone,
+{
+    fcn: Arc<Mutex<CachingRealFn<CountingRealFn<F>>>>,
+    solver: ConjugateGradientAskTell<F::Vector>,
+}
+//}}}
+//{{{ impl: ConjugateGradient
+impl<F: RealFn> ConjugateGradient<F>
 where
     F::Vector: VectorOps<ScalarType = f64>
         + Add<Output = F::Vector>
         + Sub<Output = F::Vector>
         + Neg<Output = F::Vector>
         + Clone
+        + DenseVector
+        + fmt::Debug
         + fmt::Display,
     f64: Mul<F::Vector, Output = F::Vector>,
 {
-    type Vector = F::Vector;
-
-    fn minimize(&mut self) -> Result<Returns<Self::Vector>, Error> {
-        info!(target: "cg", "--- Entering minimize() ---");
-        let mut xk = self.x_init.clone();
-        let mut xk_prev = self.x_init.clone();
-        let mut fk = self.fcn.eval(&xk);
-        let mut fk_prev = fk;
-        let mut grad_fk = self.fcn.grad(&xk);
-        let mut grad_fk_prev = grad_fk.clone();
-        let mut grad_fk_prev_norm = grad_fk_prev.norm();
-        let mut grad_fk_norm = grad_fk.norm();
-        let mut direction = -grad_fk.clone();
+    pub fn new(fcn: F, x0: F::Vector, opts: Options) -> Self {
+        // The counter sits inside the cache so that it only sees real evaluations
+        let fcn_shared = arc_real_fn(CachingRealFn::new(
+            CountingRealFn::new(fcn),
+            opts.uncon_opts.cache_size,
+            opts.uncon_opts.cache_policy,
+        ));
+        Self {
+            fcn: fcn_shared,
+            solver: ConjugateGradientAskTell::new(x0, opts),
+        }
+    }
 
-        //{{{ trace
-        info!(target: "cg", "Initial values upon entry: ");
-        info!(target: "cg", "f0 = {fk:1.4e} norm_f0 = {grad_fk_norm:1.4e}");
-        trace!(target: "cg", "\n\nx0 = \n{xk}\n\ngrad_f0 = \n{grad_fk}\n\n");
-        //}}}
+    /// Creates a minimizer which continues the run captured in `state`.
+    ///
+    /// With the same function and options the remaining iterations are identical to those of
+    /// the uninterrupted run. The evaluation cache and its hit counts are restored as well.
+    pub fn resume_from(fcn: F, state: State, opts: Options) -> Self {
+        let mut cg = Self::new(fcn, F::Vector::from_slice(&state.x), opts.clone());
+        {
+            let mut fcn_lock = cg.fcn.lock().unwrap();
+            fcn_lock.num_func_hits = state.num_func_hits;
+            fcn_lock.num_grad_hits = state.num_grad_hits;
+            fcn_lock.restore(&state.cache);
+            fcn_lock.inner_mut().num_func_evals = state.num_fun_evals;
+            fcn_lock.inner_mut().num_grad_evals = state.num_grad_evals;
+        }
+        cg.solver = ConjugateGradientAskTell::resume_from(state, opts);
+        cg
+    }
 
-        let max_iter = self.opts.uncon_opts.max_iter;
+    pub fn options(&self) -> &Options {
+        self.solver.options()
+    }
 
-        let mut line_searcher = ls::create(
-                LineSearchFcn::new(self.fcn.clone(), self.x_init.clone(), self.grad_fx_init.clone()),
-                self.opts.uncon_opts.ls_method,
-        );
+    /// Options which can be changed between steps, taking effect from the next iteration. The
+    /// evaluation cache keeps the size and policy it was created with.
+    pub fn options_mut(&mut self) -> &mut Options {
+        self.solver.options_mut()
+    }
 
-        let grad_fx_norm_init = self.grad_fx_init.norm();
+    /// Snapshot of the run, `None` before the starting point has been evaluated.
+    pub fn state(&self) -> Option<State> {
+        let fcn_lock = self.fcn.lock().unwrap();
+        Some(State {
+            num_func_hits: fcn_lock.num_func_hits,
+            num_grad_hits: fcn_lock.num_grad_hits,
+            cache: fcn_lock.entries(),
+            ..self.solver.state()?
+        })
+    }
 
-        for i in 1..max_iter {
-            //{{{ trace
-            info!(target: "cg", "======================================================================== i = {i}");
-            info!(target: "cg", "Current values fk = {fk:1.4e} grad_fk_norm = {grad_fk_norm:1.4e}");
-            info!(target: "cg","Convergence measures:");
-            info!(target: "cg", "\t||∇f(k)|| / ||∇f(0)|| = {:1.4e} ", grad_fk_norm / grad_fx_norm_init);
-            info!(target: "cg", "\t||x(k) - x(k-1)|| = {:1.4e}", (xk.clone() - xk_prev.clone()).norm());
-            trace!(target: "cg", "\n\nxk = \n{xk}\n\ndir = \n{direction}\n\n");
-            //}}}
-            let phi0 = fk;
-            let mut dphi0 = grad_fk.dot(&direction);
-            let needs_restart = i % self.opts.restart == 0;
-            let not_decreaseing = dphi0 >= 0.0;
-            if needs_restart || not_decreaseing {
-                info!(target: "cg", "\tDoing restart for reasons:  restart? {needs_restart} descent direction? {not_decreaseing}");
-                direction = -grad_fk.clone();
-                dphi0 = grad_fk.dot(&direction);
+    /// Evaluates the quantities in `need` at `x` and tells them to the solver together with
+    /// the number of real evaluations so far.
+    fn evaluate(&mut self, x: &F::Vector, need: Need) {
+        let (value, grad) = match need {
+            Need::Value => (Some(self.fcn.eval(x)), None),
+            Need::Grad => (None, Some(self.fcn.grad(x))),
+            Need::ValueAndGrad => {
+                let (fx, grad) = self.fcn.eval_with_grad(x);
+                (Some(fx), Some(grad))
             }
+        };
+        let counts = {
+            let fcn_lock = self.fcn.lock().unwrap();
+            (fcn_lock.inner().num_func_evals, fcn_lock.inner().num_grad_evals)
+        };
+        self.solver.tell_counted(value, grad, Some(counts));
+    }
+}
+//}}}
+//{{{ impl: UnconstrainedMinimizer for ConjugateGradient
+impl<F: RealFn> UnconstrainedMinimizer for ConjugateGradient<F>
+where
+    F::Vector: VectorOps<ScalarType = f64>
+        + Add<Output = F::Vector>
+        + Sub<Output = F::Vector>
+        + Neg<Output = F::Vector>
+        + Clone
+        + DenseVector
+        + fmt::Debug
+        + fmt::Display,
+    f64: Mul<F::Vector, Output = F::Vector>,
+{
+    type Vector = F::Vector;
 
-            let line_search_fcn =
-                LineSearchFcn::new(self.fcn.clone(), xk.clone(), direction.clone());
-            line_searcher.update_fcn(line_search_fcn);
-
-            let ls_ret = line_searcher.search(phi0, dphi0)?;
-            xk_prev = xk.clone();
-            xk = xk + ls_ret.alpha * direction.clone();
-            fk_prev = fk;
-            fk = ls_ret.phi_alpha;
-            grad_fk_prev = grad_fk.clone();
-            grad_fk = self.fcn.grad(&xk);
-            grad_fk_prev_norm = grad_fk_prev.norm();
-            grad_fk_norm = grad_fk.norm();
-
-            if let Some(reason) = self.is_converged(grad_fk_norm, grad_fx_norm_init) {
-                //{{{ trace
-                info!(target: "cg", "Converging with reason {reason:?}");
-                info!(target: "cg", "--- Leaving minimize() ---");
-                //}}}
-
-                let fcn_lock = self.fcn.lock().unwrap();
-                return Ok(Returns {
-                    fmin: fk,
-                    xmin: xk,
-                    reason: reason,
-                    num_iterations: i as usize,
-                    num_fun_evals: fcn_lock.num_func_evals,
-                    num_grad_evals: fcn_lock.num_grad_evals,
-                });
+    fn step(&mut self) -> Step<Self::Vector> {
+        let iteration = self.solver.iteration();
+        loop {
+            match self.solver.ask() {
+                Task::Evaluate { x, need } => {
+                    self.evaluate(&x, need);
+                    if self.solver.iteration() > iteration {
+                        return Step::Iteration(self.solver.last_iteration().unwrap());
+                    }
+                }
+                Task::Done(mut result) => {
+                    let num_cache_hits = self.fcn.lock().unwrap().num_hits();
+                    match &mut result {
+                        Ok(returns) => returns.num_cache_hits = num_cache_hits,
+                        Err(err) => err.partial_mut().num_cache_hits = num_cache_hits,
+                    }
+                    return Step::Done(result);
+                }
             }
-
-            direction = self.update_direction(
-                &grad_fk_prev,
-                &grad_fk,
-                grad_fk_prev_norm,
-                grad_fk_norm,
-                &direction,
-            );
-
-            //{{{ trace
-            trace!(target: "cg", "fk_prev = {fk_prev:1.4e}, fk = {fk:1.4e}");
-            trace!(target: "cg", "grad_fk_prev_norm = {grad_fk_prev_norm:1.4e}, fk = {grad_fk_norm:1.4e}");
-            //}}}
         }
-        //{{{ trace
-        let maxiter = self.opts.uncon_opts.max_iter;
-        info!("Did not converge within {maxiter} iterations");
-        info!(target: "cg", "--- Leaving minimize() ---");
-        //}}}
-        Err(Error::MaxIterations(self.opts.uncon_opts.max_iter as usize))
+    }
+
+    fn set_observer(&mut self, observer: Box<dyn Observer<Self::Vector>>) {
+        self.solver.set_observer(observer);
     }
 }
+//}}}
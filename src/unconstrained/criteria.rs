@@ -0,0 +1,136 @@
+//! Composable stopping criteria for the unconstrained minimizers.
+//!
+//! A [`Criterion`] is checked against a [`ConvergenceState`] after every accepted step. Leaf
+//! criteria test a single quantity and can be combined with [`Criterion::Any`] and
+//! [`Criterion::All`] to build arbitrary stopping rules.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use super::common::ConvergedReason;
+//}}}
+//{{{ std imports
+use std::time::Duration;
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: ConvergenceState
+/// Quantities a minimizer exposes to its stopping criteria after step `k`.
+#[derive(Copy, Clone, Debug)]
+pub struct ConvergenceState {
+    pub iteration: usize,
+    /// Function value `f_k`.
+    pub f: f64,
+    /// Function value `f_{k-1}` at the previous iterate.
+    pub f_prev: f64,
+    /// Euclidean norm of the gradient `‖∇f_k‖`.
+    pub grad_norm: f64,
+    /// Euclidean norm of the gradient at the starting point.
+    pub grad_norm_init: f64,
+    /// Infinity norm of the gradient `‖∇f_k‖_∞`.
+    pub grad_inf_norm: f64,
+    /// Step norm `‖x_k − x_{k−1}‖`.
+    pub step_norm: f64,
+    /// Norm of the current iterate `‖x_k‖`.
+    pub x_norm: f64,
+    /// Number of function evaluations so far.
+    pub num_fun_evals: usize,
+    /// Wall-clock time since the minimizer started.
+    pub elapsed: Duration,
+}
+//}}}
+//{{{ enum: Criterion
+/// A stopping criterion, see [`Criterion::check`] for the exact test each variant performs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Criterion {
+    /// `‖∇f_k‖ / ‖∇f_0‖ < tol`
+    GradRtol(f64),
+    /// `‖∇f_k‖ < tol`
+    GradAtol(f64),
+    /// `‖∇f_k‖_∞ <= tol`
+    GradInfNorm(f64),
+    /// `|f_k − f_{k−1}| <= tol`
+    FAtol(f64),
+    /// `|f_k − f_{k−1}| <= tol · max(|f_k|, |f_{k−1}|, 1)`
+    FRtol(f64),
+    /// `‖x_k − x_{k−1}‖ <= tol`
+    XAtol(f64),
+    /// `‖x_k − x_{k−1}‖ <= tol · max(‖x_k‖, 1)`
+    XRtol(f64),
+    /// `f_k <= target`
+    TargetF(f64),
+    /// At least this many function evaluations have been made.
+    MaxFunEvals(usize),
+    /// At least this much wall-clock time has passed.
+    TimeLimit(Duration),
+    /// Met when any member is met, reporting the first member that is.
+    Any(Vec<Criterion>),
+    /// Met when every member is met, reporting the reason of the first member. An empty set is
+    /// never met.
+    All(Vec<Criterion>),
+}
+//}}}
+//{{{ impl: Criterion
+impl Criterion {
+    /// Returns the reason for stopping if the criterion is met in `state`.
+    pub fn check(&self, state: &ConvergenceState) -> Option<ConvergedReason> {
+        let df = (state.f - state.f_prev).abs();
+        match self {
+            Criterion::GradRtol(tol) => {
+                (state.grad_norm / state.grad_norm_init < *tol).then_some(ConvergedReason::Rtol)
+            }
+            Criterion::GradAtol(tol) => (state.grad_norm < *tol).then_some(ConvergedReason::Atol),
+            Criterion::GradInfNorm(tol) => {
+                (state.grad_inf_norm <= *tol).then_some(ConvergedReason::GradInfNorm)
+            }
+            Criterion::FAtol(tol) => (df <= *tol).then_some(ConvergedReason::FAtol),
+            Criterion::FRtol(tol) => {
+                let scale = state.f.abs().max(state.f_prev.abs()).max(1.0);
+                (df <= *tol * scale).then_some(ConvergedReason::FRtol)
+            }
+            Criterion::XAtol(tol) => (state.step_norm <= *tol).then_some(ConvergedReason::XAtol),
+            Criterion::XRtol(tol) => {
+                let scale = state.x_norm.max(1.0);
+                (state.step_norm <= *tol * scale).then_some(ConvergedReason::XRtol)
+            }
+            Criterion::TargetF(target) => (state.f <= *target).then_some(ConvergedReason::TargetF),
+            Criterion::MaxFunEvals(max) => {
+                (state.num_fun_evals >= *max).then_some(ConvergedReason::MaxFunEvals)
+            }
+            Criterion::TimeLimit(limit) => {
+                (state.elapsed >= *limit).then_some(ConvergedReason::TimeLimit)
+            }
+            Criterion::Any(members) => members.iter().find_map(|c| c.check(state)),
+            Criterion::All(members) => {
+                let mut reasons = members.iter().map(|c| c.check(state));
+                let first = reasons.next()??;
+                reasons.all(|r| r.is_some()).then_some(first)
+            }
+        }
+    }
+
+    /// Combines `self` and `other` so that either one being met suffices.
+    pub fn or(self, other: Criterion) -> Criterion {
+        match self {
+            Criterion::Any(mut members) => {
+                members.push(other);
+                Criterion::Any(members)
+            }
+            _ => Criterion::Any(vec![self, other]),
+        }
+    }
+
+    /// Combines `self` and `other` so that both must be met.
+    pub fn and(self, other: Criterion) -> Criterion {
+        match self {
+            Criterion::All(mut members) => {
+                members.push(other);
+                Criterion::All(members)
+            }
+            _ => Criterion::All(vec![self, other]),
+        }
+    }
+}
+//}}}
@@ -0,0 +1,84 @@
+//! Per-iteration history of an unconstrained minimization.
+//!
+//! A [`History`] is filled by a minimizer when `Options::record_history` is set and returned in
+//! its [`Returns`](super::common::Returns). It serializes to JSON so that convergence plots and
+//! regression tests can be produced from it directly.
+//--------------------------------------------------------------------------------------------------
+
+//{{{ crate imports
+use crate::line_search::LineSearchStats;
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use serde::{Deserialize, Serialize};
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: IterationRecord
+/// Quantities recorded for a single iteration, iteration `0` being the starting point.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct IterationRecord {
+    pub iteration: usize,
+    /// Function value `f_k`.
+    pub f: f64,
+    /// Gradient norm `‖∇f_k‖`.
+    pub grad_norm: f64,
+    /// Step length `α_k` accepted by the line search.
+    pub alpha: f64,
+    /// Step norm `‖x_k − x_{k−1}‖`.
+    pub step_norm: f64,
+    /// Conjugacy parameter `β_k` used to build the search direction, `None` for methods
+    /// without one.
+    pub beta: Option<f64>,
+    /// Total number of function evaluations so far.
+    pub num_fun_evals: usize,
+    /// Total number of gradient evaluations so far.
+    pub num_grad_evals: usize,
+    /// The iterate `x_k`, only stored when `Options::record_x` is set.
+    pub x: Option<Vec<f64>>,
+    /// Statistics of the line search which produced `x_k`.
+    #[serde(default)]
+    pub line_search: LineSearchStats,
+}
+//}}}
+//{{{ struct: History
+/// Sequence of [`IterationRecord`]s in iteration order.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct History {
+    pub records: Vec<IterationRecord>,
+}
+//}}}
+//{{{ impl: History
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, record: IterationRecord) {
+        self.records.push(record);
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn last(&self) -> Option<&IterationRecord> {
+        self.records.last()
+    }
+
+    /// Serializes the history to a JSON string.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    /// Reads a history back from a JSON string produced by [`History::to_json`].
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+//}}}
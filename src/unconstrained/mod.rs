@@ -11,11 +11,24 @@
 //}}}
 //--------------------------------------------------------------------------------------------------
 
+mod barzilai_borwein;
 mod common;
 mod conjugate_gradient;
+mod criteria;
+mod history;
 
 pub use common::{
-    Error as UnconstrainedError, Options as UnonstrainedOptions, Returns as UnconstrainedReturns,
-    UnconstrainedMinimizer,
+    ConvergedReason, Error as UnconstrainedError, IterationState, Iterations, Need, Observer,
+    ObserverAction, Options as UnonstrainedOptions, Returns as UnconstrainedReturns, Step,
+    Task as UnconstrainedTask, UnconstrainedMinimizer,
+};
+pub use criteria::{ConvergenceState, Criterion};
+pub use history::{History, IterationRecord};
+pub use conjugate_gradient::{
+    ConjugateGradient, ConjugateGradientAskTell, Direction, Options as ConjugateGradientOptions,
+    State as ConjugateGradientState,
+};
+pub use barzilai_borwein::{
+    BarzilaiBorwein, Nonmonotone, Options as BarzilaiBorweinOptions,
+    Variant as BarzilaiBorweinVariant,
 };
-pub use conjugate_gradient::{ConjugateGradient, Direction, Options as ConjugateGradientOptions};
@@ -0,0 +1,165 @@
+//! Tests of the reverse-communication (ask/tell) forms of the line search and the minimizers.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::line_search::{
+    Interp, InterpAskTell, InterpOptions, LineSearch, LineSearchAskTell, LineSearchTask,
+};
+use topohedral_optimize::unconstrained::{
+    ConjugateGradient, ConjugateGradientAskTell, ConjugateGradientOptions, ConvergedReason,
+    Direction, Need, UnconstrainedMinimizer, UnconstrainedTask, UnonstrainedOptions,
+};
+use topohedral_optimize::{RealFn, RealFn1};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use topohedral_linalg::scvector::SCVector;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Parabola
+/// `φ(α) = (α − 2)² − 4`, with a minimum at `α = 2`.
+#[derive(Debug, Clone)]
+struct Parabola;
+//}}}
+//{{{ impl: RealFn1 for Parabola
+impl RealFn1 for Parabola {
+    fn eval(&mut self, alpha: f64) -> f64 {
+        (alpha - 2.0).powi(2) - 4.0
+    }
+
+    fn diff(&mut self, alpha: f64) -> f64 {
+        2.0 * (alpha - 2.0)
+    }
+}
+//}}}
+//{{{ struct: Ellipse
+/// Quadratic with a diagonal Hessian of `2 * diag(1, 10)`.
+#[derive(Debug, Clone)]
+struct Ellipse;
+//}}}
+//{{{ impl: RealFn for Ellipse
+impl RealFn for Ellipse {
+    type Vector = SCVector<f64, 2>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        (x[0] - 1.0).powi(2) + 10.0 * (x[1] + 2.0).powi(2)
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        SCVector::<f64, 2>::from_col_slice(&[2.0 * (x[0] - 1.0), 20.0 * (x[1] + 2.0)])
+    }
+}
+//}}}
+//{{{ fun: options
+fn options() -> ConjugateGradientOptions {
+    ConjugateGradientOptions {
+        uncon_opts: UnonstrainedOptions {
+            max_iter: 100,
+            cache_size: 0,
+            ..UnonstrainedOptions::default()
+        },
+        direction: Direction::PolakRibiere,
+        restart: 10,
+    }
+}
+//}}}
+//{{{ test: test_interp_ask_tell
+#[test]
+fn test_interp_ask_tell() {
+    let opts = InterpOptions::default();
+    let expected = Interp::new(Parabola, opts).search(0.0, -4.0).unwrap();
+
+    let mut search = InterpAskTell::new(opts);
+    search.start(0.0, -4.0);
+    let mut num_evals = 0;
+    let returns = loop {
+        let task = search.ask();
+        // asking again without telling repeats the request
+        assert_eq!(format!("{task:?}"), format!("{:?}", search.ask()));
+        match task {
+            LineSearchTask::Value(alpha) => search.tell(Parabola.eval(alpha)),
+            LineSearchTask::ValueAndDiff(alpha) => {
+                let (phi, dphi) = Parabola.eval_with_diff(alpha);
+                search.tell_value_and_diff(phi, dphi);
+            }
+            LineSearchTask::Converged(returns) => break returns,
+            LineSearchTask::Failed(err) => panic!("line search failed with {err:?}"),
+        }
+        num_evals += 1;
+    };
+    assert_eq!(returns.alpha, expected.alpha);
+    assert_eq!(returns.phi_alpha, expected.phi_alpha);
+    assert_eq!(returns.dphi_alpha, expected.dphi_alpha);
+    assert!(num_evals >= 3);
+
+    // the search can be restarted
+    search.start(0.0, -4.0);
+    assert!(matches!(search.ask(), LineSearchTask::Value(alpha) if alpha == opts.step1));
+}
+//}}}
+//{{{ test: test_conjugate_gradient_ask_tell
+#[test]
+fn test_conjugate_gradient_ask_tell() {
+    let x0 = SCVector::<f64, 2>::zeros();
+    let expected = ConjugateGradient::new(Ellipse, x0, options()).minimize().unwrap();
+
+    let mut cg = ConjugateGradientAskTell::new(x0, options());
+    assert!(cg.state().is_none());
+    let ret = loop {
+        match cg.ask() {
+            UnconstrainedTask::Evaluate { x, need } => {
+                let value = (need != Need::Grad).then(|| Ellipse.eval(&x));
+                let grad = (need != Need::Value).then(|| Ellipse.grad(&x));
+                cg.tell(value, grad);
+            }
+            UnconstrainedTask::Done(result) => break result.unwrap(),
+        }
+    };
+
+    // driving the minimizer by hand makes the same requests as `minimize`
+    assert!(matches!(ret.reason, ConvergedReason::Rtol | ConvergedReason::Atol));
+    assert_eq!(ret.num_iterations, expected.num_iterations);
+    assert_eq!(ret.fmin, expected.fmin);
+    assert_eq!(ret.xmin[0], expected.xmin[0]);
+    assert_eq!(ret.xmin[1], expected.xmin[1]);
+    assert_eq!(ret.num_fun_evals, expected.num_fun_evals);
+    assert_eq!(ret.num_grad_evals, expected.num_grad_evals);
+
+    // once finished the result is repeated and further values are ignored
+    cg.tell(Some(0.0), None);
+    match cg.ask() {
+        UnconstrainedTask::Done(result) => assert_eq!(result.unwrap().fmin, ret.fmin),
+        task => panic!("expected the run to be done, got {task:?}"),
+    }
+}
+//}}}
+//{{{ test: test_conjugate_gradient_ask_tell_first_request
+#[test]
+fn test_conjugate_gradient_ask_tell_first_request() {
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[3.0, 4.0]);
+    let mut cg = ConjugateGradientAskTell::new(x0, options());
+
+    // the starting point needs both the value and the gradient
+    match cg.ask() {
+        UnconstrainedTask::Evaluate { x, need } => {
+            assert_eq!(x[0], 3.0);
+            assert_eq!(x[1], 4.0);
+            assert_eq!(need, Need::ValueAndGrad);
+            cg.tell(Some(Ellipse.eval(&x)), Some(Ellipse.grad(&x)));
+        }
+        task => panic!("expected an evaluation, got {task:?}"),
+    }
+    let state = cg.state().unwrap();
+    assert_eq!(state.iteration, 0);
+    assert_eq!(state.num_fun_evals, 1);
+    assert_eq!(state.num_grad_evals, 1);
+
+    // line search trial points only need the value
+    assert!(matches!(cg.ask(), UnconstrainedTask::Evaluate { need: Need::Value, .. }));
+}
+//}}}
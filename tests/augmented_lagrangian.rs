@@ -0,0 +1,282 @@
+//! Tests of the augmented Lagrangian method.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::bound_constrained::{Bounds, LbfgsbOptions};
+use topohedral_optimize::constrained::{
+    AugmentedLagrangian, AugmentedLagrangianOptions, BoundConstrainedSubsolver,
+    ConstrainedError, ConstrainedProblem, UnconstrainedSubsolver,
+};
+use topohedral_optimize::unconstrained::{
+    BarzilaiBorwein, BarzilaiBorweinOptions, ConjugateGradient, ConjugateGradientOptions,
+    Direction, UnonstrainedOptions,
+};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use approx::assert_relative_eq;
+use topohedral_linalg::scvector::SCVector;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+type Vec2 = SCVector<f64, 2>;
+
+//{{{ struct: Circle
+/// `x₀ + x₁` on the circle `x₀² + x₁² = 2`, minimized at `(−1, −1)` with `λ = −1/2`.
+#[derive(Debug, Clone)]
+struct Circle;
+//}}}
+//{{{ impl: ConstrainedProblem for Circle
+impl ConstrainedProblem for Circle {
+    type Vector = Vec2;
+
+    fn num_eq(&self) -> usize {
+        1
+    }
+
+    fn num_ineq(&self) -> usize {
+        0
+    }
+
+    fn eval(&mut self, x: &Vec2) -> f64 {
+        x[0] + x[1]
+    }
+
+    fn grad(&mut self, _x: &Vec2) -> Vec2 {
+        Vec2::from_col_slice(&[1.0, 1.0])
+    }
+
+    fn eq(&mut self, x: &Vec2) -> Vec<f64> {
+        vec![x[0] * x[0] + x[1] * x[1] - 2.0]
+    }
+
+    fn ineq(&mut self, _x: &Vec2) -> Vec<f64> {
+        vec![]
+    }
+
+    fn eq_jac_tr(&mut self, x: &Vec2, lambda: &[f64]) -> Vec2 {
+        Vec2::from_col_slice(&[2.0 * x[0] * lambda[0], 2.0 * x[1] * lambda[0]])
+    }
+
+    fn ineq_jac_tr(&mut self, _x: &Vec2, _mu: &[f64]) -> Vec2 {
+        Vec2::zeros()
+    }
+}
+//}}}
+//{{{ struct: Bracken
+/// `(x₀ − 2)² + (x₁ − 1)²` subject to `x₀ − 2x₁ + 1 = 0` and `1 − x₀²/4 − x₁² >= 0`, the
+/// problem of Bracken and McCormick, optionally with a second inequality `2 − x₀ − x₁ >= 0`.
+#[derive(Debug, Clone)]
+struct Bracken {
+    sum: bool,
+}
+//}}}
+//{{{ impl: ConstrainedProblem for Bracken
+impl ConstrainedProblem for Bracken {
+    type Vector = Vec2;
+
+    fn num_eq(&self) -> usize {
+        1
+    }
+
+    fn num_ineq(&self) -> usize {
+        1 + self.sum as usize
+    }
+
+    fn eval(&mut self, x: &Vec2) -> f64 {
+        (x[0] - 2.0).powi(2) + (x[1] - 1.0).powi(2)
+    }
+
+    fn grad(&mut self, x: &Vec2) -> Vec2 {
+        Vec2::from_col_slice(&[2.0 * (x[0] - 2.0), 2.0 * (x[1] - 1.0)])
+    }
+
+    fn eq(&mut self, x: &Vec2) -> Vec<f64> {
+        vec![x[0] - 2.0 * x[1] + 1.0]
+    }
+
+    fn ineq(&mut self, x: &Vec2) -> Vec<f64> {
+        let mut c = vec![1.0 - 0.25 * x[0] * x[0] - x[1] * x[1]];
+        if self.sum {
+            c.push(2.0 - x[0] - x[1]);
+        }
+        c
+    }
+
+    fn eq_jac_tr(&mut self, _x: &Vec2, lambda: &[f64]) -> Vec2 {
+        Vec2::from_col_slice(&[lambda[0], -2.0 * lambda[0]])
+    }
+
+    fn ineq_jac_tr(&mut self, x: &Vec2, mu: &[f64]) -> Vec2 {
+        let mut out = [-0.5 * x[0] * mu[0], -2.0 * x[1] * mu[0]];
+        if self.sum {
+            out[0] -= mu[1];
+            out[1] -= mu[1];
+        }
+        Vec2::from_col_slice(&out)
+    }
+}
+//}}}
+//{{{ struct: Halfplane
+/// `(x₀ − 2)² + (x₁ − 1)²` subject to `2 − x₀ − x₁ >= 0`, or to the unsatisfiable
+/// `x₀² + 1 = 0` when `infeasible` is set.
+#[derive(Debug, Clone)]
+struct Halfplane {
+    infeasible: bool,
+}
+//}}}
+//{{{ impl: ConstrainedProblem for Halfplane
+impl ConstrainedProblem for Halfplane {
+    type Vector = Vec2;
+
+    fn num_eq(&self) -> usize {
+        self.infeasible as usize
+    }
+
+    fn num_ineq(&self) -> usize {
+        1
+    }
+
+    fn eval(&mut self, x: &Vec2) -> f64 {
+        (x[0] - 2.0).powi(2) + (x[1] - 1.0).powi(2)
+    }
+
+    fn grad(&mut self, x: &Vec2) -> Vec2 {
+        Vec2::from_col_slice(&[2.0 * (x[0] - 2.0), 2.0 * (x[1] - 1.0)])
+    }
+
+    fn eq(&mut self, x: &Vec2) -> Vec<f64> {
+        if self.infeasible {
+            vec![x[0] * x[0] + 1.0]
+        } else {
+            vec![]
+        }
+    }
+
+    fn ineq(&mut self, x: &Vec2) -> Vec<f64> {
+        vec![2.0 - x[0] - x[1]]
+    }
+
+    fn eq_jac_tr(&mut self, x: &Vec2, lambda: &[f64]) -> Vec2 {
+        match lambda.first() {
+            Some(l) => Vec2::from_col_slice(&[2.0 * x[0] * l, 0.0]),
+            None => Vec2::zeros(),
+        }
+    }
+
+    fn ineq_jac_tr(&mut self, _x: &Vec2, mu: &[f64]) -> Vec2 {
+        Vec2::from_col_slice(&[-mu[0], -mu[0]])
+    }
+}
+//}}}
+//{{{ fun: barzilai_borwein
+/// Subsolver running the Barzilai-Borwein method to an absolute gradient tolerance.
+fn barzilai_borwein<F>(fcn: F, x0: Vec2, tol: f64) -> BarzilaiBorwein<F>
+where
+    F: topohedral_optimize::RealFn<Vector = Vec2>,
+{
+    let opts = BarzilaiBorweinOptions {
+        uncon_opts: UnonstrainedOptions {
+            grad_rtol: 0.0,
+            grad_atol: tol,
+            max_iter: 10_000,
+            ..UnonstrainedOptions::default()
+        },
+        ..BarzilaiBorweinOptions::default()
+    };
+    BarzilaiBorwein::new(fcn, x0, opts)
+}
+//}}}
+//{{{ test: test_augmented_lagrangian_equality
+#[test]
+fn test_augmented_lagrangian_equality() {
+    let x0 = Vec2::from_col_slice(&[0.5, -0.5]);
+    let subsolver = UnconstrainedSubsolver::new(barzilai_borwein);
+    let ret = AugmentedLagrangian::new(Circle, x0, subsolver, AugmentedLagrangianOptions::default())
+        .minimize()
+        .unwrap();
+    assert_relative_eq!(ret.xmin[0], -1.0, epsilon = 1e-6);
+    assert_relative_eq!(ret.xmin[1], -1.0, epsilon = 1e-6);
+    assert_relative_eq!(ret.lambda[0], -0.5, epsilon = 1e-6);
+    assert!(ret.kkt.feasibility <= 1e-6 && ret.kkt.stationarity <= 1e-6);
+}
+//}}}
+//{{{ test: test_augmented_lagrangian_mixed
+#[test]
+fn test_augmented_lagrangian_mixed() {
+    let x0 = Vec2::from_col_slice(&[2.0, 2.0]);
+    let xmin = [(7.0f64.sqrt() - 1.0) / 2.0, (7.0f64.sqrt() + 1.0) / 4.0];
+
+    // with the conjugate gradient method as the subsolver, the second inequality is inactive
+    let subsolver = UnconstrainedSubsolver::new(|fcn, x0, tol| {
+        let opts = ConjugateGradientOptions {
+            uncon_opts: UnonstrainedOptions {
+                grad_rtol: 0.0,
+                grad_atol: tol,
+                max_iter: 10_000,
+                ..UnonstrainedOptions::default()
+            },
+            direction: Direction::PolakRibiere,
+            restart: 10,
+        };
+        ConjugateGradient::new(fcn, x0, opts)
+    });
+    let problem = Bracken { sum: true };
+    let ret = AugmentedLagrangian::new(problem, x0, subsolver, AugmentedLagrangianOptions::default())
+        .minimize()
+        .unwrap();
+    assert_relative_eq!(ret.xmin[0], xmin[0], epsilon = 1e-5);
+    assert_relative_eq!(ret.xmin[1], xmin[1], epsilon = 1e-5);
+    assert!(ret.mu[0] > 0.0);
+    assert_eq!(ret.mu[1], 0.0);
+    assert!(ret.kkt.complementarity <= 1e-6);
+}
+//}}}
+//{{{ test: test_augmented_lagrangian_bounds
+#[test]
+fn test_augmented_lagrangian_bounds() {
+    // the bound on the second variable moves the minimizer along the constraint to (1.2, 0.8)
+    let x0 = Vec2::from_col_slice(&[0.0, 5.0]);
+    let bounds = Bounds::new(vec![f64::NEG_INFINITY, 0.8], vec![f64::INFINITY, 10.0]).unwrap();
+    let subsolver = BoundConstrainedSubsolver::new(bounds, LbfgsbOptions::default());
+    let problem = Halfplane { infeasible: false };
+    let ret = AugmentedLagrangian::new(problem, x0, subsolver, AugmentedLagrangianOptions::default())
+        .minimize()
+        .unwrap();
+    assert_relative_eq!(ret.xmin[0], 1.2, epsilon = 1e-6);
+    assert_eq!(ret.xmin[1], 0.8);
+    assert_relative_eq!(ret.mu[0], 1.6, epsilon = 1e-6);
+}
+//}}}
+//{{{ test: test_augmented_lagrangian_errors
+#[test]
+fn test_augmented_lagrangian_errors() {
+    let x0 = Vec2::from_col_slice(&[0.0, 0.0]);
+    let opts = AugmentedLagrangianOptions {
+        penalty_max: 1e6,
+        ..AugmentedLagrangianOptions::default()
+    };
+    let problem = Halfplane { infeasible: true };
+    let subsolver = UnconstrainedSubsolver::new(barzilai_borwein);
+    let err = AugmentedLagrangian::new(problem, x0, subsolver, opts)
+        .minimize()
+        .unwrap_err();
+    assert!(matches!(err, ConstrainedError::PenaltyLimit { .. }));
+    // the least violation is at x₀ = 0
+    assert_relative_eq!(err.partial().kkt.feasibility, 1.0, epsilon = 1e-3);
+
+    let opts = AugmentedLagrangianOptions {
+        max_iter: 1,
+        ..AugmentedLagrangianOptions::default()
+    };
+    let subsolver = UnconstrainedSubsolver::new(barzilai_borwein);
+    let err = AugmentedLagrangian::new(Bracken { sum: false }, x0, subsolver, opts)
+        .minimize()
+        .unwrap_err();
+    assert!(matches!(err, ConstrainedError::MaxIterations { max_iter: 1, .. }));
+}
+//}}}
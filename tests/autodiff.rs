@@ -0,0 +1,334 @@
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+#![feature(impl_trait_in_assoc_type)]
+
+//{{{ crate imports
+use topohedral_optimize::autodiff::{
+    Dual, ForwardDiff, HyperDual, ReverseDiff, Scalar, ScalarFn, Tape,
+};
+use topohedral_optimize::gradient_check::{check_gradient, Options as GradientCheckOptions};
+use topohedral_optimize::line_search::{InterpOptions, LineSearchMethod, LineSearchOptions};
+use topohedral_optimize::unconstrained::{
+    ConjugateGradient, ConjugateGradientOptions, Direction, UnconstrainedMinimizer,
+    UnonstrainedOptions,
+};
+use topohedral_optimize::RealFn;
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use approx::assert_relative_eq;
+use topohedral_linalg::{scvector::SCVector, smatrix::SMatrix};
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Quadratic3
+/// The 3D quadratic `(x - c)ᵀ A (x - c)` from `tests/functions.rs`, written generically.
+#[derive(Debug, Clone)]
+struct Quadratic3 {
+    center: [f64; 3],
+    coeffs: SMatrix<f64, 3, 3>,
+}
+//}}}
+//{{{ impl: Quadratic3
+impl Quadratic3 {
+    fn new() -> Self {
+        #[rustfmt::skip]
+        let coeffs = SMatrix::<f64, 3, 3>::from_row_slice(&[
+            5.0, 1.0, 2.0,
+            1.0, 5.0, 3.0,
+            2.0, 3.0, 5.0,
+        ]);
+        Self {
+            center: [0.0; 3],
+            coeffs,
+        }
+    }
+}
+//}}}
+//{{{ impl: ScalarFn for Quadratic3
+impl ScalarFn for Quadratic3 {
+    fn eval<S: Scalar>(&self, x: &[S]) -> S {
+        let mut out = S::from_f64(0.0);
+        for i in 0..3 {
+            for j in 0..3 {
+                let xi = x[i] - self.center[i];
+                let xj = x[j] - self.center[j];
+                out = out + xi * xj * self.coeffs[(i, j)];
+            }
+        }
+        out
+    }
+}
+//}}}
+//{{{ struct: Rosenbrock
+#[derive(Debug, Clone, Copy)]
+struct Rosenbrock {
+    a: f64,
+    b: f64,
+}
+//}}}
+//{{{ impl: ScalarFn for Rosenbrock
+impl ScalarFn for Rosenbrock {
+    fn eval<S: Scalar>(&self, x: &[S]) -> S {
+        (-x[0] + self.a).powi(2) + (x[1] - x[0].powi(2)).powi(2) * self.b
+    }
+}
+//}}}
+//{{{ struct: Elementary
+/// Exercises every elementary function of `Scalar`.
+#[derive(Debug, Clone, Copy)]
+struct Elementary;
+//}}}
+//{{{ impl: ScalarFn for Elementary
+impl ScalarFn for Elementary {
+    fn eval<S: Scalar>(&self, x: &[S]) -> S {
+        x[0].sin() * x[1].exp() + x[2].ln() / x[0].sqrt() - x[1].cos().tanh()
+            + (x[2] * x[0]).tan().abs()
+            + x[3].powf(2.5)
+            + x[3] / x[1]
+    }
+}
+//}}}
+//{{{ struct: Shifted
+#[derive(Debug, Clone, Copy)]
+struct Shifted {
+    xmin: [f64; 5],
+}
+//}}}
+//{{{ impl: ScalarFn for Shifted
+impl ScalarFn for Shifted {
+    fn eval<S: Scalar>(&self, x: &[S]) -> S {
+        let mut out = S::from_f64(0.0);
+        for (i, (xi, ci)) in x.iter().zip(self.xmin.iter()).enumerate() {
+            out = out + (*xi - *ci).powi(2) * (i + 1) as f64;
+        }
+        out
+    }
+}
+//}}}
+//{{{ struct: ExtendedQuadratic
+/// A weakly coupled quadratic in `N` variables with minimum at `x_i = 1`.
+#[derive(Debug, Clone, Copy)]
+struct ExtendedQuadratic<const N: usize>;
+//}}}
+//{{{ impl: ScalarFn for ExtendedQuadratic
+impl<const N: usize> ScalarFn for ExtendedQuadratic<N> {
+    fn eval<S: Scalar>(&self, x: &[S]) -> S {
+        let mut out = S::from_f64(0.0);
+        for (i, xi) in x.iter().enumerate() {
+            out = out + (*xi - 1.0).powi(2) * (1.0 + (i % 7) as f64);
+        }
+        for i in 1..N {
+            out = out + ((x[i] - 1.0) - (x[i - 1] - 1.0)).powi(2) * 0.1;
+        }
+        out
+    }
+}
+//}}}
+//{{{ test: test_dual_arithmetic
+#[test]
+fn test_dual_arithmetic() {
+    let x = Dual::variable(2.0);
+    let y = (x * x + 3.0 * x) / (x - 1.0);
+    // y = (x^2 + 3x) / (x - 1), y' = (x^2 - 2x - 3) / (x - 1)^2
+    assert_relative_eq!(y.re, 10.0);
+    assert_relative_eq!(y.eps, -3.0);
+
+    let h = HyperDual::new(0.5, 1.0, 1.0, 0.0);
+    let z = h.sin() * h.exp();
+    // (sin x e^x)'' = 2 cos x e^x
+    assert_relative_eq!(z.e12, 2.0 * 0.5f64.cos() * 0.5f64.exp(), epsilon = 1e-12);
+}
+//}}}
+//{{{ test: test_forward_quadratic_static_3d
+#[test]
+fn test_forward_quadratic_static_3d() {
+    let mut f = ForwardDiff::<_, SCVector<f64, 3>>::new(Quadratic3::new());
+
+    let x = SCVector::<f64, 3>::ones();
+    assert_relative_eq!(f.eval(&x), 27.0);
+    let grad = f.grad(&x);
+    let exp_grad = [16.0, 18.0, 20.0];
+    for i in 0..3 {
+        assert_relative_eq!(grad[i], exp_grad[i], epsilon = 1e-12);
+    }
+
+    // the Hessian is A + Aᵀ
+    let v = SCVector::<f64, 3>::from_col_slice(&[1.0, -2.0, 0.5]);
+    let hv = f.hess_vec(&x, &v);
+    let exp_hv = [10.0 - 4.0 + 2.0, 2.0 - 20.0 + 3.0, 4.0 - 12.0 + 5.0];
+    for i in 0..3 {
+        assert_relative_eq!(hv[i], exp_hv[i], epsilon = 1e-12);
+    }
+    assert_relative_eq!(f.directional(&x, &v), 16.0 - 36.0 + 10.0, epsilon = 1e-12);
+}
+//}}}
+//{{{ test: test_forward_rosenbrock_hessian
+#[test]
+fn test_forward_rosenbrock_hessian() {
+    let (a, b) = (1.0, 100.0);
+    let mut f = ForwardDiff::<_, SCVector<f64, 2>>::new(Rosenbrock { a, b });
+    let x = SCVector::<f64, 2>::from_col_slice(&[-1.2, 1.0]);
+    let v = SCVector::<f64, 2>::from_col_slice(&[0.3, -0.7]);
+
+    let (grad, hv) = f.grad_hess_vec(&x, &v);
+    let (x0, x1) = (x[0], x[1]);
+    assert_relative_eq!(
+        grad[0],
+        -2.0 * (a - x0) - 4.0 * b * x0 * (x1 - x0 * x0),
+        epsilon = 1e-10
+    );
+    assert_relative_eq!(grad[1], 2.0 * b * (x1 - x0 * x0), epsilon = 1e-10);
+
+    let h00 = 2.0 - 4.0 * b * (x1 - 3.0 * x0 * x0);
+    let h01 = -4.0 * b * x0;
+    let h11 = 2.0 * b;
+    assert_relative_eq!(hv[0], h00 * v[0] + h01 * v[1], epsilon = 1e-10);
+    assert_relative_eq!(hv[1], h01 * v[0] + h11 * v[1], epsilon = 1e-10);
+}
+//}}}
+//{{{ test: test_forward_elementary_functions
+#[test]
+fn test_forward_elementary_functions() {
+    let mut f = ForwardDiff::<_, SCVector<f64, 4>>::new(Elementary);
+    let x = SCVector::<f64, 4>::from_col_slice(&[0.7, 0.4, 1.3, 2.1]);
+    let report = check_gradient(&mut f, &x, &GradientCheckOptions::default());
+    assert!(report.passed, "{report:?}");
+}
+//}}}
+//{{{ test: test_forward_with_conjugate_gradient
+#[test]
+fn test_forward_with_conjugate_gradient() {
+    let xmin = [3.0, -1.0, 0.5, 2.0, -4.0];
+    let fcn = ForwardDiff::<_, SCVector<f64, 5>>::new(Shifted { xmin });
+    let x0 = SCVector::<f64, 5>::zeros();
+
+    let mut cg = ConjugateGradient::new(
+        fcn,
+        x0,
+        ConjugateGradientOptions {
+            uncon_opts: UnonstrainedOptions {
+                grad_rtol: 1e-8,
+                grad_atol: 1e-10,
+                max_iter: 100,
+                ls_method: LineSearchMethod::Interp(InterpOptions {
+                    ls_opts: LineSearchOptions::default(),
+                    step1: 0.5,
+                    step2: 1.0,
+                    scale_factor: 1.5,
+                    maxiter: 10,
+                }),
+                ..UnonstrainedOptions::default()
+            },
+            direction: Direction::PolakRibiere,
+            restart: 10,
+        },
+    );
+    let ret = cg.minimize().unwrap();
+    for (i, xi) in xmin.iter().enumerate() {
+        assert_relative_eq!(ret.xmin[i], *xi, epsilon = 1e-6);
+    }
+}
+//}}}
+//{{{ test: test_tape_gradient
+#[test]
+fn test_tape_gradient() {
+    let tape = Tape::new();
+    let x = tape.vars(&[2.0, 3.0]);
+    // f = x0 * x1 + sin(x0) / x1 - 4
+    let y = x[0] * x[1] + x[0].sin() / x[1] - 4.0;
+    let grad = tape.gradient(y);
+    assert_eq!(tape.num_inputs(), 2);
+    assert_relative_eq!(y.value(), 6.0 + 2.0f64.sin() / 3.0 - 4.0);
+    assert_relative_eq!(grad[0], 3.0 + 2.0f64.cos() / 3.0, epsilon = 1e-14);
+    assert_relative_eq!(grad[1], 2.0 - 2.0f64.sin() / 9.0, epsilon = 1e-14);
+
+    // constants never touch the tape
+    let c = <topohedral_optimize::autodiff::Var as Scalar>::from_f64(1.5).exp();
+    assert!(c.index().is_none());
+    assert_eq!(tape.gradient(c), vec![0.0, 0.0]);
+
+    // x⁰ has a zero derivative at x = 0, as for the dual numbers
+    let tape = Tape::new();
+    let x = tape.var(0.0);
+    let y = x.powi(0) + x.powi(1) * 2.0;
+    let grad = tape.gradient(y);
+    assert_eq!(y.value(), 1.0);
+    assert_eq!(grad, vec![2.0]);
+    assert_eq!(Dual::variable(0.0).powi(0).eps, 0.0);
+}
+//}}}
+//{{{ test: test_tape_mixing
+#[test]
+#[should_panic(expected = "operands recorded on different tapes")]
+fn test_tape_mixing() {
+    let tape_a = Tape::new();
+    let tape_b = Tape::new();
+    let _ = tape_a.var(1.0) + tape_b.var(2.0);
+}
+//}}}
+//{{{ test: test_reverse_matches_forward
+#[test]
+fn test_reverse_matches_forward() {
+    let x = SCVector::<f64, 4>::from_col_slice(&[0.7, 0.4, 1.3, 2.1]);
+    let mut fwd = ForwardDiff::<_, SCVector<f64, 4>>::new(Elementary);
+    let mut rev = ReverseDiff::<_, SCVector<f64, 4>>::new(Elementary);
+    let grad_fwd = fwd.grad(&x);
+    let grad_rev = rev.grad(&x);
+    for i in 0..4 {
+        assert_relative_eq!(grad_fwd[i], grad_rev[i], epsilon = 1e-12);
+    }
+    assert!(rev.tape_len().is_none());
+}
+//}}}
+//{{{ test: test_reverse_replay
+#[test]
+fn test_reverse_replay() {
+    let mut rec = ReverseDiff::<_, SCVector<f64, 2>>::new(Rosenbrock { a: 1.0, b: 100.0 });
+    let mut rep = ReverseDiff::<_, SCVector<f64, 2>>::replaying(Rosenbrock { a: 1.0, b: 100.0 });
+    let points = [[-1.2, 1.0], [0.3, -0.4], [2.0, 2.5]];
+    for p in points.iter() {
+        let x = SCVector::<f64, 2>::from_col_slice(p);
+        let g1 = rec.grad(&x);
+        let g2 = rep.grad(&x);
+        assert_relative_eq!(g1[0], g2[0], epsilon = 1e-12);
+        assert_relative_eq!(g1[1], g2[1], epsilon = 1e-12);
+    }
+    assert!(rep.tape_len().unwrap() > 2);
+}
+//}}}
+//{{{ test: test_reverse_with_conjugate_gradient
+#[test]
+fn test_reverse_with_conjugate_gradient() {
+    const N: usize = 200;
+    let fcn = ReverseDiff::<_, SCVector<f64, N>>::replaying(ExtendedQuadratic::<N>);
+    let x0 = SCVector::<f64, N>::zeros();
+
+    let mut cg = ConjugateGradient::new(
+        fcn,
+        x0,
+        ConjugateGradientOptions {
+            uncon_opts: UnonstrainedOptions {
+                grad_rtol: 1e-8,
+                grad_atol: 1e-10,
+                max_iter: 500,
+                ls_method: LineSearchMethod::Interp(InterpOptions {
+                    ls_opts: LineSearchOptions::default(),
+                    step1: 0.5,
+                    step2: 1.0,
+                    scale_factor: 1.5,
+                    maxiter: 10,
+                }),
+                ..UnonstrainedOptions::default()
+            },
+            direction: Direction::PolakRibiere,
+            restart: 50,
+        },
+    );
+    let ret = cg.minimize().unwrap();
+    for i in 0..N {
+        assert_relative_eq!(ret.xmin[i], 1.0, epsilon = 1e-5);
+    }
+}
+//}}}
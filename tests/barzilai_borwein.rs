@@ -0,0 +1,164 @@
+//! Tests of the Barzilai-Borwein minimizer.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::unconstrained::{
+    BarzilaiBorwein, BarzilaiBorweinOptions, BarzilaiBorweinVariant, ConvergedReason, Direction,
+    IterationState, Nonmonotone, ObserverAction, UnconstrainedError, UnconstrainedMinimizer,
+    UnonstrainedOptions,
+};
+use topohedral_optimize::RealFn;
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use approx::assert_relative_eq;
+use topohedral_linalg::dvector::{DVector, VecType};
+use topohedral_linalg::scvector::SCVector;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Diagonal
+/// `Σ (i + 1) (xᵢ − 1)² / 2`, with a condition number equal to the dimension.
+#[derive(Debug, Clone)]
+struct Diagonal;
+//}}}
+//{{{ impl: RealFn for Diagonal
+impl RealFn for Diagonal {
+    type Vector = DVector<f64>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        (0..x.len()).map(|i| 0.5 * (i + 1) as f64 * (x[i] - 1.0).powi(2)).sum()
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        let values: Vec<f64> = (0..x.len()).map(|i| (i + 1) as f64 * (x[i] - 1.0)).collect();
+        DVector::from_slice(&values, VecType::Col)
+    }
+}
+//}}}
+//{{{ struct: Rosenbrock
+#[derive(Debug, Clone)]
+struct Rosenbrock;
+//}}}
+//{{{ impl: RealFn for Rosenbrock
+impl RealFn for Rosenbrock {
+    type Vector = SCVector<f64, 2>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        (1.0 - x[0]).powi(2) + 100.0 * (x[1] - x[0] * x[0]).powi(2)
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        let dx0 = -2.0 * (1.0 - x[0]) - 400.0 * x[0] * (x[1] - x[0] * x[0]);
+        let dx1 = 200.0 * (x[1] - x[0] * x[0]);
+        SCVector::<f64, 2>::from_col_slice(&[dx0, dx1])
+    }
+}
+//}}}
+//{{{ test: test_barzilai_borwein_quadratic
+#[test]
+fn test_barzilai_borwein_quadratic() {
+    let x0 = DVector::from_slice(&[0.0; 20], VecType::Col);
+    let variants = [
+        BarzilaiBorweinVariant::Long,
+        BarzilaiBorweinVariant::Short,
+        BarzilaiBorweinVariant::Alternating,
+        BarzilaiBorweinVariant::Adaptive { kappa: 0.15 },
+    ];
+    for variant in variants {
+        // without globalization every iteration takes its step with a single evaluation
+        let opts = BarzilaiBorweinOptions {
+            variant,
+            nonmonotone: None,
+            ..BarzilaiBorweinOptions::default()
+        };
+        let ret = BarzilaiBorwein::new(Diagonal, x0.clone(), opts)
+            .minimize()
+            .unwrap();
+        assert!(matches!(ret.reason, ConvergedReason::Rtol | ConvergedReason::Atol));
+        for i in 0..20 {
+            assert_relative_eq!(ret.xmin[i], 1.0, epsilon = 1e-4);
+        }
+        assert_eq!(ret.num_fun_evals, ret.num_iterations + 1);
+        assert_eq!(ret.num_grad_evals, ret.num_iterations + 1);
+        assert_eq!(ret.line_search.num_rejected, 0);
+    }
+}
+//}}}
+//{{{ test: test_barzilai_borwein_rosenbrock
+#[test]
+fn test_barzilai_borwein_rosenbrock() {
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[-1.2, 1.0]);
+    // a monotone search would throw away most of the spectral steps in the curved valley
+    let variants = [
+        BarzilaiBorweinVariant::Long,
+        BarzilaiBorweinVariant::Alternating,
+        BarzilaiBorweinVariant::Adaptive { kappa: 0.15 },
+    ];
+    for variant in variants {
+        let opts = BarzilaiBorweinOptions {
+            uncon_opts: UnonstrainedOptions {
+                grad_rtol: 1e-9,
+                ..UnonstrainedOptions::default()
+            },
+            variant,
+            nonmonotone: Some(Nonmonotone {
+                memory: 10,
+                ..Nonmonotone::default()
+            }),
+            ..BarzilaiBorweinOptions::default()
+        };
+        let ret = BarzilaiBorwein::new(Rosenbrock, x0, opts).minimize().unwrap();
+        assert_relative_eq!(ret.xmin[0], 1.0, epsilon = 1e-4);
+        assert_relative_eq!(ret.xmin[1], 1.0, epsilon = 1e-4);
+        assert_eq!(ret.line_search.num_searches, ret.num_iterations);
+    }
+}
+//}}}
+//{{{ test: test_barzilai_borwein_observer
+#[test]
+fn test_barzilai_borwein_observer() {
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[-1.2, 1.0]);
+    let mut bb = BarzilaiBorwein::new(Rosenbrock, x0, BarzilaiBorweinOptions::default());
+    bb.set_observer(Box::new(|state: &IterationState<SCVector<f64, 2>>| {
+        assert_eq!(state.direction, Some(Direction::Steepest));
+        assert!(state.alpha > 0.0);
+        if state.iteration == 5 {
+            ObserverAction::Stop
+        } else {
+            ObserverAction::Continue
+        }
+    }));
+    let states: Vec<_> = bb.iterations().collect();
+    assert_eq!(states.len(), 5);
+    let ret = bb.minimize().unwrap();
+    assert!(matches!(ret.reason, ConvergedReason::ObserverStop));
+    assert_eq!(ret.num_iterations, 5);
+}
+//}}}
+//{{{ test: test_barzilai_borwein_errors
+#[test]
+fn test_barzilai_borwein_errors() {
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[-1.2, 1.0]);
+    let opts = BarzilaiBorweinOptions {
+        uncon_opts: UnonstrainedOptions {
+            max_iter: 3,
+            ..UnonstrainedOptions::default()
+        },
+        ..BarzilaiBorweinOptions::default()
+    };
+    let err = BarzilaiBorwein::new(Rosenbrock, x0, opts.clone())
+        .minimize()
+        .unwrap_err();
+    assert!(matches!(err, UnconstrainedError::MaxIterations { max_iter: 3, .. }));
+    assert!(err.partial().fmin < Rosenbrock.eval(&x0));
+
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[f64::NAN, 1.0]);
+    let err = BarzilaiBorwein::new(Rosenbrock, x0, opts).minimize().unwrap_err();
+    assert!(matches!(err, UnconstrainedError::NonFiniteValue { .. }));
+}
+//}}}
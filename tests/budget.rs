@@ -0,0 +1,207 @@
+//! Tests of evaluation counting, evaluation budgets and line search statistics.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::line_search::{
+    Backtracking, BacktrackingOptions, Interp, InterpOptions, LineSearch, LineSearchError,
+    LineSearchMethod,
+};
+use topohedral_optimize::unconstrained::{
+    ConjugateGradient, ConjugateGradientOptions, Direction, UnconstrainedError,
+    UnconstrainedMinimizer, UnonstrainedOptions,
+};
+use topohedral_optimize::{Budget, CountingRealFn, CountingRealFn1, RealFn, RealFn1};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use topohedral_linalg::scvector::SCVector;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Parabola
+/// `φ(α) = (α − 2)²`, with a minimum at `α = 2`.
+#[derive(Debug, Clone)]
+struct Parabola;
+//}}}
+//{{{ impl: RealFn1 for Parabola
+impl RealFn1 for Parabola {
+    fn eval(&mut self, alpha: f64) -> f64 {
+        (alpha - 2.0).powi(2)
+    }
+
+    fn diff(&mut self, alpha: f64) -> f64 {
+        2.0 * (alpha - 2.0)
+    }
+}
+//}}}
+//{{{ struct: Diagonal
+/// Quadratic with a diagonal Hessian of `2 * diag(1, ..., 4)` and a minimum at `(1, ..., 1)`.
+#[derive(Debug, Clone)]
+struct Diagonal;
+//}}}
+//{{{ impl: RealFn for Diagonal
+impl RealFn for Diagonal {
+    type Vector = SCVector<f64, 4>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        (0..4).map(|i| (i + 1) as f64 * (x[i] - 1.0).powi(2)).sum()
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        let mut out = Self::Vector::zeros();
+        for i in 0..4 {
+            out[i] = 2.0 * (i + 1) as f64 * (x[i] - 1.0);
+        }
+        out
+    }
+}
+//}}}
+//{{{ fun: options
+fn options(budget: Budget) -> ConjugateGradientOptions {
+    ConjugateGradientOptions {
+        uncon_opts: UnonstrainedOptions {
+            max_iter: 100,
+            record_history: true,
+            budget,
+            ..UnonstrainedOptions::default()
+        },
+        direction: Direction::PolakRibiere,
+        restart: 10,
+    }
+}
+//}}}
+//{{{ test: test_counting_real_fn
+#[test]
+fn test_counting_real_fn() {
+    let x = SCVector::<f64, 4>::zeros();
+    let budget = Budget::new(Some(3), None);
+    let mut fcn = CountingRealFn::new(Diagonal).with_budget(budget);
+    fcn.eval(&x);
+    fcn.eval_with_grad(&x);
+    assert_eq!(fcn.num_func_evals, 2);
+    assert_eq!(fcn.num_grad_evals, 1);
+    assert!(fcn.allows(1, 5));
+    assert!(!fcn.allows(2, 0));
+    assert_eq!(fcn.remaining(), Budget::new(Some(1), None));
+
+    // the scalar wrapper refuses an evaluation beyond its budget with NaN
+    let mut fcn1 = CountingRealFn1::new(Parabola).with_budget(Budget::new(Some(2), Some(2)));
+    assert_eq!(fcn1.eval(1.0), 1.0);
+    assert_eq!(fcn1.diff(1.0), -2.0);
+    assert_eq!(fcn1.eval_with_diff(1.0), (1.0, -2.0));
+    assert_eq!(fcn1.num_evals, 2);
+    assert_eq!(fcn1.num_diffs, 2);
+    assert!(!fcn1.allows(1, 0));
+    assert!(!fcn1.allows(0, 1));
+    assert_eq!(fcn1.remaining(), Budget::new(Some(0), Some(0)));
+    assert!(fcn1.eval(1.0).is_nan());
+    assert!(fcn1.diff(1.0).is_nan());
+    assert_eq!((fcn1.num_evals, fcn1.num_diffs), (2, 2));
+}
+//}}}
+//{{{ test: test_line_search_budget
+#[test]
+fn test_line_search_budget() {
+    // the statistics of a search match the evaluations it made
+    let mut search = Interp::new(CountingRealFn1::new(Parabola), InterpOptions::default());
+    search.search(4.0, -4.0).unwrap();
+    let stats = search.stats();
+    assert_eq!(stats.num_searches, 1);
+    assert!(stats.num_evals > 0);
+    assert!(stats.num_diffs > 0);
+
+    // a budget too small for the search stops it before it is exceeded
+    let fcn = CountingRealFn1::new(Parabola);
+    let budget = Budget::new(Some(1), None);
+    let mut search = Interp::new(fcn, InterpOptions::default()).with_budget(budget);
+    assert_eq!(search.search(4.0, -4.0).unwrap_err(), LineSearchError::BudgetExhausted);
+    assert_eq!(search.stats().num_evals, 1);
+
+    // as it does the backtracking search, the budget counting afresh for each search
+    let opts = BacktrackingOptions::default();
+    let mut search = Backtracking::new(Parabola, opts).with_budget(Budget::new(Some(0), None));
+    assert_eq!(search.search(4.0, -4.0).unwrap_err(), LineSearchError::BudgetExhausted);
+    assert_eq!(search.stats().num_evals, 0);
+    let mut search = search.with_budget(Budget::new(Some(1), None));
+    search.search(4.0, -4.0).unwrap();
+    search.search(4.0, -4.0).unwrap();
+
+    // while a total budget is charged with the evaluations of every search
+    let fcn = CountingRealFn1::new(Parabola);
+    let budget = Budget::new(Some(2), None);
+    let mut search = Backtracking::new(fcn, opts).with_total_budget(budget);
+    search.search(4.0, -4.0).unwrap();
+    search.search(4.0, -4.0).unwrap();
+    assert_eq!(search.search(4.0, -4.0).unwrap_err(), LineSearchError::BudgetExhausted);
+    let fcn = CountingRealFn1::new(Parabola);
+    let mut search = Interp::new(fcn, InterpOptions::default());
+    search.search(4.0, -4.0).unwrap();
+    let num_evals = search.stats().num_evals;
+    let budget = Budget::new(Some(num_evals + 1), None);
+    let mut search = search.with_total_budget(budget);
+    assert_eq!(search.search(4.0, -4.0).unwrap_err(), LineSearchError::BudgetExhausted);
+    assert_eq!(search.stats().num_evals, 1);
+}
+//}}}
+//{{{ test: test_conjugate_gradient_budget
+#[test]
+fn test_conjugate_gradient_budget() {
+    let x0 = SCVector::<f64, 4>::zeros();
+    let ret = ConjugateGradient::new(Diagonal, x0, options(Budget::default()))
+        .minimize()
+        .unwrap();
+
+    // the totals over the line searches add up to those of the run
+    let ls = ret.line_search;
+    assert_eq!(ls.num_searches, ret.num_iterations);
+    assert_eq!(ls.num_evals + 1, ret.num_fun_evals + ret.num_cache_hits);
+    assert!(ls.evals_per_search() > 1.0);
+    let history = ret.history.as_ref().unwrap();
+    let per_iteration: usize = history.records.iter().map(|rec| rec.line_search.num_evals).sum();
+    assert_eq!(per_iteration, ls.num_evals);
+
+    // a smaller budget stops the run without exceeding it
+    for max_fevals in [1, 5, ret.num_fun_evals - 1] {
+        let budget = Budget::new(Some(max_fevals), None);
+        let err = ConjugateGradient::new(Diagonal, x0, options(budget))
+            .minimize()
+            .unwrap_err();
+        assert!(matches!(err, UnconstrainedError::BudgetExhausted { budget: b, .. } if b == budget));
+        assert!(err.partial().num_fun_evals <= max_fevals);
+        assert!(err.partial().fmin <= Diagonal.eval(&x0));
+    }
+    let budget = Budget::new(None, Some(2));
+    let err = ConjugateGradient::new(Diagonal, x0, options(budget))
+        .minimize()
+        .unwrap_err();
+    assert!(err.partial().num_grad_evals <= 2);
+
+    // and one which suffices does not interfere, cached requests being charged too
+    let max_fevals = ret.num_fun_evals + ret.num_cache_hits;
+    let budget = Budget::new(Some(max_fevals), Some(ret.num_grad_evals));
+    let ret_budget = ConjugateGradient::new(Diagonal, x0, options(budget))
+        .minimize()
+        .unwrap();
+    assert_eq!(ret_budget.fmin, ret.fmin);
+}
+//}}}
+//{{{ test: test_conjugate_gradient_backtracking
+#[test]
+fn test_conjugate_gradient_backtracking() {
+    // backtracking never asks for a derivative, so the gradient at each accepted step is
+    // requested on its own
+    let x0 = SCVector::<f64, 4>::zeros();
+    let mut opts = options(Budget::default());
+    opts.uncon_opts.max_iter = 1000;
+    opts.uncon_opts.ls_method = LineSearchMethod::Backtracking(BacktrackingOptions::default());
+    let ret = ConjugateGradient::new(Diagonal, x0, opts).minimize().unwrap();
+    assert!(ret.grad_norm < 1e-6);
+    assert_eq!(ret.num_grad_evals, ret.num_iterations + 1);
+    assert_eq!(ret.line_search.num_diffs, 0);
+    assert_eq!(ret.line_search.num_evals + 1, ret.num_fun_evals + ret.num_cache_hits);
+}
+//}}}
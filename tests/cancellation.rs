@@ -0,0 +1,314 @@
+//! Tests of cooperative cancellation and wall-clock time limits.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::bound_constrained::Bounds;
+use topohedral_optimize::constrained::{
+    ConjugateGradientSubsolver, ConstrainedError, ConstrainedProblem, InteriorPoint,
+    InteriorPointOptions, PenaltyMethod, PenaltyOptions, Sqp, SqpOptions,
+};
+use topohedral_optimize::line_search::{
+    Backtracking, BacktrackingOptions, Interp, InterpOptions, LineSearch, LineSearchError,
+};
+use topohedral_optimize::unconstrained::{
+    BarzilaiBorwein, BarzilaiBorweinOptions, ConjugateGradient, ConjugateGradientOptions,
+    Direction, UnconstrainedError, UnconstrainedMinimizer, UnonstrainedOptions,
+};
+use topohedral_optimize::{CancelToken, RealFn, RealFn1};
+//}}}
+//{{{ std imports
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+//}}}
+//{{{ dep imports
+use topohedral_linalg::scvector::SCVector;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Parabola
+/// `φ(α) = (α − 2)²`, counting its evaluations.
+#[derive(Debug, Clone, Default)]
+struct Parabola {
+    num_evals: Arc<AtomicUsize>,
+}
+//}}}
+//{{{ impl: RealFn1 for Parabola
+impl RealFn1 for Parabola {
+    fn eval(&mut self, alpha: f64) -> f64 {
+        self.num_evals.fetch_add(1, Ordering::Relaxed);
+        (alpha - 2.0).powi(2)
+    }
+
+    fn diff(&mut self, alpha: f64) -> f64 {
+        self.num_evals.fetch_add(1, Ordering::Relaxed);
+        2.0 * (alpha - 2.0)
+    }
+}
+//}}}
+//{{{ struct: Impatient
+/// Quadratic with a diagonal Hessian of `2 * diag(1, ..., 4)` which cancels `token` on its
+/// `cancel_at`-th function evaluation, as a user pressing stop would.
+#[derive(Debug, Clone)]
+struct Impatient {
+    token: CancelToken,
+    cancel_at: usize,
+    num_evals: Arc<AtomicUsize>,
+}
+//}}}
+//{{{ impl: RealFn for Impatient
+impl RealFn for Impatient {
+    type Vector = SCVector<f64, 4>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        if self.num_evals.fetch_add(1, Ordering::Relaxed) + 1 == self.cancel_at {
+            self.token.cancel();
+        }
+        (0..4).map(|i| (i + 1) as f64 * (x[i] - 1.0).powi(2)).sum()
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        let mut out = Self::Vector::zeros();
+        for i in 0..4 {
+            out[i] = 2.0 * (i + 1) as f64 * (x[i] - 1.0);
+        }
+        out
+    }
+}
+//}}}
+//{{{ struct: ImpatientCircle
+/// `x₀ + x₁` on the circle `x₀² + x₁² = 2`, which cancels `token` on its `cancel_at`-th
+/// objective evaluation.
+#[derive(Debug, Clone)]
+struct ImpatientCircle {
+    token: CancelToken,
+    cancel_at: usize,
+    num_evals: Arc<AtomicUsize>,
+}
+//}}}
+//{{{ impl: ConstrainedProblem for ImpatientCircle
+impl ConstrainedProblem for ImpatientCircle {
+    type Vector = SCVector<f64, 2>;
+
+    fn num_eq(&self) -> usize {
+        1
+    }
+
+    fn num_ineq(&self) -> usize {
+        0
+    }
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        if self.num_evals.fetch_add(1, Ordering::Relaxed) + 1 == self.cancel_at {
+            self.token.cancel();
+        }
+        x[0] + x[1]
+    }
+
+    fn grad(&mut self, _x: &Self::Vector) -> Self::Vector {
+        Self::Vector::from_col_slice(&[1.0, 1.0])
+    }
+
+    fn eq(&mut self, x: &Self::Vector) -> Vec<f64> {
+        vec![x[0] * x[0] + x[1] * x[1] - 2.0]
+    }
+
+    fn ineq(&mut self, _x: &Self::Vector) -> Vec<f64> {
+        vec![]
+    }
+
+    fn eq_jac_tr(&mut self, x: &Self::Vector, lambda: &[f64]) -> Self::Vector {
+        Self::Vector::from_col_slice(&[2.0 * x[0] * lambda[0], 2.0 * x[1] * lambda[0]])
+    }
+
+    fn ineq_jac_tr(&mut self, _x: &Self::Vector, _mu: &[f64]) -> Self::Vector {
+        Self::Vector::zeros()
+    }
+}
+//}}}
+//{{{ fun: options
+fn options(cancel: Option<CancelToken>, time_limit: Option<Duration>) -> ConjugateGradientOptions {
+    ConjugateGradientOptions {
+        uncon_opts: UnonstrainedOptions {
+            max_iter: 100,
+            cancel,
+            time_limit,
+            ..UnonstrainedOptions::default()
+        },
+        direction: Direction::PolakRibiere,
+        restart: 10,
+    }
+}
+//}}}
+//{{{ test: test_line_search_cancel
+#[test]
+fn test_line_search_cancel() {
+    let parabola = Parabola::default();
+    let num_evals = parabola.num_evals.clone();
+
+    // an already cancelled token stops the search before any evaluation
+    let token = CancelToken::new();
+    token.cancel();
+    let err = Interp::new(parabola.clone(), InterpOptions::default())
+        .with_cancel(token.clone())
+        .search(4.0, -4.0)
+        .unwrap_err();
+    assert_eq!(err, LineSearchError::Cancelled);
+    assert_eq!(num_evals.load(Ordering::Relaxed), 0);
+
+    // as does a deadline which has passed
+    let err = Interp::new(parabola.clone(), InterpOptions::default())
+        .with_deadline(Instant::now())
+        .search(4.0, -4.0)
+        .unwrap_err();
+    assert_eq!(err, LineSearchError::TimeLimit);
+    assert_eq!(num_evals.load(Ordering::Relaxed), 0);
+
+    // a reset token and a distant deadline do not interfere
+    token.reset();
+    let ret = Interp::new(parabola.clone(), InterpOptions::default())
+        .with_cancel(token.clone())
+        .with_deadline(Instant::now() + Duration::from_secs(3600))
+        .search(4.0, -4.0)
+        .unwrap();
+    assert!(ret.phi_alpha < 4.0);
+    let num_evals_before = num_evals.load(Ordering::Relaxed);
+
+    // the backtracking search checks the same conditions
+    token.cancel();
+    let err = Backtracking::new(parabola.clone(), BacktrackingOptions::default())
+        .with_cancel(token)
+        .search(4.0, -4.0)
+        .unwrap_err();
+    assert_eq!(err, LineSearchError::Cancelled);
+    let err = Backtracking::new(parabola, BacktrackingOptions::default())
+        .with_deadline(Instant::now())
+        .search(4.0, -4.0)
+        .unwrap_err();
+    assert_eq!(err, LineSearchError::TimeLimit);
+    assert_eq!(num_evals.load(Ordering::Relaxed), num_evals_before);
+}
+//}}}
+//{{{ test: test_conjugate_gradient_cancel
+#[test]
+fn test_conjugate_gradient_cancel() {
+    let x0 = SCVector::<f64, 4>::zeros();
+    let token = CancelToken::new();
+    let fcn = Impatient {
+        token: token.clone(),
+        cancel_at: 12,
+        num_evals: Arc::new(AtomicUsize::new(0)),
+    };
+    let f0 = fcn.clone().eval(&x0);
+    fcn.num_evals.store(0, Ordering::Relaxed);
+
+    // the run stops right after the evaluation which cancelled it
+    let opts = options(Some(token.clone()), None);
+    let mut cg = ConjugateGradient::new(fcn.clone(), x0, opts.clone());
+    let err = cg.minimize().unwrap_err();
+    assert!(matches!(err, UnconstrainedError::Cancelled { .. }));
+    let partial = err.partial();
+    assert_eq!(partial.num_fun_evals, 12);
+    assert!(partial.num_iterations > 0);
+    assert!(partial.fmin < f0);
+
+    // the run can be picked up again once the token is reset
+    token.reset();
+    let fcn = Impatient { cancel_at: 0, ..fcn };
+    let state = cg.state().unwrap();
+    let ret = ConjugateGradient::resume_from(fcn, state, opts).minimize().unwrap();
+    assert!(ret.fmin < 1e-10);
+}
+//}}}
+//{{{ test: test_conjugate_gradient_time_limit
+#[test]
+fn test_conjugate_gradient_time_limit() {
+    let x0 = SCVector::<f64, 4>::zeros();
+    let fcn = Impatient {
+        token: CancelToken::new(),
+        cancel_at: 0,
+        num_evals: Arc::new(AtomicUsize::new(0)),
+    };
+
+    // only the starting point is evaluated with no time to spare
+    let opts = options(None, Some(Duration::ZERO));
+    let err = ConjugateGradient::new(fcn.clone(), x0, opts).minimize().unwrap_err();
+    assert!(matches!(err, UnconstrainedError::TimeLimit { time_limit, .. } if time_limit.is_zero()));
+    assert_eq!(err.partial().num_iterations, 0);
+    assert_eq!(err.partial().num_fun_evals, 1);
+
+    // a generous limit does not interfere
+    let opts = options(None, Some(Duration::from_secs(3600)));
+    let ret = ConjugateGradient::new(fcn.clone(), x0, opts).minimize().unwrap();
+    assert!(ret.fmin < 1e-10);
+
+    // the clock starts with the run rather than with the minimizer
+    let time_limit = Duration::from_millis(50);
+    let mut cg = ConjugateGradient::new(fcn.clone(), x0, options(None, Some(time_limit)));
+    let bb_opts = BarzilaiBorweinOptions {
+        uncon_opts: options(None, Some(time_limit)).uncon_opts,
+        ..BarzilaiBorweinOptions::default()
+    };
+    let mut bb = BarzilaiBorwein::new(fcn, x0, bb_opts);
+    std::thread::sleep(2 * time_limit);
+    assert!(cg.minimize().unwrap().fmin < 1e-10);
+    assert!(bb.minimize().unwrap().fmin < 1e-10);
+}
+//}}}
+//{{{ test: test_constrained_cancel
+#[test]
+fn test_constrained_cancel() {
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[-2.0, 1.0]);
+    let token = CancelToken::new();
+    let problem = ImpatientCircle {
+        token: token.clone(),
+        cancel_at: 20,
+        num_evals: Arc::new(AtomicUsize::new(0)),
+    };
+
+    // the token reaches the subsolver, which stops in the middle of the first subproblem
+    let opts = PenaltyOptions {
+        cancel: Some(token.clone()),
+        ..PenaltyOptions::default()
+    };
+    let subsolver = ConjugateGradientSubsolver::new(options(None, None));
+    let err = PenaltyMethod::new(problem.clone(), x0, subsolver, opts).minimize().unwrap_err();
+    assert!(matches!(err, ConstrainedError::Cancelled { .. }));
+    assert_eq!(err.partial().num_iterations, 1);
+    assert_eq!(problem.num_evals.load(Ordering::Relaxed), 21);
+
+    // the methods without a subsolver check the token themselves
+    let opts = SqpOptions {
+        cancel: Some(token.clone()),
+        ..SqpOptions::default()
+    };
+    let err = Sqp::new(problem.clone(), x0, Bounds::unbounded(2), opts).minimize().unwrap_err();
+    assert!(matches!(err, ConstrainedError::Cancelled { .. }));
+    assert_eq!(err.partial().num_iterations, 0);
+    let opts = InteriorPointOptions {
+        time_limit: Some(Duration::ZERO),
+        ..InteriorPointOptions::default()
+    };
+    let err = InteriorPoint::new(problem, x0, opts).minimize().unwrap_err();
+    assert!(matches!(err, ConstrainedError::TimeLimit { time_limit, .. } if time_limit.is_zero()));
+    assert_eq!(err.partial().num_iterations, 0);
+
+    // a reset token and a generous limit do not interfere
+    token.reset();
+    let problem = ImpatientCircle {
+        token: token.clone(),
+        cancel_at: 0,
+        num_evals: Arc::new(AtomicUsize::new(0)),
+    };
+    let opts = SqpOptions {
+        cancel: Some(token),
+        time_limit: Some(Duration::from_secs(3600)),
+        ..SqpOptions::default()
+    };
+    let ret = Sqp::new(problem, x0, Bounds::unbounded(2), opts).minimize().unwrap();
+    assert!((ret.fmin + 2.0).abs() < 1e-6);
+}
+//}}}
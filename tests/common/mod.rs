@@ -0,0 +1,226 @@
+//! Constrained test problems shared by the SQP and interior point tests.
+//!
+//! Problems with simple bounds can pose them as inequalities, for methods that take no separate
+//! bounds.
+//--------------------------------------------------------------------------------------------------
+#![allow(dead_code)]
+
+//{{{ crate imports
+use topohedral_optimize::constrained::ConstrainedProblem;
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use topohedral_linalg::scvector::SCVector;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+pub type Vec2 = SCVector<f64, 2>;
+pub type Vec3 = SCVector<f64, 3>;
+pub type Vec4 = SCVector<f64, 4>;
+
+//{{{ struct: Hs071
+/// Hock-Schittkowski problem 71, `x₀x₃(x₀ + x₁ + x₂) + x₂` subject to `x₀x₁x₂x₃ >= 25`,
+/// `‖x‖² = 40` and `1 <= x <= 5`.
+#[derive(Debug, Clone)]
+pub struct Hs071 {
+    /// Whether the bounds `1 <= x <= 5` are posed as inequalities after `x₀x₁x₂x₃ >= 25`.
+    pub bound_ineqs: bool,
+}
+//}}}
+//{{{ impl: Hs071
+impl Hs071 {
+    /// The problem without its bounds, which the caller supplies separately.
+    pub fn new() -> Self {
+        Self { bound_ineqs: false }
+    }
+
+    /// The problem with its bounds posed as inequalities.
+    pub fn with_bound_ineqs() -> Self {
+        Self { bound_ineqs: true }
+    }
+}
+//}}}
+//{{{ impl: ConstrainedProblem for Hs071
+impl ConstrainedProblem for Hs071 {
+    type Vector = Vec4;
+
+    fn num_eq(&self) -> usize {
+        1
+    }
+
+    fn num_ineq(&self) -> usize {
+        if self.bound_ineqs { 9 } else { 1 }
+    }
+
+    fn eval(&mut self, x: &Vec4) -> f64 {
+        x[0] * x[3] * (x[0] + x[1] + x[2]) + x[2]
+    }
+
+    fn grad(&mut self, x: &Vec4) -> Vec4 {
+        Vec4::from_col_slice(&[
+            x[3] * (2.0 * x[0] + x[1] + x[2]),
+            x[0] * x[3],
+            x[0] * x[3] + 1.0,
+            x[0] * (x[0] + x[1] + x[2]),
+        ])
+    }
+
+    fn eq(&mut self, x: &Vec4) -> Vec<f64> {
+        vec![(0..4).map(|i| x[i] * x[i]).sum::<f64>() - 40.0]
+    }
+
+    fn ineq(&mut self, x: &Vec4) -> Vec<f64> {
+        let mut c = vec![x[0] * x[1] * x[2] * x[3] - 25.0];
+        if self.bound_ineqs {
+            c.extend((0..4).map(|i| x[i] - 1.0));
+            c.extend((0..4).map(|i| 5.0 - x[i]));
+        }
+        c
+    }
+
+    fn eq_jac_tr(&mut self, x: &Vec4, lambda: &[f64]) -> Vec4 {
+        Vec4::from_col_slice(&[
+            2.0 * x[0] * lambda[0],
+            2.0 * x[1] * lambda[0],
+            2.0 * x[2] * lambda[0],
+            2.0 * x[3] * lambda[0],
+        ])
+    }
+
+    fn ineq_jac_tr(&mut self, x: &Vec4, mu: &[f64]) -> Vec4 {
+        let mut g = Vec4::from_col_slice(&[
+            x[1] * x[2] * x[3] * mu[0],
+            x[0] * x[2] * x[3] * mu[0],
+            x[0] * x[1] * x[3] * mu[0],
+            x[0] * x[1] * x[2] * mu[0],
+        ]);
+        if self.bound_ineqs {
+            for i in 0..4 {
+                g[i] += mu[1 + i] - mu[5 + i];
+            }
+        }
+        g
+    }
+}
+//}}}
+//{{{ struct: Hs035
+/// Hock-Schittkowski problem 35, a convex quadratic subject to `x₀ + x₁ + 2x₂ <= 3` and `x >= 0`.
+#[derive(Debug, Clone)]
+pub struct Hs035 {
+    /// Whether the bounds `x >= 0` are posed as inequalities after `x₀ + x₁ + 2x₂ <= 3`.
+    pub bound_ineqs: bool,
+}
+//}}}
+//{{{ impl: Hs035
+impl Hs035 {
+    /// The problem without its bounds, which the caller supplies separately.
+    pub fn new() -> Self {
+        Self { bound_ineqs: false }
+    }
+
+    /// The problem with its bounds posed as inequalities.
+    pub fn with_bound_ineqs() -> Self {
+        Self { bound_ineqs: true }
+    }
+}
+//}}}
+//{{{ impl: ConstrainedProblem for Hs035
+impl ConstrainedProblem for Hs035 {
+    type Vector = Vec3;
+
+    fn num_eq(&self) -> usize {
+        0
+    }
+
+    fn num_ineq(&self) -> usize {
+        if self.bound_ineqs { 4 } else { 1 }
+    }
+
+    fn eval(&mut self, x: &Vec3) -> f64 {
+        9.0 - 8.0 * x[0] - 6.0 * x[1] - 4.0 * x[2]
+            + 2.0 * x[0] * x[0]
+            + 2.0 * x[1] * x[1]
+            + x[2] * x[2]
+            + 2.0 * x[0] * x[1]
+            + 2.0 * x[0] * x[2]
+    }
+
+    fn grad(&mut self, x: &Vec3) -> Vec3 {
+        Vec3::from_col_slice(&[
+            -8.0 + 4.0 * x[0] + 2.0 * x[1] + 2.0 * x[2],
+            -6.0 + 4.0 * x[1] + 2.0 * x[0],
+            -4.0 + 2.0 * x[2] + 2.0 * x[0],
+        ])
+    }
+
+    fn eq(&mut self, _x: &Vec3) -> Vec<f64> {
+        vec![]
+    }
+
+    fn ineq(&mut self, x: &Vec3) -> Vec<f64> {
+        let mut c = vec![3.0 - x[0] - x[1] - 2.0 * x[2]];
+        if self.bound_ineqs {
+            c.extend([x[0], x[1], x[2]]);
+        }
+        c
+    }
+
+    fn eq_jac_tr(&mut self, _x: &Vec3, _lambda: &[f64]) -> Vec3 {
+        Vec3::zeros()
+    }
+
+    fn ineq_jac_tr(&mut self, _x: &Vec3, mu: &[f64]) -> Vec3 {
+        let mut g = Vec3::from_col_slice(&[-mu[0], -mu[0], -2.0 * mu[0]]);
+        if self.bound_ineqs {
+            for i in 0..3 {
+                g[i] += mu[1 + i];
+            }
+        }
+        g
+    }
+}
+//}}}
+//{{{ struct: Parabola
+/// `(x₀ − 2)² + x₁²` subject to `x₀² = 1`, whose Jacobian vanishes at `x₀ = 0` so that the
+/// linearization there is inconsistent.
+#[derive(Debug, Clone)]
+pub struct Parabola;
+//}}}
+//{{{ impl: ConstrainedProblem for Parabola
+impl ConstrainedProblem for Parabola {
+    type Vector = Vec2;
+
+    fn num_eq(&self) -> usize {
+        1
+    }
+
+    fn num_ineq(&self) -> usize {
+        0
+    }
+
+    fn eval(&mut self, x: &Vec2) -> f64 {
+        (x[0] - 2.0).powi(2) + x[1] * x[1]
+    }
+
+    fn grad(&mut self, x: &Vec2) -> Vec2 {
+        Vec2::from_col_slice(&[2.0 * (x[0] - 2.0), 2.0 * x[1]])
+    }
+
+    fn eq(&mut self, x: &Vec2) -> Vec<f64> {
+        vec![x[0] * x[0] - 1.0]
+    }
+
+    fn ineq(&mut self, _x: &Vec2) -> Vec<f64> {
+        vec![]
+    }
+
+    fn eq_jac_tr(&mut self, x: &Vec2, lambda: &[f64]) -> Vec2 {
+        Vec2::from_col_slice(&[2.0 * x[0] * lambda[0], 0.0])
+    }
+
+    fn ineq_jac_tr(&mut self, _x: &Vec2, _mu: &[f64]) -> Vec2 {
+        Vec2::zeros()
+    }
+}
+//}}}
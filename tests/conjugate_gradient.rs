@@ -3,11 +3,15 @@
 #![feature(impl_trait_in_assoc_type)]
 
 //{{{ crate imports
-use topohedral_optimize::{RealFn};
+use topohedral_optimize::{CachePolicy, RealFn};
 use topohedral_optimize::line_search::{InterpOptions, LineSearchOptions, LineSearchMethod};
 use topohedral_optimize::unconstrained::{UnconstrainedMinimizer, UnonstrainedOptions, ConjugateGradient, ConjugateGradientOptions, Direction};
+use topohedral_optimize::unconstrained::{ConvergedReason, History, IterationState, ObserverAction, UnconstrainedError};
+use topohedral_optimize::unconstrained::{ConjugateGradientState, Step};
 //}}}
 //{{{ std imports
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 //}}}
 //{{{ dep imports
 use ctor::ctor;
@@ -87,7 +91,8 @@ fn test_quadratic() {
                 step2: 1.0,
                 scale_factor: 1.5, 
                 maxiter: 10
-            })
+            }),
+            ..UnonstrainedOptions::default()
         }, 
         direction: Direction::Steepest, 
         restart: 10,
@@ -98,6 +103,436 @@ fn test_quadratic() {
     print!("{ret:?}")
 
 
+}
+//}}}
+//{{{ struct: CombinedQuadratic
+/// Quadratic which counts how often each evaluation entry point is used.
+#[derive(Debug, Clone)]
+struct CombinedQuadratic
+{
+    xmin: SCVector<f64, 5>,
+    num_eval: Arc<AtomicUsize>,
+    num_grad: Arc<AtomicUsize>,
+    num_combined: Arc<AtomicUsize>,
+}
+//}}}
+//{{{ impl: RealFn for CombinedQuadratic
+impl RealFn for CombinedQuadratic {
+
+    type Vector = SCVector<f64, 5>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        self.num_eval.fetch_add(1, Ordering::Relaxed);
+        let tmp: Self::Vector = (x - &self.xmin).into();
+        tmp.dot(&tmp)
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        self.num_grad.fetch_add(1, Ordering::Relaxed);
+        let tmp: Self::Vector = (x - &self.xmin).into();
+        2.0 * tmp
+    }
+
+    fn eval_with_grad(&mut self, x: &Self::Vector) -> (f64, Self::Vector) {
+        self.num_combined.fetch_add(1, Ordering::Relaxed);
+        let tmp: Self::Vector = (x - &self.xmin).into();
+        (tmp.dot(&tmp), 2.0 * tmp)
+    }
+}
+//}}}
+//{{{ test: test_eval_with_grad
+#[test]
+fn test_eval_with_grad() {
+
+    let xmin = [10.0, -1.0, 0.0 , 5.0, -2.0];
+    let quad = CombinedQuadratic{
+        xmin: SCVector::<f64, 5>::from_col_slice(&xmin),
+        num_eval: Arc::new(AtomicUsize::new(0)),
+        num_grad: Arc::new(AtomicUsize::new(0)),
+        num_combined: Arc::new(AtomicUsize::new(0)),
+    };
+    let (num_eval, num_grad, num_combined) =
+        (quad.num_eval.clone(), quad.num_grad.clone(), quad.num_combined.clone());
+
+    let x0 = SCVector::<f64, 5>::zeros();
+
+    let mut cg = ConjugateGradient::new(quad, x0, ConjugateGradientOptions{
+        uncon_opts: UnonstrainedOptions{
+            grad_rtol: 1e-6, 
+            grad_atol: 1e-8,
+            max_iter: 100,
+            ls_method: LineSearchMethod::Interp(InterpOptions{
+                ls_opts: LineSearchOptions::default(),
+                step1: 0.5, 
+                step2: 1.0,
+                scale_factor: 1.5, 
+                maxiter: 10
+            }),
+            cache_size: 0,
+            ..UnonstrainedOptions::default()
+        }, 
+        direction: Direction::FletcherReeves, 
+        restart: 10,
+    });
+
+    let ret = cg.minimize().unwrap();
+
+    // the start point and each accepted iterate cost exactly one combined call, so do the trial
+    // steps rejected by the curvature condition
+    let num_combined = num_combined.load(Ordering::Relaxed);
+    assert_eq!(num_combined, ret.num_iterations + ret.line_search.num_rejected + 1);
+    assert_eq!(num_grad.load(Ordering::Relaxed), 0);
+    assert_eq!(ret.num_fun_evals, num_eval.load(Ordering::Relaxed) + num_combined);
+    assert_eq!(ret.num_grad_evals, num_combined);
+    for (i, xi) in xmin.iter().enumerate() {
+        assert_relative_eq!(ret.xmin[i], *xi, epsilon = 1e-4);
+    }
+}
+//}}}
+//{{{ test: test_evaluation_cache
+#[test]
+fn test_evaluation_cache() {
+
+    let xmin = [10.0, -1.0, 0.0 , 5.0, -2.0];
+    let run = |cache_size: usize| {
+        let quad = CombinedQuadratic{
+            xmin: SCVector::<f64, 5>::from_col_slice(&xmin),
+            num_eval: Arc::new(AtomicUsize::new(0)),
+            num_grad: Arc::new(AtomicUsize::new(0)),
+            num_combined: Arc::new(AtomicUsize::new(0)),
+        };
+        let mut cg = ConjugateGradient::new(quad, SCVector::<f64, 5>::zeros(), ConjugateGradientOptions{
+            uncon_opts: UnonstrainedOptions{
+                max_iter: 100,
+                cache_size,
+                cache_policy: CachePolicy::Exact,
+                ..UnonstrainedOptions::default()
+            }, 
+            direction: Direction::FletcherReeves, 
+            restart: 10,
+        });
+        cg.minimize().unwrap()
+    };
+
+    let uncached = run(0);
+    let cached = run(4);
+
+    // an exact cache returns identical values, so the iterates are unchanged
+    assert_eq!(uncached.num_cache_hits, 0);
+    assert_eq!(cached.num_iterations, uncached.num_iterations);
+    assert_eq!(cached.fmin, uncached.fmin);
+    // coinciding interpolation candidates are only evaluated once
+    assert!(cached.num_cache_hits > 0);
+    assert!(cached.num_fun_evals + cached.num_grad_evals < uncached.num_fun_evals + uncached.num_grad_evals);
+    assert_eq!(
+        cached.num_fun_evals + cached.num_grad_evals + cached.num_cache_hits,
+        uncached.num_fun_evals + uncached.num_grad_evals
+    );
+}
+//}}}
+//{{{ struct: ScaledQuadratic
+/// Quadratic with a diagonal Hessian of `2 * diag(1, ..., 5)`.
+#[derive(Debug, Clone, Copy)]
+struct ScaledQuadratic
+{
+    xmin: SCVector<f64, 5>,
+}
+//}}}
+//{{{ impl: RealFn for ScaledQuadratic
+impl RealFn for ScaledQuadratic {
+
+    type Vector = SCVector<f64, 5>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        (0..5).map(|i| (i + 1) as f64 * (x[i] - self.xmin[i]).powi(2)).sum()
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        let mut out = Self::Vector::zeros();
+        for i in 0..5 {
+            out[i] = 2.0 * (i + 1) as f64 * (x[i] - self.xmin[i]);
+        }
+        out
+    }
+}
+//}}}
+//{{{ test: test_observer
+#[test]
+fn test_observer() {
+
+    let quad = ScaledQuadratic{
+        xmin: SCVector::<f64, 5>::from_col_slice(&[1000.0, -100.0, 0.0 , 567.0, -23.0])
+    };
+    let x0 = SCVector::<f64, 5>::zeros();
+    let opts = ConjugateGradientOptions{
+        uncon_opts: UnonstrainedOptions{
+            max_iter: 100,
+            ..UnonstrainedOptions::default()
+        }, 
+        direction: Direction::FletcherReeves, 
+        restart: 2,
+    };
+
+    // an observer which never stops sees every iteration
+    let states: Arc<Mutex<Vec<IterationState<SCVector<f64, 5>>>>> = Arc::new(Mutex::new(Vec::new()));
+    let states_obs = states.clone();
+    let mut cg = ConjugateGradient::new(quad, x0, opts.clone());
+    cg.set_observer(Box::new(move |state: &IterationState<SCVector<f64, 5>>| {
+        states_obs.lock().unwrap().push(state.clone());
+        ObserverAction::Continue
+    }));
+    let ret = cg.minimize().unwrap();
+    let states = states.lock().unwrap();
+    assert_eq!(states.len(), ret.num_iterations);
+    for (i, state) in states.iter().enumerate() {
+        assert_eq!(state.iteration, i + 1);
+        assert!(state.alpha > 0.0);
+        if state.restarted || i == 0 {
+            assert_eq!(state.direction, Some(Direction::Steepest));
+        } else {
+            assert_eq!(state.direction, Some(Direction::FletcherReeves));
+        }
+    }
+    let last = states.last().unwrap();
+    assert_eq!(last.f, ret.fmin);
+
+    // an observer can end the minimization early
+    let mut cg = ConjugateGradient::new(quad, x0, opts);
+    cg.set_observer(Box::new(|state: &IterationState<SCVector<f64, 5>>| {
+        if state.iteration == 2 { ObserverAction::Stop } else { ObserverAction::Continue }
+    }));
+    let ret = cg.minimize().unwrap();
+    assert!(matches!(ret.reason, ConvergedReason::ObserverStop));
+    assert_eq!(ret.num_iterations, 2);
+}
+//}}}
+//{{{ test: test_step
+#[test]
+fn test_step() {
+
+    let quad = ScaledQuadratic{
+        xmin: SCVector::<f64, 5>::from_col_slice(&[1000.0, -100.0, 0.0 , 567.0, -23.0])
+    };
+    let x0 = SCVector::<f64, 5>::zeros();
+    let opts = ConjugateGradientOptions{
+        uncon_opts: UnonstrainedOptions{
+            max_iter: 100,
+            ..UnonstrainedOptions::default()
+        }, 
+        direction: Direction::FletcherReeves, 
+        restart: 10,
+    };
+    let full = ConjugateGradient::new(quad, x0, opts.clone()).minimize().unwrap();
+
+    // stepping through the run gives the same iterates as minimize
+    let mut cg = ConjugateGradient::new(quad, x0, opts.clone());
+    let mut num_steps = 0;
+    let ret = loop {
+        match cg.step() {
+            Step::Iteration(state) => {
+                num_steps += 1;
+                assert_eq!(state.iteration, num_steps);
+            }
+            Step::Done(result) => break result.unwrap(),
+        }
+    };
+    assert_eq!(num_steps, full.num_iterations);
+    assert_eq!(ret.fmin, full.fmin);
+    assert_eq!(ret.num_fun_evals, full.num_fun_evals);
+    // further steps repeat the result
+    assert!(matches!(cg.step(), Step::Done(Ok(ret)) if ret.fmin == full.fmin));
+
+    // the iterator stops when the run is done, minimize then gives the result
+    let mut cg = ConjugateGradient::new(quad, x0, opts.clone());
+    assert_eq!(cg.iterations().count(), full.num_iterations);
+    assert_eq!(cg.minimize().unwrap().fmin, full.fmin);
+
+    // options changed between steps apply from the next iteration
+    let mut cg = ConjugateGradient::new(quad, x0, opts);
+    let states: Vec<_> = cg.iterations().take(2).collect();
+    assert_eq!(states[1].direction, Some(Direction::FletcherReeves));
+    cg.options_mut().restart = 1;
+    let states: Vec<_> = cg.iterations().collect();
+    assert!(!states.is_empty());
+    for state in states {
+        assert!(state.restarted);
+        assert_eq!(state.direction, Some(Direction::Steepest));
+    }
+}
+//}}}
+//{{{ test: test_history
+#[test]
+fn test_history() {
+
+    let quad = ScaledQuadratic{
+        xmin: SCVector::<f64, 5>::from_col_slice(&[1.0, -2.0, 3.0 , -4.0, 5.0])
+    };
+    let x0 = SCVector::<f64, 5>::zeros();
+    let opts = ConjugateGradientOptions{
+        uncon_opts: UnonstrainedOptions{
+            max_iter: 100,
+            record_history: true,
+            record_x: true,
+            ..UnonstrainedOptions::default()
+        }, 
+        direction: Direction::PolakRibiere, 
+        restart: 10,
+    };
+
+    let ret = ConjugateGradient::new(quad, x0, opts.clone()).minimize().unwrap();
+    let history = ret.history.clone().unwrap();
+
+    // the starting point is iteration 0
+    assert_eq!(history.len(), ret.num_iterations + 1);
+    assert_eq!(history.records[0].iteration, 0);
+    assert_eq!(history.records[0].beta, None);
+    assert_eq!(history.records[0].x, Some(vec![0.0; 5]));
+    for pair in history.records.windows(2) {
+        assert_eq!(pair[1].iteration, pair[0].iteration + 1);
+        assert!(pair[1].f <= pair[0].f);
+        assert!(pair[1].alpha > 0.0);
+        assert!(pair[1].num_fun_evals >= pair[0].num_fun_evals);
+        assert!(pair[1].num_grad_evals >= pair[0].num_grad_evals);
+    }
+    let last = history.last().unwrap();
+    assert_eq!(last.f, ret.fmin);
+    assert_eq!(last.num_fun_evals, ret.num_fun_evals);
+    assert_eq!(last.num_grad_evals, ret.num_grad_evals);
+    for (i, xi) in last.x.as_ref().unwrap().iter().enumerate() {
+        assert_eq!(*xi, ret.xmin[i]);
+    }
+
+    // round trip through JSON
+    let json = history.to_json().unwrap();
+    let parsed = History::from_json(&json).unwrap();
+    assert_eq!(parsed.len(), history.len());
+    for (a, b) in parsed.records.iter().zip(history.records.iter()) {
+        assert_eq!(a.iteration, b.iteration);
+        assert_eq!(a.num_fun_evals, b.num_fun_evals);
+        assert_relative_eq!(a.f, b.f, max_relative = 1e-14);
+        assert_relative_eq!(a.grad_norm, b.grad_norm, max_relative = 1e-14);
+        assert_relative_eq!(a.alpha, b.alpha, max_relative = 1e-14);
+    }
+
+    // no history unless asked for
+    let opts = ConjugateGradientOptions{
+        uncon_opts: UnonstrainedOptions::default(),
+        ..opts
+    };
+    let ret = ConjugateGradient::new(quad, x0, opts).minimize().unwrap();
+    assert!(ret.history.is_none());
+}
+//}}}
+//{{{ struct: WrongGradient
+/// `‖x‖²` with the sign of its gradient flipped, so that no descent step can be found.
+#[derive(Debug, Clone, Copy)]
+struct WrongGradient;
+//}}}
+//{{{ impl: RealFn for WrongGradient
+impl RealFn for WrongGradient {
+
+    type Vector = SCVector<f64, 5>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        x.dot(x)
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        -2.0 * *x
+    }
+}
+//}}}
+//{{{ test: test_partial_results
+#[test]
+fn test_partial_results() {
+
+    let quad = ScaledQuadratic{
+        xmin: SCVector::<f64, 5>::from_col_slice(&[1.0, -2.0, 3.0 , -4.0, 5.0])
+    };
+    let x0 = SCVector::<f64, 5>::zeros();
+    let f0 = ScaledQuadratic{ xmin: quad.xmin }.eval(&x0);
+    let opts = ConjugateGradientOptions{
+        uncon_opts: UnonstrainedOptions{
+            max_iter: 3,
+            record_history: true,
+            ..UnonstrainedOptions::default()
+        }, 
+        direction: Direction::PolakRibiere, 
+        restart: 10,
+    };
+
+    // running out of iterations keeps the last iterate
+    let err = ConjugateGradient::new(quad, x0, opts.clone()).minimize().unwrap_err();
+    assert!(matches!(err, UnconstrainedError::MaxIterations{ max_iter: 3, .. }));
+    let partial = err.partial();
+    assert!(matches!(partial.reason, ConvergedReason::NotConverged));
+    assert_eq!(partial.num_iterations, 2);
+    assert!(partial.fmin < f0);
+    assert!(partial.grad_norm > 0.0);
+    assert!(partial.num_fun_evals > 0);
+    assert_eq!(partial.history.as_ref().unwrap().len(), 3);
+    let partial = err.into_partial();
+    assert_eq!(partial.history.unwrap().last().unwrap().f, partial.fmin);
+
+    // a failed line search keeps the starting point
+    let x0 = SCVector::<f64, 5>::ones();
+    let err = ConjugateGradient::new(WrongGradient, x0, opts).minimize().unwrap_err();
+    assert!(matches!(err, UnconstrainedError::LineSearch{ .. }));
+    let partial = err.partial();
+    assert_eq!(partial.num_iterations, 0);
+    assert_eq!(partial.fmin, 5.0);
+    assert_relative_eq!(partial.grad_norm, 2.0 * 5.0_f64.sqrt());
+}
+//}}}
+//{{{ test: test_resume_from
+#[test]
+fn test_resume_from() {
+
+    let quad = ScaledQuadratic{
+        xmin: SCVector::<f64, 5>::from_col_slice(&[1.0, -2.0, 3.0 , -4.0, 5.0])
+    };
+    let x0 = SCVector::<f64, 5>::zeros();
+    let opts = ConjugateGradientOptions{
+        uncon_opts: UnonstrainedOptions{
+            grad_rtol: 1e-12,
+            grad_atol: 1e-12,
+            max_iter: 200,
+            record_history: true,
+            ..UnonstrainedOptions::default()
+        }, 
+        direction: Direction::PolakRibiere, 
+        restart: 3,
+    };
+
+    let full = ConjugateGradient::new(quad, x0, opts.clone()).minimize().unwrap();
+    assert!(full.num_iterations > 5);
+
+    // interrupt after four iterations and checkpoint through JSON, options included
+    let mut cg = ConjugateGradient::new(quad, x0, opts.clone());
+    assert!(cg.state().is_none());
+    cg.set_observer(Box::new(|state: &IterationState<SCVector<f64, 5>>| {
+        if state.iteration == 4 { ObserverAction::Stop } else { ObserverAction::Continue }
+    }));
+    cg.minimize().unwrap();
+    let state = cg.state().unwrap();
+    assert_eq!(state.iteration, 4);
+    assert!(!state.cache.is_empty());
+    let state_json = serde_json::to_string(&state).unwrap();
+    let opts_json = serde_json::to_string(&opts).unwrap();
+
+    let state: ConjugateGradientState = serde_json::from_str(&state_json).unwrap();
+    let opts: ConjugateGradientOptions = serde_json::from_str(&opts_json).unwrap();
+    let resumed = ConjugateGradient::resume_from(quad, state, opts).minimize().unwrap();
+
+    assert_eq!(resumed.num_iterations, full.num_iterations);
+    assert_eq!(resumed.fmin.to_bits(), full.fmin.to_bits());
+    for i in 0..5 {
+        assert_eq!(resumed.xmin[i].to_bits(), full.xmin[i].to_bits());
+    }
+    assert_eq!(resumed.num_fun_evals, full.num_fun_evals);
+    assert_eq!(resumed.num_grad_evals, full.num_grad_evals);
+    assert_eq!(resumed.num_cache_hits, full.num_cache_hits);
+    assert_eq!(resumed.history, full.history);
 }
 //}}}
 //{{{ struct: Quartic 
@@ -154,7 +589,8 @@ fn test_quartic() {
                 step2: 1.0 ,
                 scale_factor: 1.5, 
                 maxiter: 10
-            })
+            }),
+            ..UnonstrainedOptions::default()
         }, 
         direction: Direction::FletcherReeves, 
         restart: 100,
@@ -223,7 +659,8 @@ fn test_rosenbrock() {
                 step2: 1.0 ,
                 scale_factor: 1.5, 
                 maxiter: 100
-            })
+            }),
+            ..UnonstrainedOptions::default()
         }, 
         direction: Direction::FletcherReeves, 
         restart: 100,
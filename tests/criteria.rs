@@ -0,0 +1,178 @@
+//! Tests of the composable stopping criteria shared by the unconstrained minimizers.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::unconstrained::{
+    ConjugateGradient, ConjugateGradientOptions, ConvergedReason, ConvergenceState, Criterion,
+    Direction, UnconstrainedMinimizer, UnonstrainedOptions,
+};
+use topohedral_optimize::RealFn;
+//}}}
+//{{{ std imports
+use std::time::Duration;
+//}}}
+//{{{ dep imports
+use topohedral_linalg::scvector::SCVector;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Ellipse
+/// Quadratic with a diagonal Hessian of `2 * diag(1, 10)`.
+#[derive(Debug, Clone)]
+struct Ellipse;
+//}}}
+//{{{ impl: RealFn for Ellipse
+impl RealFn for Ellipse {
+    type Vector = SCVector<f64, 2>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        (x[0] - 1.0).powi(2) + 10.0 * (x[1] + 2.0).powi(2)
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        SCVector::<f64, 2>::from_col_slice(&[2.0 * (x[0] - 1.0), 20.0 * (x[1] + 2.0)])
+    }
+}
+//}}}
+//{{{ fun: state
+fn state() -> ConvergenceState {
+    ConvergenceState {
+        iteration: 5,
+        f: 1.0,
+        f_prev: 1.0 + 1e-9,
+        grad_norm: 1e-3,
+        grad_norm_init: 1.0,
+        grad_inf_norm: 5e-4,
+        step_norm: 1e-7,
+        x_norm: 10.0,
+        num_fun_evals: 20,
+        elapsed: Duration::from_millis(10),
+    }
+}
+//}}}
+//{{{ fun: options
+fn options(criteria: Criterion) -> ConjugateGradientOptions {
+    ConjugateGradientOptions {
+        uncon_opts: UnonstrainedOptions {
+            grad_rtol: 0.0,
+            grad_atol: 0.0,
+            max_iter: 500,
+            criteria: Some(criteria),
+            ..UnonstrainedOptions::default()
+        },
+        direction: Direction::PolakRibiere,
+        restart: 10,
+    }
+}
+//}}}
+//{{{ test: test_leaf_criteria
+#[test]
+fn test_leaf_criteria() {
+    let state = state();
+    let met = |c: Criterion| c.check(&state);
+
+    assert!(matches!(met(Criterion::GradRtol(1e-2)), Some(ConvergedReason::Rtol)));
+    assert!(met(Criterion::GradRtol(1e-4)).is_none());
+    assert!(matches!(met(Criterion::GradAtol(1e-2)), Some(ConvergedReason::Atol)));
+    assert!(matches!(met(Criterion::GradInfNorm(1e-3)), Some(ConvergedReason::GradInfNorm)));
+    assert!(met(Criterion::GradInfNorm(1e-4)).is_none());
+    assert!(matches!(met(Criterion::FAtol(1e-8)), Some(ConvergedReason::FAtol)));
+    assert!(met(Criterion::FAtol(1e-10)).is_none());
+    assert!(matches!(met(Criterion::FRtol(1e-8)), Some(ConvergedReason::FRtol)));
+    assert!(matches!(met(Criterion::XAtol(1e-6)), Some(ConvergedReason::XAtol)));
+    assert!(met(Criterion::XAtol(1e-8)).is_none());
+    // relative to ‖x‖ = 10
+    assert!(matches!(met(Criterion::XRtol(1e-8)), Some(ConvergedReason::XRtol)));
+    assert!(matches!(met(Criterion::TargetF(2.0)), Some(ConvergedReason::TargetF)));
+    assert!(met(Criterion::TargetF(0.5)).is_none());
+    assert!(matches!(met(Criterion::MaxFunEvals(20)), Some(ConvergedReason::MaxFunEvals)));
+    assert!(met(Criterion::MaxFunEvals(21)).is_none());
+    assert!(matches!(
+        met(Criterion::TimeLimit(Duration::from_millis(5))),
+        Some(ConvergedReason::TimeLimit)
+    ));
+    assert!(met(Criterion::TimeLimit(Duration::from_secs(1))).is_none());
+}
+//}}}
+//{{{ test: test_composite_criteria
+#[test]
+fn test_composite_criteria() {
+    let state = state();
+
+    // any reports the first member which is met
+    let any = Criterion::TargetF(0.5)
+        .or(Criterion::XAtol(1e-6))
+        .or(Criterion::FAtol(1e-8));
+    assert_eq!(
+        any,
+        Criterion::Any(vec![
+            Criterion::TargetF(0.5),
+            Criterion::XAtol(1e-6),
+            Criterion::FAtol(1e-8)
+        ])
+    );
+    assert!(matches!(any.check(&state), Some(ConvergedReason::XAtol)));
+
+    // all needs every member and reports the first
+    let all = Criterion::FRtol(1e-8).and(Criterion::XAtol(1e-6));
+    assert!(matches!(all.check(&state), Some(ConvergedReason::FRtol)));
+    let all = all.and(Criterion::TargetF(0.5));
+    assert!(all.check(&state).is_none());
+    assert!(Criterion::All(vec![]).check(&state).is_none());
+    assert!(Criterion::Any(vec![]).check(&state).is_none());
+
+    // nesting
+    let nested = Criterion::Any(vec![all, Criterion::GradInfNorm(1e-3)]);
+    assert!(matches!(nested.check(&state), Some(ConvergedReason::GradInfNorm)));
+}
+//}}}
+//{{{ test: test_conjugate_gradient_criteria
+#[test]
+fn test_conjugate_gradient_criteria() {
+    let x0 = SCVector::<f64, 2>::zeros();
+
+    // stops as soon as the target is reached
+    let ret = ConjugateGradient::new(Ellipse, x0, options(Criterion::TargetF(1e-2)))
+        .minimize()
+        .unwrap();
+    assert!(matches!(ret.reason, ConvergedReason::TargetF));
+    assert!(ret.fmin <= 1e-2);
+
+    // stops on the evaluation budget
+    let ret = ConjugateGradient::new(Ellipse, x0, options(Criterion::MaxFunEvals(6)))
+        .minimize()
+        .unwrap();
+    assert!(matches!(ret.reason, ConvergedReason::MaxFunEvals));
+    assert!(ret.num_fun_evals >= 6);
+
+    // a small step and a small f change together
+    let criteria = Criterion::XAtol(1e-8).and(Criterion::FAtol(1e-12));
+    let ret = ConjugateGradient::new(Ellipse, x0, options(criteria))
+        .minimize()
+        .unwrap();
+    assert!(matches!(ret.reason, ConvergedReason::XAtol));
+    assert!(ret.fmin < 1e-12);
+
+    // the gradient tolerances still apply alongside the criteria
+    let mut opts = options(Criterion::TargetF(-1.0));
+    opts.uncon_opts.grad_atol = 1e-6;
+    let ret = ConjugateGradient::new(Ellipse, x0, opts).minimize().unwrap();
+    assert!(matches!(ret.reason, ConvergedReason::Atol));
+
+    // with the tolerances off, a gradient criterion composed with `and` waits for its partner
+    let criteria = Criterion::GradAtol(1e3).and(Criterion::MaxFunEvals(6));
+    let ret = ConjugateGradient::new(Ellipse, x0, options(criteria.clone()))
+        .minimize()
+        .unwrap();
+    assert!(matches!(ret.reason, ConvergedReason::Atol));
+    assert!(ret.num_fun_evals >= 6);
+    let mut opts = options(criteria);
+    opts.uncon_opts.grad_atol = 1e3;
+    let ret = ConjugateGradient::new(Ellipse, x0, opts).minimize().unwrap();
+    assert!(matches!(ret.reason, ConvergedReason::Atol));
+    assert!(ret.num_fun_evals < 6);
+}
+//}}}
@@ -7,7 +7,7 @@
 #![feature(impl_trait_in_assoc_type)]
 
 //{{{ crate imports
-use topohedral_optimize::{line_search::LineSearchFcn, RealFn, RealFn1};
+use topohedral_optimize::{line_search::LineSearchFcn, CachePolicy, CachingRealFn, RealFn, RealFn1};
 //}}}
 //{{{ std imports
 use std::{rc::Rc, sync::Mutex};
@@ -143,6 +143,10 @@ fn test_quadratic_static_3d_line_search() {
     assert_relative_eq!(phi2, 27.0, epsilon = 1e-10);
     assert_relative_eq!(dphi2, 16.0 - 2.0*18.0 + 20.0, epsilon = 1e-10);
 
+    let (phi3, dphi3) = line_fcn1.eval_with_diff(0.5);
+    assert_relative_eq!(phi3, line_fcn1.eval(0.5), epsilon = 1e-10);
+    assert_relative_eq!(dphi3, line_fcn1.diff(0.5), epsilon = 1e-10);
+
 }
 //}}}
 //{{{ test: test_quadratic_static_rc_line_search
@@ -208,3 +212,58 @@ fn test_quadratic_static_arc_line_search() {
 //{{{ struct: QuadraticDynamic
 //}}}
 //}}}
+//{{{ test: test_caching_real_fn
+#[test]
+fn test_caching_real_fn() {
+    let quad = QuadraticStatic::<3>::new1();
+    let x1 = SCVector::<f64, 3>::from_col_slice(&[1.0, 2.0, 3.0]);
+    let x2 = SCVector::<f64, 3>::from_col_slice(&[-1.0, 0.5, 2.0]);
+    let x3 = SCVector::<f64, 3>::from_col_slice(&[0.0, 1.0, 0.0]);
+
+    // exact matching with room for two points
+    let mut cached = CachingRealFn::new(quad.clone(), 2, CachePolicy::Exact);
+    let f1 = cached.eval(&x1);
+    assert_eq!(cached.num_hits(), 0);
+    // value is cached, only the gradient is new
+    let (f1_again, g1) = cached.eval_with_grad(&x1);
+    assert_eq!(f1_again, f1);
+    assert_eq!((cached.num_func_hits, cached.num_grad_hits), (1, 0));
+    let g1_again = cached.grad(&x1);
+    for i in 0..3 {
+        assert_eq!(g1_again[i], g1[i]);
+    }
+    assert_eq!((cached.num_func_hits, cached.num_grad_hits), (1, 1));
+    // x1 is evicted once two newer points have been seen
+    cached.eval(&x2);
+    cached.eval(&x3);
+    cached.eval(&x1);
+    assert_eq!(cached.num_func_hits, 1);
+    // a nearby point misses under the exact policy
+    let x1_near = SCVector::<f64, 3>::from_col_slice(&[1.0, 2.0, 3.0 + 1e-12]);
+    cached.eval(&x1_near);
+    assert_eq!(cached.num_func_hits, 1);
+
+    // the tolerance policy treats it as the same point
+    let mut cached = CachingRealFn::new(quad.clone(), 2, CachePolicy::Tolerance(1e-10));
+    cached.eval(&x1);
+    assert_relative_eq!(cached.eval(&x1_near), f1);
+    assert_eq!(cached.num_func_hits, 1);
+
+    // the entries carry over to another cache, within its capacity
+    cached.grad(&x2);
+    let entries = cached.entries();
+    assert_eq!(entries.len(), 2);
+    assert_eq!((entries[0].f, entries[1].grad.as_ref()), (None, None));
+    let mut restored = CachingRealFn::new(quad.clone(), 1, CachePolicy::Exact);
+    restored.restore(&entries);
+    assert_eq!(restored.entries(), entries[..1]);
+    restored.grad(&x2);
+    assert_eq!(restored.num_grad_hits, 1);
+
+    // zero capacity passes every call through
+    let mut cached = CachingRealFn::new(quad, 0, CachePolicy::Exact);
+    cached.eval(&x1);
+    cached.eval(&x1);
+    assert_eq!(cached.num_hits(), 0);
+}
+//}}}
@@ -0,0 +1,181 @@
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+#![feature(impl_trait_in_assoc_type)]
+
+//{{{ crate imports
+use topohedral_optimize::gradient_check::{check_derivative, check_gradient, Options, Scheme};
+use topohedral_optimize::{RealFn, RealFn1};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use approx::assert_relative_eq;
+use topohedral_linalg::scvector::SCVector;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Rosenbrock
+#[derive(Debug, Clone, Copy)]
+struct Rosenbrock {
+    a: f64,
+    b: f64,
+    /// Deliberately corrupts the second gradient component when set.
+    broken: bool,
+}
+//}}}
+//{{{ impl: RealFn for Rosenbrock
+impl RealFn for Rosenbrock {
+    type Vector = SCVector<f64, 2>;
+
+    fn eval(&mut self, xvec: &Self::Vector) -> f64 {
+        let x = xvec[0];
+        let y = xvec[1];
+        (self.a - x).powi(2) + self.b * (y - x.powi(2)).powi(2)
+    }
+
+    fn grad(&mut self, xvec: &Self::Vector) -> Self::Vector {
+        let a = self.a;
+        let b = self.b;
+        let x = xvec[0];
+        let y = xvec[1];
+        let mut out = SCVector::<f64, 2>::zeros();
+        out[0] = -2.0 * (a - x) - 4.0 * b * x * (y - x.powi(2));
+        out[1] = 2.0 * b * (y - x.powi(2));
+        if self.broken {
+            out[1] *= 1.1;
+        }
+        out
+    }
+}
+//}}}
+//{{{ struct: SumOfSquares
+#[derive(Debug, Clone, Copy)]
+struct SumOfSquares {
+    broken: bool,
+}
+//}}}
+//{{{ impl: RealFn for SumOfSquares
+impl RealFn for SumOfSquares {
+    type Vector = SCVector<f64, 6>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        x.iter()
+            .enumerate()
+            .map(|(i, xi)| (i + 1) as f64 * xi * xi)
+            .sum()
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        let mut out = SCVector::<f64, 6>::zeros();
+        for i in 0..6 {
+            out[i] = 2.0 * (i + 1) as f64 * x[i];
+        }
+        if self.broken {
+            out[3] = 0.0;
+        }
+        out
+    }
+}
+//}}}
+//{{{ struct: Sine
+#[derive(Debug, Clone, Copy)]
+struct Sine;
+//}}}
+//{{{ impl: RealFn1 for Sine
+impl RealFn1 for Sine {
+    fn eval(&mut self, x: f64) -> f64 {
+        x.sin()
+    }
+
+    fn diff(&mut self, x: f64) -> f64 {
+        x.cos()
+    }
+}
+//}}}
+//{{{ test: test_correct_gradient_passes
+#[test]
+fn test_correct_gradient_passes() {
+    let mut f = Rosenbrock {
+        a: 1.0,
+        b: 100.0,
+        broken: false,
+    };
+    let x = SCVector::<f64, 2>::from_col_slice(&[-1.2, 1.0]);
+    let report = check_gradient(&mut f, &x, &Options::default());
+    assert!(report.passed);
+    assert_eq!(report.components.len(), 2);
+    assert!(report.directions.is_empty());
+    for comp in report.components.iter() {
+        assert_relative_eq!(comp.analytic, comp.approx, max_relative = 1e-6);
+    }
+}
+//}}}
+//{{{ test: test_wrong_gradient_fails
+#[test]
+fn test_wrong_gradient_fails() {
+    let mut f = Rosenbrock {
+        a: 1.0,
+        b: 100.0,
+        broken: true,
+    };
+    let x = SCVector::<f64, 2>::from_col_slice(&[-1.2, 1.0]);
+    let report = check_gradient(&mut f, &x, &Options::default());
+    assert!(!report.passed);
+    assert_eq!(report.worst, Some(1));
+    let worst = report.worst_component().unwrap();
+    assert!(!worst.passed);
+    assert!(report.components[0].passed);
+    assert_relative_eq!(worst.rel_error, 0.1 / 1.1, max_relative = 1e-4);
+}
+//}}}
+//{{{ test: test_forward_scheme
+#[test]
+fn test_forward_scheme() {
+    let mut f = Rosenbrock {
+        a: 1.0,
+        b: 100.0,
+        broken: false,
+    };
+    let x = SCVector::<f64, 2>::from_col_slice(&[0.5, 0.5]);
+    let opts = Options {
+        scheme: Scheme::Forward,
+        step: 1e-7,
+        rtol: 1e-4,
+        ..Options::default()
+    };
+    let report = check_gradient(&mut f, &x, &opts);
+    assert!(report.passed);
+}
+//}}}
+//{{{ test: test_directional_check
+#[test]
+fn test_directional_check() {
+    let x = SCVector::<f64, 6>::from_col_slice(&[1.0, -2.0, 3.0, 0.5, -0.25, 4.0]);
+    let opts = Options {
+        max_components: 4,
+        num_directions: 8,
+        ..Options::default()
+    };
+
+    let mut good = SumOfSquares { broken: false };
+    let report = check_gradient(&mut good, &x, &opts);
+    assert!(report.components.is_empty());
+    assert_eq!(report.directions.len(), 8);
+    assert!(report.worst.is_none());
+    assert!(report.passed);
+
+    let mut bad = SumOfSquares { broken: true };
+    let report = check_gradient(&mut bad, &x, &opts);
+    assert!(!report.passed);
+}
+//}}}
+//{{{ test: test_check_derivative
+#[test]
+fn test_check_derivative() {
+    let mut f = Sine;
+    let report = check_derivative(&mut f, 0.3, &Options::default());
+    assert!(report.passed);
+    assert_eq!(report.components.len(), 1);
+    assert_relative_eq!(report.components[0].approx, 0.3f64.cos(), epsilon = 1e-8);
+}
+//}}}
@@ -0,0 +1,82 @@
+//! Tests of the primal-dual interior point method.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::constrained::{ConstrainedError, InteriorPoint, InteriorPointOptions};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use approx::assert_relative_eq;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+mod common;
+use common::{Hs035, Hs071, Parabola, Vec2, Vec3, Vec4};
+
+//{{{ test: test_interior_point_hs071
+#[test]
+fn test_interior_point_hs071() {
+    let x0 = Vec4::from_col_slice(&[1.0, 5.0, 5.0, 1.0]);
+    let ret = InteriorPoint::new(Hs071::with_bound_ineqs(), x0, InteriorPointOptions::default())
+        .minimize()
+        .unwrap();
+    let xmin = [1.0, 4.74299963, 3.82114998, 1.37940829];
+    for (i, xi) in xmin.iter().enumerate() {
+        assert_relative_eq!(ret.xmin[i], xi, epsilon = 1e-5);
+    }
+    assert_relative_eq!(ret.fmin, 17.0140173, epsilon = 1e-5);
+    assert_relative_eq!(ret.lambda[0], -0.16146857, epsilon = 1e-4);
+    assert_relative_eq!(ret.mu[0], 0.55229366, epsilon = 1e-4);
+    assert!(ret.mu.iter().all(|m| *m > 0.0));
+}
+//}}}
+//{{{ test: test_interior_point_hs035
+#[test]
+fn test_interior_point_hs035() {
+    let x0 = Vec3::from_col_slice(&[0.5, 0.5, 0.5]);
+    let ret = InteriorPoint::new(Hs035::with_bound_ineqs(), x0, InteriorPointOptions::default())
+        .minimize()
+        .unwrap();
+    assert_relative_eq!(ret.xmin[0], 4.0 / 3.0, epsilon = 1e-6);
+    assert_relative_eq!(ret.xmin[1], 7.0 / 9.0, epsilon = 1e-6);
+    assert_relative_eq!(ret.xmin[2], 4.0 / 9.0, epsilon = 1e-6);
+    assert_relative_eq!(ret.fmin, 1.0 / 9.0, epsilon = 1e-6);
+    assert_relative_eq!(ret.mu[0], 2.0 / 9.0, epsilon = 1e-6);
+    assert!(ret.kkt.complementarity <= 1e-6);
+}
+//}}}
+//{{{ test: test_interior_point_singular_jacobian
+#[test]
+fn test_interior_point_singular_jacobian() {
+    let x0 = Vec2::from_col_slice(&[0.0, 1.0]);
+    let ret = InteriorPoint::new(Parabola, x0, InteriorPointOptions::default())
+        .minimize()
+        .unwrap();
+    assert_relative_eq!(ret.xmin[0], 1.0, epsilon = 1e-6);
+    assert_relative_eq!(ret.xmin[1], 0.0, epsilon = 1e-6);
+    assert_relative_eq!(ret.lambda[0], -1.0, epsilon = 1e-5);
+}
+//}}}
+//{{{ test: test_interior_point_errors
+#[test]
+fn test_interior_point_errors() {
+    let x0 = Vec2::from_col_slice(&[f64::NAN, 1.0]);
+    let err = InteriorPoint::new(Parabola, x0, InteriorPointOptions::default())
+        .minimize()
+        .unwrap_err();
+    assert!(matches!(err, ConstrainedError::NonFiniteValue { .. }));
+
+    let x0 = Vec4::from_col_slice(&[1.0, 5.0, 5.0, 1.0]);
+    let opts = InteriorPointOptions {
+        max_iter: 2,
+        ..InteriorPointOptions::default()
+    };
+    let err = InteriorPoint::new(Hs071::with_bound_ineqs(), x0, opts).minimize().unwrap_err();
+    assert!(matches!(err, ConstrainedError::MaxIterations { max_iter: 2, .. }));
+    assert_eq!(err.partial().num_iterations, 2);
+}
+//}}}
@@ -0,0 +1,288 @@
+//! Tests of the bound-constrained L-BFGS-B minimizer.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::bound_constrained::{
+    ActiveBound, BoundConstrainedError, BoundConstrainedStep, Bounds, BoundsError, Lbfgsb,
+    LbfgsbOptions, LbfgsbState,
+};
+use topohedral_optimize::unconstrained::{ConvergedReason, IterationState, ObserverAction};
+use topohedral_optimize::{Budget, CancelToken, RealFn};
+//}}}
+//{{{ std imports
+use std::time::Duration;
+//}}}
+//{{{ dep imports
+use approx::assert_relative_eq;
+use topohedral_linalg::dvector::{DVector, VecType};
+use topohedral_linalg::scvector::SCVector;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Boxed
+/// `Σ (i + 1) (xᵢ − cᵢ)²`, which panics when evaluated outside of `[lower, upper]`.
+#[derive(Debug, Clone)]
+struct Boxed {
+    center: Vec<f64>,
+    lower: f64,
+    upper: f64,
+}
+//}}}
+//{{{ impl: RealFn for Boxed
+impl RealFn for Boxed {
+    type Vector = DVector<f64>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        (0..x.len())
+            .map(|i| {
+                assert!(self.lower <= x[i] && x[i] <= self.upper, "evaluated outside the box");
+                (i + 1) as f64 * (x[i] - self.center[i]).powi(2)
+            })
+            .sum()
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        let values: Vec<f64> = (0..x.len())
+            .map(|i| 2.0 * (i + 1) as f64 * (x[i] - self.center[i]))
+            .collect();
+        DVector::from_slice(&values, VecType::Col)
+    }
+}
+//}}}
+//{{{ struct: Rosenbrock
+#[derive(Debug, Clone)]
+struct Rosenbrock;
+//}}}
+//{{{ impl: RealFn for Rosenbrock
+impl RealFn for Rosenbrock {
+    type Vector = SCVector<f64, 2>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        (1.0 - x[0]).powi(2) + 100.0 * (x[1] - x[0] * x[0]).powi(2)
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        let dx0 = -2.0 * (1.0 - x[0]) - 400.0 * x[0] * (x[1] - x[0] * x[0]);
+        let dx1 = 200.0 * (x[1] - x[0] * x[0]);
+        SCVector::<f64, 2>::from_col_slice(&[dx0, dx1])
+    }
+}
+//}}}
+//{{{ fun: options
+/// Default options which stop on the projected gradient alone.
+fn options() -> LbfgsbOptions {
+    LbfgsbOptions {
+        criteria: None,
+        ..LbfgsbOptions::default()
+    }
+}
+//}}}
+//{{{ test: test_bounds
+#[test]
+fn test_bounds() {
+    assert_eq!(
+        Bounds::new(vec![0.0], vec![1.0, 2.0]).unwrap_err(),
+        BoundsError::LengthMismatch { lower: 1, upper: 2 }
+    );
+    assert_eq!(
+        Bounds::new(vec![0.0, 3.0], vec![1.0, 2.0]).unwrap_err(),
+        BoundsError::Inverted { index: 1 }
+    );
+    assert_eq!(
+        Bounds::new(vec![0.0, f64::NAN], vec![1.0, 2.0]).unwrap_err(),
+        BoundsError::NaN { index: 1 }
+    );
+
+    let bounds = Bounds::new(vec![0.0, f64::NEG_INFINITY], vec![1.0, 0.0]).unwrap();
+    let mut x = [2.0, 1.0];
+    assert!(!bounds.contains(&x));
+    bounds.project(&mut x);
+    assert_eq!(x, [1.0, 0.0]);
+    assert_eq!(bounds.active(&x), vec![Some(ActiveBound::Upper), Some(ActiveBound::Upper)]);
+    // a gradient pushing out of the box leaves no projected gradient
+    assert_eq!(bounds.projected_gradient(&x, &[-1.0, -1.0]), vec![0.0, 0.0]);
+    assert_eq!(bounds.projected_gradient(&x, &[1.0, 0.0]), vec![-1.0, 0.0]);
+}
+//}}}
+//{{{ test: test_lbfgsb_unbounded
+#[test]
+fn test_lbfgsb_unbounded() {
+    let fcn = Boxed {
+        center: vec![1.0, -2.0, 3.0, 0.5],
+        lower: f64::NEG_INFINITY,
+        upper: f64::INFINITY,
+    };
+    let x0 = DVector::from_slice(&[0.0; 4], VecType::Col);
+    let mut lbfgsb = Lbfgsb::new(fcn.clone(), x0, Bounds::unbounded(4), options());
+    let ret = lbfgsb.minimize().unwrap();
+    assert!(matches!(ret.reason, ConvergedReason::ProjGrad));
+    for i in 0..4 {
+        assert_relative_eq!(ret.xmin[i], fcn.center[i], epsilon = 1e-6);
+    }
+    assert!(ret.active_set().is_empty());
+    assert_eq!(ret.num_fun_evals, ret.num_grad_evals);
+}
+//}}}
+//{{{ test: test_lbfgsb_active_bounds
+#[test]
+fn test_lbfgsb_active_bounds() {
+    // the centre lies outside of the box in the first and last variables
+    let fcn = Boxed {
+        center: vec![2.0, 0.5, 0.25, -1.0],
+        lower: 0.0,
+        upper: 1.0,
+    };
+    let bounds = Bounds::uniform(4, 0.0, 1.0).unwrap();
+    // an infeasible start is projected onto the box before it is evaluated
+    let x0 = DVector::from_slice(&[-3.0, 3.0, 0.5, 0.5], VecType::Col);
+    let ret = Lbfgsb::new(fcn, x0, bounds, options()).minimize().unwrap();
+    assert!(matches!(ret.reason, ConvergedReason::ProjGrad));
+    assert_eq!(ret.xmin[0], 1.0);
+    assert_relative_eq!(ret.xmin[1], 0.5, epsilon = 1e-6);
+    assert_relative_eq!(ret.xmin[2], 0.25, epsilon = 1e-6);
+    assert_eq!(ret.xmin[3], 0.0);
+    assert_eq!(
+        ret.active,
+        vec![Some(ActiveBound::Upper), None, None, Some(ActiveBound::Lower)]
+    );
+    assert_eq!(ret.active_set(), vec![0, 3]);
+    assert!(ret.proj_grad_norm <= 1e-5);
+}
+//}}}
+//{{{ test: test_lbfgsb_rosenbrock
+#[test]
+fn test_lbfgsb_rosenbrock() {
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[-1.2, 1.0]);
+    let opts = options();
+
+    // with the minimum inside of the box
+    let bounds = Bounds::uniform(2, -2.0, 2.0).unwrap();
+    let ret = Lbfgsb::new(Rosenbrock, x0, bounds, opts.clone()).minimize().unwrap();
+    assert_relative_eq!(ret.xmin[0], 1.0, epsilon = 1e-4);
+    assert_relative_eq!(ret.xmin[1], 1.0, epsilon = 1e-4);
+    assert_eq!(ret.active, vec![None, None]);
+
+    // and with the first variable held below it, which leaves x₁ = x₀²
+    let bounds = Bounds::new(vec![-2.0, -2.0], vec![0.5, 2.0]).unwrap();
+    let ret = Lbfgsb::new(Rosenbrock, x0, bounds, opts).minimize().unwrap();
+    assert_eq!(ret.xmin[0], 0.5);
+    assert_relative_eq!(ret.xmin[1], 0.25, epsilon = 1e-6);
+    assert_eq!(ret.active, vec![Some(ActiveBound::Upper), None]);
+    assert!(ret.num_iterations < 100);
+}
+//}}}
+//{{{ test: test_lbfgsb_errors
+#[test]
+fn test_lbfgsb_errors() {
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[-1.2, 1.0]);
+    let opts = LbfgsbOptions {
+        max_iter: 3,
+        ..LbfgsbOptions::default()
+    };
+    let bounds = Bounds::unbounded(2);
+    let err = Lbfgsb::new(Rosenbrock, x0, bounds.clone(), opts.clone())
+        .minimize()
+        .unwrap_err();
+    assert!(matches!(err, BoundConstrainedError::MaxIterations { max_iter: 3, .. }));
+    assert_eq!(err.partial().num_iterations, 3);
+    assert!(err.partial().fmin < Rosenbrock.eval(&x0));
+
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[f64::INFINITY, 1.0]);
+    let err = Lbfgsb::new(Rosenbrock, x0, bounds, opts)
+        .minimize()
+        .unwrap_err();
+    assert!(matches!(err, BoundConstrainedError::NonFiniteValue { .. }));
+    assert_eq!(err.partial().num_fun_evals, 1);
+}
+//}}}
+//{{{ test: test_lbfgsb_interrupted
+#[test]
+fn test_lbfgsb_interrupted() {
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[-1.2, 1.0]);
+    let bounds = Bounds::unbounded(2);
+
+    // a cancelled token, a used up time limit and a budget each stop the run with the starting
+    // point as the partial result
+    let token = CancelToken::new();
+    token.cancel();
+    let opts = LbfgsbOptions {
+        cancel: Some(token),
+        ..options()
+    };
+    let err = Lbfgsb::new(Rosenbrock, x0, bounds.clone(), opts)
+        .minimize()
+        .unwrap_err();
+    assert!(matches!(err, BoundConstrainedError::Cancelled { .. }));
+    assert_eq!(err.partial().num_fun_evals, 1);
+
+    let opts = LbfgsbOptions {
+        time_limit: Some(Duration::ZERO),
+        ..options()
+    };
+    let err = Lbfgsb::new(Rosenbrock, x0, bounds.clone(), opts)
+        .minimize()
+        .unwrap_err();
+    assert!(matches!(err, BoundConstrainedError::TimeLimit { .. }));
+
+    let budget = Budget::new(Some(10), None);
+    let opts = LbfgsbOptions {
+        budget,
+        ..options()
+    };
+    let err = Lbfgsb::new(Rosenbrock, x0, bounds.clone(), opts)
+        .minimize()
+        .unwrap_err();
+    assert!(matches!(err, BoundConstrainedError::BudgetExhausted { budget: b, .. } if b == budget));
+    assert_eq!(err.partial().num_fun_evals, 10);
+    assert!(err.partial().fmin < Rosenbrock.eval(&x0));
+
+    // as does an observer, which sees the iterations taken by `step`
+    let mut lbfgsb = Lbfgsb::new(Rosenbrock, x0, bounds, options());
+    lbfgsb.set_observer(Box::new(|state: &IterationState<_>| {
+        if state.iteration < 5 {
+            ObserverAction::Continue
+        } else {
+            ObserverAction::Stop
+        }
+    }));
+    for i in 1..5 {
+        match lbfgsb.step() {
+            BoundConstrainedStep::Iteration(state) => assert_eq!(state.iteration, i),
+            BoundConstrainedStep::Done(_) => panic!("finished early"),
+        }
+    }
+    let ret = lbfgsb.minimize().unwrap();
+    assert!(matches!(ret.reason, ConvergedReason::ObserverStop));
+    assert_eq!(ret.num_iterations, 5);
+}
+//}}}
+//{{{ test: test_lbfgsb_resume
+#[test]
+fn test_lbfgsb_resume() {
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[-1.2, 1.0]);
+    let bounds = Bounds::new(vec![-2.0, -2.0], vec![0.5, 2.0]).unwrap();
+    let expected = Lbfgsb::new(Rosenbrock, x0, bounds.clone(), options())
+        .minimize()
+        .unwrap();
+
+    // a run stopped after a few iterations continues with the same correction pairs
+    let mut lbfgsb = Lbfgsb::new(Rosenbrock, x0, bounds.clone(), options());
+    for _ in 0..6 {
+        lbfgsb.step();
+    }
+    let state = lbfgsb.state().unwrap();
+    assert_eq!(state.iteration, 6);
+    assert!(!state.memory.is_empty());
+    let json = serde_json::to_string(&state).unwrap();
+    let state: LbfgsbState = serde_json::from_str(&json).unwrap();
+    let ret = Lbfgsb::resume_from(Rosenbrock, state, bounds, options())
+        .minimize()
+        .unwrap();
+    assert_eq!(ret.xmin, expected.xmin);
+    assert_eq!(ret.num_iterations, expected.num_iterations);
+    assert_eq!(ret.num_fun_evals, expected.num_fun_evals);
+}
+//}}}
@@ -6,7 +6,7 @@
 use topohedral_optimize::line_search::LineSearch;
 use topohedral_optimize::line_search::LineSearchOptions;
 use topohedral_optimize::{
-    line_search::{Interp, InterpOptions, LineSearchFcn},
+    line_search::{Backtracking, BacktrackingOptions, Interp, InterpOptions, LineSearchFcn},
     RealFn, RealFn1,
 };
 //}}}
@@ -146,6 +146,30 @@ fn test_cubic_1d() {
     assert_relative_eq!(out.alpha, exp_alpha, epsilon = 1e-10);
 }
 
+#[test]
+fn test_backtracking_quadratic_1d() {
+    // The full step is accepted as soon as it decreases the function enough
+    let mut q1 = Quadratic1D {
+        root1: 1.0,
+        root2: 2.0,
+    };
+    let mut backtracking = Backtracking::new(q1.clone(), BacktrackingOptions::default());
+    let out = backtracking.search(q1.eval(0.0), q1.diff(0.0)).unwrap();
+    assert_relative_eq!(out.alpha, 1.0, epsilon = 1e-14);
+    assert_eq!(backtracking.stats().num_evals, 1);
+
+    // Otherwise the quadratic interpolation lands on the minimizer
+    let mut q2 = Quadratic1D {
+        root1: 0.1,
+        root2: 0.2,
+    };
+    let mut backtracking = Backtracking::new(q2.clone(), BacktrackingOptions::default());
+    let out = backtracking.search(q2.eval(0.0), q2.diff(0.0)).unwrap();
+    assert_relative_eq!(out.alpha, q2.extrema(), epsilon = 1e-12);
+    assert_eq!(backtracking.stats().num_evals, 2);
+    assert_eq!(backtracking.stats().num_rejected, 1);
+}
+
 #[derive(Clone, Copy, Debug)]
 struct Fcn1 {
     beta: f64,
@@ -0,0 +1,143 @@
+//! Tests of the linear programming solvers.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::bound_constrained::Bounds;
+use topohedral_optimize::linprog::{
+    LinearProgram, LinprogError, LinprogReturns, PredictorCorrector, PredictorCorrectorOptions,
+    Simplex, SimplexOptions,
+};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use approx::assert_relative_eq;
+use topohedral_linalg::dmatrix::DMatrix;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ fun: solve_both
+fn solve_both(problem: LinearProgram) -> [Result<LinprogReturns, LinprogError>; 2] {
+    [
+        Simplex::new(problem.clone(), SimplexOptions::default()).minimize(),
+        PredictorCorrector::new(problem, PredictorCorrectorOptions::default()).minimize(),
+    ]
+}
+//}}}
+//{{{ test: test_linprog_bounded
+#[test]
+fn test_linprog_bounded() {
+    // max 3x₀ + 2x₁ subject to x₀ + x₁ <= 4, x₀ + 3x₁ <= 9 and 0 <= x₀ <= 3
+    let a_ineq = DMatrix::from_row_slice(&[-1.0, -1.0, -1.0, -3.0], 2, 2);
+    let bounds = Bounds::new(vec![0.0, 0.0], vec![3.0, f64::INFINITY]).unwrap();
+    let problem = LinearProgram::new(vec![-3.0, -2.0])
+        .with_inequalities(a_ineq, vec![-4.0, -9.0])
+        .with_bounds(bounds);
+    for ret in solve_both(problem) {
+        let ret = ret.unwrap();
+        assert_relative_eq!(ret.x[0], 3.0, epsilon = 1e-7);
+        assert_relative_eq!(ret.x[1], 1.0, epsilon = 1e-7);
+        assert_relative_eq!(ret.objective, -11.0, epsilon = 1e-7);
+        assert_relative_eq!(ret.mu[0], 2.0, epsilon = 1e-7);
+        assert_relative_eq!(ret.mu[1], 0.0, epsilon = 1e-7);
+        assert_relative_eq!(ret.reduced_costs[0], -1.0, epsilon = 1e-7);
+        assert_relative_eq!(ret.reduced_costs[1], 0.0, epsilon = 1e-7);
+    }
+}
+//}}}
+//{{{ test: test_linprog_free_variables
+#[test]
+fn test_linprog_free_variables() {
+    // x₀ + 2x₁ − x₂ on the plane x₀ + x₁ + x₂ = 4, given twice, with x₁ >= x₀ − 1, x₀ >= 0,
+    // x₁ free and x₂ <= 3
+    let a_eq = DMatrix::from_row_slice(&[1.0, 1.0, 1.0, 2.0, 2.0, 2.0], 2, 3);
+    let a_ineq = DMatrix::from_row_slice(&[-1.0, 1.0, 0.0], 1, 3);
+    let bounds = Bounds::new(
+        vec![0.0, f64::NEG_INFINITY, f64::NEG_INFINITY],
+        vec![f64::INFINITY, f64::INFINITY, 3.0],
+    )
+    .unwrap();
+    let problem = LinearProgram::new(vec![1.0, 2.0, -1.0])
+        .with_equalities(a_eq, vec![4.0, 8.0])
+        .with_inequalities(a_ineq, vec![-1.0])
+        .with_bounds(bounds);
+    for ret in solve_both(problem) {
+        let ret = ret.unwrap();
+        assert_relative_eq!(ret.x[0], 1.0, epsilon = 1e-7);
+        assert_relative_eq!(ret.x[1], 0.0, epsilon = 1e-7);
+        assert_relative_eq!(ret.x[2], 3.0, epsilon = 1e-7);
+        assert_relative_eq!(ret.objective, -2.0, epsilon = 1e-7);
+        assert_relative_eq!(ret.lambda[0] + 2.0 * ret.lambda[1], 1.5, epsilon = 1e-7);
+        assert_relative_eq!(ret.mu[0], 0.5, epsilon = 1e-7);
+        assert_relative_eq!(ret.reduced_costs[2], -2.5, epsilon = 1e-7);
+    }
+}
+//}}}
+//{{{ test: test_linprog_degenerate
+#[test]
+fn test_linprog_degenerate() {
+    // Beale's example, on which the simplex method cycles with Dantzig's rule
+    let a_ineq = DMatrix::from_row_slice(
+        &[-0.25, 8.0, 1.0, -9.0, -0.5, 12.0, 0.5, -3.0, 0.0, 0.0, -1.0, 0.0],
+        3,
+        4,
+    );
+    let problem = LinearProgram::new(vec![-0.75, 20.0, -0.5, 6.0])
+        .with_inequalities(a_ineq, vec![0.0, 0.0, -1.0]);
+    for ret in solve_both(problem) {
+        let ret = ret.unwrap();
+        assert_relative_eq!(ret.objective, -1.25, epsilon = 1e-7);
+        assert_relative_eq!(ret.x[0], 1.0, epsilon = 1e-7);
+        assert_relative_eq!(ret.x[2], 1.0, epsilon = 1e-7);
+        assert!(ret.mu.iter().all(|m| *m >= -1e-9));
+    }
+}
+//}}}
+//{{{ test: test_linprog_feasibility
+#[test]
+fn test_linprog_feasibility() {
+    // a zero cost only asks for a point of x₀ = x₁ with x₀ >= 10⁸, far enough out for the
+    // iterates to look like a certificate of infeasibility while τ is still large
+    let a_eq = DMatrix::from_row_slice(&[1.0, -1.0], 1, 2);
+    let a_ineq = DMatrix::from_row_slice(&[1.0, 0.0], 1, 2);
+    let problem = LinearProgram::new(vec![0.0, 0.0])
+        .with_equalities(a_eq, vec![0.0])
+        .with_inequalities(a_ineq, vec![1e8]);
+    for ret in solve_both(problem) {
+        let ret = ret.unwrap();
+        assert_eq!(ret.objective, 0.0);
+        assert_relative_eq!(ret.x[0], ret.x[1], max_relative = 1e-7);
+        assert!(ret.x[0] >= 1e8 * (1.0 - 1e-7));
+    }
+}
+//}}}
+//{{{ test: test_linprog_errors
+#[test]
+fn test_linprog_errors() {
+    // x₀ + x₁ <= 1 and x₀ + x₁ >= 2
+    let a_ineq = DMatrix::from_row_slice(&[-1.0, -1.0, 1.0, 1.0], 2, 2);
+    let problem = LinearProgram::new(vec![1.0, 1.0]).with_inequalities(a_ineq, vec![-1.0, 2.0]);
+    for ret in solve_both(problem) {
+        assert_eq!(ret.unwrap_err(), LinprogError::Infeasible);
+    }
+
+    // −x₀ − x₁ on the ray x₀ = x₁ >= 0
+    let a_eq = DMatrix::from_row_slice(&[1.0, -1.0], 1, 2);
+    let problem = LinearProgram::new(vec![-1.0, -1.0]).with_equalities(a_eq, vec![0.0]);
+    for ret in solve_both(problem.clone()) {
+        assert_eq!(ret.unwrap_err(), LinprogError::Unbounded);
+    }
+
+    let opts = PredictorCorrectorOptions {
+        max_iter: 1,
+        ..PredictorCorrectorOptions::default()
+    };
+    let a_ineq = DMatrix::from_row_slice(&[-1.0, -1.0], 1, 2);
+    let problem = LinearProgram::new(vec![-1.0, -2.0]).with_inequalities(a_ineq, vec![-4.0]);
+    let err = PredictorCorrector::new(problem, opts).minimize().unwrap_err();
+    assert_eq!(err, LinprogError::MaxIterations { max_iter: 1 });
+}
+//}}}
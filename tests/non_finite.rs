@@ -0,0 +1,151 @@
+//! Tests of the handling of NaN and infinite values by the line search and the minimizers.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::line_search::{
+    Interp, InterpOptions, LineSearch, LineSearchError, LineSearchOptions,
+};
+use topohedral_optimize::unconstrained::{
+    ConjugateGradient, ConjugateGradientOptions, Direction, UnconstrainedError,
+    UnconstrainedMinimizer, UnonstrainedOptions,
+};
+use topohedral_optimize::{RealFn, RealFn1};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use topohedral_linalg::scvector::SCVector;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Cliff
+/// `φ(α) = (α − 0.5)²`, which is `value` for steps beyond `edge`.
+#[derive(Debug, Clone)]
+struct Cliff {
+    edge: f64,
+    value: f64,
+}
+//}}}
+//{{{ impl: RealFn1 for Cliff
+impl RealFn1 for Cliff {
+    fn eval(&mut self, alpha: f64) -> f64 {
+        if alpha > self.edge {
+            self.value
+        } else {
+            (alpha - 0.5).powi(2)
+        }
+    }
+
+    fn diff(&mut self, alpha: f64) -> f64 {
+        if alpha > self.edge {
+            self.value
+        } else {
+            2.0 * (alpha - 0.5)
+        }
+    }
+}
+//}}}
+//{{{ struct: Walled
+/// Quadratic with a diagonal Hessian of `2 * diag(1, ..., 4)` and a minimum at `(1, ..., 1)`,
+/// which is infinite once any coordinate exceeds `1.5`.
+#[derive(Debug, Clone)]
+struct Walled;
+//}}}
+//{{{ impl: RealFn for Walled
+impl RealFn for Walled {
+    type Vector = SCVector<f64, 4>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        if (0..4).any(|i| x[i] > 1.5) {
+            return f64::INFINITY;
+        }
+        (0..4).map(|i| (i + 1) as f64 * (x[i] - 1.0).powi(2)).sum()
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        let mut out = Self::Vector::zeros();
+        for i in 0..4 {
+            out[i] = 2.0 * (i + 1) as f64 * (x[i] - 1.0);
+        }
+        out
+    }
+}
+//}}}
+//{{{ fun: options
+fn options() -> ConjugateGradientOptions {
+    ConjugateGradientOptions {
+        uncon_opts: UnonstrainedOptions {
+            max_iter: 100,
+            ..UnonstrainedOptions::default()
+        },
+        direction: Direction::PolakRibiere,
+        restart: 10,
+    }
+}
+//}}}
+//{{{ test: test_line_search_backtracks
+#[test]
+fn test_line_search_backtracks() {
+    // the initial step of 1 lies beyond the edge, for both NaN and infinite values
+    for value in [f64::NAN, f64::INFINITY] {
+        let mut search = Interp::new(Cliff { edge: 0.75, value }, InterpOptions::default());
+        let ret = search.search(0.25, -1.0).unwrap();
+        assert!(ret.alpha <= 0.75);
+        assert!(ret.phi_alpha.is_finite());
+        assert!(search.stats().num_nonfinite > 0);
+    }
+}
+//}}}
+//{{{ test: test_line_search_nonfinite_retries
+#[test]
+fn test_line_search_nonfinite_retries() {
+    // every positive step is infeasible, so the search gives up after the allowed retries
+    let opts = InterpOptions {
+        ls_opts: LineSearchOptions {
+            max_nonfinite: 3,
+            ..LineSearchOptions::default()
+        },
+        ..InterpOptions::default()
+    };
+    let fcn = Cliff {
+        edge: 0.0,
+        value: f64::NAN,
+    };
+    let mut search = Interp::new(fcn.clone(), opts);
+    let err = search.search(0.25, -1.0).unwrap_err();
+    assert!(matches!(err, LineSearchError::NonFiniteValue { alpha } if alpha > 0.0));
+    assert_eq!(search.stats().num_nonfinite, 4);
+    assert_eq!(search.stats().num_evals, 4);
+
+    // a non-finite start fails before any evaluation
+    let mut search = Interp::new(fcn, opts);
+    let err = search.search(f64::NAN, -1.0).unwrap_err();
+    assert_eq!(err, LineSearchError::NonFiniteValue { alpha: 0.0 });
+    assert_eq!(search.stats().num_evals, 0);
+}
+//}}}
+//{{{ test: test_conjugate_gradient_nonfinite
+#[test]
+fn test_conjugate_gradient_nonfinite() {
+    // trial points beyond the wall are backed off from
+    let x0 = SCVector::<f64, 4>::zeros();
+    let ret = ConjugateGradient::new(Walled, x0, options()).minimize().unwrap();
+    assert!(ret.fmin < 1e-10);
+    assert!(ret.line_search.num_nonfinite > 0);
+
+    // a starting point beyond it is reported along with the point
+    let x0 = SCVector::<f64, 4>::from_col_slice(&[0.0, 0.0, 0.0, 2.0]);
+    let err = ConjugateGradient::new(Walled, x0, options()).minimize().unwrap_err();
+    match err {
+        UnconstrainedError::NonFiniteValue { x, partial } => {
+            assert_eq!(x[3], 2.0);
+            assert_eq!(partial.num_iterations, 0);
+            assert_eq!(partial.num_fun_evals, 1);
+        }
+        err => panic!("expected a non-finite value error, got {err:?}"),
+    }
+}
+//}}}
@@ -0,0 +1,205 @@
+//! Tests of the nonlinear equation solvers.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::nonlinear_solve::{
+    Broyden, BroydenOptions, BroydenUpdate, Newton, NewtonKrylov, NewtonKrylovOptions,
+    NewtonOptions, NonlinearSolveError, Residual,
+};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use approx::assert_relative_eq;
+use topohedral_linalg::dmatrix::DMatrix;
+use topohedral_linalg::dvector::{DVector, VecType};
+use topohedral_linalg::scvector::SCVector;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+type Vec1 = SCVector<f64, 1>;
+type Vec2 = SCVector<f64, 2>;
+
+//{{{ struct: Rosenbrock
+/// `(10(x₁ − x₀²), 1 − x₀)`, whose root `(1, 1)` minimizes the Rosenbrock function.
+#[derive(Debug, Clone)]
+struct Rosenbrock {
+    analytic: bool,
+}
+//}}}
+//{{{ impl: Residual for Rosenbrock
+impl Residual for Rosenbrock {
+    type Vector = Vec2;
+
+    fn eval(&mut self, x: &Vec2) -> Vec2 {
+        Vec2::from_col_slice(&[10.0 * (x[1] - x[0] * x[0]), 1.0 - x[0]])
+    }
+
+    fn jacobian(&mut self, x: &Vec2) -> Option<DMatrix<f64>> {
+        self.analytic
+            .then(|| DMatrix::from_row_slice(&[-20.0 * x[0], 10.0, -1.0, 0.0], 2, 2))
+    }
+}
+//}}}
+//{{{ struct: Circle
+/// `(x₀² + x₁² − 2, exp(x₀ − 1) + x₁³ − 2)`, with a root at `(1, 1)`.
+#[derive(Debug, Clone)]
+struct Circle;
+//}}}
+//{{{ impl: Residual for Circle
+impl Residual for Circle {
+    type Vector = Vec2;
+
+    fn eval(&mut self, x: &Vec2) -> Vec2 {
+        Vec2::from_col_slice(&[
+            x[0] * x[0] + x[1] * x[1] - 2.0,
+            (x[0] - 1.0).exp() + x[1].powi(3) - 2.0,
+        ])
+    }
+}
+//}}}
+//{{{ struct: Bratu
+/// Central differences of the Bratu problem `−u'' = λ exp(u)` on `(0, 1)` with `u(0) = u(1) = 0`
+/// at `n` interior points.
+#[derive(Debug, Clone)]
+struct Bratu {
+    n: usize,
+    lambda: f64,
+}
+//}}}
+//{{{ impl: Residual for Bratu
+impl Residual for Bratu {
+    type Vector = DVector<f64>;
+
+    fn eval(&mut self, u: &DVector<f64>) -> DVector<f64> {
+        let h2 = (1.0 / (self.n + 1) as f64).powi(2);
+        let values: Vec<f64> = (0..self.n)
+            .map(|i| {
+                let left = if i > 0 { u[i - 1] } else { 0.0 };
+                let right = if i + 1 < self.n { u[i + 1] } else { 0.0 };
+                (2.0 * u[i] - left - right) / h2 - self.lambda * u[i].exp()
+            })
+            .collect();
+        DVector::from_slice(&values, VecType::Col)
+    }
+}
+//}}}
+//{{{ struct: Scalar
+/// `x² + c`, or `ln x` when `log` is set, with its derivative.
+#[derive(Debug, Clone)]
+struct Scalar {
+    c: f64,
+    log: bool,
+}
+//}}}
+//{{{ impl: Residual for Scalar
+impl Residual for Scalar {
+    type Vector = Vec1;
+
+    fn eval(&mut self, x: &Vec1) -> Vec1 {
+        let value = if self.log {
+            x[0].ln()
+        } else {
+            x[0] * x[0] + self.c
+        };
+        Vec1::from_col_slice(&[value])
+    }
+
+    fn jacobian(&mut self, x: &Vec1) -> Option<DMatrix<f64>> {
+        let value = if self.log { 1.0 / x[0] } else { 2.0 * x[0] };
+        Some(DMatrix::from_row_slice(&[value], 1, 1))
+    }
+}
+//}}}
+//{{{ test: test_newton
+#[test]
+fn test_newton() {
+    for analytic in [true, false] {
+        let x0 = Vec2::from_col_slice(&[-1.2, 1.0]);
+        let ret = Newton::new(Rosenbrock { analytic }, x0, NewtonOptions::default())
+            .solve()
+            .unwrap();
+        assert_relative_eq!(ret.x[0], 1.0, epsilon = 1e-9);
+        assert_relative_eq!(ret.x[1], 1.0, epsilon = 1e-9);
+        assert!(ret.residual_norm <= 1e-10);
+        assert!(ret.num_iterations <= 20);
+        assert_eq!(ret.num_jac_evals > 0, analytic);
+    }
+}
+//}}}
+//{{{ test: test_broyden
+#[test]
+fn test_broyden() {
+    for update in [BroydenUpdate::Good, BroydenUpdate::Bad] {
+        let opts = BroydenOptions {
+            update,
+            ..BroydenOptions::default()
+        };
+        let x0 = Vec2::from_col_slice(&[1.5, 0.8]);
+        let ret = Broyden::new(Circle, x0, opts).solve().unwrap();
+        assert_relative_eq!(ret.x[0], 1.0, epsilon = 1e-9);
+        assert_relative_eq!(ret.x[1], 1.0, epsilon = 1e-9);
+        assert!(ret.residual_norm <= 1e-10);
+        assert_eq!(ret.num_jac_evals, 0);
+    }
+}
+//}}}
+//{{{ test: test_newton_krylov
+#[test]
+fn test_newton_krylov() {
+    let bratu = Bratu { n: 40, lambda: 1.0 };
+    let x0 = DVector::from_slice(&[0.0; 40], VecType::Col);
+    let ret = NewtonKrylov::new(bratu.clone(), x0.clone(), NewtonKrylovOptions::default())
+        .solve()
+        .unwrap();
+    assert!(ret.residual_norm <= 1e-10);
+    assert!(ret.num_inner_iterations >= ret.num_iterations);
+
+    let newton = Newton::new(bratu, x0, NewtonOptions::default())
+        .solve()
+        .unwrap();
+    for i in 0..40 {
+        assert_relative_eq!(ret.x[i], newton.x[i], epsilon = 1e-8);
+    }
+    // The solution is symmetric with a maximum of about 0.1405 in the middle
+    assert_relative_eq!(ret.x[0], ret.x[39], epsilon = 1e-8);
+    assert_relative_eq!(ret.x[19], 0.1405, epsilon = 1e-3);
+}
+//}}}
+//{{{ test: test_nonlinear_solve_errors
+#[test]
+fn test_nonlinear_solve_errors() {
+    // x² + 1 has a vanishing derivative at the start and no root at all
+    let x0 = Vec1::from_col_slice(&[0.0]);
+    let residual = Scalar { c: 1.0, log: false };
+    let err = Newton::new(residual, x0, NewtonOptions::default())
+        .solve()
+        .unwrap_err();
+    assert!(matches!(err, NonlinearSolveError::SingularJacobian { .. }));
+    assert_eq!(err.partial().num_iterations, 0);
+
+    let x0 = Vec1::from_col_slice(&[-1.0]);
+    let residual = Scalar { c: 0.0, log: true };
+    let err = Newton::new(residual, x0, NewtonOptions::default())
+        .solve()
+        .unwrap_err();
+    assert!(matches!(err, NonlinearSolveError::NonFiniteValue { .. }));
+
+    let opts = NewtonOptions {
+        max_iter: 1,
+        ..NewtonOptions::default()
+    };
+    let x0 = Vec2::from_col_slice(&[-1.2, 1.0]);
+    let err = Newton::new(Rosenbrock { analytic: true }, x0, opts)
+        .solve()
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        NonlinearSolveError::MaxIterations { max_iter: 1, .. }
+    ));
+    assert_eq!(err.into_partial().num_iterations, 1);
+}
+//}}}
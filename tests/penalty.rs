@@ -0,0 +1,238 @@
+//! Tests of the quadratic and l1 penalty methods.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::constrained::{
+    ConjugateGradientSubsolver, ConstrainedError, ConstrainedProblem, Penalty, PenaltyFn,
+    PenaltyMethod, PenaltyOptions, UnconstrainedSubsolver,
+};
+use topohedral_optimize::unconstrained::{
+    BarzilaiBorwein, BarzilaiBorweinOptions, ConjugateGradientOptions, Direction,
+    UnonstrainedOptions,
+};
+use topohedral_optimize::RealFn;
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use approx::assert_relative_eq;
+use topohedral_linalg::scvector::SCVector;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+type Vec2 = SCVector<f64, 2>;
+
+//{{{ struct: Circle
+/// `x₀ + x₁` on the circle `x₀² + x₁² = 2`, minimized at `(−1, −1)` with `λ = −1/2`.
+#[derive(Debug, Clone)]
+struct Circle;
+//}}}
+//{{{ impl: ConstrainedProblem for Circle
+impl ConstrainedProblem for Circle {
+    type Vector = Vec2;
+
+    fn num_eq(&self) -> usize {
+        1
+    }
+
+    fn num_ineq(&self) -> usize {
+        0
+    }
+
+    fn eval(&mut self, x: &Vec2) -> f64 {
+        x[0] + x[1]
+    }
+
+    fn grad(&mut self, _x: &Vec2) -> Vec2 {
+        Vec2::from_col_slice(&[1.0, 1.0])
+    }
+
+    fn eq(&mut self, x: &Vec2) -> Vec<f64> {
+        vec![x[0] * x[0] + x[1] * x[1] - 2.0]
+    }
+
+    fn ineq(&mut self, _x: &Vec2) -> Vec<f64> {
+        vec![]
+    }
+
+    fn eq_jac_tr(&mut self, x: &Vec2, lambda: &[f64]) -> Vec2 {
+        Vec2::from_col_slice(&[2.0 * x[0] * lambda[0], 2.0 * x[1] * lambda[0]])
+    }
+
+    fn ineq_jac_tr(&mut self, _x: &Vec2, _mu: &[f64]) -> Vec2 {
+        Vec2::zeros()
+    }
+}
+//}}}
+//{{{ struct: Bracken
+/// `(x₀ − 2)² + (x₁ − 1)²` subject to `x₀ − 2x₁ + 1 = 0` and `1 − x₀²/4 − x₁² >= 0`, the
+/// problem of Bracken and McCormick.
+#[derive(Debug, Clone)]
+struct Bracken;
+//}}}
+//{{{ impl: ConstrainedProblem for Bracken
+impl ConstrainedProblem for Bracken {
+    type Vector = Vec2;
+
+    fn num_eq(&self) -> usize {
+        1
+    }
+
+    fn num_ineq(&self) -> usize {
+        1
+    }
+
+    fn eval(&mut self, x: &Vec2) -> f64 {
+        (x[0] - 2.0).powi(2) + (x[1] - 1.0).powi(2)
+    }
+
+    fn grad(&mut self, x: &Vec2) -> Vec2 {
+        Vec2::from_col_slice(&[2.0 * (x[0] - 2.0), 2.0 * (x[1] - 1.0)])
+    }
+
+    fn eq(&mut self, x: &Vec2) -> Vec<f64> {
+        vec![x[0] - 2.0 * x[1] + 1.0]
+    }
+
+    fn ineq(&mut self, x: &Vec2) -> Vec<f64> {
+        vec![1.0 - 0.25 * x[0] * x[0] - x[1] * x[1]]
+    }
+
+    fn eq_jac_tr(&mut self, _x: &Vec2, lambda: &[f64]) -> Vec2 {
+        Vec2::from_col_slice(&[lambda[0], -2.0 * lambda[0]])
+    }
+
+    fn ineq_jac_tr(&mut self, x: &Vec2, mu: &[f64]) -> Vec2 {
+        Vec2::from_col_slice(&[-0.5 * x[0] * mu[0], -2.0 * x[1] * mu[0]])
+    }
+}
+//}}}
+//{{{ fun: subsolver
+fn subsolver() -> ConjugateGradientSubsolver {
+    ConjugateGradientSubsolver::new(ConjugateGradientOptions {
+        uncon_opts: UnonstrainedOptions {
+            max_iter: 10_000,
+            ..UnonstrainedOptions::default()
+        },
+        direction: Direction::PolakRibiere,
+        restart: 10,
+    })
+}
+//}}}
+//{{{ test: test_penalty_fn
+#[test]
+fn test_penalty_fn() {
+    // at x = (0, 0) the equality is violated by −2
+    let x = Vec2::zeros();
+    let mut quadratic = PenaltyFn::new(Circle, Penalty::Quadratic, 10.0);
+    assert_relative_eq!(quadratic.eval(&x), 20.0);
+    let mut l1 = PenaltyFn::new(Circle, Penalty::L1 { smoothing: 0.0 }, 10.0);
+    assert_relative_eq!(l1.eval(&x), 20.0);
+    let mut smoothed = PenaltyFn::new(Circle, Penalty::L1 { smoothing: 1.5 }, 10.0);
+    assert_relative_eq!(smoothed.eval(&x), 25.0);
+
+    // the gradients agree with central differences away from the constraint
+    let x = Vec2::from_col_slice(&[0.3, -0.7]);
+    for mut fcn in [quadratic, l1, smoothed] {
+        let grad = fcn.grad(&x);
+        for i in 0..2 {
+            let mut xp = x;
+            let mut xm = x;
+            xp[i] += 1e-6;
+            xm[i] -= 1e-6;
+            let fd = (fcn.eval(&xp) - fcn.eval(&xm)) / 2e-6;
+            assert_relative_eq!(grad[i], fd, epsilon = 1e-5);
+        }
+    }
+}
+//}}}
+//{{{ test: test_penalty_quadratic
+#[test]
+fn test_penalty_quadratic() {
+    let x0 = Vec2::from_col_slice(&[0.5, -0.5]);
+    let opts = PenaltyOptions {
+        feas_tol: 1e-5,
+        ..PenaltyOptions::default()
+    };
+    let ret = PenaltyMethod::new(Circle, x0, subsolver(), opts)
+        .minimize()
+        .unwrap();
+    assert_relative_eq!(ret.xmin[0], -1.0, epsilon = 1e-4);
+    assert_relative_eq!(ret.xmin[1], -1.0, epsilon = 1e-4);
+    // the multiplier estimate −ρ c_E tends to λ = −1/2
+    assert_relative_eq!(ret.lambda[0], -0.5, epsilon = 1e-3);
+    assert!(ret.penalty >= 1e4);
+}
+//}}}
+//{{{ test: test_penalty_l1
+#[test]
+fn test_penalty_l1() {
+    let x0 = Vec2::from_col_slice(&[2.0, 2.0]);
+    let xmin = [(7.0f64.sqrt() - 1.0) / 2.0, (7.0f64.sqrt() + 1.0) / 4.0];
+    let opts = PenaltyOptions {
+        penalty: Penalty::L1 { smoothing: 1e-2 },
+        ..PenaltyOptions::default()
+    };
+    // the nonmonotone Barzilai-Borwein method copes better than the Wolfe line search of the
+    // conjugate gradient method with the sharp bend of the smoothed penalty
+    let subsolver = UnconstrainedSubsolver::new(|fcn, x0, tol| {
+        let opts = BarzilaiBorweinOptions {
+            uncon_opts: UnonstrainedOptions {
+                grad_rtol: 0.0,
+                grad_atol: tol,
+                max_iter: 10_000,
+                ..UnonstrainedOptions::default()
+            },
+            ..BarzilaiBorweinOptions::default()
+        };
+        BarzilaiBorwein::new(fcn, x0, opts)
+    });
+    let ret = PenaltyMethod::new(Bracken, x0, subsolver, opts)
+        .minimize()
+        .unwrap();
+    assert_relative_eq!(ret.xmin[0], xmin[0], epsilon = 1e-5);
+    assert_relative_eq!(ret.xmin[1], xmin[1], epsilon = 1e-5);
+    // being exact, the l1 penalty needs far less than the quadratic one
+    assert!(ret.penalty <= 100.0);
+}
+//}}}
+//{{{ test: test_penalty_errors
+#[test]
+fn test_penalty_errors() {
+    let x0 = Vec2::from_col_slice(&[f64::NAN, 0.0]);
+    let err = PenaltyMethod::new(Circle, x0, subsolver(), PenaltyOptions::default())
+        .minimize()
+        .unwrap_err();
+    assert!(matches!(err, ConstrainedError::NonFiniteValue { .. }));
+
+    let x0 = Vec2::zeros();
+    let opts = PenaltyOptions {
+        max_iter: 2,
+        ..PenaltyOptions::default()
+    };
+    let err = PenaltyMethod::new(Circle, x0, subsolver(), opts)
+        .minimize()
+        .unwrap_err();
+    assert!(matches!(err, ConstrainedError::MaxIterations { max_iter: 2, .. }));
+    assert_eq!(err.partial().penalty, 100.0);
+
+    // a feasible point the subsolver cannot improve on is not retried with the same subproblem
+    let x0 = Vec2::from_col_slice(&[2.0f64.sqrt(), 0.0]);
+    let subsolver = ConjugateGradientSubsolver::new(ConjugateGradientOptions {
+        uncon_opts: UnonstrainedOptions {
+            max_iter: 0,
+            ..UnonstrainedOptions::default()
+        },
+        direction: Direction::PolakRibiere,
+        restart: 10,
+    });
+    let err = PenaltyMethod::new(Circle, x0, subsolver, PenaltyOptions::default())
+        .minimize()
+        .unwrap_err();
+    assert!(matches!(err, ConstrainedError::Subproblem { .. }));
+    assert_eq!(err.partial().num_iterations, 1);
+}
+//}}}
@@ -0,0 +1,110 @@
+//! Tests of the convex quadratic programming solver.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::quadratic::{
+    ActiveSet, ActiveSetOptions, QuadraticError, QuadraticProgram, QuadraticReturns,
+};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use approx::assert_relative_eq;
+use topohedral_linalg::dmatrix::DMatrix;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ fun: solve
+fn solve(problem: QuadraticProgram) -> Result<QuadraticReturns, QuadraticError> {
+    ActiveSet::new(problem, ActiveSetOptions::default()).minimize()
+}
+//}}}
+//{{{ test: test_quadratic_inequalities
+#[test]
+fn test_quadratic_inequalities() {
+    // Example 16.4 of Nocedal and Wright, (x₀ − 1)² + (x₁ − 2.5)² over a pentagon
+    let hessian = DMatrix::from_row_slice(&[2.0, 0.0, 0.0, 2.0], 2, 2);
+    let a_ineq = DMatrix::from_row_slice(
+        &[1.0, -2.0, -1.0, -2.0, -1.0, 2.0, 1.0, 0.0, 0.0, 1.0],
+        5,
+        2,
+    );
+    let problem = QuadraticProgram::new(hessian, vec![-2.0, -5.0])
+        .with_inequalities(a_ineq, vec![-2.0, -6.0, -2.0, 0.0, 0.0]);
+    let ret = solve(problem).unwrap();
+    assert_relative_eq!(ret.x[0], 1.4, epsilon = 1e-10);
+    assert_relative_eq!(ret.x[1], 1.7, epsilon = 1e-10);
+    assert_eq!(ret.active, vec![0]);
+    assert_relative_eq!(ret.mu[0], 0.8, epsilon = 1e-10);
+    assert!(ret.mu[1..].iter().all(|m| *m == 0.0));
+    assert_relative_eq!(ret.objective, 0.8 - 7.25, epsilon = 1e-10);
+}
+//}}}
+//{{{ test: test_quadratic_equalities
+#[test]
+fn test_quadratic_equalities() {
+    // ½‖x‖² on the plane x₀ + x₁ + x₂ = 3, given twice, and within x₂ <= 0.5
+    let hessian = DMatrix::from_row_slice(&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0], 3, 3);
+    let a_eq = DMatrix::from_row_slice(&[1.0, 1.0, 1.0, 2.0, 2.0, 2.0], 2, 3);
+    let problem = QuadraticProgram::new(hessian, vec![0.0; 3]).with_equalities(a_eq, vec![3.0, 6.0]);
+    let ret = solve(problem.clone()).unwrap();
+    for xi in &ret.x {
+        assert_relative_eq!(*xi, 1.0, epsilon = 1e-10);
+    }
+    assert_relative_eq!(ret.lambda[0] + 2.0 * ret.lambda[1], 1.0, epsilon = 1e-10);
+    assert!(ret.active.is_empty());
+
+    let a_ineq = DMatrix::from_row_slice(&[0.0, 0.0, -1.0], 1, 3);
+    let ret = solve(problem.with_inequalities(a_ineq, vec![-0.5])).unwrap();
+    assert_relative_eq!(ret.x[0], 1.25, epsilon = 1e-10);
+    assert_relative_eq!(ret.x[1], 1.25, epsilon = 1e-10);
+    assert_relative_eq!(ret.x[2], 0.5, epsilon = 1e-10);
+    assert_eq!(ret.active, vec![0]);
+    assert_relative_eq!(ret.mu[0], 0.75, epsilon = 1e-10);
+}
+//}}}
+//{{{ test: test_quadratic_linear
+#[test]
+fn test_quadratic_linear() {
+    // with a zero Hessian the solution is the vertex x₀ + 2x₁ = 4, 3x₀ + x₁ = 6
+    let a_ineq = DMatrix::from_row_slice(&[-1.0, -2.0, -3.0, -1.0, 1.0, 0.0, 0.0, 1.0], 4, 2);
+    let problem = QuadraticProgram::new(DMatrix::zeros(2, 2), vec![-1.0, -1.0])
+        .with_inequalities(a_ineq, vec![-4.0, -6.0, 0.0, 0.0]);
+    let ret = solve(problem).unwrap();
+    assert_relative_eq!(ret.x[0], 1.6, epsilon = 1e-10);
+    assert_relative_eq!(ret.x[1], 1.2, epsilon = 1e-10);
+    assert_eq!(ret.active, vec![0, 1]);
+    assert_relative_eq!(ret.mu[0], 0.4, epsilon = 1e-10);
+    assert_relative_eq!(ret.mu[1], 0.2, epsilon = 1e-10);
+}
+//}}}
+//{{{ test: test_quadratic_errors
+#[test]
+fn test_quadratic_errors() {
+    // −x₀ + x₁² for x₀ >= 0 decreases without bound along x₀
+    let hessian = DMatrix::from_row_slice(&[0.0, 0.0, 0.0, 2.0], 2, 2);
+    let a_ineq = DMatrix::from_row_slice(&[1.0, 0.0], 1, 2);
+    let problem = QuadraticProgram::new(hessian.clone(), vec![-1.0, 0.0])
+        .with_inequalities(a_ineq, vec![0.0]);
+    match solve(problem) {
+        Err(QuadraticError::Unbounded { direction, .. }) => {
+            assert_relative_eq!(direction[0], 1.0, epsilon = 1e-10);
+            assert_relative_eq!(direction[1], 0.0, epsilon = 1e-10);
+        }
+        other => panic!("expected an unbounded program, got {other:?}"),
+    }
+
+    // x₀ >= 1 and x₀ <= 0
+    let a_ineq = DMatrix::from_row_slice(&[1.0, 0.0, -1.0, 0.0], 2, 2);
+    let problem = QuadraticProgram::new(hessian, vec![-1.0, 0.0])
+        .with_inequalities(a_ineq, vec![1.0, 0.0]);
+    assert_eq!(solve(problem).unwrap_err(), QuadraticError::Infeasible);
+
+    let hessian = DMatrix::from_row_slice(&[1.0, 0.0, 0.0, -1.0], 2, 2);
+    let problem = QuadraticProgram::new(hessian, vec![0.0, 0.0]);
+    assert_eq!(solve(problem).unwrap_err(), QuadraticError::NotConvex);
+}
+//}}}
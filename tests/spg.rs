@@ -0,0 +1,238 @@
+//! Tests of the projections and the spectral projected gradient minimizer.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::bound_constrained::Bounds;
+use topohedral_optimize::projected::{
+    Ball, NonNegative, ProjectedError, Projection, Simplex, Spg, SpgOptions,
+};
+use topohedral_optimize::unconstrained::ConvergedReason;
+use topohedral_optimize::{Budget, CancelToken, RealFn};
+//}}}
+//{{{ std imports
+use std::time::Duration;
+//}}}
+//{{{ dep imports
+use approx::assert_relative_eq;
+use topohedral_linalg::dvector::{DVector, VecType};
+use topohedral_linalg::scvector::SCVector;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+//{{{ struct: Distance
+/// `‖x − target‖²`, whose minimizer over a set is the projection of `target` onto it.
+#[derive(Debug, Clone)]
+struct Distance {
+    target: Vec<f64>,
+}
+//}}}
+//{{{ impl: RealFn for Distance
+impl RealFn for Distance {
+    type Vector = DVector<f64>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        (0..x.len()).map(|i| (x[i] - self.target[i]).powi(2)).sum()
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        let values: Vec<f64> = (0..x.len()).map(|i| 2.0 * (x[i] - self.target[i])).collect();
+        DVector::from_slice(&values, VecType::Col)
+    }
+}
+//}}}
+//{{{ struct: Linear
+/// `aᵀx` with `a = (3, 4)`.
+#[derive(Debug, Clone)]
+struct Linear;
+//}}}
+//{{{ impl: RealFn for Linear
+impl RealFn for Linear {
+    type Vector = SCVector<f64, 2>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        3.0 * x[0] + 4.0 * x[1]
+    }
+
+    fn grad(&mut self, _x: &Self::Vector) -> Self::Vector {
+        SCVector::<f64, 2>::from_col_slice(&[3.0, 4.0])
+    }
+}
+//}}}
+//{{{ struct: Rosenbrock
+#[derive(Debug, Clone)]
+struct Rosenbrock;
+//}}}
+//{{{ impl: RealFn for Rosenbrock
+impl RealFn for Rosenbrock {
+    type Vector = SCVector<f64, 2>;
+
+    fn eval(&mut self, x: &Self::Vector) -> f64 {
+        (1.0 - x[0]).powi(2) + 100.0 * (x[1] - x[0] * x[0]).powi(2)
+    }
+
+    fn grad(&mut self, x: &Self::Vector) -> Self::Vector {
+        let dx0 = -2.0 * (1.0 - x[0]) - 400.0 * x[0] * (x[1] - x[0] * x[0]);
+        let dx1 = 200.0 * (x[1] - x[0] * x[0]);
+        SCVector::<f64, 2>::from_col_slice(&[dx0, dx1])
+    }
+}
+//}}}
+//{{{ test: test_projections
+#[test]
+fn test_projections() {
+    let mut x = [1.0, -2.0, 0.0];
+    NonNegative.project(&mut x);
+    assert_eq!(x, [1.0, 0.0, 0.0]);
+
+    let mut x = [0.9, 0.6, -1.0];
+    Simplex::new().project(&mut x);
+    assert_relative_eq!(x[0], 0.65, epsilon = 1e-12);
+    assert_relative_eq!(x[1], 0.35, epsilon = 1e-12);
+    assert_eq!(x[2], 0.0);
+    let mut x = [1.0, 1.0, 1.0, 1.0];
+    Simplex::with_radius(2.0).project(&mut x);
+    assert_eq!(x, [0.5; 4]);
+
+    let mut x = [3.0, 4.0];
+    Ball::new(1.0).project(&mut x);
+    assert_relative_eq!(x[0], 0.6, epsilon = 1e-12);
+    assert_relative_eq!(x[1], 0.8, epsilon = 1e-12);
+    let mut x = [1.0, 1.5];
+    Ball::centered(vec![1.0, 1.0], 1.0).project(&mut x);
+    assert_eq!(x, [1.0, 1.5]);
+    let mut x = [1.0, 3.0];
+    Ball::centered(vec![1.0, 1.0], 1.0).project(&mut x);
+    assert_eq!(x, [1.0, 2.0]);
+
+    let bounds = Bounds::uniform(2, 0.0, 1.0).unwrap();
+    let mut x = [2.0, 0.5];
+    Projection::project(&bounds, &mut x);
+    assert_eq!(x, [1.0, 0.5]);
+    assert_eq!(Projection::projected_gradient(&bounds, &x, &[-1.0, 1.0]), vec![0.0, -0.5]);
+}
+//}}}
+//{{{ test: test_spg_simplex
+#[test]
+fn test_spg_simplex() {
+    let fcn = Distance {
+        target: vec![0.9, 0.6, -1.0],
+    };
+    let x0 = DVector::from_slice(&[0.0, 0.0, 5.0], VecType::Col);
+    let ret = Spg::new(fcn, x0, Simplex::new(), SpgOptions::default())
+        .minimize()
+        .unwrap();
+    assert!(matches!(ret.reason, ConvergedReason::ProjGrad));
+    assert_relative_eq!(ret.xmin[0], 0.65, epsilon = 1e-6);
+    assert_relative_eq!(ret.xmin[1], 0.35, epsilon = 1e-6);
+    assert_relative_eq!(ret.xmin[2], 0.0, epsilon = 1e-6);
+}
+//}}}
+//{{{ test: test_spg_ball
+#[test]
+fn test_spg_ball() {
+    // a linear function is minimized on the boundary, opposite to its gradient
+    let x0 = SCVector::<f64, 2>::zeros();
+    let ret = Spg::new(Linear, x0, Ball::new(2.0), SpgOptions::default())
+        .minimize()
+        .unwrap();
+    assert_relative_eq!(ret.xmin[0], -1.2, epsilon = 1e-6);
+    assert_relative_eq!(ret.xmin[1], -1.6, epsilon = 1e-6);
+    assert_relative_eq!(ret.fmin, -10.0, epsilon = 1e-6);
+}
+//}}}
+//{{{ test: test_spg_rosenbrock
+#[test]
+fn test_spg_rosenbrock() {
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[-1.2, 1.0]);
+
+    // the minimum lies within the orthant, for both the monotone and nonmonotone searches
+    for memory in [1, 10] {
+        let opts = SpgOptions {
+            memory,
+            max_iter: 10_000,
+            ..SpgOptions::default()
+        };
+        let ret = Spg::new(Rosenbrock, x0, NonNegative, opts).minimize().unwrap();
+        assert_relative_eq!(ret.xmin[0], 1.0, epsilon = 1e-4);
+        assert_relative_eq!(ret.xmin[1], 1.0, epsilon = 1e-4);
+    }
+
+    // with the first variable held below it, as for L-BFGS-B
+    let bounds = Bounds::new(vec![-2.0, -2.0], vec![0.5, 2.0]).unwrap();
+    let ret = Spg::new(Rosenbrock, x0, bounds, SpgOptions::default())
+        .minimize()
+        .unwrap();
+    assert_eq!(ret.xmin[0], 0.5);
+    assert_relative_eq!(ret.xmin[1], 0.25, epsilon = 1e-6);
+}
+//}}}
+//{{{ test: test_spg_backtracking
+#[test]
+fn test_spg_backtracking() {
+    // The first step is the unit step along d = 1, the quadratic (α − 0.05)² is not minimized
+    // within [0.1, 0.9] so it is halved, and from α = 0.5 its minimizer is safeguarded
+    // relative to α and taken exactly
+    let x0 = DVector::from_slice(&[0.0], VecType::Col);
+    let bounds = Bounds::new(vec![-10.0], vec![10.0]).unwrap();
+    let distance = Distance { target: vec![0.05] };
+    let opts = SpgOptions {
+        memory: 1,
+        ..SpgOptions::default()
+    };
+    let ret = Spg::new(distance, x0, bounds, opts).minimize().unwrap();
+    assert_eq!(ret.num_iterations, 1);
+    assert_eq!(ret.num_fun_evals, 4);
+    assert_relative_eq!(ret.xmin[0], 0.05, epsilon = 1e-12);
+}
+//}}}
+//{{{ test: test_spg_errors
+#[test]
+fn test_spg_errors() {
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[-1.2, 1.0]);
+    let opts = SpgOptions {
+        max_iter: 3,
+        ..SpgOptions::default()
+    };
+    let err = Spg::new(Rosenbrock, x0, NonNegative, opts.clone()).minimize().unwrap_err();
+    assert!(matches!(err, ProjectedError::MaxIterations { max_iter: 3, .. }));
+    // the starting point was projected onto the orthant
+    assert!(err.partial().fmin < Rosenbrock.eval(&SCVector::<f64, 2>::from_col_slice(&[0.0, 1.0])));
+
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[f64::NAN, 1.0]);
+    let err = Spg::new(Rosenbrock, x0, Ball::new(1.0), opts).minimize().unwrap_err();
+    assert!(matches!(err, ProjectedError::NonFiniteValue { .. }));
+
+    // a cancelled token, a used up time limit and a budget stop the run before it is exceeded,
+    // with the last iterate as the partial result
+    let x0 = SCVector::<f64, 2>::from_col_slice(&[-1.2, 1.0]);
+    let token = CancelToken::new();
+    token.cancel();
+    let opts = SpgOptions {
+        cancel: Some(token),
+        ..SpgOptions::default()
+    };
+    let err = Spg::new(Rosenbrock, x0, NonNegative, opts).minimize().unwrap_err();
+    assert!(matches!(err, ProjectedError::Cancelled { .. }));
+    assert_eq!(err.partial().num_fun_evals, 1);
+
+    let opts = SpgOptions {
+        time_limit: Some(Duration::ZERO),
+        ..SpgOptions::default()
+    };
+    let err = Spg::new(Rosenbrock, x0, NonNegative, opts).minimize().unwrap_err();
+    assert!(matches!(err, ProjectedError::TimeLimit { .. }));
+
+    let budget = Budget::new(Some(8), Some(4));
+    let opts = SpgOptions {
+        budget,
+        ..SpgOptions::default()
+    };
+    let err = Spg::new(Rosenbrock, x0, NonNegative, opts).minimize().unwrap_err();
+    assert!(matches!(err, ProjectedError::BudgetExhausted { budget: b, .. } if b == budget));
+    assert!(err.partial().num_fun_evals <= 8 && err.partial().num_grad_evals <= 4);
+    assert!(err.partial().num_iterations > 0);
+}
+//}}}
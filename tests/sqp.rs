@@ -0,0 +1,84 @@
+//! Tests of the sequential quadratic programming method.
+//!
+//--------------------------------------------------------------------------------------------------
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+
+//{{{ crate imports
+use topohedral_optimize::bound_constrained::Bounds;
+use topohedral_optimize::constrained::{ConstrainedError, Sqp, SqpOptions};
+//}}}
+//{{{ std imports
+//}}}
+//{{{ dep imports
+use approx::assert_relative_eq;
+//}}}
+//--------------------------------------------------------------------------------------------------
+
+mod common;
+use common::{Hs035, Hs071, Parabola, Vec2, Vec3, Vec4};
+
+//{{{ test: test_sqp_hs071
+#[test]
+fn test_sqp_hs071() {
+    let x0 = Vec4::from_col_slice(&[1.0, 5.0, 5.0, 1.0]);
+    let bounds = Bounds::uniform(4, 1.0, 5.0).unwrap();
+    let ret = Sqp::new(Hs071::new(), x0, bounds, SqpOptions::default())
+        .minimize()
+        .unwrap();
+    let xmin = [1.0, 4.74299963, 3.82114998, 1.37940829];
+    for (i, xi) in xmin.iter().enumerate() {
+        assert_relative_eq!(ret.xmin[i], xi, epsilon = 1e-5);
+    }
+    assert_relative_eq!(ret.fmin, 17.0140173, epsilon = 1e-6);
+    assert_relative_eq!(ret.lambda[0], -0.16146857, epsilon = 1e-4);
+    assert_relative_eq!(ret.mu[0], 0.55229366, epsilon = 1e-4);
+}
+//}}}
+//{{{ test: test_sqp_hs035
+#[test]
+fn test_sqp_hs035() {
+    let x0 = Vec3::from_col_slice(&[0.5, 0.5, 0.5]);
+    let bounds = Bounds::new(vec![0.0; 3], vec![f64::INFINITY; 3]).unwrap();
+    let ret = Sqp::new(Hs035::new(), x0, bounds, SqpOptions::default())
+        .minimize()
+        .unwrap();
+    assert_relative_eq!(ret.xmin[0], 4.0 / 3.0, epsilon = 1e-6);
+    assert_relative_eq!(ret.xmin[1], 7.0 / 9.0, epsilon = 1e-6);
+    assert_relative_eq!(ret.xmin[2], 4.0 / 9.0, epsilon = 1e-6);
+    assert_relative_eq!(ret.fmin, 1.0 / 9.0, epsilon = 1e-8);
+    assert_relative_eq!(ret.mu[0], 2.0 / 9.0, epsilon = 1e-6);
+}
+//}}}
+//{{{ test: test_sqp_inconsistent_linearization
+#[test]
+fn test_sqp_inconsistent_linearization() {
+    let x0 = Vec2::from_col_slice(&[0.0, 1.0]);
+    let ret = Sqp::new(Parabola, x0, Bounds::unbounded(2), SqpOptions::default())
+        .minimize()
+        .unwrap();
+    assert_relative_eq!(ret.xmin[0], 1.0, epsilon = 1e-6);
+    assert_relative_eq!(ret.xmin[1], 0.0, epsilon = 1e-6);
+    assert_relative_eq!(ret.lambda[0], -1.0, epsilon = 1e-5);
+}
+//}}}
+//{{{ test: test_sqp_errors
+#[test]
+fn test_sqp_errors() {
+    let x0 = Vec2::from_col_slice(&[f64::NAN, 1.0]);
+    let err = Sqp::new(Parabola, x0, Bounds::unbounded(2), SqpOptions::default())
+        .minimize()
+        .unwrap_err();
+    assert!(matches!(err, ConstrainedError::NonFiniteValue { .. }));
+
+    let x0 = Vec4::from_col_slice(&[1.0, 5.0, 5.0, 1.0]);
+    let bounds = Bounds::uniform(4, 1.0, 5.0).unwrap();
+    let opts = SqpOptions {
+        max_iter: 2,
+        ..SqpOptions::default()
+    };
+    let err = Sqp::new(Hs071::new(), x0, bounds, opts).minimize().unwrap_err();
+    assert!(matches!(err, ConstrainedError::MaxIterations { max_iter: 2, .. }));
+    assert_eq!(err.partial().num_iterations, 2);
+}
+//}}}